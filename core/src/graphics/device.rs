@@ -50,6 +50,7 @@ pub trait Device<B: Backend> {
   fn create_semaphore(&self) -> Arc<B::Semaphore>;
   fn graphics_queue(&self) -> &Arc<B::Queue>;
   fn prerendered_frames(&self) -> u32;
+  fn min_storage_buffer_offset_alignment(&self) -> usize;
   fn supports_bindless(&self) -> bool;
   fn supports_ray_tracing(&self) -> bool;
   fn supports_indirect(&self) -> bool;
@@ -21,6 +21,7 @@ pub enum Format {
   RGBA32Float,
   RG16UNorm,
   RG8UNorm,
+  RG8SNorm,
   R32UInt,
   RGBA16Float,
   R11G11B10Float,
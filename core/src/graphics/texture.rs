@@ -42,7 +42,11 @@ impl Default for TextureLayout {
 pub enum TextureDimension {
   Dim1D,
   Dim2D,
-  Dim3D
+  /// A 2D texture that always gets sampled as an array, even if it only has a single layer.
+  Dim2DArray,
+  Dim3D,
+  /// A 2D texture where every group of 6 array layers makes up the faces of a cube.
+  DimCube
 }
 
 #[derive(Clone, Eq, PartialEq, Hash, Debug)]
@@ -28,6 +28,41 @@ uint getClusterIndexWithDepth(vec2 fragCoord, float depth, float zNear, float zF
   return getClusterIndex(fragCoord, z, clusterCount, viewportSize, clusterZScale, clusterZBias);
 }
 
+// The light bitmasks contain all point lights followed by all spot lights.
+uint clusteredLightCount() {
+  return pointLightCount + spotLightCount;
+}
+
+// Fades the light out towards its range so the cutoff of the light binning isn't visible.
+float windowedFalloff(float dist, float range) {
+  float ratio = dist / range;
+  float window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+  return window * window;
+}
+
+vec3 clusteredLightContribution(uint lightIndex, vec3 worldPos, vec3 viewDir, vec3 normal, vec3 f0, vec3 albedo, float roughness, float metalness) {
+  vec3 fragToLight;
+  vec3 radiance;
+  if (lightIndex < pointLightCount) {
+    PointLight light = pointLights[lightIndex];
+    fragToLight = light.positionAndIntensity.xyz - worldPos;
+    float dist = length(fragToLight);
+    float attenuation = 1.0 / max(light.attenuation.x + light.attenuation.y * dist + light.attenuation.z * dist * dist, 0.0001);
+    radiance = light.colorAndRange.rgb * (light.positionAndIntensity.w * attenuation * windowedFalloff(dist, light.colorAndRange.w));
+  } else {
+    SpotLight light = spotLights[lightIndex - pointLightCount];
+    fragToLight = light.positionAndIntensity.xyz - worldPos;
+    float dist = length(fragToLight);
+    vec3 lightDir = fragToLight / max(dist, 0.0001);
+    // Smooth falloff between the inner and the outer cone, same as KHR_lights_punctual.
+    float coneAttenuation = clamp(dot(light.directionAndAngleScale.xyz, -lightDir) * light.directionAndAngleScale.w + light.attenuationAndAngleOffset.w, 0.0, 1.0);
+    coneAttenuation *= coneAttenuation;
+    float attenuation = 1.0 / max(light.attenuationAndAngleOffset.x + light.attenuationAndAngleOffset.y * dist + light.attenuationAndAngleOffset.z * dist * dist, 0.0001);
+    radiance = light.colorAndRange.rgb * (light.positionAndIntensity.w * coneAttenuation * attenuation * windowedFalloff(dist, light.colorAndRange.w));
+  }
+  return pbr(normalize(fragToLight), viewDir, normal, f0, albedo, radiance, roughness, metalness);
+}
+
 #ifdef DEBUG
 bool validateCluster(vec3 viewPos, Cluster cluster) {
   vec3 viewPos = (camera.view * vec4(worldPos, 1)).xyz;
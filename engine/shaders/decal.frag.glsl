@@ -0,0 +1,25 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_GOOGLE_include_directive : enable
+
+#include "descriptor_sets.inc.glsl"
+
+layout(location = 0) in vec2 in_uv;
+layout(location = 1) in vec2 in_lightmap_uv;
+layout(location = 2) in flat uint in_lightmap_styles;
+layout(location = 4) in flat float in_lightmap_bump_page_width;
+layout(location = 5) in float in_fade;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 0) uniform sampler2D albedo;
+layout(set = DESCRIPTOR_SET_FREQUENT, binding = 0) uniform sampler2DArray lightmap;
+
+#include "lightmap.inc.glsl"
+
+void main(void) {
+  // Decals don't have normal maps, so bumped lightmaps get sampled with the flat surface normal.
+  vec3 lighting = vec3(0.3) + sampleLightmap(lightmap, in_lightmap_uv, in_lightmap_styles, in_lightmap_bump_page_width, vec3(0.0, 0.0, 1.0));
+  vec4 color = texture(albedo, in_uv);
+  out_color = vec4(color.rgb * lighting, color.a * in_fade);
+}
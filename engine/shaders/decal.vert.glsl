@@ -0,0 +1,50 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_GOOGLE_include_directive : enable
+
+#include "descriptor_sets.inc.glsl"
+#include "camera.inc.glsl"
+
+layout(location = 0) in vec3 in_pos;
+layout(location = 2) in vec2 in_uv;
+layout(location = 3) in vec2 in_lightmap_uv;
+layout(location = 5) in uint in_lightmap_styles;
+layout(location = 7) in float in_lightmap_bump_page_width;
+
+layout(location = 0) out vec2 out_uv;
+layout(location = 1) out vec2 out_lightmap_uv;
+layout(location = 2) out flat uint out_lightmap_styles;
+layout(location = 4) out flat float out_lightmap_bump_page_width;
+layout(location = 5) out float out_fade;
+
+layout(push_constant) uniform FadeConstants {
+  float fadeStart;
+  float fadeEnd;
+};
+
+#include "frame_set.inc.glsl"
+
+// Decals lie exactly on top of the surfaces they were clipped to,
+// so they get pulled towards the camera to win the depth test.
+#define DECAL_DEPTH_BIAS 0.002
+#define DECAL_DEPTH_BIAS_PER_DISTANCE 0.0005
+
+void main(void) {
+  vec3 toCamera = camera.position.xyz - in_pos;
+  float distance = length(toCamera);
+  vec3 biasedPos = in_pos + toCamera / max(distance, 0.0001) * min(DECAL_DEPTH_BIAS + distance * DECAL_DEPTH_BIAS_PER_DISTANCE, distance * 0.5);
+
+  out_uv = in_uv;
+  out_lightmap_uv = in_lightmap_uv;
+  out_lightmap_styles = in_lightmap_styles;
+  out_lightmap_bump_page_width = in_lightmap_bump_page_width;
+  // A fade end of 0 means that the decal never fades out.
+  out_fade = fadeEnd > 0.0 ? clamp((fadeEnd - distance) / max(fadeEnd - fadeStart, 0.0001), 0.0, 1.0) : 1.0;
+
+  mat4 jitterMat;
+  jitterMat[0] = vec4(1.0, 0.0, 0.0, 0.0);
+  jitterMat[1] = vec4(0.0, 1.0, 0.0, 0.0);
+  jitterMat[2] = vec4(0.0, 0.0, 1.0, 0.0);
+  jitterMat[3] = vec4(jitterPoint.x, jitterPoint.y, 0.0, 1.0);
+  gl_Position = jitterMat * swapchainTransform * camera.viewProj * vec4(biasedPos, 1);
+}
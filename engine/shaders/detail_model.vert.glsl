@@ -0,0 +1,39 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_GOOGLE_include_directive : enable
+
+#include "descriptor_sets.inc.glsl"
+#include "camera.inc.glsl"
+
+layout(location = 0) in vec3 in_pos;
+layout(location = 2) in vec2 in_uv;
+
+layout(location = 0) out vec3 out_world_pos;
+layout(location = 1) out vec2 out_uv;
+layout(location = 2) out vec3 out_color;
+
+#include "frame_set.inc.glsl"
+
+struct DetailModelInstance {
+  mat4 transform;
+  vec4 color;
+};
+
+layout(set = DESCRIPTOR_SET_FREQUENT, binding = 0, std430) readonly restrict buffer instancesSSBO {
+  DetailModelInstance instances[];
+};
+
+void main(void) {
+  DetailModelInstance instance = instances[gl_InstanceIndex];
+  vec4 worldPos = instance.transform * vec4(in_pos, 1);
+  out_world_pos = worldPos.xyz;
+  out_uv = in_uv;
+  out_color = instance.color.rgb;
+
+  mat4 jitterMat;
+  jitterMat[0] = vec4(1.0, 0.0, 0.0, 0.0);
+  jitterMat[1] = vec4(0.0, 1.0, 0.0, 0.0);
+  jitterMat[2] = vec4(0.0, 0.0, 1.0, 0.0);
+  jitterMat[3] = vec4(jitterPoint.x, jitterPoint.y, 0.0, 1.0);
+  gl_Position = jitterMat * swapchainTransform * camera.viewProj * worldPos;
+}
@@ -0,0 +1,27 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_GOOGLE_include_directive : enable
+
+#include "descriptor_sets.inc.glsl"
+#include "camera.inc.glsl"
+
+layout(location = 0) in vec3 in_world_pos;
+layout(location = 1) in vec2 in_uv;
+layout(location = 2) in vec3 in_color;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 0) uniform sampler2D albedo;
+
+#include "frame_set.inc.glsl"
+#include "detail_props.inc.glsl"
+
+void main(void) {
+  vec4 color = texture(albedo, in_uv);
+  if (color.a < 0.5) {
+    discard;
+  }
+  detailFade(in_world_pos, camera.position.xyz);
+  // Detail props aren't covered by the lightmap, VRAD stores a single color per prop instead.
+  out_color = vec4(color.rgb * in_color, 1.0);
+}
@@ -0,0 +1,21 @@
+#ifndef DETAIL_PROPS_INC_GLSL
+#define DETAIL_PROPS_INC_GLSL
+
+#include "descriptor_sets.inc.glsl"
+
+layout(set = DESCRIPTOR_SET_FREQUENT, binding = 1, std140) uniform DetailUBO {
+  float fadeStart;
+  float fadeEnd;
+};
+
+// Discards the fragment with a dither pattern that gets denser between fadeStart and fadeEnd,
+// so the detail props don't need to get sorted and blended to fade out.
+void detailFade(vec3 worldPos, vec3 cameraPos) {
+  float fade = clamp((distance(worldPos, cameraPos) - fadeStart) / max(fadeEnd - fadeStart, 0.0001), 0.0, 1.0);
+  float noise = fract(52.9829189 * fract(dot(gl_FragCoord.xy, vec2(0.06711056, 0.00583715))));
+  if (fade > noise) {
+    discard;
+  }
+}
+
+#endif
@@ -0,0 +1,68 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_GOOGLE_include_directive : enable
+
+#include "descriptor_sets.inc.glsl"
+#include "camera.inc.glsl"
+
+layout(location = 0) out vec3 out_world_pos;
+layout(location = 1) out vec2 out_uv;
+layout(location = 2) out vec3 out_color;
+
+#include "frame_set.inc.glsl"
+
+#define ORIENTATION_FIXED 0
+#define ORIENTATION_SCREEN_ALIGNED 1
+#define ORIENTATION_SCREEN_ALIGNED_VERTICAL 2
+
+struct DetailSprite {
+  vec4 positionAndOrientation;
+  vec4 right;
+  vec4 up;
+  // min right, min up, max right, max up
+  vec4 extents;
+  // upper left uv, lower right uv
+  vec4 texCoords;
+  vec4 color;
+};
+
+layout(set = DESCRIPTOR_SET_FREQUENT, binding = 0, std430) readonly restrict buffer spritesSSBO {
+  DetailSprite sprites[];
+};
+
+const vec2 corners[6] = vec2[](
+  vec2(0.0, 0.0), vec2(1.0, 0.0), vec2(1.0, 1.0),
+  vec2(0.0, 0.0), vec2(1.0, 1.0), vec2(0.0, 1.0)
+);
+
+void main(void) {
+  DetailSprite sprite = sprites[gl_VertexIndex / 6];
+  vec2 corner = corners[gl_VertexIndex % 6];
+  vec3 position = sprite.positionAndOrientation.xyz;
+  uint orientation = uint(sprite.positionAndOrientation.w);
+
+  vec3 right = sprite.right.xyz;
+  vec3 up = sprite.up.xyz;
+  if (orientation == ORIENTATION_SCREEN_ALIGNED) {
+    right = camera.invView[0].xyz;
+    up = camera.invView[1].xyz;
+  } else if (orientation == ORIENTATION_SCREEN_ALIGNED_VERTICAL) {
+    vec3 toCamera = camera.position.xyz - position;
+    toCamera.y = 0.0;
+    up = vec3(0.0, 1.0, 0.0);
+    right = length(toCamera) > 0.0001 ? normalize(cross(up, toCamera)) : camera.invView[0].xyz;
+  }
+
+  vec2 offset = mix(sprite.extents.xy, sprite.extents.zw, corner);
+  vec3 worldPos = position + right * offset.x + up * offset.y;
+  out_world_pos = worldPos;
+  out_uv = vec2(mix(sprite.texCoords.x, sprite.texCoords.z, corner.x), mix(sprite.texCoords.w, sprite.texCoords.y, corner.y));
+  out_color = sprite.color.rgb;
+
+  mat4 jitterMat;
+  jitterMat[0] = vec4(1.0, 0.0, 0.0, 0.0);
+  jitterMat[1] = vec4(0.0, 1.0, 0.0, 0.0);
+  jitterMat[2] = vec4(0.0, 0.0, 1.0, 0.0);
+  jitterMat[3] = vec4(jitterPoint.x, jitterPoint.y, 0.0, 1.0);
+  gl_Position = jitterMat * swapchainTransform * camera.viewProj * vec4(worldPos, 1);
+}
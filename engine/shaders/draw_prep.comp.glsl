@@ -31,6 +31,14 @@ layout(std430, set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 1, std430) restrict
 
 shared uint[2] visible;
 
+// Pixel height of a sphere with a diameter of one world unit at the origin of the drawable.
+float projectedScreenSize(mat4 transform) {
+  vec3 position = transform[3].xyz;
+  float scale = max(length(transform[0].xyz), max(length(transform[1].xyz), length(transform[2].xyz)));
+  float distance = max(length(position - camera.position.xyz), camera.zNear);
+  return abs(camera.proj[1][1]) * scale * float(rtSize.y) * 0.5 / distance;
+}
+
 void main() {
   #ifdef DEBUG
   if (gl_LocalInvocationIndex == 0) {
@@ -45,6 +53,11 @@ void main() {
     GPUMeshPart part = scene.parts[draw.partIndex];
     uint drawableIndex = draw.drawableIndex;
     bool drawableVisible = (visibleBitmasks[drawableIndex / 32] & (1 << (drawableIndex % 32))) != 0;
+    GPUDrawable drawable = scene.drawables[drawableIndex];
+    float screenSize = projectedScreenSize(drawable.transform);
+    drawableVisible = drawableVisible && screenSize >= part.lodMinScreenSize && screenSize < part.lodMaxScreenSize;
+    // The 3D skybox gets drawn separately by the sky view.
+    drawableVisible = drawableVisible && drawable.isInSkyArea == 0;
     #ifndef DEBUG
     bool emitDraw = drawableVisible;
     #else
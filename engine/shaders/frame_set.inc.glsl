@@ -26,21 +26,38 @@ layout(set = DESCRIPTOR_SET_FRAME, binding = 5, std140) uniform SetupUBO {
   float clusterZBias;
   float clusterZScale;
   uvec3 clusterCount;
+  uint spotLightCount;
   mat4 swapchainTransform;
   vec2 jitterPoint;
   uvec2 rtSize;
 };
 struct PointLight {
   vec4 positionAndIntensity;
+  vec4 colorAndRange;
+  // Constant, linear and quadratic attenuation.
+  vec4 attenuation;
 };
 layout(set = DESCRIPTOR_SET_FRAME, binding = 6, std140) uniform PointLightUBO {
   PointLight pointLights[1024];
 };
 struct DirectionalLight {
   vec4 directionAndIntensity;
+  vec4 color;
 };
 layout(set = DESCRIPTOR_SET_FRAME, binding = 7, std140) uniform DirectionalLightUBO {
   DirectionalLight directionalLights[1024];
 };
+layout(set = DESCRIPTOR_SET_FRAME, binding = 8, std140) uniform LightStylesUBO {
+  vec4 lightStyles[16];
+};
+struct SpotLight {
+  vec4 positionAndIntensity;
+  vec4 directionAndAngleScale;
+  vec4 colorAndRange;
+  vec4 attenuationAndAngleOffset;
+};
+layout(set = DESCRIPTOR_SET_FRAME, binding = 9, std140) uniform SpotLightUBO {
+  SpotLight spotLights[1024];
+};
 
 #endif
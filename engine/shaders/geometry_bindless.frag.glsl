@@ -14,10 +14,13 @@ layout(location = 0) in vec3 in_worldPosition;
 layout(location = 1) in vec2 in_uv;
 layout(location = 2) in vec2 in_lightmap_uv;
 layout(location = 3) in flat uint in_materialIndex;
+layout(location = 4) in flat uint in_lightmap_styles;
+layout(location = 6) in flat float in_lightmap_bump_page_width;
+layout(location = 7) in vec4 in_tangent;
 
 layout(location = 0) out vec4 out_color;
 
-layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 0) uniform sampler2D lightmap;
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 0) uniform sampler2DArray lightmap;
 layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 1) uniform sampler albedoSampler;
 layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 2) uniform sampler2D shadows;
 layout(set = DESCRIPTOR_SET_TEXTURES_BINDLESS, binding = 0) uniform texture2D albedo_global[];
@@ -41,6 +44,7 @@ layout(std430, set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 5, std430) readonly
 #endif
 
 #include "frame_set.inc.glsl"
+#include "lightmap.inc.glsl"
 
 #define FS
 #include "util.inc.glsl"
@@ -78,17 +82,18 @@ void main(void) {
 
   vec3 lighting = vec3(0);
   lighting += 0.3;
-  lighting += texture(lightmap, in_lightmap_uv).xyz;
+  vec3 lightmapNormal = lightmapTangentSpaceNormal(normal, normal, in_tangent);
+  lighting += sampleLightmap(lightmap, in_lightmap_uv, in_lightmap_styles, in_lightmap_bump_page_width, lightmapNormal);
   lighting *= texture(ssao, vec2(gl_FragCoord.x / rtSize.x, gl_FragCoord.y / rtSize.y)).rrr;
   lighting *= texture(shadows, vec2(gl_FragCoord.x / rtSize.x, gl_FragCoord.y / rtSize.y)).rrr;
   lighting += 0.3;
 
   for (uint i = 0; i < directionalLightCount; i++) {
     DirectionalLight light = directionalLights[i];
-    lighting += pbr(-light.directionAndIntensity.xyz, viewDir, normal, f0, albedo, vec3(light.directionAndIntensity.w), roughness, metalness);
+    lighting += pbr(-light.directionAndIntensity.xyz, viewDir, normal, f0, albedo, light.color.rgb * light.directionAndIntensity.w, roughness, metalness);
   }
 
-  uint lightBitmaskCount = (pointLightCount + 31) / 32;
+  uint lightBitmaskCount = (clusteredLightCount() + 31) / 32;
   uint bitmaskOffset = lightBitmaskCount * clusterIndex;
   for (uint i = 0; i < lightBitmaskCount; i++) {
     uint bitmaskIndex = bitmaskOffset + i;
@@ -104,11 +109,7 @@ void main(void) {
       bool lightActive = (bitmask & singleBitMask) == singleBitMask;
       bitmask &= ~singleBitMask;
       if (lightActive) {
-        PointLight light = pointLights[i * 32 + bitIndex];
-        vec3 fragToLight = light.positionAndIntensity.xyz - in_worldPosition;
-        vec3 lightDir = normalize(fragToLight);
-        float lightSquaredDist = dot(fragToLight, fragToLight);
-        lighting += pbr(lightDir, viewDir, normal, f0, albedo, vec3(light.positionAndIntensity.w / lightSquaredDist), roughness, metalness);
+        lighting += clusteredLightContribution(i * 32 + bitIndex, in_worldPosition, viewDir, normal, f0, albedo, roughness, metalness);
       }
     }
   }
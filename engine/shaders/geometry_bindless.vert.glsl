@@ -11,11 +11,17 @@ layout(location = 1) in vec3 in_normal;
 layout(location = 2) in vec2 in_uv;
 layout(location = 3) in vec2 in_lightmap_uv;
 layout(location = 4) in float in_alpha;
+layout(location = 5) in uint in_lightmap_styles;
+layout(location = 7) in float in_lightmap_bump_page_width;
+layout(location = 8) in vec4 in_tangent;
 
 layout(location = 0) out vec3 out_worldPosition;
 layout(location = 1) out vec2 out_uv;
 layout(location = 2) out vec2 out_lightmap_uv;
 layout(location = 3) out flat uint out_materialIndex;
+layout(location = 4) out flat uint out_lightmap_styles;
+layout(location = 6) out flat float out_lightmap_bump_page_width;
+layout(location = 7) out vec4 out_tangent;
 
 #include "frame_set.inc.glsl"
 
@@ -39,6 +45,9 @@ void main(void) {
   out_uv = in_uv;
   out_lightmap_uv = in_lightmap_uv;
   out_materialIndex = materialIndex;
+  out_lightmap_styles = in_lightmap_styles;
+  out_lightmap_bump_page_width = in_lightmap_bump_page_width;
+  out_tangent = vec4((model * vec4(in_tangent.xyz, 0)).xyz, in_tangent.w);
 
   mat4 jitterMat;
   jitterMat[0] = vec4(1.0, 0.0, 0.0, 0.0);
@@ -11,23 +11,53 @@ struct GPUMeshPart {
   uint meshFirstIndex;
   uint meshIndexCount;
   uint meshVertexOffset;
+  float lodMinScreenSize;
+  float lodMaxScreenSize;
 };
 
 struct GPUMaterial {
   vec4 albedoColor;
+  vec4 environmentMapTint;
+  vec4 emissiveFactor;
+  vec4 emissiveMaskChannels;
   float roughnessFactor;
   float metalnessFactor;
+  float occlusionStrength;
+  float alphaCutoff;
   uint albedoTextureIndex;
-  uint _padding; // 16 byte alignment because of the vec4 member
+  uint albedo2TextureIndex;
+  uint blendModulateTextureIndex;
+  uint normalTextureIndex;
+  uint ormTextureIndex;
+  uint emissiveTextureIndex;
+  uint normal2TextureIndex;
+  uint albedo3TextureIndex;
+  uint albedo4TextureIndex;
+  uint hasAlbedo2;
+  uint hasBlendModulate;
+  uint hasNormalMap;
+  uint hasOrmMap;
+  uint hasEmissiveMap;
+  uint hasNormal2;
+  uint hasMultiblend;
+  uint alphaMode;
+  uint _padding;
+  uint _padding1;
+  uint _padding2;
 };
 
 struct GPUDrawable {
   mat4 transform;
   mat4 oldTransform;
   uint meshIndex;
+  uint cubemapIndex;
+  uint hasAmbientCube;
+  uint vertexColorsOffset;
+  uint skinningOffset;
+  uint bonePaletteOffset;
+  uint isInSkyArea;
   uint _padding;
-  uint _padding1;
-  uint _padding2; // 16 byte alignment because of the mat4 members
+  vec4 ambientCube[6];
 };
 
 struct GPUBoundingBox {
@@ -50,6 +80,10 @@ struct GPUMesh {
 #define DRAW_CAPACITY 4096
 #define MATERIAL_CAPACITY 4096
 #define MESH_CAPACITY 4096
+#define CUBEMAP_CAPACITY 128
+#define NO_CUBEMAP 0xffffffff
+#define NO_VERTEX_COLORS 0xffffffff
+#define NO_SKINNING 0xffffffff
 
 struct GPUScene {
   uint partCount;
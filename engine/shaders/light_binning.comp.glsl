@@ -23,6 +23,7 @@ layout(std430, set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 1, std430) readonly
 layout(std430, set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 2, std430) readonly buffer setupBuffer {
   uint clusterCount;
   uint pointLightCount;
+  uint spotLightCount;
 };
 
 struct PointLight {
@@ -37,14 +38,27 @@ layout (std430, set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 4) buffer lightBit
   uint lightBitmasks[];
 };
 
+struct SpotLight {
+  vec3 position;
+  float radius;
+  vec3 direction;
+  float outerConeAngle;
+};
+layout(std430, set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 5, std430) readonly buffer spotLightsBuffer {
+  SpotLight spotLights[];
+};
+
 bool pointLightIntersectsCluster(PointLight light, Cluster cluster);
+bool spotLightIntersectsCluster(SpotLight light, Cluster cluster);
 
-shared vec3 viewSpacePointLights[64];
+// The bitmasks contain all point lights followed by all spot lights.
+shared vec3 viewSpacePositions[64];
+shared vec3 viewSpaceDirections[64];
 
 void main() {
   uint clusterIndex = gl_GlobalInvocationID.x;
 
-  uint lightCount = pointLightCount;
+  uint lightCount = pointLightCount + spotLightCount;
   uint lightOffset = 0;
   uint bitmaskCount = (lightCount + 31) / 32;
 
@@ -60,8 +74,14 @@ void main() {
     uint batchSize = min(gl_WorkGroupSize.x, lightCount - lightOffset);
     uint lightIndex = lightOffset + gl_LocalInvocationIndex;
     if (uint(gl_LocalInvocationIndex) < batchSize) {
-      PointLight light = pointLights[lightIndex];
-      viewSpacePointLights[gl_LocalInvocationIndex] = (camera.view * vec4(light.position, 1)).xyz;
+      if (lightIndex < pointLightCount) {
+        PointLight light = pointLights[lightIndex];
+        viewSpacePositions[gl_LocalInvocationIndex] = (camera.view * vec4(light.position, 1)).xyz;
+      } else {
+        SpotLight light = spotLights[lightIndex - pointLightCount];
+        viewSpacePositions[gl_LocalInvocationIndex] = (camera.view * vec4(light.position, 1)).xyz;
+        viewSpaceDirections[gl_LocalInvocationIndex] = normalize((camera.view * vec4(light.direction, 0)).xyz);
+      }
     }
 
     barrier();
@@ -72,9 +92,18 @@ void main() {
         uint bitmaskIndex = lightIndex / 32;
         uint bitIndex = lightIndex % 32;
         Cluster cluster = clusters[clusterIndex];
-        PointLight light = pointLights[lightIndex];
-        light.position = viewSpacePointLights[i];
-        if (pointLightIntersectsCluster(light, cluster)) {
+        bool intersects;
+        if (lightIndex < pointLightCount) {
+          PointLight light = pointLights[lightIndex];
+          light.position = viewSpacePositions[i];
+          intersects = pointLightIntersectsCluster(light, cluster);
+        } else {
+          SpotLight light = spotLights[lightIndex - pointLightCount];
+          light.position = viewSpacePositions[i];
+          light.direction = viewSpaceDirections[i];
+          intersects = spotLightIntersectsCluster(light, cluster);
+        }
+        if (intersects) {
           // debugPrintfEXT("Light %d visible in cluster %d.", lightIndex, clusterIndex);
           atomicOr(lightBitmasks[bitmaskCount * clusterIndex + bitmaskIndex], 1 << bitIndex);
         }
@@ -93,3 +122,22 @@ bool pointLightIntersectsCluster(PointLight light, Cluster cluster) {
   vec3 dist = closest - light.position;
   return dot(dist, dist) <= (light.radius * light.radius);
 }
+
+// tests the bounding sphere of the cluster against the cone
+// https://bartwronski.com/2017/04/13/cull-that-cone/
+// light position and direction have to be in view space
+bool spotLightIntersectsCluster(SpotLight light, Cluster cluster) {
+  if (!pointLightIntersectsCluster(PointLight(light.position, light.radius), cluster)) {
+    return false;
+  }
+
+  vec3 sphereCenter = (cluster.minPoint.xyz + cluster.maxPoint.xyz) * 0.5;
+  float sphereRadius = length(cluster.maxPoint.xyz - cluster.minPoint.xyz) * 0.5;
+  vec3 toCenter = sphereCenter - light.position;
+  float toCenterSquaredLength = dot(toCenter, toCenter);
+  float distanceAlongAxis = dot(toCenter, light.direction);
+  float distanceToCone = cos(light.outerConeAngle) * sqrt(max(toCenterSquaredLength - distanceAlongAxis * distanceAlongAxis, 0.0)) - distanceAlongAxis * sin(light.outerConeAngle);
+  bool outsideAngle = distanceToCone > sphereRadius;
+  bool behind = distanceAlongAxis < -sphereRadius;
+  return !(outsideAngle || behind);
+}
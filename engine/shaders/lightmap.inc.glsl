@@ -0,0 +1,79 @@
+#ifndef LIGHTMAP_INC_GLSL
+#define LIGHTMAP_INC_GLSL
+
+#include "frame_set.inc.glsl"
+
+#define LIGHTMAP_MAX_LAYERS 4
+#define LIGHT_STYLE_COUNT 64
+
+// The tangent space directions VRAD bakes the three bumped lightmaps for.
+const vec3 BUMP_BASIS[3] = vec3[](
+  vec3(0.81649658, 0.0, 0.57735027),
+  vec3(-0.40824829, 0.70710678, 0.57735027),
+  vec3(-0.40824829, -0.70710678, 0.57735027)
+);
+
+float lightStyleIntensity(uint style) {
+  style = min(style, LIGHT_STYLE_COUNT - 1);
+  return lightStyles[style / 4][style % 4];
+}
+
+// Transforms the shading normal into the tangent space of the bump basis.
+vec3 lightmapTangentSpaceNormal(vec3 normal, vec3 geometricNormal, vec4 tangent) {
+  vec3 bitangent = cross(geometricNormal, tangent.xyz) * tangent.w;
+  return vec3(dot(normal, tangent.xyz), dot(normal, bitangent), dot(normal, geometricNormal));
+}
+
+// Bumped lightmaps store the flat lightmap followed by the three directional ones next to each other.
+vec3 sampleLightmapLayer(sampler2DArray lightmap, vec2 uv, uint layer, float bumpPageWidth, vec3 tangentSpaceNormal) {
+  if (bumpPageWidth == 0.0) {
+    return texture(lightmap, vec3(uv, layer)).rgb;
+  }
+
+  vec3 weights = vec3(
+    clamp(dot(tangentSpaceNormal, BUMP_BASIS[0]), 0.0, 1.0),
+    clamp(dot(tangentSpaceNormal, BUMP_BASIS[1]), 0.0, 1.0),
+    clamp(dot(tangentSpaceNormal, BUMP_BASIS[2]), 0.0, 1.0)
+  );
+  weights *= weights;
+  vec3 lighting = texture(lightmap, vec3(uv + vec2(bumpPageWidth, 0), layer)).rgb * weights.x
+    + texture(lightmap, vec3(uv + vec2(bumpPageWidth * 2, 0), layer)).rgb * weights.y
+    + texture(lightmap, vec3(uv + vec2(bumpPageWidth * 3, 0), layer)).rgb * weights.z;
+  return lighting / max(weights.x + weights.y + weights.z, 0.0001);
+}
+
+// Every array layer of the lightmap belongs to one light style.
+// lightmapStyles contains the light style plus one for every layer, one byte per layer.
+vec3 sampleLightmap(sampler2DArray lightmap, vec2 uv, uint lightmapStyles, float bumpPageWidth, vec3 tangentSpaceNormal) {
+  if (lightmapStyles == 0) {
+    return sampleLightmapLayer(lightmap, uv, 0, bumpPageWidth, tangentSpaceNormal) * lightStyleIntensity(0);
+  }
+
+  vec3 lighting = vec3(0);
+  for (uint layer = 0; layer < LIGHTMAP_MAX_LAYERS; layer++) {
+    uint style = (lightmapStyles >> (layer * 8)) & 0xFF;
+    if (style == 0) {
+      break;
+    }
+    lighting += sampleLightmapLayer(lightmap, uv, layer, bumpPageWidth, tangentSpaceNormal) * lightStyleIntensity(style - 1);
+  }
+  return lighting;
+}
+
+// Props aren't covered by the lightmap, they get lit by the ambient cube around them.
+// The cube stores one color for each axis direction: +X, -X, +Y, -Y, +Z, -Z
+vec3 sampleAmbientCube(vec4 ambientCube[6], vec3 normal) {
+  vec3 weights = normal * normal;
+  vec3 lighting = weights.x * (normal.x >= 0.0 ? ambientCube[0].rgb : ambientCube[1].rgb)
+    + weights.y * (normal.y >= 0.0 ? ambientCube[2].rgb : ambientCube[3].rgb)
+    + weights.z * (normal.z >= 0.0 ? ambientCube[4].rgb : ambientCube[5].rgb);
+  return lighting * lightStyleIntensity(0);
+}
+
+// VRAD stores the baked lighting of static props per vertex in gamma space,
+// scaled down by the overbright factor of 2 so it fits into 8 bits.
+vec3 decodeVertexLighting(vec3 color) {
+  return pow(color * 2.0, vec3(2.2)) * lightStyleIntensity(0);
+}
+
+#endif
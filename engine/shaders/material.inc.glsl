@@ -0,0 +1,30 @@
+#ifndef MATERIAL_INC_GLSL
+#define MATERIAL_INC_GLSL
+
+#define ALPHA_MODE_OPAQUE 0
+#define ALPHA_MODE_MASK 1
+#define ALPHA_MODE_BLEND 2
+
+// Tangent space normal maps store the normal in [0, 1], the sign in the w component of the tangent flips the bitangent.
+// Meshes without tangents keep the vertex normal.
+vec3 applyNormalMap(vec3 normal, vec4 tangent, vec3 normalMapSample) {
+  vec3 orthogonalTangent = tangent.xyz - normal * dot(normal, tangent.xyz);
+  if (dot(orthogonalTangent, orthogonalTangent) < 0.000001) {
+    return normal;
+  }
+  orthogonalTangent = normalize(orthogonalTangent);
+  vec3 bitangent = cross(normal, orthogonalTangent) * (tangent.w < 0.0 ? -1.0 : 1.0);
+  vec3 tangentSpaceNormal = normalMapSample * 2.0 - 1.0;
+  return normalize(orthogonalTangent * tangentSpaceNormal.x + bitangent * tangentSpaceNormal.y + normal * tangentSpaceNormal.z);
+}
+
+// glTF style emissive textures hold the color. Source self illumination instead uses one channel
+// as a mask for the albedo, the mask channels select it.
+vec3 emissiveColor(vec3 emissiveFactor, vec4 emissiveSample, vec4 maskChannels, vec3 albedo) {
+  if (maskChannels == vec4(0.0)) {
+    return emissiveFactor * emissiveSample.rgb;
+  }
+  return emissiveFactor * albedo * dot(emissiveSample, maskChannels);
+}
+
+#endif
@@ -11,6 +11,14 @@ layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 0) writeonly uniform image2
 layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 1, r32ui) readonly uniform uimage2D primitiveIds;
 layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 2, rg16) readonly uniform image2D barycentrics;
 #include "frame_set.inc.glsl"
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 3, std430) readonly buffer skinningDataSSBO {
+  uint skinningData[];
+};
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 4, std430) readonly buffer bonePaletteSSBO {
+  mat4 bonePalette[];
+};
+#define SKINNING_DATA_ARRAY_NAME skinningData
+#define BONE_PALETTE_ARRAY_NAME bonePalette
 #include "vis_buf.inc.glsl"
 
 void main() {
@@ -52,4 +52,24 @@ vec3 fresnelSchlick(float cosTheta, vec3 f0) {
   return f0 + (1.0 - f0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
 }
 
+// Analytical approximation of the preintegrated environment BRDF by Karis
+// https://www.unrealengine.com/en-US/blog/physically-based-shading-on-mobile
+vec3 environmentBRDFApprox(vec3 f0, float roughness, float nDotV) {
+  const vec4 c0 = vec4(-1.0, -0.0275, -0.572, 0.022);
+  const vec4 c1 = vec4(1.0, 0.0425, 1.04, -0.04);
+  vec4 r = roughness * c0 + c1;
+  float a004 = min(r.x * r.x, exp2(-9.28 * nDotV)) * r.x + r.y;
+  vec2 ab = vec2(-1.04, 1.04) * a004 + r.zw;
+  return f0 * ab.x + ab.y;
+}
+
+// The tint of the environment map acts as the reflectance at normal incidence.
+vec3 sampleEnvironmentMap(samplerCube environmentMap, vec3 viewDir, vec3 normal, vec3 tint, float roughness) {
+  vec3 reflected = reflect(-viewDir, normal);
+  float lod = roughness * float(textureQueryLevels(environmentMap) - 1);
+  // The baked cubemaps use the Z up coordinate system of Source.
+  vec3 radiance = textureLod(environmentMap, reflected.xzy, lod).rgb;
+  return radiance * environmentBRDFApprox(tint, roughness, max(dot(normal, viewDir), 0.0));
+}
+
 #endif
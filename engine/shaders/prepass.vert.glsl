@@ -6,10 +6,12 @@
 
 layout(location = 0) in vec3 in_pos;
 layout(location = 1) in vec3 in_normal;
+layout(location = 2) in vec2 in_uv;
 
 layout(location = 0) out vec4 out_position;
 layout(location = 1) out vec3 out_normal;
 layout(location = 2) out vec4 out_oldPosition;
+layout(location = 3) out vec2 out_uv;
 
 layout(set = DESCRIPTOR_SET_FREQUENT, binding = 0) uniform CurrentLowFrequencyUbo {
     mat4 viewProj;
@@ -44,6 +46,7 @@ void main(void) {
     out_normal = normalize((normalMat * vec4(in_normal, 0.0)).xyz); // shouldnt be necessary
     out_position = transformedPos;
     out_oldPosition = transformedOldPos;
+    out_uv = in_uv;
 
     mat4 jitterMat;
     jitterMat[0] = vec4(1.0, 0.0, 0.0, 0.0);
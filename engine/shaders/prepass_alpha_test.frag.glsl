@@ -0,0 +1,34 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_GOOGLE_include_directive : enable
+
+#include "descriptor_sets.inc.glsl"
+
+layout(location = 0) in vec4 in_position;
+layout(location = 1) in vec3 in_normal;
+layout(location = 2) in vec4 in_oldPosition;
+layout(location = 3) in vec2 in_uv;
+
+layout(location = 0) out vec4 out_normal;
+layout(location = 1) out vec2 out_motion;
+
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 0) uniform sampler2D albedo;
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 1) uniform AlphaTestUBO {
+  vec4 albedoColor;
+  float alphaCutoff;
+};
+
+void main(void) {
+  // Alpha tested surfaces must not write depth where they are cut out, the geometry pass only draws what's in the depth buffer.
+  float alpha = albedoColor.a * texture(albedo, in_uv).a;
+  if (alpha < alphaCutoff) {
+    discard;
+  }
+
+  out_normal = vec4(in_normal, 0);
+
+  vec2 transformedPos = (in_position.xy / in_position.w) * 0.5;
+  vec2 transformedOldPos = (in_oldPosition.xy / in_oldPosition.w) * 0.5;
+  out_motion = transformedPos - transformedOldPos;
+  out_motion.y = -out_motion.y;
+}
@@ -28,11 +28,31 @@ layout (set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 5) readonly buffer lightBi
   uint lightBitmasks[];
 };
 
-layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 6) uniform sampler2D lightmap;
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 6) uniform sampler2DArray lightmap;
 layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 7) uniform sampler2D shadows;
 layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 8) uniform sampler2D ssao;
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 9) uniform sampler2D depth;
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 10) uniform sampler2D skybox;
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 11) uniform samplerCube cubemaps[CUBEMAP_CAPACITY];
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 12, std430) readonly buffer vertexColorsSSBO {
+  uint vertexColors[];
+};
+#define VERTEX_COLORS_ARRAY_NAME vertexColors
+// The vertex colors buffer is the vertex asset buffer, so it also holds the skinning streams.
+#define SKINNING_DATA_ARRAY_NAME vertexColors
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 15, std430) readonly buffer bonePaletteSSBO {
+  mat4 bonePalette[];
+};
+#define BONE_PALETTE_ARRAY_NAME bonePalette
+
+#include "shadow_map.inc.glsl"
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 13) uniform sampler2DArrayShadow shadowMap;
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 14, std140) uniform ShadowCascadesUBO {
+  ShadowCascades shadowCascades;
+};
 
 #include "frame_set.inc.glsl"
+#include "lightmap.inc.glsl"
 
 #ifdef DEBUG
 struct Cluster {
@@ -51,6 +71,8 @@ layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 6, std430) readonly buffer
 
 #include "vis_buf.inc.glsl"
 #include "clustered_shading.inc.glsl"
+#include "vertex_transition.inc.glsl"
+#include "material.inc.glsl"
 
 void main() {
   ivec2 texSize = imageSize(outputTexture);
@@ -60,12 +82,17 @@ void main() {
   vec2 texCoord = vec2((float(gl_GlobalInvocationID.x) + 0.5) / float(texSize.x), (float(gl_GlobalInvocationID.y) + 0.5) / float(texSize.y));
   ivec2 iTexCoord = ivec2(gl_GlobalInvocationID.xy);
 
+  // Nothing was drawn to this pixel, show the sky.
+  if (texelFetch(depth, iTexCoord, 0).r >= 1.0) {
+    imageStore(outputTexture, iTexCoord, texelFetch(skybox, iTexCoord, 0));
+    return;
+  }
+
   uint id = imageLoad(primitiveIds, iTexCoord).x;
   vec2 barycentrics = imageLoad(barycentrics, iTexCoord).xy;
   Vertex vertex = getVertex(id, barycentrics);
 
   vec3 viewPos = (camera.view * vec4(vertex.position, 1.0)).xyz;
-  vec3 normal = vertex.normal;
   vec2 uv = vertex.uv;
   vec2 albedoUV = uv;
 
@@ -80,9 +107,51 @@ void main() {
 
   GPUMaterial material = getMaterial(id);
 
+  vec4 multiblend = vec4(0.0);
+  if (material.hasMultiblend != 0) {
+    multiblend = normalizedMultiblendWeights(unpackUnorm4x8(vertex.multiblend));
+  }
+  bool isMultiblend = dot(multiblend, vec4(1.0)) > 0.0;
+
+  // Multiblend blends the normals with the weight of the second layer.
+  float blendFactor = 0.0;
+  if (isMultiblend) {
+    blendFactor = multiblend.y;
+  } else if (material.hasAlbedo2 != 0) {
+    blendFactor = vertex.alpha;
+    if (material.hasBlendModulate != 0) {
+      blendFactor = vertexTransitionBlendFactor(blendFactor, texture(sampler2D(albedo_global[material.blendModulateTextureIndex], albedoSampler), albedoUV).rg);
+    }
+  }
+
+  vec3 normal = vertex.normal;
+  if (material.hasNormalMap != 0) {
+    normal = applyNormalMap(vertex.normal, vertex.tangent, texture(sampler2D(albedo_global[material.normalTextureIndex], albedoSampler), uv).xyz);
+  }
+  if (material.hasNormal2 != 0) {
+    vec3 normal2 = applyNormalMap(vertex.normal, vertex.tangent, texture(sampler2D(albedo_global[material.normal2TextureIndex], albedoSampler), uv).xyz);
+    normal = normalize(mix(normal, normal2, blendFactor));
+  }
+
   float roughness = material.roughnessFactor;
   float metalness = material.metalnessFactor;
+  float occlusion = 1.0;
+  if (material.hasOrmMap != 0) {
+    vec3 orm = texture(sampler2D(albedo_global[material.ormTextureIndex], albedoSampler), uv).rgb;
+    occlusion = mix(1.0, orm.r, material.occlusionStrength);
+    roughness *= orm.g;
+    metalness *= orm.b;
+  }
   vec3 albedo = material.albedoColor.rgb * texture(sampler2D(albedo_global[material.albedoTextureIndex], albedoSampler), albedoUV).rgb;
+  if (isMultiblend) {
+    vec3 otherLayers = texture(sampler2D(albedo_global[material.albedo2TextureIndex], albedoSampler), albedoUV).rgb * multiblend.y
+      + texture(sampler2D(albedo_global[material.albedo3TextureIndex], albedoSampler), albedoUV).rgb * multiblend.z
+      + texture(sampler2D(albedo_global[material.albedo4TextureIndex], albedoSampler), albedoUV).rgb * multiblend.w;
+    albedo = albedo * multiblend.x + material.albedoColor.rgb * otherLayers;
+  } else if (material.hasAlbedo2 != 0) {
+    vec3 albedo2 = material.albedoColor.rgb * texture(sampler2D(albedo_global[material.albedo2TextureIndex], albedoSampler), albedoUV).rgb;
+    albedo = mix(albedo, albedo2, blendFactor);
+  }
 
   vec3 viewDir = normalize(camera.position.xyz - vertex.position.xyz);
   vec3 f0 = vec3(0.04);
@@ -90,19 +159,28 @@ void main() {
 
   vec3 lighting = vec3(0);
   lighting += vec3(0.3); // ambient
-  lighting += texture(lightmap, vertex.lightmapUv).xyz;
-  lighting *= texture(ssao, texCoord).rrr;
+  GPUDrawable drawable = getDrawable(id);
+  if (drawable.vertexColorsOffset != NO_VERTEX_COLORS) {
+    lighting += decodeVertexLighting(getVertexLighting(id, barycentrics, drawable.vertexColorsOffset));
+  } else if (drawable.hasAmbientCube != 0) {
+    lighting += sampleAmbientCube(drawable.ambientCube, normal);
+  } else {
+    vec3 lightmapNormal = lightmapTangentSpaceNormal(normal, vertex.normal, vertex.tangent);
+    lighting += sampleLightmap(lightmap, vertex.lightmapUv, vertex.lightmapStyles, vertex.lightmapBumpPageWidth, lightmapNormal);
+  }
+  lighting *= texture(ssao, texCoord).rrr * occlusion;
 
   for (uint i = 0; i < directionalLightCount; i++) {
     DirectionalLight light = directionalLights[i];
-    vec3 lightContribution = pbr(-light.directionAndIntensity.xyz, viewDir, normal, f0, albedo, vec3(light.directionAndIntensity.w), roughness, metalness);
+    vec3 lightContribution = pbr(-light.directionAndIntensity.xyz, viewDir, normal, f0, albedo, light.color.rgb * light.directionAndIntensity.w, roughness, metalness);
     if (i == 0) {
       lightContribution *= texture(shadows, texCoord).rrr;
+      lightContribution *= sampleShadowCascades(shadowMap, shadowCascades, vertex.position, vertex.normal, light.directionAndIntensity.xyz, viewPos.z);
     }
     lighting += lightContribution;
   }
 
-  uint lightBitmaskCount = (pointLightCount + 31) / 32;
+  uint lightBitmaskCount = (clusteredLightCount() + 31) / 32;
   uint bitmaskOffset = lightBitmaskCount * clusterIndex;
   for (uint i = 0; i < lightBitmaskCount; i++) {
     uint bitmaskIndex = bitmaskOffset + i;
@@ -118,14 +196,22 @@ void main() {
       bool lightActive = (bitmask & singleBitMask) == singleBitMask;
       bitmask &= ~singleBitMask;
       if (lightActive) {
-        PointLight light = pointLights[i * 32 + bitIndex];
-        vec3 fragToLight = light.positionAndIntensity.xyz - vertex.position;
-        vec3 lightDir = normalize(fragToLight);
-        float lightSquaredDist = dot(fragToLight, fragToLight);
-        lighting += pbr(lightDir, viewDir, normal, f0, albedo, vec3(light.positionAndIntensity.w / lightSquaredDist), roughness, metalness);
+        lighting += clusteredLightContribution(i * 32 + bitIndex, vertex.position, viewDir, normal, f0, albedo, roughness, metalness);
       }
     }
   }
 
-  imageStore(outputTexture, iTexCoord, vec4(lighting * albedo, 1));
+  vec3 reflection = vec3(0);
+  // Only materials with an environment map get a tint, its alpha marks that the material reflects the cubemaps.
+  if (drawable.cubemapIndex != NO_CUBEMAP && material.environmentMapTint.a != 0.0) {
+    reflection = sampleEnvironmentMap(cubemaps[nonuniformEXT(drawable.cubemapIndex)], viewDir, normal, material.environmentMapTint.rgb, roughness);
+  }
+
+  vec3 emissive = material.emissiveFactor.rgb;
+  if (material.hasEmissiveMap != 0) {
+    vec4 emissiveSample = texture(sampler2D(albedo_global[material.emissiveTextureIndex], albedoSampler), uv);
+    emissive = emissiveColor(material.emissiveFactor.rgb, emissiveSample, material.emissiveMaskChannels, albedo);
+  }
+
+  imageStore(outputTexture, iTexCoord, vec4(lighting * albedo + reflection + emissive, 1));
 }
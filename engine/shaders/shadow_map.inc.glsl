@@ -0,0 +1,44 @@
+#ifndef SHADOW_MAP_H
+#define SHADOW_MAP_H
+
+#define SHADOW_CASCADE_COUNT 4
+
+struct ShadowCascades {
+  mat4 viewProj[SHADOW_CASCADE_COUNT];
+  // View space depth at which each cascade ends.
+  vec4 splitDepths;
+  // World space size of a single shadow map texel in each cascade.
+  vec4 texelSizes;
+  uint cascadeCount;
+};
+
+// Returns 1.0 for fully lit surfaces. Cascade count is 0 if the shadow maps weren't rendered this frame.
+float sampleShadowCascades(sampler2DArrayShadow shadowMap, ShadowCascades cascades, vec3 worldPos, vec3 normal, vec3 lightDir, float viewDepth) {
+  uint cascade = 0;
+  while (cascade < cascades.cascadeCount && viewDepth > cascades.splitDepths[cascade]) {
+    cascade++;
+  }
+  if (cascade >= cascades.cascadeCount) {
+    return 1.0;
+  }
+
+  // Push the position along the normal to avoid acne on surfaces that are nearly parallel to the light.
+  float nDotL = clamp(dot(normal, -lightDir), 0.0, 1.0);
+  float normalOffset = cascades.texelSizes[cascade] * 1.5 * (1.0 - nDotL);
+  vec4 shadowPos = cascades.viewProj[cascade] * vec4(worldPos + normal * normalOffset, 1.0);
+  shadowPos.xyz /= shadowPos.w;
+  vec2 shadowUV = shadowPos.xy * 0.5 + 0.5;
+  float depth = shadowPos.z - 0.0005;
+
+  // 3x3 PCF on top of the bilinear comparison filter.
+  vec2 texelSize = 1.0 / vec2(textureSize(shadowMap, 0).xy);
+  float shadow = 0.0;
+  for (int y = -1; y <= 1; y++) {
+    for (int x = -1; x <= 1; x++) {
+      shadow += texture(shadowMap, vec4(shadowUV + vec2(x, y) * texelSize, float(cascade), depth));
+    }
+  }
+  return shadow / 9.0;
+}
+
+#endif
@@ -0,0 +1,20 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_GOOGLE_include_directive : enable
+
+#include "descriptor_sets.inc.glsl"
+#include "skinning.inc.glsl"
+
+layout(location = 0) in vec3 in_pos;
+
+layout(set = DESCRIPTOR_SET_FREQUENT, binding = 0) uniform CascadeUbo {
+  mat4 lightViewProj;
+};
+
+layout(push_constant) uniform VeryHighFrequencyUbo {
+  mat4 model;
+};
+
+void main(void) {
+  gl_Position = lightViewProj * model * skinningMatrix() * vec4(in_pos, 1);
+}
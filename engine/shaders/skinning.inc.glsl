@@ -0,0 +1,28 @@
+#ifndef SKINNING_H
+#define SKINNING_H
+
+#include "descriptor_sets.inc.glsl"
+
+struct SkinningVertex {
+  vec4 boneIndices;
+  vec4 boneWeights;
+};
+
+layout(std430, set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 4) readonly buffer SkinningVerticesSSBO {
+  SkinningVertex skinningVertices[];
+};
+layout(std430, set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 5) readonly buffer BonePaletteSSBO {
+  mat4 bonePalette[];
+};
+
+// Index values are local to the mesh, so the vertex index also addresses the skinning stream.
+mat4 skinningMatrix() {
+  SkinningVertex skinning = skinningVertices[gl_VertexIndex];
+  mat4 skinningMat = mat4(0.0);
+  for (uint i = 0; i < 4; i++) {
+    skinningMat += bonePalette[uint(skinning.boneIndices[i])] * skinning.boneWeights[i];
+  }
+  return skinningMat;
+}
+
+#endif
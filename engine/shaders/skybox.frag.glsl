@@ -0,0 +1,15 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_GOOGLE_include_directive : enable
+
+#include "descriptor_sets.inc.glsl"
+
+layout(location = 0) in vec4 in_direction;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 0) uniform samplerCube skybox;
+
+void main(void) {
+  out_color = vec4(texture(skybox, in_direction.xyz / in_direction.w).rgb, 1.0);
+}
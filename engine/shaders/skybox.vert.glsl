@@ -0,0 +1,15 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+layout(location = 0) out vec4 out_direction;
+
+layout(push_constant) uniform VeryHighFrequencyUbo {
+  mat4 invViewProj;
+};
+
+void main(void) {
+  // A single triangle that covers the whole screen.
+  vec2 position = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2) * 2.0 - 1.0;
+  out_direction = invViewProj * vec4(position, 1.0, 1.0);
+  gl_Position = vec4(position, 1.0, 1.0);
+}
@@ -0,0 +1,19 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_GOOGLE_include_directive : enable
+
+#include "descriptor_sets.inc.glsl"
+
+layout(location = 0) in vec2 in_uv;
+layout(location = 1) in vec2 in_lightmap_uv;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 0) uniform sampler2D albedo;
+layout(set = DESCRIPTOR_SET_FREQUENT, binding = 0) uniform sampler2DArray lightmap;
+
+void main(void) {
+  // The 3D skybox only uses the first lightmap layer, light styles and bumped lighting are ignored.
+  vec3 lighting = vec3(0.3) + texture(lightmap, vec3(in_lightmap_uv, 0)).rgb;
+  out_color = vec4(texture(albedo, in_uv).rgb * lighting, 1);
+}
@@ -0,0 +1,20 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+layout(location = 0) in vec3 in_pos;
+layout(location = 2) in vec2 in_uv;
+layout(location = 3) in vec2 in_lightmap_uv;
+
+layout(location = 0) out vec2 out_uv;
+layout(location = 1) out vec2 out_lightmap_uv;
+
+layout(push_constant) uniform VeryHighFrequencyUbo {
+  mat4 viewProj;
+  mat4 model;
+};
+
+void main(void) {
+  out_uv = in_uv;
+  out_lightmap_uv = in_lightmap_uv;
+  gl_Position = viewProj * model * vec4(in_pos, 1);
+}
@@ -0,0 +1,13 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_GOOGLE_include_directive : enable
+
+#include "descriptor_sets.inc.glsl"
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 0) uniform sampler2D skybox;
+
+void main(void) {
+  out_color = vec4(texelFetch(skybox, ivec2(gl_FragCoord.xy), 0).rgb, 1);
+}
@@ -0,0 +1,9 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+void main(void) {
+  // A single triangle that covers the whole screen on the far plane,
+  // so the depth test only lets it through where there is no geometry.
+  vec2 position = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2) * 2.0 - 1.0;
+  gl_Position = vec4(position, 1.0, 1.0);
+}
@@ -10,21 +10,57 @@
 layout(location = 0) in vec3 in_worldPosition;
 layout(location = 1) in vec2 in_uv;
 layout(location = 2) in vec2 in_lightmap_uv;
+layout(location = 3) in flat uint in_lightmap_styles;
+layout(location = 5) in flat float in_lightmap_bump_page_width;
+layout(location = 6) in vec4 in_tangent;
+layout(location = 7) in vec3 in_vertex_lighting;
+layout(location = 8) in float in_alpha;
+layout(location = 9) in vec3 in_normal;
+layout(location = 10) in vec4 in_multiblend;
 
 layout(location = 0) out vec4 out_color;
 
 #include "frame_set.inc.glsl"
+#include "lightmap.inc.glsl"
 
 layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 0) uniform sampler2D albedo;
 layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 1) uniform sampler2D roughness_map;
 layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 2) uniform sampler2D metalness_map;
 layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 3) uniform MaterialBuffer {
   vec4 albedo_color;
+  vec4 environment_map_tint;
+  vec4 emissive_factor;
+  vec4 emissive_mask_channels;
   float roughness_factor;
   float metalness_factor;
+  float occlusion_strength;
+  float alpha_cutoff;
   uint albedoTextureIndex;
+  uint hasAlbedo2;
+  uint hasBlendModulate;
+  uint hasNormalMap;
+  uint hasOrmMap;
+  uint hasEmissiveMap;
+  uint hasNormal2;
+  uint hasMultiblend;
+  uint alphaMode;
 } material;
-layout(set = DESCRIPTOR_SET_FREQUENT, binding = 0) uniform sampler2D lightmap;
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 6) uniform samplerCube environmentMap;
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 7) uniform DrawableBuffer {
+  vec4 ambientCube[6];
+  uint hasAmbientCube;
+  uint hasVertexColors;
+} drawableInfo;
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 9) uniform sampler2D albedo2;
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 10) uniform sampler2D blendModulate;
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 11) uniform sampler2D normalMap;
+// Occlusion in red, roughness in green and metalness in blue.
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 12) uniform sampler2D ormMap;
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 13) uniform sampler2D emissiveMap;
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 14) uniform sampler2D normalMap2;
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 15) uniform sampler2D albedo3;
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 16) uniform sampler2D albedo4;
+layout(set = DESCRIPTOR_SET_FREQUENT, binding = 0) uniform sampler2DArray lightmap;
 layout(set = DESCRIPTOR_SET_FREQUENT, binding = 1) uniform sampler albedoSampler;
 layout(set = DESCRIPTOR_SET_FREQUENT, binding = 2) uniform sampler2D shadows;
 
@@ -39,6 +75,12 @@ layout (std430, set = DESCRIPTOR_SET_FREQUENT, binding = 3) readonly buffer ligh
 
 layout(set = DESCRIPTOR_SET_FREQUENT, binding = 4) uniform sampler2D ssao;
 
+#include "shadow_map.inc.glsl"
+layout(set = DESCRIPTOR_SET_FREQUENT, binding = 6) uniform sampler2DArrayShadow shadowMap;
+layout(set = DESCRIPTOR_SET_FREQUENT, binding = 7, std140) uniform ShadowCascadesUBO {
+  ShadowCascades shadowCascades;
+};
+
 #ifdef DEBUG
 layout(std430, set = DESCRIPTOR_SET_FREQUENT, binding = 5, std430) readonly buffer clusterAABB {
   Cluster clusters[];
@@ -51,12 +93,44 @@ layout(std430, set = DESCRIPTOR_SET_FREQUENT, binding = 5, std430) readonly buff
 #include "pbr.inc.glsl"
 
 #include "clustered_shading.inc.glsl"
+#include "vertex_transition.inc.glsl"
+#include "material.inc.glsl"
 
 void main(void) {
   vec2 uv = in_uv;
   vec2 albedoUV = unjitterTextureUv(in_uv, jitterPoint * vec2(rtSize));
 
-  vec3 normal = reconstructNormalFS(gl_FragCoord.xy / vec2(rtSize), gl_FragCoord.z, camera.invView * camera.invProj);
+  vec4 albedoSample = material.albedo_color * texture(albedo, uv);
+  if (material.alphaMode == ALPHA_MODE_MASK && albedoSample.a < material.alpha_cutoff) {
+    discard;
+  }
+
+  vec4 multiblend = vec4(0.0);
+  if (material.hasMultiblend != 0) {
+    multiblend = normalizedMultiblendWeights(in_multiblend);
+  }
+  bool isMultiblend = dot(multiblend, vec4(1.0)) > 0.0;
+
+  // Multiblend blends the normals with the weight of the second layer.
+  float blendFactor = 0.0;
+  if (isMultiblend) {
+    blendFactor = multiblend.y;
+  } else if (material.hasAlbedo2 != 0) {
+    blendFactor = in_alpha;
+    if (material.hasBlendModulate != 0) {
+      blendFactor = vertexTransitionBlendFactor(blendFactor, texture(blendModulate, uv).rg);
+    }
+  }
+
+  vec3 vertexNormal = normalize(in_normal);
+  vec3 normal = vertexNormal;
+  if (material.hasNormalMap != 0) {
+    normal = applyNormalMap(vertexNormal, in_tangent, texture(normalMap, uv).xyz);
+  }
+  if (material.hasNormal2 != 0) {
+    vec3 normal2 = applyNormalMap(vertexNormal, in_tangent, texture(normalMap2, uv).xyz);
+    normal = normalize(mix(normal, normal2, blendFactor));
+  }
 
   uint clusterIndex = getClusterIndexWithDepth(gl_FragCoord.xy, gl_FragCoord.z, camera.zNear, camera.zFar, clusterCount, rtSize, clusterZScale, clusterZBias);
   uint maxClusterCount = clusterCount.x * clusterCount.y * clusterCount.z;
@@ -71,7 +145,20 @@ void main(void) {
 
   float roughness = material.roughness_factor * texture(roughness_map, uv).r;
   float metalness = material.metalness_factor * texture(metalness_map, uv).r;
-  vec3 albedo = material.albedo_color.rgb * texture(albedo, uv).rgb;
+  float occlusion = 1.0;
+  if (material.hasOrmMap != 0) {
+    vec3 orm = texture(ormMap, uv).rgb;
+    occlusion = mix(1.0, orm.r, material.occlusion_strength);
+    roughness *= orm.g;
+    metalness *= orm.b;
+  }
+  vec3 albedo = albedoSample.rgb;
+  if (isMultiblend) {
+    vec3 otherLayers = texture(albedo2, uv).rgb * multiblend.y + texture(albedo3, uv).rgb * multiblend.z + texture(albedo4, uv).rgb * multiblend.w;
+    albedo = albedo * multiblend.x + material.albedo_color.rgb * otherLayers;
+  } else if (material.hasAlbedo2 != 0) {
+    albedo = mix(albedo, material.albedo_color.rgb * texture(albedo2, uv).rgb, blendFactor);
+  }
 
   vec3 viewDir = normalize(camera.position.xyz - in_worldPosition.xyz);
   vec3 f0 = vec3(0.04);
@@ -80,19 +167,35 @@ void main(void) {
   vec2 fullscreenTexCoord = vec2(gl_FragCoord.x / rtSize.x, gl_FragCoord.y / rtSize.y);
   vec3 lighting = vec3(0);
   lighting += vec3(0.3); // ambient
-  lighting += texture(lightmap, in_lightmap_uv).xyz;
-  lighting *= texture(ssao, fullscreenTexCoord).rrr;
+  if (drawableInfo.hasVertexColors != 0) {
+    lighting += decodeVertexLighting(in_vertex_lighting);
+  } else if (drawableInfo.hasAmbientCube != 0) {
+    lighting += sampleAmbientCube(drawableInfo.ambientCube, normal);
+  } else {
+    vec3 lightmapNormal = lightmapTangentSpaceNormal(normal, vertexNormal, in_tangent);
+    lighting += sampleLightmap(lightmap, in_lightmap_uv, in_lightmap_styles, in_lightmap_bump_page_width, lightmapNormal);
+  }
+  // SSAO and the ray traced shadows are computed for the opaque surface behind translucent ones.
+  bool isTranslucent = material.alphaMode == ALPHA_MODE_BLEND;
+  if (!isTranslucent) {
+    lighting *= texture(ssao, fullscreenTexCoord).rrr;
+  }
+  lighting *= occlusion;
 
+  float viewDepth = (camera.view * vec4(in_worldPosition, 1.0)).z;
   for (uint i = 0; i < directionalLightCount; i++) {
     DirectionalLight light = directionalLights[i];
-    vec3 lightContribution = pbr(-light.directionAndIntensity.xyz, viewDir, normal, f0, albedo, vec3(light.directionAndIntensity.w), roughness, metalness);
+    vec3 lightContribution = pbr(-light.directionAndIntensity.xyz, viewDir, normal, f0, albedo, light.color.rgb * light.directionAndIntensity.w, roughness, metalness);
     if (i == 0) {
-      lightContribution *= texture(shadows, fullscreenTexCoord).rrr;
+      if (!isTranslucent) {
+        lightContribution *= texture(shadows, fullscreenTexCoord).rrr;
+      }
+      lightContribution *= sampleShadowCascades(shadowMap, shadowCascades, in_worldPosition, vertexNormal, light.directionAndIntensity.xyz, viewDepth);
     }
     lighting += lightContribution;
   }
 
-  uint lightBitmaskCount = (pointLightCount + 31) / 32;
+  uint lightBitmaskCount = (clusteredLightCount() + 31) / 32;
   uint bitmaskOffset = lightBitmaskCount * clusterIndex;
   for (uint i = 0; i < lightBitmaskCount; i++) {
     uint bitmaskIndex = bitmaskOffset + i;
@@ -108,13 +211,21 @@ void main(void) {
       bool lightActive = (bitmask & singleBitMask) == singleBitMask;
       bitmask &= ~singleBitMask;
       if (lightActive) {
-        PointLight light = pointLights[i * 32 + bitIndex];
-        vec3 fragToLight = light.positionAndIntensity.xyz - in_worldPosition;
-        vec3 lightDir = normalize(fragToLight);
-        float lightSquaredDist = dot(fragToLight, fragToLight);
-        lighting += pbr(lightDir, viewDir, normal, f0, albedo, vec3(light.positionAndIntensity.w / lightSquaredDist), roughness, metalness);
+        lighting += clusteredLightContribution(i * 32 + bitIndex, in_worldPosition, viewDir, normal, f0, albedo, roughness, metalness);
       }
     }
   }
-  out_color = vec4(lighting * albedo, 1);
+  // Only materials with an environment map get a tint, its alpha marks that the material reflects the cubemaps.
+  vec3 reflection = vec3(0);
+  if (material.environment_map_tint.a != 0.0) {
+    reflection = sampleEnvironmentMap(environmentMap, viewDir, normal, material.environment_map_tint.rgb, roughness);
+  }
+  vec3 emissive = vec3(0);
+  if (material.hasEmissiveMap != 0) {
+    emissive = emissiveColor(material.emissive_factor.rgb, texture(emissiveMap, uv), material.emissive_mask_channels, albedo);
+  } else {
+    emissive = material.emissive_factor.rgb;
+  }
+  float alpha = isTranslucent ? albedoSample.a : 1.0;
+  out_color = vec4(lighting * albedo + reflection + emissive, alpha);
 }
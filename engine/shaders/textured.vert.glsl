@@ -10,13 +10,29 @@ layout(location = 1) in vec3 in_normal;
 layout(location = 2) in vec2 in_uv;
 layout(location = 3) in vec2 in_lightmap_uv;
 layout(location = 4) in float in_alpha;
+layout(location = 5) in uint in_lightmap_styles;
+layout(location = 6) in uint in_multiblend;
+layout(location = 7) in float in_lightmap_bump_page_width;
+layout(location = 8) in vec4 in_tangent;
 
 layout(location = 0) out vec3 out_worldPosition;
 layout(location = 1) out vec2 out_uv;
 layout(location = 2) out vec2 out_lightmap_uv;
+layout(location = 3) out flat uint out_lightmap_styles;
+layout(location = 5) out flat float out_lightmap_bump_page_width;
+layout(location = 6) out vec4 out_tangent;
+layout(location = 7) out vec3 out_vertex_lighting;
+layout(location = 8) out float out_alpha;
+layout(location = 9) out vec3 out_normal;
+layout(location = 10) out vec4 out_multiblend;
 
 #include "frame_set.inc.glsl"
 
+// Index values are local to the mesh, so the vertex index also addresses the baked lighting.
+layout(std430, set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 8) readonly buffer VertexColorsSSBO {
+  uint vertexColors[];
+};
+
 layout(push_constant) uniform VeryHighFrequencyUbo {
   mat4 model;
 };
@@ -32,6 +48,13 @@ void main(void) {
   out_worldPosition = (model * pos).xyz;
   out_uv = in_uv;
   out_lightmap_uv = in_lightmap_uv;
+  out_lightmap_styles = in_lightmap_styles;
+  out_lightmap_bump_page_width = in_lightmap_bump_page_width;
+  out_tangent = vec4((model * vec4(in_tangent.xyz, 0)).xyz, in_tangent.w);
+  out_vertex_lighting = unpackUnorm4x8(vertexColors[gl_VertexIndex]).rgb;
+  out_alpha = in_alpha;
+  out_normal = (model * vec4(in_normal, 0)).xyz;
+  out_multiblend = unpackUnorm4x8(in_multiblend);
 
   mat4 jitterMat;
   jitterMat[0] = vec4(1.0, 0.0, 0.0, 0.0);
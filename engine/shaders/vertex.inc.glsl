@@ -3,10 +3,15 @@
 
 struct Vertex {
   vec3 position;
+  uint multiblend;
   vec3 normal;
   vec2 uv;
   vec2 lightmapUv;
   float alpha;
+  uint lightmapStyles;
+  uint _padding;
+  float lightmapBumpPageWidth;
+  vec4 tangent;
 };
 
 #endif
@@ -0,0 +1,19 @@
+#ifndef VERTEX_TRANSITION_INC_GLSL
+#define VERTEX_TRANSITION_INC_GLSL
+
+// WorldVertexTransition materials blend from the first to the second layer with the vertex alpha.
+// The blend modulate texture narrows the transition: green is its center and red its half width.
+float vertexTransitionBlendFactor(float alpha, vec2 blendModulate) {
+  float minBlend = clamp(blendModulate.g - blendModulate.r, 0.0, 1.0);
+  float maxBlend = clamp(blendModulate.g + blendModulate.r, 0.0, 1.0);
+  return smoothstep(minBlend, maxBlend, alpha);
+}
+
+// Multiblend materials mix four layers with the per vertex weights of displacements.
+// Returns the weights scaled to a sum of one, all zero if the vertex doesn't have any.
+vec4 normalizedMultiblendWeights(vec4 weights) {
+  float sum = dot(weights, vec4(1.0));
+  return sum > 0.0 ? weights / sum : vec4(0.0);
+}
+
+#endif
@@ -25,6 +25,28 @@ uint getPrimitiveIndex(uint id) {
   return id & 0xffff;
 }
 
+#ifdef BONE_PALETTE_ARRAY_NAME
+// The skinning stream is read as raw 32 bit words, each vertex consists of 4 bone indices followed by 4 weights.
+// Index values are local to the mesh, so the vertex index also addresses the skinning stream.
+mat4 getSkinningMatrix(GPUDrawable drawable, uint vertexIndex) {
+  uint base = drawable.skinningOffset + vertexIndex * 8;
+  mat4 skinningMat = mat4(0.0);
+  for (uint i = 0; i < 4; i++) {
+    uint boneIndex = uint(uintBitsToFloat(SKINNING_DATA_ARRAY_NAME[base + i]));
+    float boneWeight = uintBitsToFloat(SKINNING_DATA_ARRAY_NAME[base + 4 + i]);
+    skinningMat += BONE_PALETTE_ARRAY_NAME[drawable.bonePaletteOffset + boneIndex] * boneWeight;
+  }
+  return skinningMat;
+}
+
+Vertex skinVertex(Vertex vertex, mat4 skinningMat) {
+  vertex.position = (skinningMat * vec4(vertex.position, 1)).xyz;
+  vertex.normal = (skinningMat * vec4(vertex.normal, 0)).xyz;
+  vertex.tangent.xyz = (skinningMat * vec4(vertex.tangent.xyz, 0)).xyz;
+  return vertex;
+}
+#endif
+
 Vertex interpolateVertex(vec2 barycentrics, Vertex vertices[3]) {
   vec3 bary = vec3(barycentrics.x, barycentrics.y, 1 - barycentrics.x - barycentrics.y);
   Vertex interpolated;
@@ -33,6 +55,10 @@ Vertex interpolateVertex(vec2 barycentrics, Vertex vertices[3]) {
   interpolated.uv = vertices[0].uv * bary.x + vertices[1].uv * bary.y + vertices[2].uv * bary.z;
   interpolated.lightmapUv = vertices[0].lightmapUv * bary.x + vertices[1].lightmapUv * bary.y + vertices[2].lightmapUv * bary.z;
   interpolated.alpha = vertices[0].alpha * bary.x + vertices[1].alpha * bary.y + vertices[2].alpha * bary.z;
+  interpolated.multiblend = packUnorm4x8(unpackUnorm4x8(vertices[0].multiblend) * bary.x + unpackUnorm4x8(vertices[1].multiblend) * bary.y + unpackUnorm4x8(vertices[2].multiblend) * bary.z);
+  interpolated.lightmapStyles = vertices[0].lightmapStyles;
+  interpolated.lightmapBumpPageWidth = vertices[0].lightmapBumpPageWidth;
+  interpolated.tangent = vec4(vertices[0].tangent.xyz * bary.x + vertices[1].tangent.xyz * bary.y + vertices[2].tangent.xyz * bary.z, vertices[0].tangent.w);
   return interpolated;
 }
 
@@ -52,6 +78,15 @@ Vertex[3] getVertices(uint id) {
   readVertices[0] = VERTICES_ARRAY_NAME[part.meshVertexOffset + index0];
   readVertices[1] = VERTICES_ARRAY_NAME[part.meshVertexOffset + index1];
   readVertices[2] = VERTICES_ARRAY_NAME[part.meshVertexOffset + index2];
+
+#ifdef BONE_PALETTE_ARRAY_NAME
+  GPUDrawable drawable = GPU_SCENE_NAME.drawables[draw.drawableIndex];
+  if (drawable.skinningOffset != NO_SKINNING) {
+    readVertices[0] = skinVertex(readVertices[0], getSkinningMatrix(drawable, index0));
+    readVertices[1] = skinVertex(readVertices[1], getSkinningMatrix(drawable, index1));
+    readVertices[2] = skinVertex(readVertices[2], getSkinningMatrix(drawable, index2));
+  }
+#endif
   return readVertices;
 }
 
@@ -68,9 +103,31 @@ Vertex getVertex(uint id, vec2 barycentrics) {
   Vertex vertex = interpolateVertex(barycentrics, vertices);
   vertex.position = (drawable.transform * vec4(vertex.position, 1)).xyz;
   vertex.normal = normalize((transposedTransform * vec4(vertex.normal, 0)).xyz);
+  vertex.tangent.xyz = (drawable.transform * vec4(vertex.tangent.xyz, 0)).xyz;
   return vertex;
 }
 
+#ifdef VERTEX_COLORS_ARRAY_NAME
+// The baked lighting is addressed with the same mesh local index values as the vertices.
+vec3 getVertexLighting(uint id, vec2 barycentrics, uint vertexColorsOffset) {
+  GPUDraw draw = GPU_SCENE_NAME.draws[getDrawIndex(id)];
+  GPUMeshPart part = GPU_SCENE_NAME.parts[draw.partIndex];
+
+  uint firstIndex = part.meshFirstIndex + getPrimitiveIndex(id) * 3;
+  vec3 color0 = unpackUnorm4x8(VERTEX_COLORS_ARRAY_NAME[vertexColorsOffset + INDICES_ARRAY_NAME[firstIndex]]).rgb;
+  vec3 color1 = unpackUnorm4x8(VERTEX_COLORS_ARRAY_NAME[vertexColorsOffset + INDICES_ARRAY_NAME[firstIndex + 1]]).rgb;
+  vec3 color2 = unpackUnorm4x8(VERTEX_COLORS_ARRAY_NAME[vertexColorsOffset + INDICES_ARRAY_NAME[firstIndex + 2]]).rgb;
+  vec3 bary = vec3(barycentrics.x, barycentrics.y, 1 - barycentrics.x - barycentrics.y);
+  return color0 * bary.x + color1 * bary.y + color2 * bary.z;
+}
+#endif
+
+GPUDrawable getDrawable(uint id) {
+  uint drawIndex = getDrawIndex(id);
+  GPUDraw draw = scene.draws[drawIndex];
+  return scene.drawables[draw.drawableIndex];
+}
+
 GPUMaterial getMaterial(uint id) {
   uint drawIndex = getDrawIndex(id);
   GPUDraw draw = scene.draws[drawIndex];
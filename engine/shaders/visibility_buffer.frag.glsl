@@ -2,17 +2,34 @@
 #extension GL_ARB_separate_shader_objects : enable
 #extension GL_GOOGLE_include_directive : enable
 #extension GL_NV_fragment_shader_barycentric : require
+#extension GL_EXT_nonuniform_qualifier : require
 #ifdef DEBUG
 #extension GL_EXT_debug_printf : enable
 #endif
 
 layout(location = 0) in flat uint in_drawIndex;
 layout(location = 1) in flat uint in_firstIndex;
+layout(location = 2) in vec2 in_uv;
+layout(location = 3) in flat uint in_materialIndex;
 
 layout(location = 0) out uint out_primitiveId;
 layout(location = 1) out vec2 out_barycentrics;
 
+#include "frame_set.inc.glsl"
+#include "material.inc.glsl"
+
+layout(set = DESCRIPTOR_SET_TEXTURES_BINDLESS, binding = 0) uniform texture2D albedo_global[];
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 0) uniform sampler albedoSampler;
+
 void main(void) {
+  GPUMaterial material = scene.materials[in_materialIndex];
+  if (material.alphaMode == ALPHA_MODE_MASK) {
+    float alpha = material.albedoColor.a * texture(sampler2D(albedo_global[nonuniformEXT(material.albedoTextureIndex)], albedoSampler), in_uv).a;
+    if (alpha < material.alphaCutoff) {
+      discard;
+    }
+  }
+
   out_primitiveId = uint(((in_drawIndex & 0xffff) << 16) | (gl_PrimitiveID & 0xffff));
   out_barycentrics = gl_BaryCoordNV.xy;
 }
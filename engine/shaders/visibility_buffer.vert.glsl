@@ -3,12 +3,25 @@
 #extension GL_GOOGLE_include_directive : enable
 
 layout(location = 0) in vec3 in_pos;
+layout(location = 2) in vec2 in_uv;
 
 layout(location = 0) out flat uint out_drawIndex;
 layout(location = 1) out flat uint out_firstIndex;
+layout(location = 2) out vec2 out_uv;
+layout(location = 3) out flat uint out_materialIndex;
 
 #include "frame_set.inc.glsl"
 
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 1, std430) readonly buffer skinningDataSSBO {
+  uint skinningData[];
+};
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 2, std430) readonly buffer bonePaletteSSBO {
+  mat4 bonePalette[];
+};
+#define SKINNING_DATA_ARRAY_NAME skinningData
+#define BONE_PALETTE_ARRAY_NAME bonePalette
+#include "vis_buf.inc.glsl"
+
 invariant gl_Position;
 
 void main(void) {
@@ -21,9 +34,14 @@ void main(void) {
   uint drawableIndex = draw.drawableIndex;
   GPUDrawable drawable = scene.drawables[drawableIndex];
   mat4 model = drawable.transform;
+  if (drawable.skinningOffset != NO_SKINNING) {
+    pos = getSkinningMatrix(drawable, gl_VertexIndex - part.meshVertexOffset) * pos;
+  }
 
   out_drawIndex = drawIndex;
   out_firstIndex = part.meshFirstIndex;
+  out_uv = in_uv;
+  out_materialIndex = materialIndex;
 
   mat4 mvp = camera.viewProj * model;
   mat4 mv = camera.view * model;
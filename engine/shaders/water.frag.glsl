@@ -0,0 +1,57 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_GOOGLE_include_directive : enable
+
+#include "descriptor_sets.inc.glsl"
+#include "util.inc.glsl"
+
+layout(location = 0) in vec3 in_world_pos;
+layout(location = 1) in vec2 in_uv;
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 0) uniform sampler2D normalMap;
+layout(set = DESCRIPTOR_SET_FREQUENT, binding = 0) uniform sampler2D refractionColor;
+layout(set = DESCRIPTOR_SET_FREQUENT, binding = 1) uniform sampler2D refractionDepth;
+layout(set = DESCRIPTOR_SET_FREQUENT, binding = 2) uniform samplerCube skybox;
+
+#include "frame_set.inc.glsl"
+#include "water.inc.glsl"
+
+// How far the normal map shifts the refracted image in screen space.
+#define REFRACTION_DISTORTION 0.02
+
+void main(void) {
+  // The normal map is in tangent space with Z pointing up, water surfaces are always horizontal.
+  vec3 tangentNormal = hasNormalMap != 0 ? texture(normalMap, in_uv).xyz * 2.0 - 1.0 : vec3(0.0, 0.0, 1.0);
+  vec3 normal = normalize(vec3(tangentNormal.x, tangentNormal.z, tangentNormal.y));
+
+  vec2 screenUv = gl_FragCoord.xy / vec2(textureSize(refractionColor, 0));
+  vec2 refractionUv = screenUv + tangentNormal.xy * REFRACTION_DISTORTION;
+  // Geometry in front of the water must not get refracted into it.
+  float depth = textureLod(refractionDepth, refractionUv, 0).x;
+  if (depth < gl_FragCoord.z) {
+    refractionUv = screenUv;
+    depth = textureLod(refractionDepth, refractionUv, 0).x;
+  }
+  vec3 refraction = textureLod(refractionColor, refractionUv, 0).rgb * refractTint.rgb;
+
+  // Seen from below, the geometry above the surface isn't in the water.
+  if (isUnderwater != 0) {
+    out_color = vec4(refraction, 1.0);
+    return;
+  }
+
+  vec3 behindWater = worldSpacePosition(refractionUv, depth, camera.invViewProj);
+  refraction = mix(refraction, fogColor.rgb, waterFogFactor(distance(in_world_pos, behindWater)));
+
+  // Reflections are either composited on top by the screen space reflection pass or fall back to the skybox.
+  vec3 color = refraction;
+  if (reflectSkybox != 0) {
+    vec3 viewDir = normalize(in_world_pos - camera.position.xyz);
+    float fresnel = 0.02 + 0.98 * pow(1.0 - max(dot(-viewDir, normal), 0.0), 5.0);
+    vec3 reflection = texture(skybox, reflect(viewDir, normal)).rgb * reflectTint.rgb;
+    color = mix(refraction, reflection, fresnel);
+  }
+  out_color = vec4(color, 1.0);
+}
@@ -0,0 +1,19 @@
+#ifndef WATER_H
+#define WATER_H
+
+layout(set = DESCRIPTOR_SET_VERY_FREQUENT, binding = 1, std140) uniform WaterUBO {
+  vec4 fogColor;
+  vec4 reflectTint;
+  vec4 refractTint;
+  float fogStart;
+  float fogEnd;
+  uint hasNormalMap;
+  uint reflectSkybox;
+  uint isUnderwater;
+};
+
+float waterFogFactor(float distance) {
+  return clamp((distance - fogStart) / max(fogEnd - fogStart, 0.0001), 0.0, 1.0);
+}
+
+#endif
@@ -0,0 +1,31 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_GOOGLE_include_directive : enable
+
+#include "descriptor_sets.inc.glsl"
+#include "camera.inc.glsl"
+
+layout(location = 0) in vec3 in_pos;
+layout(location = 2) in vec2 in_uv;
+
+layout(location = 0) out vec3 out_world_pos;
+layout(location = 1) out vec2 out_uv;
+
+#include "frame_set.inc.glsl"
+
+layout(push_constant) uniform VeryHighFrequencyUbo {
+  mat4 model;
+};
+
+void main(void) {
+  vec4 world_pos = model * vec4(in_pos, 1);
+  out_world_pos = world_pos.xyz;
+  out_uv = in_uv;
+
+  mat4 jitterMat;
+  jitterMat[0] = vec4(1.0, 0.0, 0.0, 0.0);
+  jitterMat[1] = vec4(0.0, 1.0, 0.0, 0.0);
+  jitterMat[2] = vec4(0.0, 0.0, 1.0, 0.0);
+  jitterMat[3] = vec4(jitterPoint.x, jitterPoint.y, 0.0, 1.0);
+  gl_Position = jitterMat * swapchainTransform * camera.viewProj * world_pos;
+}
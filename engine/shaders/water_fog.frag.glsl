@@ -0,0 +1,21 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_GOOGLE_include_directive : enable
+
+#include "descriptor_sets.inc.glsl"
+#include "util.inc.glsl"
+
+layout(location = 0) out vec4 out_color;
+
+layout(set = DESCRIPTOR_SET_FREQUENT, binding = 1) uniform sampler2D refractionDepth;
+
+#include "frame_set.inc.glsl"
+#include "water.inc.glsl"
+
+void main(void) {
+  // Fogs the whole screen while the camera is under water.
+  vec2 screenUv = gl_FragCoord.xy / vec2(textureSize(refractionDepth, 0));
+  float depth = textureLod(refractionDepth, screenUv, 0).x;
+  vec3 position = worldSpacePosition(screenUv, depth, camera.invViewProj);
+  out_color = vec4(fogColor.rgb, waterFogFactor(distance(position, camera.position.xyz)));
+}
@@ -0,0 +1,112 @@
+use std::sync::Arc;
+
+use legion::systems::Builder;
+use nalgebra::UnitQuaternion;
+use sourcerenderer_core::{Matrix4, Platform};
+
+use crate::asset::{AssetManager, BonePose, Skeleton, AnimationSequence};
+use crate::game::TickDuration;
+use crate::renderer::StaticRenderableComponent;
+
+pub fn install<P: Platform>(systems: &mut Builder, asset_manager: &Arc<AssetManager<P>>) {
+  systems.add_system(animate_models_system::<P>(asset_manager.clone()));
+}
+
+/// Plays a sequence of the skinned model that belongs to the StaticRenderableComponent of the same entity.
+#[derive(Clone, Debug)]
+pub struct AnimatedModelComponent {
+  pub sequence: String,
+  pub playback_rate: f32,
+  /// Progress through the sequence from 0 to 1.
+  pub cycle: f32,
+  bone_palette: Box<[Matrix4]>
+}
+
+impl AnimatedModelComponent {
+  pub fn new(sequence: &str) -> Self {
+    Self {
+      sequence: sequence.to_string(),
+      playback_rate: 1f32,
+      cycle: 0f32,
+      bone_palette: Box::new([])
+    }
+  }
+
+  /// The skinning matrices of the current pose. Empty until the skeleton is loaded.
+  pub fn bone_palette(&self) -> &[Matrix4] {
+    &self.bone_palette
+  }
+}
+
+#[system(for_each)]
+fn animate_models<P: Platform>(
+  #[state] asset_manager: &Arc<AssetManager<P>>,
+  #[resource] tick_duration: &TickDuration,
+  renderable: &StaticRenderableComponent,
+  animated_model: &mut AnimatedModelComponent) {
+  let skeleton = asset_manager.get_skeleton(&renderable.model_path);
+  if skeleton.is_none() {
+    return;
+  }
+  let skeleton = skeleton.unwrap();
+  let sequence = skeleton.sequences.iter()
+    .find(|sequence| sequence.name.eq_ignore_ascii_case(&animated_model.sequence))
+    .or_else(|| skeleton.sequences.first());
+  let sequence = match sequence {
+    Some(sequence) if !sequence.frames.is_empty() => sequence,
+    _ => return
+  };
+
+  let duration = (sequence.frames.len() - 1).max(1) as f32 / sequence.fps.max(1f32);
+  animated_model.cycle += tick_duration.0.as_secs_f32() * animated_model.playback_rate / duration;
+  animated_model.cycle = if sequence.looping {
+    animated_model.cycle.rem_euclid(1f32)
+  } else {
+    animated_model.cycle.clamp(0f32, 1f32)
+  };
+
+  let poses = sample_sequence(sequence, animated_model.cycle);
+  animated_model.bone_palette = build_bone_palette(&skeleton, &poses);
+}
+
+fn sample_sequence(sequence: &AnimationSequence, cycle: f32) -> Vec<BonePose> {
+  let frame = cycle * (sequence.frames.len() - 1) as f32;
+  let first_frame = (frame.floor() as usize).min(sequence.frames.len() - 1);
+  let second_frame = (first_frame + 1).min(sequence.frames.len() - 1);
+  let t = frame - first_frame as f32;
+
+  sequence.frames[first_frame].iter()
+    .zip(sequence.frames[second_frame].iter())
+    .map(|(first, second)| {
+      // Interpolate along the shorter arc.
+      let second_rotation = if first.rotation.coords.dot(&second.rotation.coords) < 0f32 {
+        UnitQuaternion::new_unchecked(-second.rotation.into_inner())
+      } else {
+        second.rotation
+      };
+      BonePose {
+        position: first.position.lerp(&second.position, t),
+        rotation: first.rotation.nlerp(&second_rotation, t)
+      }
+    })
+    .collect()
+}
+
+fn build_bone_palette(skeleton: &Skeleton, poses: &[BonePose]) -> Box<[Matrix4]> {
+  // Parent bones always come before their children.
+  let mut bone_transforms = Vec::<Matrix4>::with_capacity(skeleton.bones.len());
+  for (index, bone) in skeleton.bones.iter().enumerate() {
+    let pose = poses.get(index).unwrap_or(&bone.bind_pose);
+    let local_transform = Matrix4::new_translation(&pose.position) * pose.rotation.to_homogeneous();
+    let transform = match bone.parent {
+      Some(parent) if parent < bone_transforms.len() => bone_transforms[parent] * local_transform,
+      _ => local_transform
+    };
+    bone_transforms.push(transform);
+  }
+
+  bone_transforms.iter()
+    .zip(skeleton.bones.iter())
+    .map(|(transform, bone)| transform * bone.inverse_bind_matrix)
+    .collect()
+}
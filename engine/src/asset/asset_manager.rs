@@ -3,7 +3,8 @@ use std::sync::{Arc, RwLock, Mutex, Condvar};
 use std::collections::{HashMap, VecDeque};
 use log::{trace, warn, error};
 use sourcerenderer_core::platform::Platform;
-use sourcerenderer_core::{Vec4, graphics};
+use sourcerenderer_core::{Matrix4, Vec3, Vec4, graphics};
+use nalgebra::UnitQuaternion;
 use sourcerenderer_core::graphics::TextureInfo;
 use std::hash::Hash;
 
@@ -39,6 +40,8 @@ pub enum AssetType {
   Chunk,
   Container,
   Shader,
+  Skeleton,
+  VertexColors,
 }
 
 #[derive(Clone)]
@@ -47,6 +50,16 @@ pub struct MeshRange {
   pub count: u32
 }
 
+/// A contiguous range of mesh parts that get drawn together at a certain distance.
+#[derive(Clone)]
+pub struct MeshLOD {
+  pub first_part: u32,
+  pub part_count: u32,
+  /// The LOD gets used while a sphere with a diameter of one world unit covers fewer pixels vertically.
+  /// Infinite for the most detailed LOD.
+  pub max_screen_size: f32
+}
+
 pub struct Texture {
   pub info: TextureInfo,
   pub data: Box<[Box<[u8]>]>
@@ -56,8 +69,12 @@ pub struct Mesh {
   pub indices: Option<Box<[u8]>>,
   pub vertices: Box<[u8]>,
   pub parts: Box<[MeshRange]>,
+  /// Levels of detail ordered from the most to the least detailed one. Empty if all parts get drawn at every distance.
+  pub lods: Box<[MeshLOD]>,
   pub bounding_box: Option<BoundingBox>,
   pub vertex_count: u32,
+  /// Bone indices and weights for every vertex, laid out like renderer::SkinningVertex.
+  pub skinning: Option<Box<[u8]>>,
 }
 
 #[derive(Clone)]
@@ -66,6 +83,33 @@ pub struct Model {
   pub material_paths: Vec<String>
 }
 
+#[derive(Clone, Debug)]
+pub struct BonePose {
+  pub position: Vec3,
+  pub rotation: UnitQuaternion<f32>
+}
+
+pub struct SkeletonBone {
+  pub parent: Option<usize>,
+  pub bind_pose: BonePose,
+  pub inverse_bind_matrix: Matrix4
+}
+
+pub struct AnimationSequence {
+  pub name: String,
+  pub fps: f32,
+  pub looping: bool,
+  /// One pose per bone for every frame.
+  pub frames: Box<[Box<[BonePose]>]>
+}
+
+/// The bones and animations of a skinned model.
+/// Skeletons are kept on the CPU so game systems can evaluate animations.
+pub struct Skeleton {
+  pub bones: Box<[SkeletonBone]>,
+  pub sequences: Box<[AnimationSequence]>
+}
+
 #[derive(Clone)]
 pub struct Material {
   pub shader_name: String,
@@ -164,6 +208,9 @@ pub enum Asset {
   Sound,
   Material(Material),
   Shader(Box<[u8]>),
+  Skeleton(Arc<Skeleton>),
+  /// Baked per-vertex lighting of a single model instance, one RGBA8 color for every vertex of the mesh.
+  VertexColors(Box<[u32]>),
 }
 
 pub struct AssetManager<P: Platform> {
@@ -175,6 +222,7 @@ pub struct AssetManager<P: Platform> {
   renderer_receiver: Receiver<LoadedAsset>,
   cond_var: Arc<Condvar>,
   is_running: AtomicBool,
+  skeletons: RwLock<HashMap<String, Arc<Skeleton>>>,
 }
 
 struct AssetManagerInner {
@@ -206,6 +254,7 @@ impl<P: Platform> AssetManager<P> {
       renderer_receiver,
       cond_var,
       is_running: AtomicBool::new(true),
+      skeletons: RwLock::new(HashMap::new()),
     });
 
     let thread_count = 4;
@@ -227,8 +276,10 @@ impl<P: Platform> AssetManager<P> {
       vertices: vertex_buffer_data,
       indices: if !index_buffer_data.is_empty() { Some(index_buffer_data) } else { None },
       parts,
+      lods: Box::new([]),
       bounding_box,
-      vertex_count
+      vertex_count,
+      skinning: None
     };
     self.add_asset(path, Asset::Mesh(mesh), AssetLoadPriority::Normal);
   }
@@ -287,6 +338,8 @@ impl<P: Platform> AssetManager<P> {
       Asset::Model(_) => AssetType::Model,
       Asset::Sound => AssetType::Sound,
       Asset::Shader(_) => AssetType::Shader,
+      Asset::Skeleton(_) => AssetType::Skeleton,
+      Asset::VertexColors(_) => AssetType::VertexColors,
     };
 
     {
@@ -335,6 +388,17 @@ impl<P: Platform> AssetManager<P> {
           priority
         }).unwrap();
       }
+      Asset::VertexColors(colors) => {
+        self.renderer_sender.send(LoadedAsset {
+          asset: Asset::VertexColors(colors),
+          path: path.to_owned(),
+          priority
+        }).unwrap();
+      }
+      Asset::Skeleton(skeleton) => {
+        let mut skeletons = self.skeletons.write().unwrap();
+        skeletons.insert(path.to_owned(), skeleton);
+      }
       _ => unimplemented!()
     }
   }
@@ -494,6 +558,11 @@ impl<P: Platform> AssetManager<P> {
     }
   }
 
+  pub fn get_skeleton(&self, path: &str) -> Option<Arc<Skeleton>> {
+    let skeletons = self.skeletons.read().unwrap();
+    skeletons.get(path).cloned()
+  }
+
   pub fn has_open_renderer_assets(&self) -> bool {
     !self.renderer_receiver.is_empty()
   }
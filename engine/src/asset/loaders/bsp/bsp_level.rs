@@ -1,25 +1,31 @@
 
+use half::f16;
 use nalgebra::Rotation3;
-use sourcerenderer_core::{Platform, Quaternion, Vec4, graphics::{Format, SampleCount, TextureUsage, TextureDimension}};
-use crate::{asset::{AssetLoader, AssetType, Asset, Model, AssetManager, Mesh}, renderer::Lightmap};
+use sourcerenderer_core::{Matrix4, Platform, Quaternion, Vec4, graphics::{Format, SampleCount, TextureUsage, TextureDimension}};
+use crate::{asset::{AssetLoader, AssetType, Asset, Model, AssetManager, Mesh}, renderer::{Lightmap, LightStyles, Skybox, Decals, WaterSurface, WaterSurfaces, WaterVolume, WaterVolumes, Cubemaps, CubemapIndex, AmbientLightCube, VertexLighting, ClusterVisibility, ClusterVisibilityNode, PotentiallyVisibleSet, VisibilityClusters}};
 use core::slice;
 use std::{path::Path, u8};
 use std::sync::Arc;
-use sourcerenderer_bsp::{DispInfo, DispVert, Face, Map, SurfaceFlags};
-use std::collections::HashMap;
+use sourcerenderer_bsp::{CompressedLightCube, DispInfo, Face, Map, Node, SurfaceFlags, TextureInfo as BspTextureInfo};
+use sourcerenderer_bsp::game_lumps::StaticPropFlags;
+use std::collections::{BTreeMap, HashMap};
 use sourcerenderer_core::{Vec3, Vec2};
 use crate::asset::asset_manager::{AssetLoaderResult, AssetFile, MeshRange, AssetLoaderProgress, AssetLoadPriority, Texture};
 use sourcerenderer_core::graphics::TextureInfo;
-use legion::{World, WorldOptions};
+use legion::{Entity, World, WorldOptions};
 use crate::renderer::StaticRenderableComponent;
 use crate::Transform;
-use regex::Regex;
-use crate::asset::loaders::csgo_loader::CSGO_MAP_NAME_PATTERN;
-use std::io::BufReader;
+use std::io::{BufReader, Read};
 use std::collections::HashSet;
 use crate::asset::loaders::PakFileContainer;
 use super::BspLumps;
 use crate::asset::loaders::bsp::{lightmap_packer::LightmapPacker};
+use super::entities::{BspEntitySpawner, default_entity_spawners, parse_vec3, spawn_key_values};
+use super::skybox::load_skybox;
+use super::static_prop_lighting::load_static_prop_lighting;
+use super::decals::{build_decals, info_decal_projections, overlay_projections};
+use super::detail_props::build_detail_props;
+use super::displacements::{build_displacement_grids, DisplacementGrid};
 use crate::math::BoundingBox;
 
 // REFERENCE
@@ -34,46 +40,66 @@ use crate::math::BoundingBox;
 // VBSP IS CURSED
 
 pub struct BspLevelLoader {
-  map_name_regex: Regex
+  entity_spawners: Vec<Box<dyn BspEntitySpawner>>
 }
 
-const SCALING_FACTOR: f32 = 0.0236f32;
+/// Converts Source units to meters.
+pub(crate) const SCALING_FACTOR: f32 = 0.0236f32;
 
 impl BspLevelLoader {
   pub fn new() -> Self {
     Self {
-      map_name_regex: Regex::new(CSGO_MAP_NAME_PATTERN).unwrap()
+      entity_spawners: default_entity_spawners()
     }
   }
 
+  /// Spawners that get added later take precedence over the default ones.
+  pub fn add_entity_spawner(&mut self, spawner: Box<dyn BspEntitySpawner>) {
+    self.entity_spawners.insert(0, spawner);
+  }
+
+  fn spawn_entities(&self, lumps: &BspLumps, world: &mut World, assets_to_load: &mut Vec<(String, AssetType)>) {
+    for entity in &lumps.entities.entities {
+      if entity.get("classname").is_none() {
+        continue;
+      }
+      let class = entity.class_name();
+      let spawner = self.entity_spawners.iter().find(|spawner| spawner.matches(&class));
+      if let Some(spawner) = spawner {
+        spawner.spawn(entity, world, assets_to_load);
+      } else {
+        spawn_key_values(entity, world);
+      }
+    }
+  }
+
+  /// Returns the position of the face in the lightmap atlas or None if the face doesn't get drawn.
   fn build_face(&self,
                 temp: &BspLumps,
                 face: &Face,
                 brush_vertices: &mut Vec<super::Vertex>,
                 brush_indices: &mut HashMap<String, Vec<u32>>,
-                lightmap_packer: &mut LightmapPacker) {
+                lightmap_packer: &mut LightmapPacker<[f16; 4]>) -> Option<(u32, u32)> {
     let tex_info = &temp.tex_info[face.texture_info as usize];
     let ignore_flags = SurfaceFlags::NODRAW | SurfaceFlags::LIGHT | SurfaceFlags::SKY | SurfaceFlags::SKY2D | SurfaceFlags::TRIGGER;
     if tex_info.flags.intersects(ignore_flags) {
-      return;
+      return None;
     }
 
     let tex_data = &temp.tex_data[tex_info.texture_data as usize];
-    let tex_offset = &temp.tex_data_string_table[tex_data.name_string_table_id as usize];
-    let tex_name = temp.tex_string_data.get_string_at(tex_offset.0 as u32).to_str().unwrap().replace('\\', "/").to_lowercase();
+    let tex_name = Self::material_name(temp, tex_info);
 
-    let (lightmap_offset_x, lightmap_offset_y) = if face.light_offset >= 0 {
-      debug_assert!(face.light_offset % 4 == 0);
-      let offset = (face.light_offset / 4) as usize;
-      debug_assert!(face.lightmap_texture_size_in_luxels[0] > 0);
-      debug_assert!(face.lightmap_texture_size_in_luxels[1] > 0);
-      lightmap_packer.add_samples((face.lightmap_texture_size_in_luxels[0] + 1) as u32, (face.lightmap_texture_size_in_luxels[1] + 1) as u32, &temp.lighting[offset..])
+    let is_bumped = tex_info.flags.contains(SurfaceFlags::BUMPLIGHT);
+    let lightmap_offset = if face.light_offset >= 0 {
+      Self::pack_face_lightmap(temp, face, is_bumped, lightmap_packer)
     } else {
       (0, 0)
     };
+    let (lightmap_styles, lightmap_bump_page_width) = Self::lightmap_layout(face, is_bumped, lightmap_packer);
 
     let material_brush_indices = &mut brush_indices.entry(tex_name).or_default();
     let plane = &temp.planes[face.plane_index as usize];
+    let tangent = Self::calculate_tangent(&plane.normal, &tex_info.texture_vecs_s, &tex_info.texture_vecs_t);
     let root_vertex = brush_vertices.len() as u32;
 
     for surf_edge_index in face.first_edge ..face.first_edge  + face.edges_count as i32 {
@@ -86,19 +112,7 @@ impl BspLevelLoader {
       let mut uv = Self::calculate_uv(&position, &tex_info.texture_vecs_s, &tex_info.texture_vecs_t);
       uv.x /= tex_data.width as f32;
       uv.y /= tex_data.height as f32;
-      let mut lightmap_uv = Vec2::default();
-      if face.light_offset >= 0 {
-        lightmap_uv = Self::calculate_uv(&position, &tex_info.lightmap_vecs_s, &tex_info.lightmap_vecs_t);
-        lightmap_uv -= Vec2::new(face.lightmap_texture_mins_in_luxels[0] as f32, face.lightmap_texture_mins_in_luxels[1] as f32);
-        lightmap_uv += Vec2::new(0.5f32, 0.5f32);
-        debug_assert!(lightmap_uv.x >= 0f32);
-        debug_assert!(lightmap_uv.x < (face.lightmap_texture_size_in_luxels[0] + 1) as f32);
-        debug_assert!(lightmap_uv.y >= 0f32);
-        debug_assert!(lightmap_uv.y < (face.lightmap_texture_size_in_luxels[1] + 1) as f32);
-        lightmap_uv += Vec2::new(lightmap_offset_x as f32, lightmap_offset_y as f32);
-        lightmap_uv.x /= lightmap_packer.texture_width() as f32;
-        lightmap_uv.y /= lightmap_packer.texture_height() as f32;
-      }
+      let lightmap_uv = Self::calculate_lightmap_uv(face, tex_info, &position, lightmap_offset, lightmap_packer);
 
       brush_vertices.push(super::Vertex {
         position: BspLevelLoader::fixup_position(&position),
@@ -106,6 +120,9 @@ impl BspLevelLoader {
         uv,
         lightmap_uv,
         alpha: 1f32,
+        lightmap_styles,
+        lightmap_bump_page_width,
+        tangent,
         ..Default::default()
       });
 
@@ -116,14 +133,16 @@ impl BspLevelLoader {
       material_brush_indices.push(brush_vertices.len() as u32 - 1);
       material_brush_indices.push(root_vertex);
     }
+    Some(lightmap_offset)
   }
 
   fn build_displacement_face(&self,
                              temp: &BspLumps,
                              disp_info: &DispInfo,
+                             grid: &DisplacementGrid,
                              brush_vertices: &mut Vec<super::Vertex>,
                              brush_indices: &mut HashMap<String, Vec<u32>>,
-                             lightmap_packer: &mut LightmapPacker) {
+                             lightmap_packer: &mut LightmapPacker<[f16; 4]>) {
     let face = &temp.faces[disp_info.map_face as usize];
     let tex_info = &temp.tex_info[face.texture_info as usize];
     let ignore_flags = SurfaceFlags::NODRAW | SurfaceFlags::LIGHT | SurfaceFlags::SKY | SurfaceFlags::SKY2D | SurfaceFlags::TRIGGER;
@@ -132,114 +151,203 @@ impl BspLevelLoader {
     }
 
     let tex_data = &temp.tex_data[tex_info.texture_data as usize];
-    let tex_offset = &temp.tex_data_string_table[tex_data.name_string_table_id as usize];
-    let tex_name = temp.tex_string_data.get_string_at(tex_offset.0 as u32).to_str().unwrap().replace('\\', "/").to_lowercase();
-    let plane = &temp.planes[face.plane_index as usize];
+    let tex_name = Self::material_name(temp, tex_info);
     let material_brush_indices = &mut brush_indices.entry(tex_name).or_default();
 
+    let is_bumped = tex_info.flags.contains(SurfaceFlags::BUMPLIGHT);
     let (lightmap_offset_x, lightmap_offset_y) = if face.light_offset >= 0 {
-      debug_assert!(face.light_offset % 4 == 0);
-      let offset = (face.light_offset / 4) as usize;
-      debug_assert!(face.lightmap_texture_size_in_luxels[0] > 0);
-      debug_assert!(face.lightmap_texture_size_in_luxels[1] > 0);
-      lightmap_packer.add_samples((face.lightmap_texture_size_in_luxels[0] + 1) as u32, (face.lightmap_texture_size_in_luxels[1] + 1) as u32, &temp.lighting[offset..])
+      Self::pack_face_lightmap(temp, face, is_bumped, lightmap_packer)
     } else {
       (0, 0)
     };
+    let (lightmap_styles, lightmap_bump_page_width) = Self::lightmap_layout(face, is_bumped, lightmap_packer);
 
-    let mut corners = [Vec3::default(); 4];
-    let mut corners_uv = [Vec2::default(); 4];
-    let mut first_corner = 0;
-    let mut first_corner_dist_squared = f32::MAX;
-    for surf_edge_index in face.first_edge..face.first_edge + face.edges_count as i32 {
-
-      let edge_index = temp.surface_edges[surf_edge_index as usize].index;
-      let edge = temp.edges[edge_index.abs() as usize];
-      let vert_index = edge.vertex_index[if edge_index >= 0 { 0 } else { 1 }];
-      let position = temp.vertices[vert_index as usize].position;
-      let index = (surf_edge_index - face.first_edge) as usize;
-      corners[index] = position;
-      corners_uv[index] = Self::calculate_uv(&position, &tex_info.texture_vecs_s, &tex_info.texture_vecs_t);
-      corners_uv[index].x /= tex_data.width as f32;
-      corners_uv[index].y /= tex_data.height as f32;
-
-      let dist_squared = (disp_info.start_position - position).magnitude_squared();
-      if dist_squared < first_corner_dist_squared {
-        first_corner = surf_edge_index - face.first_edge;
-        first_corner_dist_squared = dist_squared;
-      }
-    }
-
-    let subdivisions = 1 << disp_info.power;
-    let size = subdivisions + 1;
-    for y in 0..subdivisions {
-      let old_len = brush_vertices.len() as u32;
+    let size = grid.size;
+    let subdivisions = (size - 1) as f32;
+    let root_vertex = brush_vertices.len() as u32;
+    for y in 0..size {
       for x in 0..size {
-        let position = Self::calculate_disp_vert(disp_info.disp_vert_start, x, y, size, &corners, first_corner, &temp.disp_verts);
+        let index = x + y * size;
+        let position = grid.positions[index];
+        let normal = grid.normals[index];
         let mut uv = Self::calculate_uv(&position, &tex_info.texture_vecs_s, &tex_info.texture_vecs_t);
         uv.x /= tex_data.width as f32;
         uv.y /= tex_data.height as f32;
         brush_vertices.push(super::Vertex {
           position: Self::fixup_position(&position),
-          normal: Self::fixup_normal(&plane.normal),
+          multiblend: grid.multiblend[index],
+          normal: Self::fixup_normal(&normal),
           uv,
           lightmap_uv: Vec2::new(
-            ((x as f32 / subdivisions as f32) * face.lightmap_texture_size_in_luxels[0] as f32 + 0.5f32 + lightmap_offset_x as f32) / (lightmap_packer.texture_width() as f32),
-            ((y as f32 / subdivisions as f32) * face.lightmap_texture_size_in_luxels[1] as f32 + 0.5f32 + lightmap_offset_y as f32) / (lightmap_packer.texture_height() as f32)
+            ((x as f32 / subdivisions) * face.lightmap_texture_size_in_luxels[0] as f32 + 0.5f32 + lightmap_offset_x as f32) / (lightmap_packer.texture_width() as f32),
+            ((y as f32 / subdivisions) * face.lightmap_texture_size_in_luxels[1] as f32 + 0.5f32 + lightmap_offset_y as f32) / (lightmap_packer.texture_height() as f32)
           ),
-          alpha: &temp.disp_verts[(disp_info.disp_vert_start + x + y * size) as usize].alpha * 255f32,
+          // The alpha of displacement vertices goes from 0 to 255.
+          alpha: temp.disp_verts[disp_info.disp_vert_start as usize + index].alpha / 255f32,
+          lightmap_styles,
+          lightmap_bump_page_width,
+          tangent: Self::calculate_tangent(&normal, &tex_info.texture_vecs_s, &tex_info.texture_vecs_t),
           ..Default::default()
         });
+      }
+    }
 
-        if brush_vertices.len() - old_len as usize >= 3 {
-          material_brush_indices.push(brush_vertices.len() as u32 - 2);
-          material_brush_indices.push(brush_vertices.len() as u32 - 1);
-          material_brush_indices.push(brush_vertices.len() as u32 - 3);
-        }
+    for y in 0..size - 1 {
+      for x in 0..size - 1 {
+        let lower_left = root_vertex + (x + y * size) as u32;
+        let lower_right = lower_left + 1;
+        let upper_left = lower_left + size as u32;
+        let upper_right = upper_left + 1;
+        material_brush_indices.extend_from_slice(&[upper_left, lower_right, lower_left, upper_right, lower_right, upper_left]);
+      }
+    }
+  }
 
-        let position = Self::calculate_disp_vert(disp_info.disp_vert_start, x, y + 1, size, &corners, first_corner, &temp.disp_verts);
-        let mut uv = Self::calculate_uv(&position, &tex_info.texture_vecs_s, &tex_info.texture_vecs_t);
-        uv.x /= tex_data.width as f32;
-        uv.y /= tex_data.height as f32;
-        brush_vertices.push(super::Vertex {
-          position: Self::fixup_position(&position),
-          normal: Self::fixup_normal(&plane.normal),
-          uv,
-          lightmap_uv: Vec2::new(
-            ((x as f32 / subdivisions as f32) * face.lightmap_texture_size_in_luxels[0] as f32 + 0.5f32 + lightmap_offset_x as f32) / (lightmap_packer.texture_width() as f32),
-            (((y + 1) as f32 / subdivisions as f32) * face.lightmap_texture_size_in_luxels[1] as f32 + 0.5f32 + lightmap_offset_y as f32) / (lightmap_packer.texture_height() as f32)
-          ),
-          alpha: &temp.disp_verts[(disp_info.disp_vert_start + x + (y + 1) * size) as usize].alpha * 255f32,
-          ..Default::default()
+  /// Copies every light style of the face into its own layer of the lightmap atlas.
+  /// Bumped faces store the flat lightmap followed by one lightmap per bump basis direction,
+  /// those get packed next to each other.
+  fn pack_face_lightmap(temp: &BspLumps, face: &Face, is_bumped: bool, lightmap_packer: &mut LightmapPacker<[f16; 4]>) -> (u32, u32) {
+    debug_assert!(face.light_offset % 4 == 0);
+    debug_assert!(face.lightmap_texture_size_in_luxels[0] > 0);
+    debug_assert!(face.lightmap_texture_size_in_luxels[1] > 0);
+    let width = (face.lightmap_texture_size_in_luxels[0] + 1) as u32;
+    let height = (face.lightmap_texture_size_in_luxels[1] + 1) as u32;
+    let pages = if is_bumped { 4 } else { 1 };
+    let position = lightmap_packer.allocate(width * pages, height);
+
+    let page_size = (width * height) as usize;
+    let offset = (face.light_offset / 4) as usize;
+    for layer in 0..Self::light_style_count(face) {
+      for page in 0..pages {
+        let samples = &temp.lighting[offset + (layer * pages as usize + page as usize) * page_size..];
+        lightmap_packer.write_texels(layer as u32, (position.0 + page * width, position.1), width, height, |i| {
+          let [r, g, b] = samples[i].color.to_rgb_f32();
+          [f16::from_f32(r), f16::from_f32(g), f16::from_f32(b), f16::ONE]
         });
+      }
+    }
+    position
+  }
 
-        if brush_vertices.len() - old_len as usize >= 3 {
-          material_brush_indices.push(brush_vertices.len() as u32 - 1);
-          material_brush_indices.push(brush_vertices.len() as u32 - 2);
-          material_brush_indices.push(brush_vertices.len() as u32 - 3);
-        }
+  /// Merges the water faces of a brush model into a single mesh with one part per material.
+  fn build_water(&self,
+                 temp: &BspLumps,
+                 faces: &[usize],
+                 face_lightmaps: &mut HashMap<usize, (u32, u32)>,
+                 lightmap_packer: &mut LightmapPacker<[f16; 4]>) -> Option<(Mesh, Vec<String>)> {
+    let mut vertices = Vec::<super::Vertex>::new();
+    let mut per_material_indices = HashMap::<String, Vec<u32>>::new();
+    for face_index in faces.iter().copied() {
+      if let Some(lightmap_offset) = self.build_face(temp, &temp.faces[face_index], &mut vertices, &mut per_material_indices, lightmap_packer) {
+        face_lightmaps.insert(face_index, lightmap_offset);
       }
     }
+    if vertices.is_empty() {
+      return None;
+    }
+
+    let mut all_indices = Vec::<u32>::new();
+    let mut mesh_ranges = Vec::<MeshRange>::new();
+    let mut materials = Vec::<String>::new();
+    for (material, indices) in per_material_indices {
+      if indices.is_empty() {
+        continue;
+      }
+      mesh_ranges.push(MeshRange {
+        start: all_indices.len() as u32,
+        count: indices.len() as u32
+      });
+      all_indices.extend_from_slice(&indices);
+      materials.push("materials/".to_string() + material.as_str() + ".vmt");
+    }
+
+    let mut min = vertices[0].position;
+    let mut max = vertices[0].position;
+    for vertex in &vertices {
+      min = min.inf(&vertex.position);
+      max = max.sup(&vertex.position);
+    }
+
+    let vertices_count = vertices.len();
+    let ptr = Box::into_raw(vertices.into_boxed_slice());
+    let data_ptr = unsafe { slice::from_raw_parts_mut(ptr as *mut u8, vertices_count * std::mem::size_of::<super::Vertex>()) as *mut [u8] };
+    let vertices_data = unsafe { Box::from_raw(data_ptr) };
+
+    let indices_count = all_indices.len();
+    let ptr = Box::into_raw(all_indices.into_boxed_slice());
+    let data_ptr = unsafe { slice::from_raw_parts_mut(ptr as *mut u8, indices_count * std::mem::size_of::<u32>()) as *mut [u8] };
+    let indices_data = unsafe { Box::from_raw(data_ptr) };
+
+    Some((Mesh {
+      vertices: vertices_data,
+      indices: Some(indices_data),
+      parts: mesh_ranges.into_boxed_slice(),
+      lods: Box::new([]),
+      bounding_box: Some(BoundingBox::new(min, max)),
+      vertex_count: vertices_count as u32,
+      skinning: None
+    }, materials))
   }
 
-  fn calculate_disp_vert(offset: i32, x: i32, y: i32, size: i32, corners: &[Vec3; 4], first_corner: i32, disp_verts: &[DispVert]) -> Vec3 {
-    let disp_vert = &disp_verts[(offset + x + y * size) as usize];
-    let tx = (x as f32) / ((size - 1) as f32);
-    let ty = (y as f32) / ((size - 1) as f32);
-    let sx = 1f32 - tx;
-    let sy = 1f32 - ty;
-
-    let relevant_corners = [
-      corners[((first_corner) & 3) as usize],
-      corners[((first_corner + 1) & 3) as usize],
-      corners[((first_corner + 2) & 3) as usize],
-      corners[((first_corner + 3) & 3) as usize],
-    ];
-    let origin = ty * (sx * relevant_corners[1] + tx * relevant_corners[2]) + sy * (sx * relevant_corners[0] + tx * relevant_corners[3]);
-    origin + disp_vert.vec * disp_vert.dist
+  /// The lower case path of the material of a texture info relative to the materials directory.
+  pub(super) fn material_name(temp: &BspLumps, tex_info: &BspTextureInfo) -> String {
+    let tex_data = &temp.tex_data[tex_info.texture_data as usize];
+    let tex_offset = &temp.tex_data_string_table[tex_data.name_string_table_id as usize];
+    temp.tex_string_data.get_string_at(tex_offset.0 as u32).to_str().unwrap().replace('\\', "/").to_lowercase()
   }
 
-  fn calculate_uv(position: &Vec3, texture_vecs_s: &Vec4, texture_vecs_t: &Vec4) -> Vec2 {
+  /// The light styles and the width of a bump page of the face, see `super::Vertex`.
+  pub(super) fn lightmap_layout(face: &Face, is_bumped: bool, lightmap_packer: &LightmapPacker<[f16; 4]>) -> (u32, f32) {
+    let lightmap_styles = if face.light_offset >= 0 { Self::pack_light_styles(face) } else { 0 };
+    let lightmap_bump_page_width = if face.light_offset >= 0 && is_bumped {
+      (face.lightmap_texture_size_in_luxels[0] + 1) as f32 / lightmap_packer.texture_width() as f32
+    } else {
+      0f32
+    };
+    (lightmap_styles, lightmap_bump_page_width)
+  }
+
+  /// Calculates the coordinates of a point on the face in the lightmap atlas.
+  pub(super) fn calculate_lightmap_uv(face: &Face, tex_info: &BspTextureInfo, position: &Vec3, lightmap_offset: (u32, u32), lightmap_packer: &LightmapPacker<[f16; 4]>) -> Vec2 {
+    if face.light_offset < 0 {
+      return Vec2::default();
+    }
+    let mut lightmap_uv = Self::calculate_uv(position, &tex_info.lightmap_vecs_s, &tex_info.lightmap_vecs_t);
+    lightmap_uv -= Vec2::new(face.lightmap_texture_mins_in_luxels[0] as f32, face.lightmap_texture_mins_in_luxels[1] as f32);
+    lightmap_uv += Vec2::new(0.5f32, 0.5f32);
+    debug_assert!(lightmap_uv.x >= 0f32);
+    debug_assert!(lightmap_uv.x < (face.lightmap_texture_size_in_luxels[0] + 1) as f32);
+    debug_assert!(lightmap_uv.y >= 0f32);
+    debug_assert!(lightmap_uv.y < (face.lightmap_texture_size_in_luxels[1] + 1) as f32);
+    lightmap_uv += Vec2::new(lightmap_offset.0 as f32, lightmap_offset.1 as f32);
+    lightmap_uv.x /= lightmap_packer.texture_width() as f32;
+    lightmap_uv.y /= lightmap_packer.texture_height() as f32;
+    lightmap_uv
+  }
+
+  /// VRAD aligns the bump basis with the texture axes, the tangent points along the U axis.
+  pub(super) fn calculate_tangent(normal: &Vec3, texture_vecs_s: &Vec4, texture_vecs_t: &Vec4) -> Vec4 {
+    let s = texture_vecs_s.xyz();
+    let t = texture_vecs_t.xyz();
+    let tangent = normal.cross(&s).cross(normal).try_normalize(f32::EPSILON).unwrap_or_else(Vec3::zeros);
+    // The coordinate fixup mirrors the space which flips the handedness of the basis.
+    let bitangent_sign = if s.cross(&t).dot(normal) < 0f32 { 1f32 } else { -1f32 };
+    let tangent = Self::fixup_normal(&tangent);
+    Vec4::new(tangent.x, tangent.y, tangent.z, bitangent_sign)
+  }
+
+  fn light_style_count(face: &Face) -> usize {
+    face.styles.iter().take_while(|style| **style != 255).count()
+  }
+
+  fn pack_light_styles(face: &Face) -> u32 {
+    face.styles.iter()
+      .take_while(|style| **style != 255)
+      .enumerate()
+      .fold(0u32, |packed, (layer, style)| packed | (*style as u32 + 1) << (layer * 8))
+  }
+
+  pub(super) fn calculate_uv(position: &Vec3, texture_vecs_s: &Vec4, texture_vecs_t: &Vec4) -> Vec2 {
     let pos4 = Vec4::new(position.x, position.y, position.z, 1.0f32);
     Vec2::new(
       pos4.dot(texture_vecs_s),
@@ -247,15 +355,152 @@ impl BspLevelLoader {
     )
   }
 
-  fn fixup_position(position: &Vec3) -> Vec3 {
+  fn build_cluster_visibility(lumps: &BspLumps) -> ClusterVisibility {
+    let nodes: Vec<ClusterVisibilityNode> = lumps.nodes.iter().map(|node| {
+      let plane = &lumps.planes[node.plane_number as usize];
+      let normal = Self::fixup_normal(&plane.normal);
+      ClusterVisibilityNode {
+        plane: Vec4::new(normal.x, normal.y, normal.z, plane.dist * SCALING_FACTOR),
+        children: node.children
+      }
+    }).collect();
+    let leaf_clusters: Vec<i16> = lumps.leafs.iter().map(|leaf| leaf.cluster).collect();
+    let cluster_count = lumps.visibility.num_clusters.max(0) as u32;
+    ClusterVisibility::new(nodes.into_boxed_slice(), leaf_clusters.into_boxed_slice(), cluster_count, |cluster| {
+      lumps.visibility.visible_clusters(cluster as i32).as_bytes().to_vec().into_boxed_slice()
+    })
+  }
+
+  /// Finds the world faces and leaves in the area of the sky camera, those make up the 3D skybox.
+  fn find_sky_area(lumps: &BspLumps) -> Option<(HashSet<usize>, Vec<usize>)> {
+    if lumps.nodes.is_empty() {
+      return None;
+    }
+    let origin = lumps.entities.entities.iter()
+      .find(|entity| entity.get("classname") == Some("sky_camera"))
+      .and_then(|entity| entity.get("origin"))
+      .and_then(parse_vec3)?;
+    let sky_leaf = &lumps.leafs[Node::find_leaf(&lumps.nodes, &lumps.planes, 0, &origin)];
+    // Area 0 is the void outside of the level.
+    if sky_leaf.area <= 0 {
+      return None;
+    }
+
+    let leaves: Vec<usize> = lumps.leafs.iter()
+      .enumerate()
+      .filter(|(_, leaf)| leaf.area == sky_leaf.area && leaf.cluster >= 0)
+      .map(|(index, _)| index)
+      .collect();
+    let faces: HashSet<usize> = leaves.iter()
+      .flat_map(|leaf_index| {
+        let leaf = &lumps.leafs[*leaf_index];
+        let first_face = leaf.first_leaf_face as usize;
+        lumps.leaf_faces[first_face .. first_face + leaf.leaf_faces_count as usize].iter()
+      })
+      .map(|leaf_face| leaf_face.index as usize)
+      .collect();
+    Some((faces, leaves))
+  }
+
+  /// Picks the cubemap for the reflections of a face the same way VBSP does it:
+  /// the closest one in front of the face or the closest one overall if there is none.
+  fn find_face_cubemap(lumps: &BspLumps, face: &Face) -> Option<usize> {
+    if lumps.cubemaps.is_empty() || face.edges_count <= 0 {
+      return None;
+    }
+
+    let mut center = Vec3::default();
+    for surf_edge_index in face.first_edge .. face.first_edge + face.edges_count as i32 {
+      let edge_index = lumps.surface_edges[surf_edge_index as usize].index;
+      let edge = lumps.edges[edge_index.abs() as usize];
+      let vert_index = edge.vertex_index[if edge_index >= 0 { 0 } else { 1 }];
+      center += lumps.vertices[vert_index as usize].position;
+    }
+    center /= face.edges_count as f32;
+    let normal = &lumps.planes[face.plane_index as usize].normal;
+
+    let mut closest = Option::<(usize, f32)>::None;
+    let mut closest_in_front = Option::<(usize, f32)>::None;
+    for (index, cubemap) in lumps.cubemaps.iter().enumerate() {
+      let delta = Vec3::new(cubemap.origin.x as f32, cubemap.origin.y as f32, cubemap.origin.z as f32) - center;
+      let distance = delta.magnitude_squared();
+      if closest.is_none_or(|(_, closest_distance)| distance < closest_distance) {
+        closest = Some((index, distance));
+      }
+      if delta.dot(normal) >= 0f32 && closest_in_front.is_none_or(|(_, closest_distance)| distance < closest_distance) {
+        closest_in_front = Some((index, distance));
+      }
+    }
+    closest_in_front.or(closest).map(|(index, _)| index)
+  }
+
+  /// Blends the ambient light samples of the leaf that contains the position
+  /// using their inverse squared distance, like the Source engine does it for props.
+  fn ambient_cube_at(lumps: &BspLumps, position: &Vec3) -> Option<[Vec3; 6]> {
+    if lumps.nodes.is_empty() {
+      return None;
+    }
+    let leaf_index = Node::find_leaf(&lumps.nodes, &lumps.planes, 0, position);
+    let leaf = &lumps.leafs[leaf_index];
+    let leaf_min = Vec3::new(leaf.mins[0] as f32, leaf.mins[1] as f32, leaf.mins[2] as f32);
+    let leaf_max = Vec3::new(leaf.maxs[0] as f32, leaf.maxs[1] as f32, leaf.maxs[2] as f32);
+
+    let samples: Vec<(Vec3, &CompressedLightCube)> = if let Some(ambient_index) = lumps.leaf_ambient_indices.get(leaf_index) {
+      lumps.leaf_ambient_lighting.iter()
+        .skip(ambient_index.first_ambient_sample as usize)
+        .take(ambient_index.ambient_sample_count as usize)
+        .map(|sample| {
+          let fraction = Vec3::new(sample.x as f32, sample.y as f32, sample.z as f32) / 255f32;
+          (leaf_min + (leaf_max - leaf_min).component_mul(&fraction), &sample.cube)
+        })
+        .collect()
+    } else {
+      // Older maps store a single cube in every leaf.
+      vec![((leaf_min + leaf_max) * 0.5f32, &leaf.ambient_lighting)]
+    };
+    if samples.is_empty() {
+      return None;
+    }
+
+    let mut cube = [Vec3::default(); 6];
+    let mut total_weight = 0f32;
+    for (sample_position, sample_cube) in samples {
+      let weight = 1f32 / ((sample_position - position).magnitude_squared() + 1f32);
+      total_weight += weight;
+      for (side, color) in sample_cube.color.iter().enumerate() {
+        let [r, g, b] = color.to_rgb_f32();
+        cube[side] += Vec3::new(r, g, b) * weight;
+      }
+    }
+
+    // The sides are ordered +X, -X, +Y, -Y, +Z, -Z and Source is Z up.
+    let mut fixed_up_cube = [Vec3::default(); 6];
+    for (side, source_side) in [0, 1, 4, 5, 2, 3].iter().enumerate() {
+      fixed_up_cube[side] = cube[*source_side] / total_weight;
+    }
+    Some(fixed_up_cube)
+  }
+
+  fn leaves_to_clusters<I: Iterator<Item = usize>>(lumps: &BspLumps, leaves: I) -> VisibilityClusters {
+    let mut clusters: Vec<u16> = leaves
+      .filter_map(|leaf| lumps.leafs.get(leaf))
+      .filter(|leaf| leaf.cluster >= 0)
+      .map(|leaf| leaf.cluster as u16)
+      .collect();
+    clusters.sort_unstable();
+    clusters.dedup();
+    VisibilityClusters(clusters.into_boxed_slice())
+  }
+
+  pub(super) fn fixup_position(position: &Vec3) -> Vec3 {
     Vec3::new(position.x, position.z, position.y) * SCALING_FACTOR
   }
 
-  fn fixup_normal(normal: &Vec3) -> Vec3 {
+  pub(super) fn fixup_normal(normal: &Vec3) -> Vec3 {
     Vec3::new(normal.x, normal.z, normal.y)
   }
 
-  fn fixup_rotation(rotation: &Vec3) -> Quaternion {
+  pub(super) fn fixup_rotation(rotation: &Vec3) -> Quaternion {
     const DEG_TO_RAD: f32 = std::f32::consts::PI / 180f32;
     // Source rotations are in the following order: Pitch Yaw Roll (Y Z X)
     // We need them in the following order: Pitch Yaw Roll (X Y Z)
@@ -270,8 +515,11 @@ impl BspLevelLoader {
 
 impl<P: Platform> AssetLoader<P> for BspLevelLoader {
   fn matches(&self, file: &mut AssetFile) -> bool {
-    let file_name = Path::new(&file.path).file_name();
-    file_name.and_then(|file_name| file_name.to_str()).map_or(false, |file_name| self.map_name_regex.is_match(file_name))
+    if !file.path.ends_with(".bsp") {
+      return false;
+    }
+    let mut identifier = [0u8; 4];
+    file.read_exact(&mut identifier).is_ok() && &identifier == b"VBSP"
   }
 
   fn load(&self, asset_file: AssetFile, manager: &Arc<AssetManager<P>>, _priority: AssetLoadPriority, progress: &Arc<AssetLoaderProgress>) -> Result<AssetLoaderResult, ()> {
@@ -296,16 +544,39 @@ impl<P: Platform> AssetLoader<P> for BspLevelLoader {
     let disp_infos = map.read_disp_infos().unwrap();
     let disp_verts = map.read_disp_verts().unwrap();
     let disp_tris = map.read_disp_tris().unwrap();
+    let disp_multiblend = map.read_disp_multiblend().unwrap();
     let pakfile = map.read_pakfile().unwrap();
-    let lighting = map.read_lighting().unwrap();
+    // HDR lighting is kept as is, it's not tonemapped down to LDR.
+    let hdr_lighting = map.read_hdr_lighting().unwrap();
+    let is_hdr = !hdr_lighting.is_empty();
+    // The ambient lighting of props has to match the lightmap.
+    let (leaf_ambient_indices, leaf_ambient_lighting) = if is_hdr {
+      (map.read_hdr_leaf_ambient_indices().unwrap(), map.read_hdr_leaf_ambient_lighting().unwrap())
+    } else {
+      (map.read_leaf_ambient_indices().unwrap(), map.read_leaf_ambient_lighting().unwrap())
+    };
+    let lighting = if is_hdr {
+      hdr_lighting
+    } else {
+      map.read_lighting().unwrap()
+    };
+    let leaf_water_data = map.read_leaf_water_data().unwrap();
     let visibility = map.read_visibility().unwrap();
     let static_props = map.read_static_props().unwrap();
+    let detail_props = map.read_detail_props().unwrap();
     let entities = map.read_entities().unwrap();
+    let cubemaps = map.read_cubemaps().unwrap();
+    let mut overlays = map.read_overlays().unwrap();
+    overlays.extend(map.read_water_overlays().unwrap().into_iter().map(|water_overlay| water_overlay.0));
+    let overlay_fades = map.read_overlay_fades().unwrap();
 
     let temp = BspLumps {
       map_name: name.to_string(),
       nodes,
       leafs,
+      leaf_ambient_indices,
+      leaf_ambient_lighting,
+      leaf_water_data,
       leaf_brushes,
       leaf_faces,
       surface_edges,
@@ -323,28 +594,81 @@ impl<P: Platform> AssetLoader<P> for BspLevelLoader {
       lighting,
       visibility,
       static_props,
-      entities
+      detail_props,
+      entities,
+      cubemaps,
+      overlays,
+      overlay_fades
     };
 
     let pakfile_container = Box::new(PakFileContainer::new(pakfile));
 
     let mut world = World::new(WorldOptions::default());
     let mut materials_to_load = HashSet::<String>::new();
-    let mut lightmap_packer = LightmapPacker::new(2048, 2048);
+    let lightmap_layers = temp.faces.iter()
+      .filter(|face| face.light_offset >= 0)
+      .map(Self::light_style_count)
+      .max()
+      .unwrap_or(1)
+      .max(1);
+    let mut lightmap_packer = LightmapPacker::<[f16; 4]>::new_layered(2048, 2048, lightmap_layers as u32);
+
+    // The world faces in the area of the sky camera get a model of their own,
+    // so they get culled using the PVS and are only drawn from the sky camera.
+    let sky_area = Self::find_sky_area(&temp);
+    let mut face_groups = Vec::<(usize, Vec<usize>, bool, Option<usize>)>::new();
+    for (brush_index, model) in brush_models.iter().enumerate() {
+      let faces = (model.first_face as usize .. (model.first_face + model.num_faces) as usize).collect::<Vec<usize>>();
+      let area_groups = match sky_area.as_ref() {
+        Some((sky_faces, _)) if brush_index == 0 => {
+          let (sky_area_faces, world_faces): (Vec<usize>, Vec<usize>) = faces.into_iter().partition(|face| sky_faces.contains(face));
+          vec![(world_faces, false), (sky_area_faces, true)]
+        }
+        _ => vec![(faces, false)]
+      };
 
+      // Faces that reflect different cubemaps have to end up in different drawables.
+      for (faces, is_sky_area) in area_groups {
+        let mut cubemap_groups = BTreeMap::<Option<usize>, Vec<usize>>::new();
+        for face in faces {
+          let cubemap = Self::find_face_cubemap(&temp, &temp.faces[face]);
+          cubemap_groups.entry(cubemap).or_default().push(face);
+        }
+        for (cubemap, faces) in cubemap_groups {
+          face_groups.push((brush_index, faces, is_sky_area, cubemap));
+        }
+      }
+    }
+
+    // The water surfaces get drawn in a separate pass after the opaque geometry.
+    let water_materials: HashSet<String> = temp.leaf_water_data.iter()
+      .filter(|water_data| water_data.surface_tex_info >= 0)
+      .map(|water_data| Self::material_name(&temp, &temp.tex_info[water_data.surface_tex_info as usize]))
+      .collect();
+    let mut water_faces = BTreeMap::<usize, Vec<usize>>::new();
+
+    let displacement_grids = build_displacement_grids(&temp, &disp_multiblend);
+
+    // Overlays and decals get clipped to the faces they cover, so they need the lightmap position of those.
+    let mut face_lightmaps = HashMap::<usize, (u32, u32)>::new();
     let mut model_index = 0;
-    for model in &brush_models {
+    for (brush_index, faces, is_sky_area, cubemap) in face_groups {
+      let model = &brush_models[brush_index];
       let mut brush_vertices = Vec::<super::Vertex>::new();
       let mut brush_indices = Vec::<u32>::new();
       let mut per_material_indices = HashMap::<String, Vec<u32>>::new();
       let mut mesh_ranges = Vec::<MeshRange>::new();
 
-      for face in &temp.faces[model.first_face as usize .. (model.first_face + model.num_faces) as usize] {
-        if face.displacement_info != -1 {
+      for face_index in faces.iter().copied() {
+        let face = &temp.faces[face_index];
+        if face.displacement_info == -1 && water_materials.contains(&Self::material_name(&temp, &temp.tex_info[face.texture_info as usize])) {
+          water_faces.entry(brush_index).or_default().push(face_index);
+        } else if face.displacement_info != -1 {
           let disp_info = &temp.disp_infos[face.displacement_info as usize];
-          self.build_displacement_face(&temp, disp_info, &mut brush_vertices, &mut per_material_indices, &mut lightmap_packer);
-        } else {
-          self.build_face(&temp, face, &mut brush_vertices, &mut per_material_indices, &mut lightmap_packer);
+          let grid = &displacement_grids[face.displacement_info as usize];
+          self.build_displacement_face(&temp, disp_info, grid, &mut brush_vertices, &mut per_material_indices, &mut lightmap_packer);
+        } else if let Some(lightmap_offset) = self.build_face(&temp, face, &mut brush_vertices, &mut per_material_indices, &mut lightmap_packer) {
+          face_lightmaps.insert(face_index, lightmap_offset);
         }
       }
 
@@ -385,17 +709,22 @@ impl<P: Platform> AssetLoader<P> for BspLevelLoader {
       let data_ptr = unsafe { slice::from_raw_parts_mut(ptr as *mut u8, indices_count * std::mem::size_of::<u32>()) as *mut [u8] };
       let indices_data = unsafe { Box::from_raw(data_ptr) };
 
-      let model_min = Self::fixup_position(&model.min);
-      let model_max = Self::fixup_position(&model.max);
-      let min = Vec3::new(model_min.x.min(model_max.x), model_min.y.min(model_max.y), model_min.z.min(model_max.z));
-      let max = Vec3::new(model_min.x.max(model_max.x), model_min.y.max(model_max.y), model_min.z.max(model_max.z));
+      // The faces of a model get split up by cubemap, so the bounds of the whole model would be too large.
+      let mut min = brush_vertices[0].position;
+      let mut max = brush_vertices[0].position;
+      for vertex in &brush_vertices {
+        min = min.inf(&vertex.position);
+        max = max.sup(&vertex.position);
+      }
 
       let mesh = Mesh {
         vertices: vertices_data,
         indices: Some(indices_data),
         parts: mesh_ranges.into_boxed_slice(),
+        lods: Box::new([]),
         bounding_box: Some(BoundingBox::new(min, max)),
-        vertex_count: vertices_count as u32
+        vertex_count: vertices_count as u32,
+        skinning: None
       };
 
       let mesh_name = format!("brushes_mesh_{}", model_index);
@@ -409,7 +738,7 @@ impl<P: Platform> AssetLoader<P> for BspLevelLoader {
       };
       manager.add_asset(&model_name, Asset::Model(model), AssetLoadPriority::Normal);
 
-      world.push(
+      let entity = world.push(
         (StaticRenderableComponent {
           model_path: model_name,
           receive_shadows: true,
@@ -417,19 +746,90 @@ impl<P: Platform> AssetLoader<P> for BspLevelLoader {
           can_move: false
         },
          Transform {
-           position: brush_models[model_index].origin,
+           position: model.origin,
            scale: Vec3::new(1.0f32, 1.0f32, 1.0f32),
            rotation: Quaternion::identity(),
          })
       );
 
+      if let Some(cubemap) = cubemap {
+        world.entry(entity).unwrap().add_component(CubemapIndex(cubemap as u32));
+      }
+
+      // The world brush model spans every cluster, so it's never culled using the PVS.
+      if is_sky_area {
+        let (_, sky_leaves) = sky_area.as_ref().unwrap();
+        let clusters = Self::leaves_to_clusters(&temp, sky_leaves.iter().copied());
+        world.entry(entity).unwrap().add_component(clusters);
+      } else if brush_index != 0 && !temp.nodes.is_empty() {
+        let mut leaves = Vec::<usize>::new();
+        Node::find_leaves_in_box(&temp.nodes, &temp.planes, 0, &(model.min + model.origin), &(model.max + model.origin), &mut leaves);
+        let clusters = Self::leaves_to_clusters(&temp, leaves.into_iter());
+        world.entry(entity).unwrap().add_component(clusters);
+      }
+
       model_index += 1;
     }
 
-    for prop in temp.static_props.props.as_ref() {
+    // The water surfaces of a brush model get drawn with the transform of the brush model.
+    let mut water_surfaces = Vec::<WaterSurface>::new();
+    for (brush_index, faces) in water_faces {
+      if let Some((mesh, materials)) = self.build_water(&temp, &faces, &mut face_lightmaps, &mut lightmap_packer) {
+        for material in &materials {
+          materials_to_load.insert(material.clone());
+        }
+        let mesh_name = format!("water_mesh_{}", brush_index);
+        let model_name = format!("water_model_{}", brush_index);
+        manager.add_asset(&mesh_name, Asset::Mesh(mesh), AssetLoadPriority::Normal);
+        manager.add_asset(&model_name, Asset::Model(Model {
+          mesh_path: mesh_name,
+          material_paths: materials
+        }), AssetLoadPriority::Normal);
+        water_surfaces.push(WaterSurface {
+          model_path: model_name,
+          transform: Matrix4::new_translation(&brush_models[brush_index].origin)
+        });
+      }
+    }
+    if !water_surfaces.is_empty() {
+      world.push((WaterSurfaces { surfaces: water_surfaces.into_boxed_slice() },));
+    }
+
+    if !temp.leaf_water_data.is_empty() {
+      let volumes: Box<[WaterVolume]> = temp.leaf_water_data.iter().map(|water_data| {
+        let material_path = if water_data.surface_tex_info >= 0 {
+          let path = "materials/".to_string() + &Self::material_name(&temp, &temp.tex_info[water_data.surface_tex_info as usize]) + ".vmt";
+          materials_to_load.insert(path.clone());
+          path
+        } else {
+          String::new()
+        };
+        WaterVolume {
+          surface_height: water_data.surface_z * SCALING_FACTOR,
+          material_path
+        }
+      }).collect();
+      let leaf_volumes = temp.leafs.iter().map(|leaf| leaf.leaf_water_data_id).collect();
+      world.push((WaterVolumes { leaf_volumes, volumes },));
+    }
+
+    let mut vertex_lit_props = Vec::<(usize, Entity)>::new();
+    for (prop_index, prop) in temp.static_props.props.iter().enumerate() {
       let name = &temp.static_props.names.as_ref()[prop.prop_type as usize];
       manager.request_asset_with_progress(name, AssetType::Model, AssetLoadPriority::Normal, progress);
-      world.push(
+      let leaves = temp.static_props.leaves.iter()
+        .skip(prop.first_leaf as usize)
+        .take(prop.leaf_count as usize)
+        .map(|leaf| *leaf as usize);
+      let clusters = Self::leaves_to_clusters(&temp, leaves);
+      // Props aren't covered by the lightmap, so they get lit by the ambient cubes around them instead.
+      let lighting_origin = if prop.flags.contains(StaticPropFlags::USE_LIGHTING_ORIGIN) {
+        &prop.lighting_origin
+      } else {
+        &prop.origin
+      };
+      let ambient_cube = Self::ambient_cube_at(&temp, lighting_origin);
+      let entity = world.push(
         (StaticRenderableComponent {
           model_path: name.clone(),
           receive_shadows: true,
@@ -440,8 +840,21 @@ impl<P: Platform> AssetLoader<P> for BspLevelLoader {
            position: Self::fixup_position(&prop.origin),
            scale: Vec3::new(1.0f32, 1.0f32, 1.0f32),
            rotation: Self::fixup_rotation(&prop.angles),
-         })
+         },
+         clusters)
       );
+      if let Some(ambient_cube) = ambient_cube {
+        world.entry(entity).unwrap().add_component(AmbientLightCube(ambient_cube));
+      }
+      if !prop.flags.contains(StaticPropFlags::NO_PER_VERTEX_LIGHTING) {
+        vertex_lit_props.push((prop_index, entity));
+      }
+    }
+
+    let mut entity_assets = Vec::<(String, AssetType)>::new();
+    self.spawn_entities(&temp, &mut world, &mut entity_assets);
+    for (path, asset_type) in entity_assets {
+      manager.request_asset_with_progress(&path, asset_type, AssetLoadPriority::Normal, progress);
     }
 
     for material in materials_to_load {
@@ -450,31 +863,97 @@ impl<P: Platform> AssetLoader<P> for BspLevelLoader {
 
     manager.add_container(pakfile_container);
 
+    // The skybox materials can be in the pakfile, so it has to be loaded after adding the container.
+    let sky_name = temp.entities.entities.iter()
+      .find(|entity| entity.get("classname") == Some("worldspawn"))
+      .and_then(|entity| entity.get("skyname"));
+    if let Some(skybox) = sky_name.and_then(|sky_name| load_skybox(manager, sky_name)) {
+      manager.add_asset("skybox", Asset::Texture(skybox), AssetLoadPriority::Normal);
+      world.push((Skybox { texture_path: "skybox".to_string() },));
+    }
+
+    // VRAD stores the per-vertex lighting of static props in the pakfile.
+    let map_name = name.trim_end_matches(".bsp").to_lowercase();
+    for (prop_index, entity) in vertex_lit_props {
+      if let Some(colors) = load_static_prop_lighting(manager, prop_index, is_hdr) {
+        let path = format!("maps/{}/sp_{}.vhv", map_name, prop_index);
+        manager.add_asset(&path, Asset::VertexColors(colors), AssetLoadPriority::Normal);
+        world.entry(entity).unwrap().add_component(VertexLighting { vertex_colors_path: path });
+      }
+    }
+
+    // VBSP bakes the cubemaps into the pakfile, HDR maps have a separate set of textures.
+    if !temp.cubemaps.is_empty() {
+      let texture_paths: Box<[String]> = temp.cubemaps.iter().map(|cubemap| {
+        let path = format!("materials/maps/{}/c{}_{}_{}", map_name, cubemap.origin.x, cubemap.origin.y, cubemap.origin.z);
+        let hdr_path = path.clone() + ".hdr.vtf";
+        let path = if manager.file_exists(&hdr_path) { hdr_path } else { path + ".vtf" };
+        manager.request_asset_with_progress(&path, AssetType::Texture, AssetLoadPriority::Normal, progress);
+        path
+      }).collect();
+      world.push((Cubemaps { texture_paths },));
+    }
+
+    // The decal materials can be in the pakfile, so their size can only be read after adding the container.
+    let mut decal_projections = overlay_projections(&temp);
+    decal_projections.extend(info_decal_projections(&temp, manager));
+    if let Some((mesh, materials, part_fades)) = build_decals(&temp, &decal_projections, &face_lightmaps, &lightmap_packer) {
+      for material in &materials {
+        manager.request_asset(material, AssetType::Material, AssetLoadPriority::Low);
+      }
+      manager.add_asset("decals_mesh", Asset::Mesh(mesh), AssetLoadPriority::Normal);
+      manager.add_asset("decals_model", Asset::Model(Model {
+        mesh_path: "decals_mesh".to_string(),
+        material_paths: materials
+      }), AssetLoadPriority::Normal);
+      world.push((Decals {
+        model_path: "decals_model".to_string(),
+        part_fades: part_fades.into_boxed_slice()
+      },));
+    }
 
+    // The detail sprite material and the detail models can be in the pakfile too.
+    if let Some(detail_props) = build_detail_props(&temp) {
+      manager.request_asset(&detail_props.sprite_material_path, AssetType::Material, AssetLoadPriority::Low);
+      for model in detail_props.models.iter() {
+        manager.request_asset_with_progress(&model.model_path, AssetType::Model, AssetLoadPriority::Normal, progress);
+      }
+      world.push((detail_props,));
+    }
+
+    // Every light style layer goes into its own array layer.
     let lightmap_info = TextureInfo {
-      dimension: TextureDimension::Dim2D,
-      format: Format::RGBA8UNorm,
+      dimension: TextureDimension::Dim2DArray,
+      format: Format::RGBA16Float,
       width: lightmap_packer.texture_width(),
       height: lightmap_packer.texture_height(),
       depth: 1,
       mip_levels: 1,
-      array_length: 1,
+      array_length: lightmap_packer.layers(),
       samples: SampleCount::Samples1,
       usage: TextureUsage::COPY_DST | TextureUsage::SAMPLED,
       supports_srgb: false,
     };
+    let layer_size = (lightmap_info.width * lightmap_info.height) as usize * std::mem::size_of::<[f16; 4]>();
     let samples = lightmap_packer.take_data();
     let samples_len = samples.len();
     let ptr = Box::into_raw(samples);
-    let data_ptr = unsafe { slice::from_raw_parts_mut(ptr as *mut u8, samples_len * std::mem::size_of::<u32>()) as *mut [u8] };
+    let data_ptr = unsafe { slice::from_raw_parts_mut(ptr as *mut u8, samples_len * std::mem::size_of::<[f16; 4]>()) as *mut [u8] };
     let data = unsafe { Box::from_raw(data_ptr) };
+    let layers: Box<[Box<[u8]>]> = data.chunks_exact(layer_size)
+      .map(|layer| layer.to_vec().into_boxed_slice())
+      .collect();
 
     manager.add_asset("lightmap", Asset::Texture(Texture {
       info: lightmap_info,
-      data: Box::new([data])
+      data: layers
     }), AssetLoadPriority::Normal);
 
-    world.push((Lightmap { path: "lightmap".to_string() },));
+    world.push((Lightmap { path: "lightmap".to_string() }, LightStyles::default()));
+
+    if temp.visibility.num_clusters > 0 {
+      world.push((PotentiallyVisibleSet(Arc::new(Self::build_cluster_visibility(&temp))),));
+    }
 
     Ok(AssetLoaderResult::Level(world))
   }
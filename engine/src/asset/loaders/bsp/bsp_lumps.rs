@@ -1,9 +1,12 @@
-use sourcerenderer_bsp::{Leaf, Node, LeafFace, LeafBrush, SurfaceEdge, Vertex, Face, Edge, Plane, TextureData, TextureDataStringTable, TextureInfo, TextureStringData, DispInfo, DispVert, DispTri, Lighting, Visibility, Entities};
-use sourcerenderer_bsp::game_lumps::StaticPropDict;
+use sourcerenderer_bsp::{Leaf, Node, LeafFace, LeafBrush, SurfaceEdge, Vertex, Face, Edge, Plane, TextureData, TextureDataStringTable, TextureInfo, TextureStringData, DispInfo, DispVert, DispTri, Lighting, Visibility, Entities, CubemapSample, Overlay, OverlayFade, LeafAmbientIndex, LeafAmbientLighting, LeafWaterData};
+use sourcerenderer_bsp::game_lumps::{StaticPropDict, DetailPropDict};
 
 pub(super) struct BspLumps {
   pub(super) map_name: String,
   pub(super) leafs: Vec<Leaf>,
+  pub(super) leaf_ambient_indices: Vec<LeafAmbientIndex>,
+  pub(super) leaf_ambient_lighting: Vec<LeafAmbientLighting>,
+  pub(super) leaf_water_data: Vec<LeafWaterData>,
   pub(super) nodes: Vec<Node>,
   pub(super) leaf_faces: Vec<LeafFace>,
   pub(super) leaf_brushes: Vec<LeafBrush>,
@@ -22,5 +25,11 @@ pub(super) struct BspLumps {
   pub(super) lighting: Vec<Lighting>,
   pub(super) visibility: Visibility,
   pub(super) static_props: StaticPropDict,
+  pub(super) detail_props: DetailPropDict,
   pub(super) entities: Entities,
+  pub(super) cubemaps: Vec<CubemapSample>,
+  /// The regular overlays followed by the water overlays.
+  pub(super) overlays: Vec<Overlay>,
+  /// The fade distances of the regular overlays, empty if none of them fade out.
+  pub(super) overlay_fades: Vec<OverlayFade>,
 }
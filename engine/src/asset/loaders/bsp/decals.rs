@@ -0,0 +1,391 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{Seek, SeekFrom};
+use std::sync::Arc;
+use core::slice;
+
+use half::f16;
+use log::warn;
+use sourcerenderer_bsp::{Node, Overlay, SurfaceFlags};
+use sourcerenderer_core::{Platform, Vec2, Vec3};
+use sourcerenderer_vmt::VMTMaterial;
+
+use crate::asset::{AssetManager, Mesh};
+use crate::asset::asset_manager::MeshRange;
+use crate::math::BoundingBox;
+use crate::renderer::DecalFade;
+use super::{BspLumps, BspLevelLoader, SCALING_FACTOR};
+use super::entities::parse_vec3;
+use super::lightmap_packer::LightmapPacker;
+
+/// The maximum distance between the origin of an infodecal and the surfaces it gets applied to.
+const INFO_DECAL_MAX_DISTANCE: f32 = 2f32;
+
+/// A texture that gets projected onto faces along the normal of its plane.
+/// Overlays come with a list of faces, infodecals pick the faces around their origin.
+pub(super) struct DecalProjection {
+  material: String,
+  render_order: u8,
+  faces: Vec<usize>,
+  origin: Vec3,
+  normal: Vec3,
+  basis_u: Vec3,
+  basis_v: Vec3,
+  /// The corners of the quad in the plane spanned by the basis vectors.
+  corners: [Vec2; 4],
+  texcoords: [Vec2; 4],
+  fade: DecalFade
+}
+
+#[derive(Clone)]
+struct ClipVertex {
+  position: Vec3,
+  /// The position in the plane of the projection.
+  coords: Vec2
+}
+
+/// Collects the overlays and water overlays placed with `info_overlay`.
+pub(super) fn overlay_projections(lumps: &BspLumps) -> Vec<DecalProjection> {
+  lumps.overlays.iter()
+    .enumerate()
+    .filter_map(|(index, overlay)| overlay_projection(lumps, overlay, index))
+    .collect()
+}
+
+fn overlay_projection(lumps: &BspLumps, overlay: &Overlay, index: usize) -> Option<DecalProjection> {
+  if overlay.tex_info < 0 {
+    return None;
+  }
+  let tex_info = lumps.tex_info.get(overlay.tex_info as usize)?;
+  let mut corners = [Vec2::default(); 4];
+  for (corner, point) in corners.iter_mut().zip(overlay.uv_points.iter()) {
+    *corner = Vec2::new(point.x, point.y);
+  }
+  // The fade distances are squared and only exist for the regular overlays.
+  let fade = lumps.overlay_fades.get(index)
+    .map(|fade| DecalFade {
+      start: fade.fade_dist_min_sq.max(0f32).sqrt() * SCALING_FACTOR,
+      end: fade.fade_dist_max_sq.max(0f32).sqrt() * SCALING_FACTOR
+    })
+    .unwrap_or_default();
+  Some(DecalProjection {
+    material: BspLevelLoader::material_name(lumps, tex_info),
+    render_order: overlay.render_order,
+    faces: overlay.faces.iter().filter(|face| **face >= 0).map(|face| *face as usize).collect(),
+    origin: overlay.origin,
+    normal: overlay.basis_normal,
+    basis_u: overlay.basis_u(),
+    basis_v: overlay.basis_v(),
+    corners,
+    texcoords: [
+      Vec2::new(overlay.u[0], overlay.v[0]),
+      Vec2::new(overlay.u[0], overlay.v[1]),
+      Vec2::new(overlay.u[1], overlay.v[1]),
+      Vec2::new(overlay.u[1], overlay.v[0])
+    ],
+    fade
+  })
+}
+
+/// Collects the `infodecal` entities. Those only have an origin, so the decal gets applied to the faces
+/// that lie in the plane closest to it and gets aligned with their texture axes.
+pub(super) fn info_decal_projections<P: Platform>(lumps: &BspLumps, manager: &Arc<AssetManager<P>>) -> Vec<DecalProjection> {
+  if lumps.nodes.is_empty() {
+    return Vec::new();
+  }
+
+  let mut projections = Vec::<DecalProjection>::new();
+  for entity in &lumps.entities.entities {
+    if entity.get("classname") != Some("infodecal") {
+      continue;
+    }
+    let origin = entity.get("origin").and_then(parse_vec3);
+    let material = entity.get("texture").map(|texture| texture.replace('\\', "/").to_lowercase());
+    if origin.is_none() || material.is_none() {
+      continue;
+    }
+    let origin = origin.unwrap();
+    let material = material.unwrap();
+    let size = decal_size(manager, &material);
+    if size.is_none() {
+      warn!("Could not determine the size of decal material: {}", material);
+      continue;
+    }
+    let size = size.unwrap();
+
+    let extent = size.x.max(size.y) * 0.5f32;
+    let mut leaves = Vec::<usize>::new();
+    Node::find_leaves_in_box(&lumps.nodes, &lumps.planes, 0, &(origin - Vec3::repeat(extent)), &(origin + Vec3::repeat(extent)), &mut leaves);
+    let mut candidates: Vec<(usize, f32)> = leaves.iter()
+      .flat_map(|leaf_index| {
+        let leaf = &lumps.leafs[*leaf_index];
+        let first_face = leaf.first_leaf_face as usize;
+        lumps.leaf_faces[first_face .. first_face + leaf.leaf_faces_count as usize].iter()
+      })
+      .map(|leaf_face| leaf_face.index as usize)
+      .collect::<HashSet<usize>>()
+      .into_iter()
+      .filter_map(|face_index| {
+        let plane = &lumps.planes[lumps.faces[face_index].plane_index as usize];
+        let distance = (plane.normal.dot(&origin) - plane.dist).abs();
+        if distance <= INFO_DECAL_MAX_DISTANCE {
+          Some((face_index, distance))
+        } else {
+          None
+        }
+      })
+      .collect();
+    candidates.sort_unstable_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap());
+    if candidates.is_empty() {
+      continue;
+    }
+
+    let closest_face = &lumps.faces[candidates[0].0];
+    let normal = lumps.planes[closest_face.plane_index as usize].normal;
+    let faces: Vec<usize> = candidates.iter()
+      .map(|(face_index, _)| *face_index)
+      .filter(|face_index| lumps.planes[lumps.faces[*face_index].plane_index as usize].normal.dot(&normal) > 0.99f32)
+      .collect();
+
+    let tex_info = &lumps.tex_info[closest_face.texture_info as usize];
+    let texture_s = tex_info.texture_vecs_s.xyz();
+    let texture_t = tex_info.texture_vecs_t.xyz();
+    let basis_u = (texture_s - normal * texture_s.dot(&normal)).try_normalize(f32::EPSILON);
+    if basis_u.is_none() {
+      continue;
+    }
+    let basis_u = basis_u.unwrap();
+    let basis_v = (texture_t - normal * texture_t.dot(&normal) - basis_u * texture_t.dot(&basis_u))
+      .try_normalize(f32::EPSILON)
+      .unwrap_or_else(|| normal.cross(&basis_u));
+
+    let half_size = size * 0.5f32;
+    projections.push(DecalProjection {
+      material,
+      render_order: 0,
+      faces,
+      origin,
+      normal,
+      basis_u,
+      basis_v,
+      corners: [
+        Vec2::new(-half_size.x, -half_size.y),
+        Vec2::new(-half_size.x, half_size.y),
+        Vec2::new(half_size.x, half_size.y),
+        Vec2::new(half_size.x, -half_size.y)
+      ],
+      texcoords: [
+        Vec2::new(0f32, 0f32),
+        Vec2::new(0f32, 1f32),
+        Vec2::new(1f32, 1f32),
+        Vec2::new(1f32, 0f32)
+      ],
+      fade: DecalFade::default()
+    });
+  }
+  projections
+}
+
+/// The size of a decal in world units, decals are as big as their base texture scaled by `$decalscale`.
+fn decal_size<P: Platform>(manager: &Arc<AssetManager<P>>, material: &str) -> Option<Vec2> {
+  let material_path = format!("materials/{}.vmt", material.trim_end_matches(".vmt"));
+  let mut file = manager.load_file(&material_path)?;
+  let material = {
+    let len = file.seek(SeekFrom::End(0)).ok()?;
+    file.seek(SeekFrom::Start(0)).ok()?;
+    VMTMaterial::new(&mut file, len as u32).ok()?
+  };
+  let scale = material.get_value("decalscale")
+    .and_then(|scale| scale.parse::<f32>().ok())
+    .unwrap_or(1f32);
+  let texture = material.get_base_texture_name()?;
+  let texture_path = "materials/".to_string() + texture.to_lowercase().replace('\\', "/").as_str().trim_matches('/').trim_end_matches(".vtf") + ".vtf";
+  let mut file = manager.load_file(&texture_path)?;
+  let header = sourcerenderer_vtf::Header::read(&mut file).ok()?;
+  Some(Vec2::new(header.width as f32, header.height as f32) * scale)
+}
+
+/// Clips the projections against the faces they cover and merges the result into a single mesh
+/// with one part per material, render order and fade distances.
+/// Returns the mesh plus the material paths and the fade distances of its parts.
+/// Displacements aren't supported and faces that don't get drawn are skipped,
+/// `face_lightmaps` contains the lightmap positions of all other faces.
+pub(super) fn build_decals(lumps: &BspLumps, projections: &[DecalProjection], face_lightmaps: &HashMap<usize, (u32, u32)>, lightmap_packer: &LightmapPacker<[f16; 4]>) -> Option<(Mesh, Vec<String>, Vec<DecalFade>)> {
+  let mut vertices = Vec::<super::Vertex>::new();
+  let mut parts = BTreeMap::<(u8, String, (u32, u32)), Vec<u32>>::new();
+
+  for projection in projections {
+    // The sign of the area tells which side of the quad edges is the inside.
+    let area: f32 = (0..4).map(|i| projection.corners[i].perp(&projection.corners[(i + 1) % 4])).sum();
+    if area.abs() <= f32::EPSILON {
+      continue;
+    }
+
+    for face_index in &projection.faces {
+      let lightmap_offset = face_lightmaps.get(face_index);
+      if lightmap_offset.is_none() {
+        continue;
+      }
+      let lightmap_offset = *lightmap_offset.unwrap();
+      let face = &lumps.faces[*face_index];
+      let plane = &lumps.planes[face.plane_index as usize];
+      if plane.normal.dot(&projection.normal) <= 0f32 {
+        continue;
+      }
+
+      let mut polygon = Vec::<ClipVertex>::with_capacity(face.edges_count as usize);
+      for surf_edge_index in face.first_edge .. face.first_edge + face.edges_count as i32 {
+        let edge_index = lumps.surface_edges[surf_edge_index as usize].index;
+        let edge = lumps.edges[edge_index.abs() as usize];
+        let vert_index = edge.vertex_index[if edge_index >= 0 { 0 } else { 1 }];
+        let position = lumps.vertices[vert_index as usize].position;
+        let delta = position - projection.origin;
+        polygon.push(ClipVertex {
+          position,
+          coords: Vec2::new(delta.dot(&projection.basis_u), delta.dot(&projection.basis_v))
+        });
+      }
+
+      let polygon = clip_polygon(polygon, &projection.corners, area.signum());
+      if polygon.len() < 3 {
+        continue;
+      }
+
+      let tex_info = &lumps.tex_info[face.texture_info as usize];
+      let is_bumped = tex_info.flags.contains(SurfaceFlags::BUMPLIGHT);
+      let (lightmap_styles, lightmap_bump_page_width) = BspLevelLoader::lightmap_layout(face, is_bumped, lightmap_packer);
+      let tangent = BspLevelLoader::calculate_tangent(&plane.normal, &tex_info.texture_vecs_s, &tex_info.texture_vecs_t);
+      let fade_key = (projection.fade.start.to_bits(), projection.fade.end.to_bits());
+      let indices = parts.entry((projection.render_order, projection.material.clone(), fade_key)).or_default();
+      let root_vertex = vertices.len() as u32;
+      for (index, vertex) in polygon.iter().enumerate() {
+        vertices.push(super::Vertex {
+          position: BspLevelLoader::fixup_position(&vertex.position),
+          normal: BspLevelLoader::fixup_normal(&plane.normal),
+          uv: interpolate_texcoords(&vertex.coords, &projection.corners, &projection.texcoords),
+          lightmap_uv: BspLevelLoader::calculate_lightmap_uv(face, tex_info, &vertex.position, lightmap_offset, lightmap_packer),
+          alpha: 1f32,
+          lightmap_styles,
+          lightmap_bump_page_width,
+          tangent,
+          ..Default::default()
+        });
+
+        // Same winding as the faces.
+        if index < 2 {
+          continue;
+        }
+        indices.push(vertices.len() as u32 - 2);
+        indices.push(vertices.len() as u32 - 1);
+        indices.push(root_vertex);
+      }
+    }
+  }
+
+  if vertices.is_empty() {
+    return None;
+  }
+
+  let mut all_indices = Vec::<u32>::new();
+  let mut mesh_ranges = Vec::<MeshRange>::new();
+  let mut materials = Vec::<String>::new();
+  let mut fades = Vec::<DecalFade>::new();
+  for ((_, material, (fade_start, fade_end)), indices) in parts {
+    if indices.is_empty() {
+      continue;
+    }
+    mesh_ranges.push(MeshRange {
+      start: all_indices.len() as u32,
+      count: indices.len() as u32
+    });
+    all_indices.extend_from_slice(&indices);
+    materials.push("materials/".to_string() + material.trim_end_matches(".vmt") + ".vmt");
+    fades.push(DecalFade {
+      start: f32::from_bits(fade_start),
+      end: f32::from_bits(fade_end)
+    });
+  }
+
+  let mut min = vertices[0].position;
+  let mut max = vertices[0].position;
+  for vertex in &vertices {
+    min = min.inf(&vertex.position);
+    max = max.sup(&vertex.position);
+  }
+
+  let vertices_count = vertices.len();
+  let ptr = Box::into_raw(vertices.into_boxed_slice());
+  let data_ptr = unsafe { slice::from_raw_parts_mut(ptr as *mut u8, vertices_count * std::mem::size_of::<super::Vertex>()) as *mut [u8] };
+  let vertices_data = unsafe { Box::from_raw(data_ptr) };
+
+  let indices_count = all_indices.len();
+  let ptr = Box::into_raw(all_indices.into_boxed_slice());
+  let data_ptr = unsafe { slice::from_raw_parts_mut(ptr as *mut u8, indices_count * std::mem::size_of::<u32>()) as *mut [u8] };
+  let indices_data = unsafe { Box::from_raw(data_ptr) };
+
+  Some((Mesh {
+    vertices: vertices_data,
+    indices: Some(indices_data),
+    parts: mesh_ranges.into_boxed_slice(),
+    lods: Box::new([]),
+    bounding_box: Some(BoundingBox::new(min, max)),
+    vertex_count: vertices_count as u32,
+    skinning: None
+  }, materials, fades))
+}
+
+/// Clips the convex polygon against the edges of the quad (Sutherland-Hodgman).
+fn clip_polygon(mut polygon: Vec<ClipVertex>, corners: &[Vec2; 4], orientation: f32) -> Vec<ClipVertex> {
+  for i in 0..4 {
+    if polygon.len() < 3 {
+      return Vec::new();
+    }
+    let edge_start = corners[i];
+    let edge = corners[(i + 1) % 4] - edge_start;
+    let distance = |coords: &Vec2| edge.perp(&(coords - edge_start)) * orientation;
+
+    let mut clipped = Vec::<ClipVertex>::with_capacity(polygon.len() + 1);
+    for (index, current) in polygon.iter().enumerate() {
+      let next = &polygon[(index + 1) % polygon.len()];
+      let current_distance = distance(&current.coords);
+      let next_distance = distance(&next.coords);
+      if current_distance >= 0f32 {
+        clipped.push(current.clone());
+      }
+      if (current_distance >= 0f32) != (next_distance >= 0f32) {
+        let t = current_distance / (current_distance - next_distance);
+        clipped.push(ClipVertex {
+          position: current.position.lerp(&next.position, t),
+          coords: current.coords.lerp(&next.coords, t)
+        });
+      }
+    }
+    polygon = clipped;
+  }
+  polygon
+}
+
+/// Interpolates the texture coordinates of the quad corners using the triangles (0, 1, 2) and (0, 2, 3).
+fn interpolate_texcoords(coords: &Vec2, corners: &[Vec2; 4], texcoords: &[Vec2; 4]) -> Vec2 {
+  let first = barycentrics(coords, &corners[0], &corners[1], &corners[2]);
+  let second = barycentrics(coords, &corners[0], &corners[2], &corners[3]);
+  match (first, second) {
+    (Some(weights), _) if weights.min() >= -0.0001f32 || second.is_none() =>
+      texcoords[0] * weights.x + texcoords[1] * weights.y + texcoords[2] * weights.z,
+    (_, Some(weights)) =>
+      texcoords[0] * weights.x + texcoords[2] * weights.y + texcoords[3] * weights.z,
+    _ => texcoords[0]
+  }
+}
+
+fn barycentrics(point: &Vec2, a: &Vec2, b: &Vec2, c: &Vec2) -> Option<Vec3> {
+  let ab = b - a;
+  let ac = c - a;
+  let ap = point - a;
+  let denominator = ab.perp(&ac);
+  if denominator.abs() <= f32::EPSILON {
+    return None;
+  }
+  let v = ap.perp(&ac) / denominator;
+  let w = ab.perp(&ap) / denominator;
+  Some(Vec3::new(1f32 - v - w, v, w))
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use sourcerenderer_bsp::game_lumps::{DetailPropOrientation, DetailPropType};
+use nalgebra::Unit;
+use sourcerenderer_core::{Matrix4, Quaternion, Vec3, Vec4};
+
+use crate::renderer::{DetailModel, DetailModelInstance, DetailProps, DetailSprite, DetailSpriteOrientation};
+
+use super::{BspLumps, SCALING_FACTOR};
+use super::bsp_level::BspLevelLoader;
+
+const DEFAULT_SPRITE_MATERIAL: &str = "detail/detailsprites";
+// The defaults of cl_detaildist and cl_detailfade in Source units.
+const FADE_END: f32 = 1200f32;
+const FADE_LENGTH: f32 = 400f32;
+
+/// Converts the detail props of the map. Sprites and shapes all use the sprite material of the map,
+/// shapes get expanded into the crossed or leaning quads that Source draws for them.
+pub(super) fn build_detail_props(lumps: &BspLumps) -> Option<DetailProps> {
+  let dict = &lumps.detail_props;
+  if dict.props.is_empty() {
+    return None;
+  }
+
+  let sprite_material = lumps.entities.entities.iter()
+    .find(|entity| entity.get("classname") == Some("worldspawn"))
+    .and_then(|entity| entity.get("detailmaterial"))
+    .unwrap_or(DEFAULT_SPRITE_MATERIAL)
+    .replace('\\', "/")
+    .to_lowercase();
+
+  let mut sprites = Vec::<DetailSprite>::new();
+  let mut model_instances = HashMap::<u16, Vec<DetailModelInstance>>::new();
+  for prop in dict.props.iter() {
+    let position = BspLevelLoader::fixup_position(&prop.origin);
+    let rotation = BspLevelLoader::fixup_rotation(&prop.angles);
+    let lighting = prop.lighting.to_rgb_f32();
+    let color = Vec3::new(lighting[0], lighting[1], lighting[2]);
+
+    if prop.prop_type == DetailPropType::Model {
+      if dict.names.get(prop.detail_model as usize).is_none() {
+        continue;
+      }
+      let transform = Matrix4::new_translation(&position)
+        * rotation.to_homogeneous()
+        * Matrix4::new_scaling(prop.scale);
+      model_instances.entry(prop.detail_model).or_default().push(DetailModelInstance {
+        transform,
+        color
+      });
+      continue;
+    }
+
+    let sprite = if let Some(sprite) = dict.sprites.get(prop.detail_model as usize) {
+      sprite
+    } else {
+      continue;
+    };
+    let scale = prop.scale * SCALING_FACTOR;
+    // The upper left corner is the one with the lowest value along the right axis and the highest along the up axis.
+    let extents = Vec4::new(
+      sprite.upper_left.x * scale,
+      sprite.lower_right.y * scale,
+      sprite.lower_right.x * scale,
+      sprite.upper_left.y * scale
+    );
+    let right = rotation * Vec3::new(0f32, 0f32, 1f32);
+    let up = rotation * Vec3::new(0f32, 1f32, 0f32);
+    let tex_coords = Vec4::new(sprite.tex_upper_left.x, sprite.tex_upper_left.y, sprite.tex_lower_right.x, sprite.tex_lower_right.y);
+    // Shapes are made of several quads that keep the orientation of the prop.
+    let (orientation, quads) = match prop.prop_type {
+      DetailPropType::ShapeCross => (DetailSpriteOrientation::Fixed, shape_cross_quads(&right, &up)),
+      DetailPropType::ShapeTri => (DetailSpriteOrientation::Fixed, shape_tri_quads(&right, &up, &extents, prop.shape_angle, prop.shape_size)),
+      _ => {
+        let orientation = match prop.orientation {
+          DetailPropOrientation::Normal => DetailSpriteOrientation::Fixed,
+          DetailPropOrientation::ScreenAligned => DetailSpriteOrientation::ScreenAligned,
+          DetailPropOrientation::ScreenAlignedVertical => DetailSpriteOrientation::ScreenAlignedVertical
+        };
+        (orientation, vec![ShapeQuad { offset: Vec3::zeros(), right, up }])
+      }
+    };
+    for quad in quads {
+      sprites.push(DetailSprite {
+        position: position + quad.offset,
+        orientation,
+        right: quad.right,
+        up: quad.up,
+        extents,
+        tex_coords,
+        color
+      });
+    }
+  }
+
+  let mut models: Vec<DetailModel> = model_instances.into_iter().map(|(model_index, instances)| DetailModel {
+    model_path: dict.names[model_index as usize].to_lowercase().replace('\\', "/"),
+    instances: instances.into_boxed_slice()
+  }).collect();
+  models.sort_by(|a, b| a.model_path.cmp(&b.model_path));
+
+  Some(DetailProps {
+    sprite_material_path: "materials/".to_string() + sprite_material.trim_end_matches(".vmt") + ".vmt",
+    sprites: sprites.into_boxed_slice(),
+    models: models.into_boxed_slice(),
+    fade_start: (FADE_END - FADE_LENGTH) * SCALING_FACTOR,
+    fade_end: FADE_END * SCALING_FACTOR
+  })
+}
+
+/// One quad of a detail shape, relative to the origin of the prop.
+struct ShapeQuad {
+  offset: Vec3,
+  right: Vec3,
+  up: Vec3
+}
+
+/// Two quads that cross each other at a right angle.
+fn shape_cross_quads(right: &Vec3, up: &Vec3) -> Vec<ShapeQuad> {
+  let quarter_turn = Quaternion::from_axis_angle(&Unit::new_normalize(*up), std::f32::consts::FRAC_PI_2);
+  vec![
+    ShapeQuad { offset: Vec3::zeros(), right: *right, up: *up },
+    ShapeQuad { offset: Vec3::zeros(), right: quarter_turn * right, up: *up }
+  ]
+}
+
+/// Three quads that form a triangle around the origin. They get pushed out by the shape size,
+/// a fraction of the sprite width, and lean towards the center by the shape angle in degrees.
+fn shape_tri_quads(right: &Vec3, up: &Vec3, extents: &Vec4, shape_angle: u8, shape_size: u8) -> Vec<ShapeQuad> {
+  let distance = shape_size as f32 / 255f32 * (extents.z - extents.x);
+  let lean = (shape_angle as f32).to_radians();
+  let up_axis = Unit::new_normalize(*up);
+  (0..3).map(|index| {
+    let turn = Quaternion::from_axis_angle(&up_axis, index as f32 * 2f32 * std::f32::consts::FRAC_PI_3);
+    let quad_right = turn * right;
+    let outwards = quad_right.cross(up).normalize();
+    ShapeQuad {
+      offset: outwards * distance,
+      right: quad_right,
+      up: up * lean.cos() - outwards * lean.sin()
+    }
+  }).collect()
+}
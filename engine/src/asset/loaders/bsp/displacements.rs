@@ -0,0 +1,200 @@
+use sourcerenderer_bsp::{DispInfo, DispMultiblend};
+use sourcerenderer_core::Vec3;
+
+use super::BspLumps;
+
+// Neighbors share vertices at exactly the same undisplaced position, the tolerance only covers float imprecision.
+const BASE_POSITION_EPSILON: f32 = 0.1f32;
+
+/// The vertex grid of a displacement in Source space, row by row.
+pub(super) struct DisplacementGrid {
+  pub(super) size: usize,
+  /// The positions on the undisplaced base face, neighbors are matched up using those.
+  base_positions: Vec<Vec3>,
+  pub(super) positions: Vec<Vec3>,
+  pub(super) normals: Vec<Vec3>,
+  /// The four-way blend weights packed into one byte per material, zero if the displacement doesn't use multiblend.
+  pub(super) multiblend: Vec<u32>
+}
+
+impl DisplacementGrid {
+  fn is_on_border(&self, index: usize) -> bool {
+    let (x, y) = (index % self.size, index / self.size);
+    x == 0 || y == 0 || x == self.size - 1 || y == self.size - 1
+  }
+
+  /// The vertex indices along the four borders of the grid.
+  fn borders(&self) -> [Vec<usize>; 4] {
+    let size = self.size;
+    [
+      (0..size).collect(),
+      (0..size).map(|x| x + (size - 1) * size).collect(),
+      (0..size).map(|y| y * size).collect(),
+      (0..size).map(|y| size - 1 + y * size).collect()
+    ]
+  }
+}
+
+/// Builds the vertex grids of all displacements with per vertex normals calculated from the grid.
+/// The vertices along the borders then get stitched with the neighbors from the neighbor tables:
+/// shared vertices get the averaged normal and position and vertices that end up in the middle
+/// of an edge of a neighbor with a lower resolution get moved onto that edge to avoid cracks.
+pub(super) fn build_displacement_grids(lumps: &BspLumps, multiblend: &[DispMultiblend]) -> Vec<DisplacementGrid> {
+  let mut multiblend_start = 0usize;
+  let grids: Vec<DisplacementGrid> = lumps.disp_infos.iter().map(|disp_info| {
+    let mut grid = build_grid(lumps, disp_info);
+    let vertex_count = grid.size * grid.size;
+    if disp_info.has_multiblend() && multiblend.len() >= multiblend_start + vertex_count {
+      grid.multiblend = multiblend[multiblend_start .. multiblend_start + vertex_count].iter()
+        .map(|blend| pack_weights(&[blend.multiblend.x, blend.multiblend.y, blend.multiblend.z, blend.multiblend.w]))
+        .collect();
+      multiblend_start += vertex_count;
+    }
+    grid
+  }).collect();
+
+  let mut stitched_positions: Vec<Vec<Vec3>> = grids.iter().map(|grid| grid.positions.clone()).collect();
+  let mut stitched_normals: Vec<Vec<Vec3>> = grids.iter().map(|grid| grid.normals.clone()).collect();
+  for (disp_index, disp_info) in lumps.disp_infos.iter().enumerate() {
+    let grid = &grids[disp_index];
+    let mut neighbors: Vec<usize> = disp_info.edge_neighbors.iter()
+      .flat_map(|neighbor| neighbor.sub_neighbors.iter())
+      .filter(|sub_neighbor| sub_neighbor.is_valid())
+      .map(|sub_neighbor| sub_neighbor.neighbor_index as usize)
+      .chain(disp_info.corner_neighbors.iter().flat_map(|corner| corner.corner_neighbor_indices().iter().map(|index| *index as usize)))
+      .filter(|neighbor_index| *neighbor_index != disp_index && *neighbor_index < grids.len())
+      .collect();
+    neighbors.sort_unstable();
+    neighbors.dedup();
+    if neighbors.is_empty() {
+      continue;
+    }
+
+    for vertex_index in (0..grid.size * grid.size).filter(|index| grid.is_on_border(*index)) {
+      let base_position = grid.base_positions[vertex_index];
+      let mut position_sum = grid.positions[vertex_index];
+      let mut normal_sum = grid.normals[vertex_index];
+      let mut shared_count = 1f32;
+      let mut edge_position = None;
+      for neighbor in neighbors.iter().map(|neighbor_index| &grids[*neighbor_index]) {
+        if let Some(shared) = find_shared_vertex(neighbor, &base_position) {
+          position_sum += neighbor.positions[shared];
+          normal_sum += neighbor.normals[shared];
+          shared_count += 1f32;
+        } else if let Some((position, normal)) = find_edge_position(neighbor, &base_position) {
+          normal_sum += normal;
+          edge_position = Some(position);
+        }
+      }
+      stitched_positions[disp_index][vertex_index] = edge_position.unwrap_or(position_sum / shared_count);
+      stitched_normals[disp_index][vertex_index] = normal_sum.try_normalize(f32::EPSILON).unwrap_or(grid.normals[vertex_index]);
+    }
+  }
+
+  grids.into_iter()
+    .zip(stitched_positions.into_iter().zip(stitched_normals.into_iter()))
+    .map(|(grid, (positions, normals))| DisplacementGrid {
+      positions,
+      normals,
+      ..grid
+    })
+    .collect()
+}
+
+fn build_grid(lumps: &BspLumps, disp_info: &DispInfo) -> DisplacementGrid {
+  let face = &lumps.faces[disp_info.map_face as usize];
+  let plane = &lumps.planes[face.plane_index as usize];
+
+  // The displacement starts at the corner closest to its start position.
+  let mut corners = [Vec3::default(); 4];
+  let mut first_corner = 0;
+  let mut first_corner_dist_squared = f32::MAX;
+  for surf_edge_index in face.first_edge..face.first_edge + face.edges_count as i32 {
+    let edge_index = lumps.surface_edges[surf_edge_index as usize].index;
+    let edge = lumps.edges[edge_index.unsigned_abs() as usize];
+    let vert_index = edge.vertex_index[if edge_index >= 0 { 0 } else { 1 }];
+    let position = lumps.vertices[vert_index as usize].position;
+    let index = (surf_edge_index - face.first_edge) as usize;
+    corners[index] = position;
+    let dist_squared = (disp_info.start_position - position).magnitude_squared();
+    if dist_squared < first_corner_dist_squared {
+      first_corner = index;
+      first_corner_dist_squared = dist_squared;
+    }
+  }
+  let corners = [
+    corners[first_corner & 3],
+    corners[(first_corner + 1) & 3],
+    corners[(first_corner + 2) & 3],
+    corners[(first_corner + 3) & 3],
+  ];
+
+  let size = (1usize << disp_info.power) + 1;
+  let mut base_positions = Vec::<Vec3>::with_capacity(size * size);
+  let mut positions = Vec::<Vec3>::with_capacity(size * size);
+  for y in 0..size {
+    for x in 0..size {
+      let tx = x as f32 / (size - 1) as f32;
+      let ty = y as f32 / (size - 1) as f32;
+      let base_position = ty * ((1f32 - tx) * corners[1] + tx * corners[2]) + (1f32 - ty) * ((1f32 - tx) * corners[0] + tx * corners[3]);
+      let disp_vert = &lumps.disp_verts[disp_info.disp_vert_start as usize + x + y * size];
+      base_positions.push(base_position);
+      positions.push(base_position + disp_vert.vec * disp_vert.dist);
+    }
+  }
+
+  // The grid axes don't necessarily form a right handed basis with the face normal.
+  let winding = (base_positions[size - 1] - base_positions[0]).cross(&(base_positions[(size - 1) * size] - base_positions[0]));
+  let winding_sign = if winding.dot(&plane.normal) < 0f32 { -1f32 } else { 1f32 };
+  let mut normals = Vec::<Vec3>::with_capacity(size * size);
+  for y in 0..size {
+    for x in 0..size {
+      let dx = positions[(x + 1).min(size - 1) + y * size] - positions[x.saturating_sub(1) + y * size];
+      let dy = positions[x + (y + 1).min(size - 1) * size] - positions[x + y.saturating_sub(1) * size];
+      let normal = dx.cross(&dy) * winding_sign;
+      normals.push(normal.try_normalize(f32::EPSILON).unwrap_or(plane.normal));
+    }
+  }
+
+  DisplacementGrid {
+    size,
+    base_positions,
+    positions,
+    normals,
+    multiblend: vec![0u32; size * size]
+  }
+}
+
+fn find_shared_vertex(grid: &DisplacementGrid, base_position: &Vec3) -> Option<usize> {
+  (0..grid.size * grid.size)
+    .filter(|index| grid.is_on_border(*index))
+    .find(|index| (grid.base_positions[*index] - base_position).magnitude_squared() < BASE_POSITION_EPSILON * BASE_POSITION_EPSILON)
+}
+
+/// Finds the border segment of the grid that the base position lies on and interpolates its position and normal.
+fn find_edge_position(grid: &DisplacementGrid, base_position: &Vec3) -> Option<(Vec3, Vec3)> {
+  for border in grid.borders().iter() {
+    for segment in border.windows(2) {
+      let start = grid.base_positions[segment[0]];
+      let end = grid.base_positions[segment[1]];
+      let direction = end - start;
+      let t = (base_position - start).dot(&direction) / direction.magnitude_squared();
+      if t <= 0f32 || t >= 1f32 {
+        continue;
+      }
+      if (start + direction * t - base_position).magnitude_squared() >= BASE_POSITION_EPSILON * BASE_POSITION_EPSILON {
+        continue;
+      }
+      let position = grid.positions[segment[0]].lerp(&grid.positions[segment[1]], t);
+      let normal = grid.normals[segment[0]].lerp(&grid.normals[segment[1]], t);
+      return Some((position, normal));
+    }
+  }
+  None
+}
+
+fn pack_weights(weights: &[f32; 4]) -> u32 {
+  weights.iter().enumerate().fold(0u32, |packed, (index, weight)| {
+    packed | (((weight.clamp(0f32, 1f32) * 255f32).round() as u32) << (index * 8))
+  })
+}
@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use legion::World;
+use sourcerenderer_bsp::{Entity, EntityClass};
+use sourcerenderer_core::{Quaternion, Vec3};
+
+use crate::asset::AssetType;
+use crate::camera::PlayerStart;
+use crate::animation::AnimatedModelComponent;
+use crate::renderer::{DirectionalLightComponent, LightAttenuation, PointLightComponent, SkyCamera, SpotLightComponent, StaticRenderableComponent};
+use crate::Transform;
+
+use super::{BspLevelLoader, SCALING_FACTOR};
+
+/// Turns entities from the BSP entity lump into ECS entities.
+pub trait BspEntitySpawner: Send + Sync {
+  fn matches(&self, class: &EntityClass) -> bool;
+  /// Assets that the spawned components reference have to be added to `assets_to_load`.
+  fn spawn(&self, entity: &Entity, world: &mut World, assets_to_load: &mut Vec<(String, AssetType)>);
+}
+
+/// Key values of entities that no spawner handled, so game code can still query them.
+#[derive(Clone, Debug)]
+pub struct EntityKeyValues {
+  pub class_name: String,
+  pub key_values: HashMap<String, String>
+}
+
+impl EntityKeyValues {
+  pub fn get(&self, key: &str) -> Option<&str> {
+    self.key_values.get(&key.to_lowercase()).map(|s| s.as_str())
+  }
+}
+
+pub(super) fn default_entity_spawners() -> Vec<Box<dyn BspEntitySpawner>> {
+  vec![
+    Box::new(LightSpawner {}),
+    Box::new(SpotLightSpawner {}),
+    Box::new(LightEnvironmentSpawner {}),
+    Box::new(PlayerStartSpawner {}),
+    Box::new(SkyCameraSpawner {}),
+    Box::new(PropSpawner {})
+  ]
+}
+
+pub(super) fn spawn_key_values(entity: &Entity, world: &mut World) {
+  let key_values = EntityKeyValues {
+    class_name: entity.get("classname").unwrap_or_default().to_string(),
+    key_values: entity.key_values().clone()
+  };
+  if entity.get("origin").is_some() {
+    world.push((key_values, entity_transform(entity)));
+  } else {
+    world.push((key_values,));
+  }
+}
+
+pub(super) fn parse_vec3(value: &str) -> Option<Vec3> {
+  let mut parts = value.split_whitespace().map(|part| part.parse::<f32>());
+  let x = parts.next()?.ok()?;
+  let y = parts.next()?.ok()?;
+  let z = parts.next()?.ok()?;
+  Some(Vec3::new(x, y, z))
+}
+
+fn entity_transform(entity: &Entity) -> Transform {
+  let origin = entity.get("origin").and_then(parse_vec3).unwrap_or_else(Vec3::zeros);
+  let angles = entity.get("angles").and_then(parse_vec3).unwrap_or_else(Vec3::zeros);
+  let scale = entity.get("modelscale").and_then(|scale| scale.parse::<f32>().ok()).unwrap_or(1f32);
+  Transform {
+    position: BspLevelLoader::fixup_position(&origin),
+    rotation: BspLevelLoader::fixup_rotation(&angles),
+    scale: Vec3::new(scale, scale, scale)
+  }
+}
+
+/// Source stores light colors as "r g b brightness" with the color channels in the range 0-255.
+fn light_intensity(entity: &Entity) -> f32 {
+  entity.get("_light")
+    .and_then(|light| light.split_whitespace().nth(3).and_then(|brightness| brightness.parse::<f32>().ok()))
+    .map(|brightness| brightness / 255f32)
+    .unwrap_or(1f32)
+}
+
+/// The color channels of "_light" are gamma encoded.
+fn light_color(entity: &Entity) -> Vec3 {
+  entity.get("_light")
+    .and_then(parse_vec3)
+    .map(|color| (color / 255f32).map(|channel| channel.powf(2.2f32)))
+    .unwrap_or_else(|| Vec3::new(1f32, 1f32, 1f32))
+}
+
+/// Rotation for entities that shine along a direction given by their angles and pitch keys.
+/// The renderer derives the light direction by rotating the up vector.
+fn light_rotation(entity: &Entity) -> Quaternion {
+  const DEG_TO_RAD: f32 = std::f32::consts::PI / 180f32;
+  let angles = entity.get("angles").and_then(parse_vec3).unwrap_or_else(Vec3::zeros);
+  // The pitch key overrides the pitch of the angles, -90 points straight down.
+  let pitch = entity.get("pitch").and_then(|pitch| pitch.parse::<f32>().ok()).unwrap_or(-angles.x) * DEG_TO_RAD;
+  let yaw = angles.y * DEG_TO_RAD;
+  let direction = Vec3::new(pitch.cos() * yaw.cos(), pitch.cos() * yaw.sin(), pitch.sin());
+  let direction = BspLevelLoader::fixup_normal(&direction);
+
+  Quaternion::rotation_between(&Vec3::new(0f32, 1f32, 0f32), &direction)
+    .unwrap_or_else(|| Quaternion::from_euler_angles(std::f32::consts::PI, 0f32, 0f32))
+}
+
+/// Intensity, range and attenuation of a light or light_spot entity.
+fn light_falloff(entity: &Entity) -> (f32, f32, LightAttenuation) {
+  let attenuation_factor = |key: &str| entity.get(key).and_then(|value| value.parse::<f32>().ok()).unwrap_or(0f32).max(0f32);
+  let mut constant = attenuation_factor("_constant_attn");
+  let mut linear = attenuation_factor("_linear_attn");
+  let mut quadratic = attenuation_factor("_quadratic_attn");
+  if constant + linear + quadratic == 0f32 {
+    constant = 0f32;
+    linear = 0f32;
+    quadratic = 1f32;
+  }
+  // The brightness is the intensity at a distance of 100 units.
+  let intensity = light_intensity(entity) * (constant + linear * 100f32 + quadratic * 10000f32);
+  let attenuation = LightAttenuation {
+    constant,
+    linear: linear / SCALING_FACTOR,
+    quadratic: quadratic / (SCALING_FACTOR * SCALING_FACTOR)
+  };
+  // A distance of 0 means that the light doesn't get cut off.
+  let range = entity.get("_distance")
+    .and_then(|distance| distance.parse::<f32>().ok())
+    .filter(|distance| *distance > 0f32)
+    .map(|distance| distance * SCALING_FACTOR)
+    .unwrap_or_else(|| attenuation.cutoff_range(intensity));
+  (intensity, range, attenuation)
+}
+
+struct LightSpawner {}
+
+impl BspEntitySpawner for LightSpawner {
+  fn matches(&self, class: &EntityClass) -> bool {
+    *class == EntityClass::Light
+  }
+
+  fn spawn(&self, entity: &Entity, world: &mut World, _assets_to_load: &mut Vec<(String, AssetType)>) {
+    let (intensity, range, attenuation) = light_falloff(entity);
+    world.push((entity_transform(entity), PointLightComponent {
+      intensity,
+      color: light_color(entity),
+      range,
+      attenuation
+    }));
+  }
+}
+
+struct SpotLightSpawner {}
+
+impl BspEntitySpawner for SpotLightSpawner {
+  fn matches(&self, class: &EntityClass) -> bool {
+    *class == EntityClass::LightSpot
+  }
+
+  fn spawn(&self, entity: &Entity, world: &mut World, _assets_to_load: &mut Vec<(String, AssetType)>) {
+    // Both cone angles are stored as half angles in degrees.
+    let outer_cone = entity.get("_cone").and_then(|cone| cone.parse::<f32>().ok()).unwrap_or(45f32);
+    let inner_cone = entity.get("_inner_cone").and_then(|cone| cone.parse::<f32>().ok()).unwrap_or(30f32).min(outer_cone);
+    let mut transform = entity_transform(entity);
+    transform.rotation = light_rotation(entity);
+    let (intensity, range, attenuation) = light_falloff(entity);
+    world.push((transform, SpotLightComponent {
+      intensity,
+      color: light_color(entity),
+      range,
+      attenuation,
+      inner_cone_angle: inner_cone.to_radians(),
+      outer_cone_angle: outer_cone.to_radians()
+    }));
+  }
+}
+
+struct LightEnvironmentSpawner {}
+
+impl BspEntitySpawner for LightEnvironmentSpawner {
+  fn matches(&self, class: &EntityClass) -> bool {
+    *class == EntityClass::LightEnvironment
+  }
+
+  fn spawn(&self, entity: &Entity, world: &mut World, _assets_to_load: &mut Vec<(String, AssetType)>) {
+    let mut transform = entity_transform(entity);
+    transform.rotation = light_rotation(entity);
+    world.push((transform, DirectionalLightComponent {
+      intensity: light_intensity(entity),
+      color: light_color(entity)
+    }));
+  }
+}
+
+struct PlayerStartSpawner {}
+
+impl BspEntitySpawner for PlayerStartSpawner {
+  fn matches(&self, class: &EntityClass) -> bool {
+    *class == EntityClass::InfoPlayerStart
+  }
+
+  fn spawn(&self, entity: &Entity, world: &mut World, _assets_to_load: &mut Vec<(String, AssetType)>) {
+    world.push((entity_transform(entity), PlayerStart {}));
+  }
+}
+
+struct SkyCameraSpawner {}
+
+impl BspEntitySpawner for SkyCameraSpawner {
+  fn matches(&self, class: &EntityClass) -> bool {
+    *class == EntityClass::SkyCamera
+  }
+
+  fn spawn(&self, entity: &Entity, world: &mut World, _assets_to_load: &mut Vec<(String, AssetType)>) {
+    let scale = entity.get("scale")
+      .and_then(|scale| scale.parse::<f32>().ok())
+      .filter(|scale| *scale > 0f32)
+      .unwrap_or(16f32);
+    world.push((entity_transform(entity), SkyCamera { scale }));
+  }
+}
+
+struct PropSpawner {}
+
+impl BspEntitySpawner for PropSpawner {
+  fn matches(&self, class: &EntityClass) -> bool {
+    matches!(class, EntityClass::PropDynamic
+      | EntityClass::PropDynamicOverride
+      | EntityClass::PropPhysics
+      | EntityClass::PropPhysicsOverride
+      | EntityClass::PropPhysicsMultiplayer)
+  }
+
+  fn spawn(&self, entity: &Entity, world: &mut World, assets_to_load: &mut Vec<(String, AssetType)>) {
+    let model = entity.get("model");
+    if model.is_none() {
+      return;
+    }
+    let model = model.unwrap().to_string();
+    assets_to_load.push((model.clone(), AssetType::Model));
+    let renderable = StaticRenderableComponent {
+      model_path: model,
+      receive_shadows: true,
+      cast_shadows: entity.get("disableshadows").is_none_or(|value| value == "0"),
+      can_move: true
+    };
+    let transform = entity_transform(entity);
+    match entity.get("defaultanim").filter(|anim| !anim.is_empty()) {
+      Some(anim) => { world.push((renderable, transform, AnimatedModelComponent::new(anim))); },
+      None => { world.push((renderable, transform)); }
+    }
+  }
+}
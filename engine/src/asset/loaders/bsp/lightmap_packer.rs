@@ -1,4 +1,3 @@
-use sourcerenderer_bsp::Lighting;
 use std::option::Option::Some;
 
 const MARGIN: u32 = 2;
@@ -23,30 +22,35 @@ impl Rect {
   }
 }
 
-pub struct LightmapPacker {
+/// Packs rectangles of texels into a texture that consists of multiple array layers.
+/// Every rectangle occupies the same spot in all layers.
+pub struct LightmapPacker<T> {
   free_list: Vec<Rect>,
-  data: Box<[u32]>,
+  data: Box<[T]>,
   lightmap_width: u32,
   lightmap_height: u32,
+  layers: u32,
   used_area: u32
 }
 
-impl LightmapPacker {
+impl<T: Copy + Default + PartialEq> LightmapPacker<T> {
   pub fn new(lightmap_width: u32, lightmap_height: u32) -> Self {
+    Self::new_layered(lightmap_width, lightmap_height, 1)
+  }
+
+  pub fn new_layered(lightmap_width: u32, lightmap_height: u32, layers: u32) -> Self {
     assert_ne!(lightmap_width, 0);
     assert_ne!(lightmap_height, 0);
+    assert_ne!(layers, 0);
     let texture_rect = Rect::new(lightmap_width, lightmap_height);
-    let size = (lightmap_width * lightmap_height) as usize;
-    let mut data = Vec::<u32>::with_capacity(size);
-    for _i in 0..size {
-      data.push(0); // this is slow and stupid
-    }
+    let size = (lightmap_width * lightmap_height * layers) as usize;
 
     Self {
       free_list: vec![texture_rect],
-      data: data.into_boxed_slice(),
+      data: vec![T::default(); size].into_boxed_slice(),
       lightmap_width,
       lightmap_height,
+      layers,
       used_area: 0
     }
   }
@@ -116,23 +120,35 @@ impl LightmapPacker {
     spot
   }
 
-  pub fn add_samples(&mut self, width: u32, height: u32, data: &[Lighting]) -> (u32, u32) {
+  /// Adds texels to the first layer.
+  pub fn add_texels(&mut self, width: u32, height: u32, data: &[T]) -> (u32, u32) {
     assert!((data.len() as u32) >= width * height);
+    let position = self.allocate(width, height);
+    self.write_texels(0, position, width, height, |i| data[i]);
+    position
+  }
+
+  /// Reserves a spot in every layer.
+  pub fn allocate(&mut self, width: u32, height: u32) -> (u32, u32) {
     let rect = self.find_space(width, height).unwrap();
+    self.used_area += width * height;
+    (rect.x, rect.y)
+  }
+
+  pub fn write_texels<F: Fn(usize) -> T>(&mut self, layer: u32, position: (u32, u32), width: u32, height: u32, texel: F) {
+    assert!(layer < self.layers);
+    let layer_offset = (layer * self.lightmap_width * self.lightmap_height) as usize;
     for y in 0 .. height {
       for x in 0 .. width {
         let i = (x + y * width) as usize;
-        let offset = (x + rect.x + (y + rect.y) * self.lightmap_width) as usize;
-        let sample = &data[i].color;
-        debug_assert_eq!(self.data[offset], 0u32);
-        self.data[offset] = sample.to_u32_color();
+        let offset = layer_offset + (x + position.0 + (y + position.1) * self.lightmap_width) as usize;
+        debug_assert!(self.data[offset] == T::default());
+        self.data[offset] = texel(i);
       }
     }
-    self.used_area += width * height;
-    (rect.x, rect.y)
   }
 
-  pub fn take_data(self) -> Box<[u32]> {
+  pub fn take_data(self) -> Box<[T]> {
     self.data
   }
 
@@ -140,7 +156,12 @@ impl LightmapPacker {
     self.lightmap_width
   }
 
+  /// The height of a single layer.
   pub fn texture_height(&self) -> u32 {
     self.lightmap_height
   }
+
+  pub fn layers(&self) -> u32 {
+    self.layers
+  }
 }
@@ -1,8 +1,18 @@
 mod bsp_lumps;
 mod bsp_level;
+mod q3_bsp_level;
 mod lightmap_packer;
 mod vertex;
+mod entities;
+mod skybox;
+mod static_prop_lighting;
+mod decals;
+mod detail_props;
+mod displacements;
 
 pub use bsp_level::BspLevelLoader;
+pub(crate) use bsp_level::SCALING_FACTOR;
+pub use q3_bsp_level::Q3BspLevelLoader;
 use bsp_lumps::BspLumps;
 pub use vertex::Vertex;
+pub use entities::{BspEntitySpawner, EntityKeyValues};
@@ -0,0 +1,455 @@
+use core::slice;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufReader, Read};
+use std::ops::{Add, Mul};
+use std::path::Path;
+use std::sync::Arc;
+
+use legion::{World, WorldOptions};
+use log::warn;
+use sourcerenderer_core::{Platform, Quaternion, Vec2, Vec3, Vec4};
+use sourcerenderer_core::graphics::{Format, SampleCount, TextureDimension, TextureInfo, TextureUsage};
+use sourcerenderer_q3_bsp::{BrushModel, Entity, Face, FaceType, Leaf, Lightmap, Map, MeshVert, Node, Plane, SurfaceFlags, Vertex as Q3Vertex, VisData, LIGHTMAP_SIZE};
+
+use crate::asset::{Asset, AssetLoader, AssetManager, AssetType, Material, Mesh, Model};
+use crate::asset::asset_manager::{AssetFile, AssetLoaderProgress, AssetLoaderResult, AssetLoadPriority, MeshRange, Texture};
+use crate::camera::PlayerStart;
+use crate::math::BoundingBox;
+use crate::renderer::{ClusterVisibility, ClusterVisibilityNode, Lightmap as LightmapComponent, PotentiallyVisibleSet, StaticRenderableComponent};
+use crate::Transform;
+
+use super::{BspLevelLoader, EntityKeyValues, Vertex, SCALING_FACTOR};
+use super::entities::parse_vec3;
+use super::lightmap_packer::LightmapPacker;
+
+// REFERENCE
+// https://www.mralligator.com/q3/
+// https://github.com/id-Software/Quake-III-Arena/blob/master/code/renderer/tr_curve.c
+
+/// Loads IBSP version 46 levels used by Quake 3 and games built on its engine.
+pub struct Q3BspLevelLoader {}
+
+/// Every biquadratic patch gets split into this many quads along each axis.
+const PATCH_TESSELLATION_LEVEL: u32 = 8;
+const TEXTURE_EXTENSIONS: [&str; 3] = [".tga", ".jpg", ".png"];
+
+impl Q3BspLevelLoader {
+  pub fn new() -> Self {
+    Self {}
+  }
+
+  fn build_face(
+    face: &Face,
+    vertices: &[Q3Vertex],
+    mesh_verts: &[MeshVert],
+    lightmaps: &LightmapAtlas,
+    brush_vertices: &mut Vec<Vertex>,
+    material_indices: &mut Vec<u32>) {
+    let face_vertices = lump_range(vertices, face.first_vertex, face.vertex_count);
+    let face_mesh_verts = lump_range(mesh_verts, face.mesh_vert, face.mesh_vert_count);
+    let (face_vertices, face_mesh_verts) = match (face_vertices, face_mesh_verts) {
+      (Some(face_vertices), Some(face_mesh_verts)) if face_mesh_verts.iter().all(|mesh_vert| mesh_vert.index >= 0 && (mesh_vert.index as usize) < face_vertices.len()) => (face_vertices, face_mesh_verts),
+      _ => {
+        warn!("Skipping face with out of bounds vertices");
+        return;
+      }
+    };
+
+    let base_vertex = brush_vertices.len() as u32;
+    for vertex in face_vertices {
+      brush_vertices.push(Vertex {
+        position: BspLevelLoader::fixup_position(&vertex.position),
+        normal: BspLevelLoader::fixup_normal(&vertex.normal),
+        uv: vertex.tex_coord[0],
+        lightmap_uv: lightmaps.lightmap_uv(face.lightmap_index, &vertex.tex_coord[1]),
+        alpha: 1f32,
+        ..Default::default()
+      });
+    }
+
+    for mesh_vert in face_mesh_verts {
+      material_indices.push(base_vertex + mesh_vert.index as u32);
+    }
+  }
+
+  fn build_patch_face(
+    face: &Face,
+    vertices: &[Q3Vertex],
+    lightmaps: &LightmapAtlas,
+    brush_vertices: &mut Vec<Vertex>,
+    material_indices: &mut Vec<u32>) {
+    let width = face.size[0] as usize;
+    let height = face.size[1] as usize;
+    if width < 3 || height < 3 || width * height > face.vertex_count as usize {
+      return;
+    }
+    let control_points = if let Some(control_points) = lump_range(vertices, face.first_vertex, face.vertex_count) {
+      control_points
+    } else {
+      warn!("Skipping patch with out of bounds control points");
+      return;
+    };
+
+    // The control point grid consists of biquadratic patches that share their edges.
+    let row_length = PATCH_TESSELLATION_LEVEL + 1;
+    for patch_y in 0..(height - 1) / 2 {
+      for patch_x in 0..(width - 1) / 2 {
+        let mut patch = Vec::<&Q3Vertex>::with_capacity(9);
+        for y in 0..3 {
+          for x in 0..3 {
+            patch.push(&control_points[(patch_y * 2 + y) * width + patch_x * 2 + x]);
+          }
+        }
+
+        let base_vertex = brush_vertices.len() as u32;
+        for y in 0..=PATCH_TESSELLATION_LEVEL {
+          let v = y as f32 / PATCH_TESSELLATION_LEVEL as f32;
+          for x in 0..=PATCH_TESSELLATION_LEVEL {
+            let u = x as f32 / PATCH_TESSELLATION_LEVEL as f32;
+            let position = evaluate_patch(&patch, u, v, |vertex| vertex.position);
+            let normal = evaluate_patch(&patch, u, v, |vertex| vertex.normal);
+            let uv = evaluate_patch(&patch, u, v, |vertex| vertex.tex_coord[0]);
+            let lightmap_uv = evaluate_patch(&patch, u, v, |vertex| vertex.tex_coord[1]);
+            brush_vertices.push(Vertex {
+              position: BspLevelLoader::fixup_position(&position),
+              normal: BspLevelLoader::fixup_normal(&normal.try_normalize(f32::EPSILON).unwrap_or(normal)),
+              uv,
+              lightmap_uv: lightmaps.lightmap_uv(face.lightmap_index, &lightmap_uv),
+              alpha: 1f32,
+              ..Default::default()
+            });
+          }
+        }
+
+        for y in 0..PATCH_TESSELLATION_LEVEL {
+          for x in 0..PATCH_TESSELLATION_LEVEL {
+            let top_left = base_vertex + y * row_length + x;
+            let bottom_left = top_left + row_length;
+            push_patch_triangle(brush_vertices, material_indices, [top_left, bottom_left, top_left + 1]);
+            push_patch_triangle(brush_vertices, material_indices, [top_left + 1, bottom_left, bottom_left + 1]);
+          }
+        }
+      }
+    }
+  }
+
+  fn build_cluster_visibility(nodes: &[Node], planes: &[Plane], leafs: &[Leaf], vis_data: &VisData) -> ClusterVisibility {
+    let nodes: Vec<ClusterVisibilityNode> = nodes.iter().map(|node| {
+      let plane = &planes[node.plane as usize];
+      let normal = BspLevelLoader::fixup_normal(&plane.normal);
+      ClusterVisibilityNode {
+        plane: Vec4::new(normal.x, normal.y, normal.z, plane.dist * SCALING_FACTOR),
+        children: node.children
+      }
+    }).collect();
+    let leaf_clusters: Vec<i16> = leafs.iter().map(|leaf| leaf.cluster as i16).collect();
+    let cluster_count = vis_data.clusters_count.max(0) as u32;
+    ClusterVisibility::new(nodes.into_boxed_slice(), leaf_clusters.into_boxed_slice(), cluster_count, |cluster| {
+      vis_data.visible_clusters(cluster as i32)
+        .map(|bytes| bytes.to_vec().into_boxed_slice())
+        .unwrap_or_else(|| vec![0xFFu8; (cluster_count as usize + 7) / 8].into_boxed_slice())
+    })
+  }
+
+  fn spawn_entities(entities: &[Entity], world: &mut World) {
+    for entity in entities {
+      let class_name = entity.class_name().unwrap_or_default();
+      let origin = entity.get("origin").and_then(parse_vec3).unwrap_or_else(Vec3::zeros);
+      let angle = entity.get("angle").and_then(|angle| angle.parse::<f32>().ok()).unwrap_or(0f32);
+      let transform = Transform {
+        position: BspLevelLoader::fixup_position(&origin),
+        rotation: BspLevelLoader::fixup_rotation(&Vec3::new(0f32, angle, 0f32)),
+        scale: Vec3::new(1f32, 1f32, 1f32)
+      };
+
+      match class_name {
+        "info_player_start" | "info_player_deathmatch" => {
+          world.push((transform, PlayerStart {}));
+        }
+        _ => {
+          let key_values = EntityKeyValues {
+            class_name: class_name.to_string(),
+            key_values: entity.key_values().clone()
+          };
+          world.push((key_values, transform));
+        }
+      }
+    }
+  }
+
+  fn find_texture<P: Platform>(manager: &Arc<AssetManager<P>>, shader_name: &str) -> Option<String> {
+    TEXTURE_EXTENSIONS.iter()
+      .map(|extension| shader_name.to_string() + extension)
+      .find(|path| manager.file_exists(path))
+  }
+}
+
+impl<P: Platform> AssetLoader<P> for Q3BspLevelLoader {
+  fn matches(&self, file: &mut AssetFile) -> bool {
+    if !file.path.ends_with(".bsp") {
+      return false;
+    }
+    let mut identifier = [0u8; 4];
+    file.read_exact(&mut identifier).is_ok() && &identifier == b"IBSP"
+  }
+
+  fn load(&self, asset_file: AssetFile, manager: &Arc<AssetManager<P>>, _priority: AssetLoadPriority, progress: &Arc<AssetLoaderProgress>) -> Result<AssetLoaderResult, ()> {
+    let path = asset_file.path.clone();
+    let name = Path::new(&path).file_name().unwrap().to_str().unwrap();
+    let buf_reader = BufReader::new(asset_file);
+    let mut map = Map::read(name, buf_reader).map_err(|e| warn!("Could not read Quake 3 map {}: {:?}", path, e))?;
+    let entities = map.read_entities().map_err(|_e| ())?;
+    let shaders = map.read_shaders().map_err(|_e| ())?;
+    let planes = map.read_planes().map_err(|_e| ())?;
+    let nodes = map.read_nodes().map_err(|_e| ())?;
+    let leafs = map.read_leafs().map_err(|_e| ())?;
+    let brush_models = map.read_brush_models().map_err(|_e| ())?;
+    let vertices = map.read_vertices().map_err(|_e| ())?;
+    let mesh_verts = map.read_mesh_verts().map_err(|_e| ())?;
+    let faces = map.read_faces().map_err(|_e| ())?;
+    let lightmaps = map.read_lightmaps().map_err(|_e| ())?;
+    let vis_data = map.read_vis_data().map_err(|_e| ())?;
+
+    let mut world = World::new(WorldOptions::default());
+    let lightmap_atlas = LightmapAtlas::new(&lightmaps);
+    let mut materials_to_load = HashSet::<String>::new();
+
+    for (model_index, model) in brush_models.iter().enumerate() {
+      let mut brush_vertices = Vec::<Vertex>::new();
+      let mut per_material_indices = HashMap::<String, Vec<u32>>::new();
+
+      let model_faces = if let Some(model_faces) = lump_range(&faces, model.first_face, model.num_faces) {
+        model_faces
+      } else {
+        warn!("Skipping brush model {} with out of bounds faces", model_index);
+        continue;
+      };
+      for face in model_faces {
+        let shader = match shaders.get(face.texture as usize) {
+          Some(shader) if !shader.surface_flags.intersects(SurfaceFlags::NODRAW | SurfaceFlags::SKY) => shader,
+          _ => continue
+        };
+        let material_indices = per_material_indices.entry(shader.name.to_lowercase()).or_default();
+        match face.face_type {
+          FaceType::Polygon | FaceType::Mesh => Self::build_face(face, &vertices, &mesh_verts, &lightmap_atlas, &mut brush_vertices, material_indices),
+          FaceType::Patch => Self::build_patch_face(face, &vertices, &lightmap_atlas, &mut brush_vertices, material_indices),
+          FaceType::Billboard => {}
+        }
+      }
+
+      let mut brush_indices = Vec::<u32>::new();
+      let mut mesh_ranges = Vec::<MeshRange>::new();
+      let mut materials = Vec::<String>::new();
+      for (material, indices) in per_material_indices.drain() {
+        if indices.is_empty() {
+          continue;
+        }
+        materials_to_load.insert(material.clone());
+        mesh_ranges.push(MeshRange {
+          start: brush_indices.len() as u32,
+          count: indices.len() as u32
+        });
+        brush_indices.extend_from_slice(&indices);
+        materials.push(material);
+      }
+
+      let vertices_count = brush_vertices.len();
+      if vertices_count == 0 {
+        continue;
+      }
+
+      let vertices_box = brush_vertices.into_boxed_slice();
+      let ptr = Box::into_raw(vertices_box);
+      let data_ptr = unsafe { slice::from_raw_parts_mut(ptr as *mut u8, vertices_count * std::mem::size_of::<Vertex>()) as *mut [u8] };
+      let vertices_data = unsafe { Box::from_raw(data_ptr) };
+
+      let indices_count = brush_indices.len();
+      let indices_box = brush_indices.into_boxed_slice();
+      let ptr = Box::into_raw(indices_box);
+      let data_ptr = unsafe { slice::from_raw_parts_mut(ptr as *mut u8, indices_count * std::mem::size_of::<u32>()) as *mut [u8] };
+      let indices_data = unsafe { Box::from_raw(data_ptr) };
+
+      let mesh = Mesh {
+        vertices: vertices_data,
+        indices: Some(indices_data),
+        parts: mesh_ranges.into_boxed_slice(),
+        lods: Box::new([]),
+        bounding_box: Some(Self::model_bounding_box(model)),
+        vertex_count: vertices_count as u32,
+        skinning: None
+      };
+      let mesh_name = format!("{}_brushes_mesh_{}", name, model_index);
+      manager.add_asset(&mesh_name, Asset::Mesh(mesh), AssetLoadPriority::Normal);
+
+      let model_name = format!("{}_brushes_model_{}", name, model_index);
+      manager.add_asset(&model_name, Asset::Model(Model {
+        mesh_path: mesh_name,
+        material_paths: materials
+      }), AssetLoadPriority::Normal);
+
+      world.push(
+        (StaticRenderableComponent {
+          model_path: model_name,
+          receive_shadows: true,
+          cast_shadows: true,
+          can_move: false
+        },
+         Transform {
+           position: Vec3::new(0f32, 0f32, 0f32),
+           scale: Vec3::new(1.0f32, 1.0f32, 1.0f32),
+           rotation: Quaternion::identity(),
+         })
+      );
+    }
+
+    // Quake 3 materials are shader scripts, only their base texture is used here.
+    for material in materials_to_load {
+      let texture_path = Self::find_texture(manager, &material);
+      let material_asset = if let Some(texture_path) = texture_path {
+        manager.request_asset_with_progress(&texture_path, AssetType::Texture, AssetLoadPriority::Low, progress);
+        Material::new_pbr(&texture_path, 0f32, 0f32)
+      } else {
+        Material::new_pbr_color(Vec4::new(1f32, 1f32, 1f32, 1f32), 0f32, 0f32)
+      };
+      manager.add_asset(&material, Asset::Material(material_asset), AssetLoadPriority::Low);
+    }
+
+    Self::spawn_entities(&entities.entities, &mut world);
+
+    let lightmap_path = format!("{}_lightmap", name);
+    let (lightmap_width, lightmap_height) = (lightmap_atlas.packer.texture_width(), lightmap_atlas.packer.texture_height());
+    let samples = lightmap_atlas.packer.take_data();
+    let samples_len = samples.len();
+    let ptr = Box::into_raw(samples);
+    let data_ptr = unsafe { slice::from_raw_parts_mut(ptr as *mut u8, samples_len * std::mem::size_of::<u32>()) as *mut [u8] };
+    let data = unsafe { Box::from_raw(data_ptr) };
+    manager.add_asset(&lightmap_path, Asset::Texture(Texture {
+      // The lightmap gets sampled as an array with one layer per light style, Quake 3 only has one.
+      info: TextureInfo {
+        dimension: TextureDimension::Dim2DArray,
+        format: Format::RGBA8UNorm,
+        width: lightmap_width,
+        height: lightmap_height,
+        depth: 1,
+        mip_levels: 1,
+        array_length: 1,
+        samples: SampleCount::Samples1,
+        usage: TextureUsage::COPY_DST | TextureUsage::SAMPLED,
+        supports_srgb: false,
+      },
+      data: Box::new([data])
+    }), AssetLoadPriority::Normal);
+    world.push((LightmapComponent { path: lightmap_path },));
+
+    if vis_data.clusters_count > 0 && !nodes.is_empty() {
+      world.push((PotentiallyVisibleSet(Arc::new(Self::build_cluster_visibility(&nodes, &planes, &leafs, &vis_data))),));
+    }
+
+    Ok(AssetLoaderResult::Level(world))
+  }
+}
+
+impl Q3BspLevelLoader {
+  fn model_bounding_box(model: &BrushModel) -> BoundingBox {
+    let model_min = BspLevelLoader::fixup_position(&model.min);
+    let model_max = BspLevelLoader::fixup_position(&model.max);
+    let min = Vec3::new(model_min.x.min(model_max.x), model_min.y.min(model_max.y), model_min.z.min(model_max.z));
+    let max = Vec3::new(model_min.x.max(model_max.x), model_min.y.max(model_max.y), model_min.z.max(model_max.z));
+    BoundingBox::new(min, max)
+  }
+}
+
+/// Packs the 128x128 lightmap pages of the level into a single texture.
+struct LightmapAtlas {
+  packer: LightmapPacker<u32>,
+  page_offsets: Vec<(u32, u32)>,
+  /// A white texel for faces that are not lightmapped.
+  fallback_offset: (u32, u32)
+}
+
+impl LightmapAtlas {
+  fn new(lightmaps: &[Lightmap]) -> Self {
+    // Every page needs some margin, keep one spare slot for the fallback texel.
+    let pages_per_row = ((lightmaps.len() + 1) as f32).sqrt().ceil() as u32;
+    let size = (pages_per_row * (LIGHTMAP_SIZE + 8)).next_power_of_two().max(LIGHTMAP_SIZE);
+    let mut packer = LightmapPacker::new(size, size);
+
+    let mut texels = Vec::<u32>::with_capacity((LIGHTMAP_SIZE * LIGHTMAP_SIZE) as usize);
+    let page_offsets = lightmaps.iter().map(|lightmap| {
+      texels.clear();
+      for y in 0..LIGHTMAP_SIZE {
+        for x in 0..LIGHTMAP_SIZE {
+          texels.push(overbright_texel(lightmap.texel(x, y)));
+        }
+      }
+      packer.add_texels(LIGHTMAP_SIZE, LIGHTMAP_SIZE, &texels)
+    }).collect();
+    let fallback_offset = packer.add_texels(1, 1, &[0xFFFFFFFFu32]);
+
+    Self {
+      packer,
+      page_offsets,
+      fallback_offset
+    }
+  }
+
+  fn lightmap_uv(&self, lightmap_index: i32, uv: &Vec2) -> Vec2 {
+    let atlas_size = Vec2::new(self.packer.texture_width() as f32, self.packer.texture_height() as f32);
+    let page_offset = if lightmap_index >= 0 { self.page_offsets.get(lightmap_index as usize) } else { None };
+    if let Some((x, y)) = page_offset {
+      let texel = uv * LIGHTMAP_SIZE as f32 + Vec2::new(*x as f32, *y as f32);
+      texel.component_div(&atlas_size)
+    } else {
+      let (x, y) = self.fallback_offset;
+      Vec2::new(x as f32 + 0.5f32, y as f32 + 0.5f32).component_div(&atlas_size)
+    }
+  }
+}
+
+/// Quake 3 brightens lightmaps by one bit and scales down colors that would overflow.
+fn overbright_texel(rgb: [u8; 3]) -> u32 {
+  let mut color = [rgb[0] as u32 * 2, rgb[1] as u32 * 2, rgb[2] as u32 * 2];
+  let max = color.iter().copied().max().unwrap();
+  if max > 255 {
+    for channel in &mut color {
+      *channel = *channel * 255 / max;
+    }
+  }
+  color[0] | color[1] << 8 | color[2] << 16 | 255 << 24
+}
+
+/// The elements of a lump that a face or model references with an offset and a count,
+/// `None` if the range is negative or reaches past the end of the lump.
+fn lump_range<T>(lump: &[T], first: i32, count: i32) -> Option<&[T]> {
+  if first < 0 || count < 0 {
+    return None;
+  }
+  let first = first as usize;
+  lump.get(first .. first.checked_add(count as usize)?)
+}
+
+fn quadratic_bezier<T>(points: [T; 3], t: f32) -> T
+  where T: Copy + Add<Output = T> + Mul<f32, Output = T> {
+  let s = 1f32 - t;
+  points[0] * (s * s) + points[1] * (2f32 * s * t) + points[2] * (t * t)
+}
+
+/// Evaluates an attribute of the 3x3 control points of a biquadratic patch.
+fn evaluate_patch<T, F>(patch: &[&Q3Vertex], u: f32, v: f32, attribute: F) -> T
+  where T: Copy + Add<Output = T> + Mul<f32, Output = T>,
+        F: Fn(&Q3Vertex) -> T {
+  let row = |y: usize| quadratic_bezier([attribute(patch[y * 3]), attribute(patch[y * 3 + 1]), attribute(patch[y * 3 + 2])], u);
+  quadratic_bezier([row(0), row(1), row(2)], v)
+}
+
+/// Polygons are wound so that the triangle normal matches the vertex normals after the coordinate fixup,
+/// patches don't store a winding so it gets derived from the interpolated normals.
+fn push_patch_triangle(vertices: &[Vertex], indices: &mut Vec<u32>, triangle: [u32; 3]) {
+  let [a, b, c] = triangle.map(|index| &vertices[index as usize]);
+  let triangle_normal = (b.position - a.position).cross(&(c.position - a.position));
+  if triangle_normal.dot(&(a.normal + b.normal + c.normal)) >= 0f32 {
+    indices.extend_from_slice(&triangle);
+  } else {
+    indices.extend_from_slice(&[triangle[0], triangle[2], triangle[1]]);
+  }
+}
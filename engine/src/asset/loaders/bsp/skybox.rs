@@ -0,0 +1,76 @@
+use std::io::{Seek, SeekFrom};
+use std::sync::Arc;
+
+use log::warn;
+use sourcerenderer_core::Platform;
+use sourcerenderer_core::graphics::TextureDimension;
+use sourcerenderer_vmt::VMTMaterial;
+
+use crate::asset::AssetManager;
+use crate::asset::asset_manager::Texture;
+use crate::asset::loaders::vtf_loader::read_vtf_texture;
+
+/// The skybox face suffixes in the order of the cube map layers (+X, -X, +Y, -Y, +Z, -Z).
+/// Source is Z up, so its up and down faces end up on the Y axis.
+const SKYBOX_FACE_SUFFIXES: [&str; 6] = ["rt", "lf", "up", "dn", "bk", "ft"];
+
+/// Combines the six materials of a 2D skybox into a single cube map.
+pub(super) fn load_skybox<P: Platform>(manager: &Arc<AssetManager<P>>, sky_name: &str) -> Option<Texture> {
+  let mut faces = Vec::<Texture>::with_capacity(6);
+  for suffix in &SKYBOX_FACE_SUFFIXES {
+    let material_path = format!("materials/skybox/{}{}.vmt", sky_name.to_lowercase(), suffix);
+    let texture_path = skybox_face_texture_path(manager, &material_path)?;
+    let file = manager.load_file(&texture_path)?;
+    let texture = read_vtf_texture(file, &texture_path).ok()?;
+    if texture.info.dimension != TextureDimension::Dim2D {
+      warn!("Skybox face {} is not a 2D texture", texture_path);
+      return None;
+    }
+    faces.push(texture);
+  }
+
+  let first_info = faces[0].info.clone();
+  let mismatch = faces.iter().any(|face|
+    face.info.format != first_info.format
+    || face.info.width != first_info.width
+    || face.info.height != first_info.height
+    || face.info.mip_levels != first_info.mip_levels
+  );
+  if mismatch {
+    warn!("The faces of skybox {} differ in size or format", sky_name);
+    return None;
+  }
+
+  // The texture data is ordered by array layer first and mip level second.
+  // Animated faces only contribute their first frame.
+  let mip_levels = first_info.mip_levels as usize;
+  let mut data = Vec::<Box<[u8]>>::with_capacity(6 * mip_levels);
+  for face in faces {
+    data.extend(face.data.into_vec().into_iter().take(mip_levels));
+  }
+
+  let mut info = first_info;
+  info.dimension = TextureDimension::DimCube;
+  info.array_length = 6;
+  Some(Texture {
+    info,
+    data: data.into_boxed_slice()
+  })
+}
+
+fn skybox_face_texture_path<P: Platform>(manager: &Arc<AssetManager<P>>, material_path: &str) -> Option<String> {
+  let mut file = manager.load_file(material_path)?;
+  let material = {
+    let len = file.seek(SeekFrom::End(0)).ok()?;
+    file.seek(SeekFrom::Start(0)).ok()?;
+    VMTMaterial::new(&mut file, len as u32).ok()?
+  };
+  // Prefer the float texture of HDR skies, the compressed HDR variant needs to be decoded in the shader.
+  let texture = material.get_value("hdrbasetexture")
+    .or_else(|| material.get_base_texture_name());
+  if texture.is_none() {
+    warn!("Skybox material without texture: {}", material_path);
+  }
+  let texture = texture?;
+  Some("materials/".to_string() + texture.to_lowercase().replace('\\', "/").as_str().trim_matches('/').trim_end_matches(".vtf") + ".vtf")
+}
@@ -0,0 +1,49 @@
+use std::io::{Seek, SeekFrom, Result as IOResult};
+use std::sync::Arc;
+
+use log::warn;
+use sourcerenderer_core::Platform;
+use sourcerenderer_vhv::{Header, MeshHeader, VertexColor};
+
+use crate::asset::AssetManager;
+use crate::asset::asset_manager::AssetFile;
+
+/// Reads the baked vertex lighting of the static prop with the given index from the pakfile.
+/// The colors are ordered like the vertices of the model, every LOD included.
+pub(super) fn load_static_prop_lighting<P: Platform>(manager: &Arc<AssetManager<P>>, prop_index: usize, is_hdr: bool) -> Option<Box<[u32]>> {
+  let path = if is_hdr {
+    format!("sp_hdr_{}.vhv", prop_index)
+  } else {
+    format!("sp_{}.vhv", prop_index)
+  };
+  let mut file = manager.load_file(&path)?;
+  match read_vertex_colors(&mut file) {
+    Ok(colors) => Some(colors),
+    Err(e) => {
+      warn!("Could not read static prop lighting {}: {:?}", path, e);
+      None
+    }
+  }
+}
+
+fn read_vertex_colors(file: &mut AssetFile) -> IOResult<Box<[u32]>> {
+  let file_start = file.seek(SeekFrom::Current(0))?;
+  let header = Header::read(file)?;
+  let mut meshes = Vec::<MeshHeader>::with_capacity(header.meshes_count.max(0) as usize);
+  for _ in 0..header.meshes_count {
+    meshes.push(MeshHeader::read(file)?);
+  }
+
+  let mut colors = Vec::<u32>::with_capacity(header.vertexes_count as usize);
+  for mesh in &meshes {
+    file.seek(SeekFrom::Start(file_start + mesh.offset as u64))?;
+    for _ in 0..mesh.vertexes_count {
+      let vertex_start = file.seek(SeekFrom::Current(0))?;
+      let color = VertexColor::read(file)?;
+      colors.push(u32::from_le_bytes([color.r, color.g, color.b, color.a]));
+      // Skip whatever else is stored with the color.
+      file.seek(SeekFrom::Start(vertex_start + header.vertex_size.max(4) as u64))?;
+    }
+  }
+  Ok(colors.into_boxed_slice())
+}
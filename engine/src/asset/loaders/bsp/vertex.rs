@@ -1,16 +1,23 @@
-use sourcerenderer_core::{Vec3, Vec2};
+use sourcerenderer_core::{Vec2, Vec3, Vec4};
 
 #[repr(C)]
 #[derive(Clone, PartialEq, Debug, Default)]
 pub struct Vertex {
   pub position: Vec3,
-  pub _padding: u32,
+  /// The four-way blend weights of displacements, one byte per material.
+  pub multiblend: u32,
   pub normal: Vec3,
   pub _padding1: u32,
   pub uv: Vec2,
   pub lightmap_uv: Vec2,
   pub alpha: f32,
-  pub _padding2: u32,
+  /// The light styles of the lightmap layers plus one, one byte per layer.
+  /// Zero means the vertex only uses the default style in the first layer.
+  pub lightmap_styles: u32,
   pub _padding3: u32,
-  pub _padding4: u32,
+  /// The horizontal distance between the pages of a bumped lightmap in lightmap UV space.
+  /// Zero if the lightmap isn't bumped.
+  pub lightmap_bump_page_width: f32,
+  /// The direction of the U texture axis in xyz, the sign of the bitangent in w.
+  pub tangent: Vec4,
 }
@@ -0,0 +1,165 @@
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use log::warn;
+use sourcerenderer_core::Platform;
+use sourcerenderer_core::platform::IO;
+use sourcerenderer_vmt::{KeyValues, KeyValuesError};
+use sourcerenderer_vpk::Package;
+
+use crate::asset::asset_manager::{AssetContainer, AssetFile};
+
+const GAMEINFO_PATH_TOKEN: &str = "|gameinfo_path|";
+const ALL_SOURCE_ENGINE_PATHS_TOKEN: &str = "|all_source_engine_paths|";
+
+/// A Source game or mod mounted using the search paths from its gameinfo.txt.
+/// Directories and VPKs are searched in the order they are listed in, so earlier entries take priority.
+pub struct GameInfoContainer<P: Platform> {
+  search_paths: Vec<SearchPath<P>>
+}
+
+enum SearchPath<P: Platform> {
+  Directory(PathBuf),
+  Package(Package<<P::IO as IO>::File>)
+}
+
+#[derive(Debug)]
+pub enum GameInfoError {
+  GameInfoNotFound,
+  InvalidGameInfo(KeyValuesError),
+  NoSearchPaths
+}
+
+impl<P: Platform> GameInfoContainer<P> {
+  /// Takes the directory that contains the gameinfo.txt, for example "Half-Life 2/hl2".
+  pub fn new(game_dir: &str) -> Result<Self, GameInfoError> {
+    let game_dir = Path::new(game_dir);
+    // Search paths without a token are relative to the directory that contains the game directories.
+    let base_dir = game_dir.parent().unwrap_or(game_dir);
+
+    let mut gameinfo_file = <P::IO as IO>::open_external_asset(game_dir.join("gameinfo.txt"))
+      .map_err(|_| GameInfoError::GameInfoNotFound)?;
+    let mut gameinfo_text = String::new();
+    gameinfo_file.read_to_string(&mut gameinfo_text).map_err(|_| GameInfoError::GameInfoNotFound)?;
+    let gameinfo = KeyValues::parse(&gameinfo_text).map_err(GameInfoError::InvalidGameInfo)?;
+    let search_path_values = gameinfo.get_block("GameInfo")
+      .and_then(|gameinfo| gameinfo.get_block("FileSystem"))
+      .and_then(|file_system| file_system.get_block("SearchPaths"))
+      .ok_or(GameInfoError::NoSearchPaths)?;
+
+    let mut resolved_paths = Vec::<PathBuf>::new();
+    for (key, value) in search_path_values.iter() {
+      let is_game_path = key.split('+')
+        .any(|path_id| path_id.eq_ignore_ascii_case("game") || path_id.eq_ignore_ascii_case("mod"));
+      let value = if let Some(value) = value.as_value() {
+        value
+      } else {
+        continue;
+      };
+      // Wildcards like "custom/*" would need a directory listing which the IO abstraction doesn't offer.
+      if !is_game_path || value.contains('*') {
+        continue;
+      }
+      let path = resolve_search_path(game_dir, base_dir, value);
+      if !resolved_paths.contains(&path) {
+        resolved_paths.push(path);
+      }
+    }
+
+    let search_paths: Vec<SearchPath<P>> = resolved_paths.into_iter()
+      .filter_map(|path| Self::mount(path))
+      .collect();
+    if search_paths.is_empty() {
+      return Err(GameInfoError::NoSearchPaths);
+    }
+
+    Ok(Self {
+      search_paths
+    })
+  }
+
+  fn mount(path: PathBuf) -> Option<SearchPath<P>> {
+    let is_package = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("vpk"));
+    if !is_package {
+      return Some(SearchPath::Directory(path));
+    }
+
+    // Multi part packages are listed without the _dir suffix of their directory file.
+    let dir_path = path.with_file_name(format!("{}_dir.vpk", path.file_stem()?.to_str()?));
+    let package_path = if <P::IO as IO>::external_asset_exists(&dir_path) {
+      dir_path
+    } else if <P::IO as IO>::external_asset_exists(&path) {
+      path
+    } else {
+      return None;
+    };
+    let package_path_str = package_path.to_str()?;
+    let file = <P::IO as IO>::open_external_asset(&package_path).ok()?;
+    Package::read(package_path_str, file, |path| <P::IO as IO>::open_external_asset(path))
+      .map_err(|e| warn!("Could not mount VPK {}: {:?}", package_path_str, e))
+      .ok()
+      .map(SearchPath::Package)
+  }
+
+  /// Maps are requested by their file name but live in the maps directory.
+  fn candidate_paths(path: &str) -> [Option<String>; 2] {
+    let map_path = if path.ends_with(".bsp") && !path.contains('/') {
+      Some(format!("maps/{}", path))
+    } else {
+      None
+    };
+    [Some(path.to_string()), map_path]
+  }
+}
+
+fn resolve_search_path(game_dir: &Path, base_dir: &Path, value: &str) -> PathBuf {
+  let lower_case_value = value.to_lowercase();
+  let (root, relative_path) = if lower_case_value.starts_with(GAMEINFO_PATH_TOKEN) {
+    (game_dir, &value[GAMEINFO_PATH_TOKEN.len()..])
+  } else if lower_case_value.starts_with(ALL_SOURCE_ENGINE_PATHS_TOKEN) {
+    (base_dir, &value[ALL_SOURCE_ENGINE_PATHS_TOKEN.len()..])
+  } else {
+    (base_dir, value)
+  };
+  let relative_path = relative_path.trim_matches(|c| c == '/' || c == '\\');
+  if relative_path.is_empty() || relative_path == "." {
+    root.to_path_buf()
+  } else {
+    root.join(relative_path)
+  }
+}
+
+impl<P: Platform> AssetContainer for GameInfoContainer<P> {
+  fn contains(&self, path: &str) -> bool {
+    let candidate_paths = Self::candidate_paths(path);
+    self.search_paths.iter().any(|search_path| {
+      candidate_paths.iter().flatten().any(|candidate_path| match search_path {
+        SearchPath::Directory(directory) => <P::IO as IO>::external_asset_exists(directory.join(candidate_path)),
+        SearchPath::Package(package) => package.find_entry(candidate_path).is_some()
+      })
+    })
+  }
+
+  fn load(&self, path: &str) -> Option<AssetFile> {
+    let candidate_paths = Self::candidate_paths(path);
+    let data = self.search_paths.iter().find_map(|search_path| {
+      candidate_paths.iter().flatten().find_map(|candidate_path| match search_path {
+        SearchPath::Directory(directory) => {
+          let mut file = <P::IO as IO>::open_external_asset(directory.join(candidate_path)).ok()?;
+          let mut buf = Vec::<u8>::new();
+          file.read_to_end(&mut buf).ok()?;
+          Some(buf.into_boxed_slice())
+        }
+        SearchPath::Package(package) => {
+          let entry = package.find_entry(candidate_path)?;
+          package.read_entry(entry, false).ok()
+        }
+      })
+    })?;
+
+    Some(AssetFile {
+      path: path.to_string(),
+      data: Cursor::new(data)
+    })
+  }
+}
@@ -5,7 +5,7 @@ use legion::{Entity, World, WorldOptions};
 use log::warn;
 use sourcerenderer_core::{Platform, Vec2, Vec3, Vec4, Quaternion};
 
-use crate::{Parent, Transform, asset::{Asset, AssetLoadPriority, AssetLoader, AssetLoaderProgress, AssetManager, Mesh, MeshRange, Model, asset_manager::{AssetFile, AssetLoaderResult}, loaders::BspVertex as Vertex, AssetType}, math::BoundingBox, renderer::{PointLightComponent, DirectionalLightComponent, StaticRenderableComponent}};
+use crate::{Parent, Transform, asset::{Asset, AssetLoadPriority, AssetLoader, AssetLoaderProgress, AssetManager, Material as AssetMaterial, MaterialValue, Mesh, MeshRange, Model, asset_manager::{AssetFile, AssetLoaderResult}, loaders::BspVertex as Vertex, AssetType}, math::BoundingBox, renderer::{PointLightComponent, DirectionalLightComponent, LightAttenuation, SpotLightComponent, StaticRenderableComponent}};
 
 pub struct GltfLoader {}
 
@@ -115,7 +115,9 @@ impl GltfLoader {
         vertices: vertices_data,
         bounding_box: bounding_box,
         parts: parts.into_boxed_slice(),
-        vertex_count: vertices_count as u32
+        lods: Box::new([]),
+        vertex_count: vertices_count as u32,
+        skinning: None
       }), AssetLoadPriority::Normal);
 
       let model_path = gltf_file_name.to_string() + "/model/" + &model_name;
@@ -144,19 +146,39 @@ impl GltfLoader {
     }
 
     if let Some(light) = node.light() {
-      let mut entry = world.entry(entity).unwrap();
       match light.kind() {
         gltf::khr_lights_punctual::Kind::Directional => {
-          entry.add_component(DirectionalLightComponent {
+          world.entry(entity).unwrap().add_component(DirectionalLightComponent {
             intensity: light.intensity() * 685f32, // Blender exports as W/m2, we need lux
+            color: Vec3::from(light.color()),
           });
         },
         gltf::khr_lights_punctual::Kind::Point => {
-          entry.add_component(PointLightComponent {
+          // glTF lights without a range are unbounded, cut them off once they get too dark.
+          let attenuation = LightAttenuation::INVERSE_SQUARE;
+          world.entry(entity).unwrap().add_component(PointLightComponent {
             intensity: light.intensity(),
+            color: Vec3::from(light.color()),
+            range: light.range().unwrap_or_else(|| attenuation.cutoff_range(light.intensity())),
+            attenuation,
           });
         },
-        gltf::khr_lights_punctual::Kind::Spot { .. } => todo!(),
+        gltf::khr_lights_punctual::Kind::Spot { inner_cone_angle, outer_cone_angle } => {
+          // glTF spot lights shine along -Z but the renderer uses the up axis of the transform.
+          let attenuation = LightAttenuation::INVERSE_SQUARE;
+          world.push((Transform {
+            position: Vec3::zeros(),
+            rotation: Quaternion::from_euler_angles(-std::f32::consts::FRAC_PI_2, 0f32, 0f32),
+            scale: Vec3::new(1f32, 1f32, 1f32),
+          }, Parent(entity), SpotLightComponent {
+            intensity: light.intensity(),
+            color: Vec3::from(light.color()),
+            range: light.range().unwrap_or_else(|| attenuation.cutoff_range(light.intensity())),
+            attenuation,
+            inner_cone_angle,
+            outer_cone_angle,
+          }));
+        },
       }
     }
 
@@ -201,6 +223,7 @@ impl GltfLoader {
     }
 
     let index_base = vertices.len() as u32;
+    let first_index = indices.len();
     let gltf_path = if let Some(last_slash) = gltf_file_name.rfind('/') {
       &gltf_file_name[..last_slash + 1]
     } else {
@@ -306,6 +329,20 @@ impl GltfLoader {
       }
     }
 
+    let tangents = primitive.get(&Semantic::Tangents);
+    if let Some(tangents) = tangents.as_ref() {
+      assert!(tangents.sparse().is_none());
+      let tangents_view = tangents.view().unwrap();
+      let tangents_data = load_buffer(gltf_file_name, gltf_path, asset_mgr, buffer_cache, &tangents_view);
+      let tangents_stride = tangents_view.stride().unwrap_or_else(|| tangents.size());
+      assert_eq!(tangents.size(), std::mem::size_of::<Vec4>());
+      for i in 0..tangents.count() {
+        let offset = tangents.offset() + i * tangents_stride;
+        let tangent = unsafe { std::ptr::read_unaligned(tangents_data[offset .. offset + tangents.size()].as_ptr() as *const Vec4) };
+        vertices[index_base as usize + i].tangent = fixup_tangent(&tangent);
+      }
+    }
+
     let indices_accessor = primitive.indices();
     if let Some(indices_accessor) = indices_accessor {
       assert!(indices_accessor.sparse().is_none());
@@ -341,56 +378,100 @@ impl GltfLoader {
       }
       assert!(buffer_cursor.seek(SeekFrom::Current(0)).unwrap() <= (view.offset() + view.length()) as u64);
     }
+
+    if tangents.is_none() {
+      if indices.len() > first_index {
+        generate_tangents(&mut vertices[index_base as usize..], &indices[first_index..], index_base);
+      } else {
+        let sequential_indices: Vec<u32> = (index_base..vertices.len() as u32).collect();
+        generate_tangents(&mut vertices[index_base as usize..], &sequential_indices, index_base);
+      }
+    }
   }
 
   fn load_material<P: Platform>(material: &Material, asset_mgr: &AssetManager<P>, gltf_file_name: &str) -> String {
-    let gltf_path = if let Some(last_slash) = gltf_file_name.rfind('/') {
-      &gltf_file_name[..last_slash + 1]
-    } else {
-      gltf_file_name
-    };
     let material_path = format!("{}/material/{}", gltf_file_name.to_string(), material.index().map_or_else(|| "default".to_string(), |index| index.to_string()));
 
     let pbr = material.pbr_metallic_roughness();
     if material.double_sided() {
       //warn!("Double sided materials are not supported, material path: {}", material_path);
     }
-    if material.alpha_mode() != AlphaMode::Opaque {
-      //warn!("Unsupported alpha mode, alpha mode: {:?}, material path: {}", material.alpha_mode(), material_path);
-    }
 
-    let albedo_info = pbr.base_color_texture();
-    let albedo_path = albedo_info.and_then(|albedo| if albedo.tex_coord() == 0 {
-      Some(albedo)
+    let albedo_path = pbr.base_color_texture()
+      .and_then(|albedo| GltfLoader::texture_path(&albedo.texture(), albedo.tex_coord(), gltf_file_name, &material_path));
+    let mut asset_material = if let Some(albedo_path) = albedo_path {
+      asset_mgr.request_asset(&albedo_path, AssetType::Texture, AssetLoadPriority::Low);
+      AssetMaterial::new_pbr(&albedo_path, pbr.roughness_factor(), pbr.metallic_factor())
     } else {
-      warn!("Found non zero texcoord for texture: {}", &material_path);
-      None
-    }).map(|albedo| {
-      if albedo.texture().sampler().wrap_s() != WrappingMode::Repeat || albedo.texture().sampler().wrap_t() != WrappingMode::Repeat {
-        warn!("Texture uses non-repeat wrap mode: s: {:?}, t: {:?}", albedo.texture().sampler().wrap_s(), albedo.texture().sampler().wrap_t());
+      let color = pbr.base_color_factor();
+      AssetMaterial::new_pbr_color(Vec4::new(color[0], color[1], color[2], color[3]), pbr.roughness_factor(), pbr.metallic_factor())
+    };
+
+    let mut add_texture = |key: &str, path: Option<String>| {
+      if let Some(path) = path {
+        asset_mgr.request_asset(&path, AssetType::Texture, AssetLoadPriority::Low);
+        asset_material.properties.insert(key.to_string(), MaterialValue::Texture(path));
       }
-      let albedo_source = albedo.texture().source().source();
-      match albedo_source {
-        gltf::image::Source::View { view, mime_type } => {
-          let mime_parts: Vec<&str> = mime_type.split('/').collect();
-          let file_type = mime_parts[1].to_lowercase();
-          format!("{}/texture/{}-{}.{}", gltf_file_name, view.offset(), view.length(), &file_type)
-        },
-        gltf::image::Source::Uri { uri, mime_type: _mime_type } => {
-          gltf_path.to_string() + uri
-        },
+    };
+    add_texture("normal", material.normal_texture()
+      .and_then(|normal| GltfLoader::texture_path(&normal.texture(), normal.tex_coord(), gltf_file_name, &material_path)));
+    // The metallic roughness texture has roughness in green and metalness in blue,
+    // occlusion is only used if it's packed into the red channel of the same texture.
+    let metallic_roughness = pbr.metallic_roughness_texture();
+    let has_packed_occlusion = material.occlusion_texture().is_some_and(|occlusion| {
+      let is_packed = metallic_roughness.as_ref().is_some_and(|metallic_roughness| metallic_roughness.texture().source().index() == occlusion.texture().source().index());
+      if !is_packed {
+        warn!("Occlusion textures that aren't packed with the metallic roughness texture are not supported, material path: {}", &material_path);
       }
+      is_packed
     });
-
-    if let Some(albedo_path) = albedo_path {
-      asset_mgr.request_asset(&albedo_path, AssetType::Material, AssetLoadPriority::Low);
-      asset_mgr.add_material(&material_path, &albedo_path, pbr.roughness_factor(), pbr.metallic_factor());
-    } else {
-      let color = pbr.base_color_factor();
-      asset_mgr.add_material_color(&material_path, Vec4::new(color[0], color[1], color[2], color[3]), pbr.roughness_factor(), pbr.metallic_factor());
+    add_texture("orm", metallic_roughness
+      .and_then(|metallic_roughness| GltfLoader::texture_path(&metallic_roughness.texture(), metallic_roughness.tex_coord(), gltf_file_name, &material_path)));
+    add_texture("emissive", material.emissive_texture()
+      .and_then(|emissive| GltfLoader::texture_path(&emissive.texture(), emissive.tex_coord(), gltf_file_name, &material_path)));
+
+    let occlusion_strength = material.occlusion_texture().map_or(0f32, |occlusion| occlusion.strength());
+    asset_material.properties.insert("occlusion_strength".to_string(), MaterialValue::Float(if has_packed_occlusion { occlusion_strength } else { 0f32 }));
+    let emissive_factor = material.emissive_factor();
+    asset_material.properties.insert("emissive_factor".to_string(), MaterialValue::Vec4(Vec4::new(emissive_factor[0], emissive_factor[1], emissive_factor[2], 1f32)));
+    match material.alpha_mode() {
+      AlphaMode::Opaque => {},
+      AlphaMode::Mask => {
+        asset_material.properties.insert("alpha_cutoff".to_string(), MaterialValue::Float(material.alpha_cutoff().unwrap_or(0.5f32)));
+      },
+      AlphaMode::Blend => {
+        asset_material.properties.insert("alpha_blend".to_string(), MaterialValue::Float(1f32));
+      }
     }
+
+    asset_mgr.add_asset(&material_path, Asset::Material(asset_material), AssetLoadPriority::Normal);
     material_path
   }
+
+  fn texture_path(texture: &gltf::Texture, tex_coord: u32, gltf_file_name: &str, material_path: &str) -> Option<String> {
+    if tex_coord != 0 {
+      warn!("Found non zero texcoord for texture: {}", material_path);
+      return None;
+    }
+    if texture.sampler().wrap_s() != WrappingMode::Repeat || texture.sampler().wrap_t() != WrappingMode::Repeat {
+      warn!("Texture uses non-repeat wrap mode: s: {:?}, t: {:?}", texture.sampler().wrap_s(), texture.sampler().wrap_t());
+    }
+    let gltf_path = if let Some(last_slash) = gltf_file_name.rfind('/') {
+      &gltf_file_name[..last_slash + 1]
+    } else {
+      gltf_file_name
+    };
+    Some(match texture.source().source() {
+      gltf::image::Source::View { view, mime_type } => {
+        let mime_parts: Vec<&str> = mime_type.split('/').collect();
+        let file_type = mime_parts[1].to_lowercase();
+        format!("{}/texture/{}-{}.{}", gltf_file_name, view.offset(), view.length(), &file_type)
+      },
+      gltf::image::Source::Uri { uri, mime_type: _mime_type } => {
+        gltf_path.to_string() + uri
+      },
+    })
+  }
 }
 
 impl<P: Platform> AssetLoader<P> for GltfLoader {
@@ -438,3 +519,42 @@ fn fixup_vec(vec: &Vec3) -> Vec3 {
   new_vec.x = -new_vec.x;
   return new_vec;
 }
+
+// Mirroring the X axis also flips the handedness of the tangent frame.
+fn fixup_tangent(tangent: &Vec4) -> Vec4 {
+  Vec4::new(-tangent.x, tangent.y, tangent.z, -tangent.w)
+}
+
+/// Calculates per vertex tangents from the UV gradients of the triangles for primitives that come without tangents.
+fn generate_tangents(vertices: &mut [Vertex], indices: &[u32], index_base: u32) {
+  let mut tangents = vec![Vec3::new(0f32, 0f32, 0f32); vertices.len()];
+  let mut bitangents = vec![Vec3::new(0f32, 0f32, 0f32); vertices.len()];
+  for triangle in indices.chunks_exact(3) {
+    let triangle_indices = [(triangle[0] - index_base) as usize, (triangle[1] - index_base) as usize, (triangle[2] - index_base) as usize];
+    let (v0, v1, v2) = (&vertices[triangle_indices[0]], &vertices[triangle_indices[1]], &vertices[triangle_indices[2]]);
+    let edge1 = v1.position - v0.position;
+    let edge2 = v2.position - v0.position;
+    let delta_uv1 = v1.uv - v0.uv;
+    let delta_uv2 = v2.uv - v0.uv;
+    let determinant = delta_uv1.x * delta_uv2.y - delta_uv2.x * delta_uv1.y;
+    if determinant.abs() < f32::EPSILON {
+      continue;
+    }
+    let tangent = (edge1 * delta_uv2.y - edge2 * delta_uv1.y) / determinant;
+    let bitangent = (edge2 * delta_uv1.x - edge1 * delta_uv2.x) / determinant;
+    for index in triangle_indices {
+      tangents[index] += tangent;
+      bitangents[index] += bitangent;
+    }
+  }
+
+  for (index, vertex) in vertices.iter_mut().enumerate() {
+    let normal = vertex.normal;
+    let tangent = tangents[index] - normal * normal.dot(&tangents[index]);
+    if let Some(tangent) = tangent.try_normalize(f32::EPSILON) {
+      // The green channel of glTF normal maps points against the direction in which V increases.
+      let sign = if normal.cross(&tangent).dot(&bitangents[index]) > 0f32 { -1f32 } else { 1f32 };
+      vertex.tangent = Vec4::new(tangent.x, tangent.y, tangent.z, sign);
+    }
+  }
+}
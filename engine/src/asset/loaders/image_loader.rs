@@ -16,15 +16,21 @@ impl ImageLoader {
 
 impl<P: Platform> AssetLoader<P> for ImageLoader {
   fn matches(&self, file: &mut AssetFile) -> bool {
-    file.path.ends_with(".png") || file.path.ends_with(".jpg") || file.path.ends_with(".jpeg")
+    file.path.ends_with(".png") || file.path.ends_with(".jpg") || file.path.ends_with(".jpeg") || file.path.ends_with(".tga")
   }
 
   fn load(&self, file: AssetFile, manager: &Arc<AssetManager<P>>, priority: AssetLoadPriority, progress: &Arc<AssetLoaderProgress>) -> Result<AssetLoaderResult, ()> {
-    let is_png = file.path.ends_with(".png");
+    let format = if file.path.ends_with(".png") {
+      ImageFormat::Png
+    } else if file.path.ends_with(".tga") {
+      ImageFormat::Tga
+    } else {
+      ImageFormat::Jpeg
+    };
 
     let path = file.path.clone();
     let buf_read = BufReader::new(file);
-    let image_reader = ImageReader::with_format(buf_read, if is_png { ImageFormat::Png } else { ImageFormat::Jpeg });
+    let image_reader = ImageReader::with_format(buf_read, format);
     let img = image_reader.decode().map_err(|_e| ())?;
     let (width, height) = img.dimensions();
 
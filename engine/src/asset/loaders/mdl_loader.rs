@@ -1,14 +1,16 @@
 use crate::{asset::{AssetLoader, AssetLoaderProgress, AssetManager, AssetLoadPriority, Asset}, math::BoundingBox};
 use std::sync::Arc;
 use std::io::{Seek, SeekFrom, Read, Result as IOResult};
-use crate::asset::asset_manager::{AssetFile, AssetLoaderResult, MeshRange};
-use sourcerenderer_mdl::{BodyPart, Header, Mesh, Model, PrimitiveRead, StringRead};
+use crate::asset::asset_manager::{AssetFile, AssetLoaderResult, MeshRange, MeshLOD};
+use sourcerenderer_mdl::{BodyPart, Header, Mesh, Model, ModelFile, PrimitiveRead, StringRead, StudioHDRFlags};
 use sourcerenderer_vtx::{BodyPartHeader, ModelHeader, ModelLODHeader, MeshHeader, Header as VTXHeader, StripGroupHeader, StripHeader, Vertex as VTXVertex};
-use sourcerenderer_vvd::{Header as VVDHeader, Vertex, VertexFileFixup};
+use sourcerenderer_vvd::{BoneWeight, Header as VVDHeader, Vertex, VertexFileFixup};
 use crate::asset::loaders::bsp::Vertex as BspVertex;
-use nalgebra::{Vector2, Vector3};
-use crate::asset::{Mesh as AssetMesh, Model as AssetModel, AssetType};
-use sourcerenderer_core::{platform::Platform, Vec3};
+use nalgebra::{Matrix3x4, Quaternion, UnitQuaternion, Vector2, Vector3};
+use crate::asset::{Mesh as AssetMesh, Model as AssetModel, AssetType, Skeleton, SkeletonBone, BonePose, AnimationSequence};
+use crate::renderer::SkinningVertex;
+use log::warn;
+use sourcerenderer_core::{platform::Platform, Matrix4, Vec3, Vec4};
 use std::slice;
 
 const SCALING_FACTOR: f32 = 0.0236f32;
@@ -20,7 +22,6 @@ impl<P: Platform> AssetLoader<P> for MDLModelLoader {
     file.path.starts_with("models/") && file.path.ends_with(".mdl")
   }
 
-  #[allow(clippy::never_loop)]
   fn load(&self, mut file: AssetFile, manager: &Arc<AssetManager<P>>, _priority: AssetLoadPriority, progress: &Arc<AssetLoaderProgress>) -> Result<AssetLoaderResult, ()> {
     if file.path.contains("autocombine") {
       print!("Model: {} is auto combined", &file.path);
@@ -116,12 +117,18 @@ impl<P: Platform> AssetLoader<P> for MDLModelLoader {
     let vvd_vertices: Box<[Vertex]> = load_geometry(&mut vvd_file).map_err(|_e| ())?;
 
     let mut vertices = Vec::<BspVertex>::with_capacity(vvd_vertices.len());
+    // Static props only have a single root bone, everything else gets skinned on the GPU.
+    let is_skinned = !header.flags.contains(StudioHDRFlags::STATIC_PROP) && header.bone_count > 0;
+    let mut skinning_vertices = Vec::<SkinningVertex>::new();
 
     let vtx_path = file.path.replace(".mdl", ".dx90.vtx");
     let mut vtx_file = manager.load_file(&vtx_path).unwrap();
 
-    let mut ranges = Vec::<MeshRange>::new();
-    let mut materials = Vec::<String>::new();
+    // Parts get grouped by LOD so every LOD ends up as a contiguous range of parts.
+    let mut lod_ranges = Vec::<Vec<MeshRange>>::new();
+    let mut lod_materials = Vec::<Vec<String>>::new();
+    let mut lod_switch_points = Vec::<f32>::new();
+    let has_shadow_lod = header.flags.contains(StudioHDRFlags::HAS_SHADOW_LOD);
     let mut indices = Vec::<u32>::new();
     let mut strip_group_indices = Vec::<u32>::new();
     let vtx_start = vtx_file.seek(SeekFrom::Current(0)).map_err(|_e| ())?;
@@ -135,12 +142,25 @@ impl<P: Platform> AssetLoader<P> for MDLModelLoader {
       for model_index in 0..body_part.models_count {
         let model_start = vtx_file.seek(SeekFrom::Current(0)).map_err(|_e| ())?;
         let model = ModelHeader::read(&mut vtx_file).map_err(|_e| ())?;
-        let model_next = vtx_file.seek(SeekFrom::Start(0)).map_err(|_e| ())?;
+        let model_next = vtx_file.seek(SeekFrom::Current(0)).map_err(|_e| ())?;
         vtx_file.seek(SeekFrom::Start(model_start + model.lod_offset as u64)).map_err(|_e| ())?;
-        for _model_lod_index in 0..model.lods_count {
+        for model_lod_index in 0..model.lods_count {
           let lod_start = vtx_file.seek(SeekFrom::Current(0)).map_err(|_e| ())?;
           let model_lod = ModelLODHeader::read(&mut vtx_file).map_err(|_e| ())?;
           let lod_next = vtx_file.seek(SeekFrom::Current(0)).map_err(|_e| ())?;
+          // Shadow LODs are only used for shadows in Source and LODs more detailed than the root LOD are never drawn.
+          // Their vertices are kept anyway, so the vertex order matches the baked vertex lighting of static props.
+          let is_shadow_lod = model_lod.switch_point < 0f32 || (has_shadow_lod && model_lod_index == model.lods_count - 1);
+          let is_drawn = !is_shadow_lod && model_lod_index >= header.root_lod as i32;
+          let lod = model_lod_index as usize;
+          if is_drawn {
+            if lod_ranges.len() <= lod {
+              lod_ranges.resize(lod + 1, Vec::new());
+              lod_materials.resize(lod + 1, Vec::new());
+              lod_switch_points.resize(lod + 1, 0f32);
+            }
+            lod_switch_points[lod] = lod_switch_points[lod].max(model_lod.switch_point);
+          }
           vtx_file.seek(SeekFrom::Start(lod_start + model_lod.mesh_offset as u64)).map_err(|_e| ())?;
           for mesh_index in 0..model_lod.meshes_count {
             let mdl_mesh = &models[body_part_index as usize][model_index as usize][mesh_index as usize];
@@ -164,6 +184,9 @@ impl<P: Platform> AssetLoader<P> for MDLModelLoader {
               vtx_file.seek(SeekFrom::Start(strip_group_start + strip_group.strips_offset as u64)).map_err(|_e| ())?;
               for _ in 0..strip_group.strips_count {
                 let strip = StripHeader::read(&mut vtx_file).map_err(|_e| ())?;
+                if !is_drawn {
+                  continue;
+                }
                 for i in 0..strip.indices_count {
                   indices.push(base_index as u32 + strip_group_indices[(strip.index_offset + i) as usize]);
                 }
@@ -186,26 +209,46 @@ impl<P: Platform> AssetLoader<P> for MDLModelLoader {
                   ..Default::default()
                 };
                 vertices.push(bsp_vertex);
+                if is_skinned {
+                  skinning_vertices.push(skinning_vertex(&vertex.bone_weights));
+                }
               }
 
               vtx_file.seek(SeekFrom::Start(strip_group_next)).map_err(|_e| ())?;
             }
 
-            materials.push(texture_paths[mdl_mesh.material as usize].clone());
-            ranges.push(MeshRange {
-              start: indices_start as u32,
-              count: (indices.len() - indices_start) as u32
-            });
+            if is_drawn {
+              lod_materials[lod].push(texture_paths[mdl_mesh.material as usize].clone());
+              lod_ranges[lod].push(MeshRange {
+                start: indices_start as u32,
+                count: (indices.len() - indices_start) as u32
+              });
+            }
             vtx_file.seek(SeekFrom::Start(mesh_next)).map_err(|_e| ())?;
           }
           vtx_file.seek(SeekFrom::Start(lod_next)).map_err(|_e| ())?;
-          break;
         }
         vtx_file.seek(SeekFrom::Start(model_next)).map_err(|_e| ())?;
       }
       vtx_file.seek(SeekFrom::Start(body_part_next)).map_err(|_e| ())?;
     }
 
+    let mut ranges = Vec::<MeshRange>::new();
+    let mut materials = Vec::<String>::new();
+    let mut lods = Vec::<MeshLOD>::new();
+    for ((lod_parts, lod_part_materials), switch_point) in lod_ranges.into_iter().zip(lod_materials).zip(lod_switch_points) {
+      if lod_parts.is_empty() {
+        continue;
+      }
+      lods.push(MeshLOD {
+        first_part: ranges.len() as u32,
+        part_count: lod_parts.len() as u32,
+        max_screen_size: if lods.is_empty() { f32::INFINITY } else { lod_max_screen_size(switch_point) }
+      });
+      ranges.extend(lod_parts);
+      materials.extend(lod_part_materials);
+    }
+
     let indices_box = indices.clone().into_boxed_slice();
     let indices_count = indices.len();
     let ptr = Box::into_raw(indices_box);
@@ -217,6 +260,14 @@ impl<P: Platform> AssetLoader<P> for MDLModelLoader {
     let data_ptr = unsafe { slice::from_raw_parts_mut(ptr as *mut u8, vertices_count * std::mem::size_of::<BspVertex>()) as *mut [u8] };
     let vertices_data = unsafe { Box::from_raw(data_ptr) };
 
+    let skinning_data = is_skinned.then(|| {
+      let skinning_box = skinning_vertices.into_boxed_slice();
+      let skinning_count = skinning_box.len();
+      let ptr = Box::into_raw(skinning_box);
+      let data_ptr = unsafe { slice::from_raw_parts_mut(ptr as *mut u8, skinning_count * std::mem::size_of::<SkinningVertex>()) as *mut [u8] };
+      unsafe { Box::from_raw(data_ptr) }
+    });
+
     let hull_min = fixup_position(&header.hull_min);
     let hull_max = fixup_position(&header.hull_max);
     let min = Vec3::new(hull_min.x.min(hull_max.x), hull_min.y.min(hull_max.y), hull_min.z.min(hull_max.z));
@@ -226,10 +277,20 @@ impl<P: Platform> AssetLoader<P> for MDLModelLoader {
       indices: Some(indices_data),
       vertices: vertices_data,
       parts: ranges.into_boxed_slice(),
+      lods: lods.into_boxed_slice(),
       bounding_box: Some(BoundingBox::new(min, max)),
-      vertex_count: vertices_count as u32
+      vertex_count: vertices_count as u32,
+      skinning: skinning_data
     }), AssetLoadPriority::Normal);
 
+    if is_skinned {
+      file.seek(SeekFrom::Start(file_start)).map_err(|_e| ())?;
+      match load_skeleton(&mut file) {
+        Ok(skeleton) => manager.add_asset(&file.path, Asset::Skeleton(Arc::new(skeleton)), AssetLoadPriority::Normal),
+        Err(e) => warn!("Could not load skeleton of model {}: {:?}", &file.path, e)
+      }
+    }
+
     manager.add_asset_with_progress(&file.path, Asset::Model(AssetModel {
       mesh_path: vtx_path,
       material_paths: materials
@@ -275,6 +336,63 @@ fn load_geometry<R: Read + Seek>(file: &mut R) -> IOResult<Box<[Vertex]>> {
   Ok(vertices.into_boxed_slice())
 }
 
+fn load_skeleton<R: Read + Seek>(reader: R) -> IOResult<Skeleton> {
+  let mut model_file = ModelFile::read(reader)?;
+  let bones = model_file.bones()?;
+  let animations = model_file.animations()?;
+  let sequences = model_file.sequences()?;
+
+  let skeleton_bones: Box<[SkeletonBone]> = bones.iter().map(|bone| SkeletonBone {
+    parent: (bone.parent >= 0).then(|| bone.parent as usize),
+    bind_pose: BonePose {
+      position: fixup_position(&bone.position),
+      rotation: fixup_rotation(&bone.quaternion)
+    },
+    inverse_bind_matrix: fixup_matrix(&bone.pose_to_bone)
+  }).collect();
+
+  // Blended sequences only play the first animation of their blend grid.
+  let skeleton_sequences: Box<[AnimationSequence]> = sequences.iter().filter_map(|sequence| {
+    let animation_index = *sequence.animation_indices.first().filter(|index| **index >= 0)?;
+    let animation = animations.get(animation_index as usize)?;
+    Some(AnimationSequence {
+      name: sequence.name.clone(),
+      fps: animation.fps,
+      looping: sequence.is_looping() || animation.is_looping(),
+      frames: animation.frames.iter().map(|frame| frame.iter().map(|pose| BonePose {
+        position: fixup_position(&pose.position),
+        rotation: fixup_rotation(&pose.rotation)
+      }).collect()).collect()
+    })
+  }).collect();
+
+  Ok(Skeleton {
+    bones: skeleton_bones,
+    sequences: skeleton_sequences
+  })
+}
+
+/// Source switches to a LOD once 100 divided by the pixel height of a sphere
+/// with a diameter of one Source unit reaches the switch point.
+fn lod_max_screen_size(switch_point: f32) -> f32 {
+  if switch_point <= 0f32 {
+    return f32::INFINITY;
+  }
+  100f32 / (switch_point * SCALING_FACTOR)
+}
+
+fn skinning_vertex(bone_weights: &BoneWeight) -> SkinningVertex {
+  let mut skinning_vertex = SkinningVertex::default();
+  for i in 0..(bone_weights.bones_count as usize).min(bone_weights.bone.len()) {
+    skinning_vertex.bone_indices[i] = bone_weights.bone[i].max(0) as f32;
+    skinning_vertex.bone_weights[i] = bone_weights.weight[i];
+  }
+  if bone_weights.bones_count == 0 {
+    skinning_vertex.bone_weights = Vec4::new(1f32, 0f32, 0f32, 0f32);
+  }
+  skinning_vertex
+}
+
 fn fixup_position(position: &Vector3<f32>) -> Vector3<f32> {
   Vector3::<f32>::new(position.x, position.z, position.y) * SCALING_FACTOR
 }
@@ -282,3 +400,27 @@ fn fixup_position(position: &Vector3<f32>) -> Vector3<f32> {
 fn fixup_normal(normal: &Vector3<f32>) -> Vector3<f32> {
   Vector3::<f32>::new(normal.x, normal.z, normal.y)
 }
+
+/// Source is Z up, so swapping Y and Z mirrors the rotation which flips its direction.
+fn fixup_rotation(rotation: &Quaternion<f32>) -> UnitQuaternion<f32> {
+  UnitQuaternion::from_quaternion(Quaternion::new(rotation.w, -rotation.i, -rotation.k, -rotation.j))
+}
+
+/// Converts a Source space bone matrix to one that works on fixed up positions.
+fn fixup_matrix(matrix: &Matrix3x4<f32>) -> Matrix4 {
+  let matrix = Matrix4::new(
+    matrix[(0, 0)], matrix[(0, 1)], matrix[(0, 2)], matrix[(0, 3)],
+    matrix[(1, 0)], matrix[(1, 1)], matrix[(1, 2)], matrix[(1, 3)],
+    matrix[(2, 0)], matrix[(2, 1)], matrix[(2, 2)], matrix[(2, 3)],
+    0f32, 0f32, 0f32, 1f32
+  );
+  let swizzle = Matrix4::new(
+    1f32, 0f32, 0f32, 0f32,
+    0f32, 0f32, 1f32, 0f32,
+    0f32, 1f32, 0f32, 0f32,
+    0f32, 0f32, 0f32, 1f32
+  );
+  let scale = Matrix4::new_nonuniform_scaling(&Vec3::new(SCALING_FACTOR, SCALING_FACTOR, SCALING_FACTOR));
+  let inverse_scale = Matrix4::new_nonuniform_scaling(&Vec3::new(1f32 / SCALING_FACTOR, 1f32 / SCALING_FACTOR, 1f32 / SCALING_FACTOR));
+  scale * swizzle * matrix * swizzle * inverse_scale
+}
@@ -1,5 +1,6 @@
 mod bsp;
 mod csgo_loader;
+mod gameinfo_container;
 mod vpk_container;
 mod vtf_loader;
 mod vmt_loader;
@@ -11,7 +12,10 @@ mod fs_container;
 mod shader_loader;
 
 pub use self::csgo_loader::CSGODirectoryContainer;
+pub use self::gameinfo_container::{GameInfoContainer, GameInfoError};
 pub use self::bsp::BspLevelLoader;
+pub use self::bsp::Q3BspLevelLoader;
+pub use self::bsp::{BspEntitySpawner, EntityKeyValues};
 pub use self::bsp::Vertex as BspVertex;
 pub use self::vpk_container::VPKContainer;
 pub use self::vpk_container::VPKContainerLoader;
@@ -1,11 +1,18 @@
 use crate::asset::{AssetLoader, Asset, AssetType, AssetManager};
 use crate::asset::asset_manager::{AssetLoaderResult, AssetFile, AssetLoaderProgress, AssetLoadPriority};
 use log::warn;
-use sourcerenderer_core::{Platform, Vec4};
+use sourcerenderer_core::{Platform, Vec3, Vec4};
 use sourcerenderer_vmt::VMTMaterial;
+use std::collections::HashMap;
 use std::io::{Seek, SeekFrom};
-use crate::asset::Material;
+use crate::asset::{Material, MaterialValue};
 use std::sync::Arc;
+use super::bsp::SCALING_FACTOR;
+
+// The exponent that Source uses when $phong is set without one.
+const DEFAULT_PHONG_EXPONENT: f32 = 5f32;
+// The reference that Source uses when $alphatest is set without one.
+const DEFAULT_ALPHA_TEST_REFERENCE: f32 = 0.5f32;
 
 pub struct VMTMaterialLoader {
 
@@ -31,27 +38,98 @@ impl<P: Platform> AssetLoader<P> for VMTMaterialLoader {
       VMTMaterial::new(&mut asset_file, len as u32)
     }.map_err(|_| ())?;
 
-    if vmt_material.is_patch() {
-      let base_path = vmt_material.get_patch_base().unwrap().replace('\\', "/").to_lowercase();
+    // Patches can include other patches.
+    let mut patches = Vec::<VMTMaterial>::new();
+    while vmt_material.is_patch() {
+      let base_path = vmt_material.get_patch_base();
+      if base_path.is_none() {
+        warn!("Material patch without base material: {}", path);
+        return Err(());
+      }
+      let base_path = base_path.unwrap().replace('\\', "/").to_lowercase();
       let base_file = manager.load_file(&base_path);
-      if base_file.is_none() {
+      if base_file.is_none() || patches.len() > 8 {
         return Err(());
       }
       let mut base_file = base_file.unwrap();
-      let mut base_material = {
+      let base_material = {
         let current = base_file.seek(SeekFrom::Current(0)).unwrap();
         let len = base_file.seek(SeekFrom::End(0)).unwrap();
         base_file.seek(SeekFrom::Start(current)).unwrap();
         VMTMaterial::new(&mut base_file, len as u32)
       }.map_err(|_| ())?;
-      base_material.apply_patch(&vmt_material);
-      vmt_material = base_material
+      patches.push(std::mem::replace(&mut vmt_material, base_material));
+    }
+    for patch in patches.iter().rev() {
+      vmt_material.apply_patch(patch);
     }
 
     let albedo_opt = vmt_material.get_base_texture_name();
     if let Some(albedo) = albedo_opt {
-      let albedo_path = "materials/".to_string() + albedo.to_lowercase().replace('\\', "/").as_str().trim_matches('/').trim_end_matches(".vtf") + ".vtf";
-      let material = Material::new_pbr(&albedo_path, 0f32, 0f32);
+      let albedo_path = texture_path(albedo);
+      // Materials without phong only have diffuse lighting in Source.
+      let roughness = if is_flag_set(&vmt_material, "phong") {
+        let exponent = vmt_material.get_value("phongexponent").and_then(|value| value.parse::<f32>().ok()).unwrap_or(DEFAULT_PHONG_EXPONENT);
+        phong_exponent_to_roughness(exponent)
+      } else {
+        1f32
+      };
+      let mut material = Material::new_pbr(&albedo_path, roughness, 0f32);
+      // Animated textures are texture arrays, $frame selects the array layer.
+      if let Some(frame) = vmt_material.get_value("frame").and_then(|frame| frame.parse::<f32>().ok()) {
+        material.properties.insert("albedo_frame".to_string(), MaterialValue::Float(frame));
+      }
+      // VBSP replaces env_cubemap with the path of the closest cubemap in the patched materials of the level.
+      // The renderer picks the cubemap by itself, so both mean that the material reflects the baked cubemaps.
+      let uses_cubemap = vmt_material.get_value("envmap")
+        .map(|envmap| envmap.replace('\\', "/").to_lowercase())
+        .is_some_and(|envmap| envmap == "env_cubemap" || envmap.starts_with("maps/"));
+      if uses_cubemap {
+        let tint = vmt_material.get_value("envmaptint").and_then(parse_color).unwrap_or_else(|| Vec3::new(1f32, 1f32, 1f32));
+        material.properties.insert("environment_map_tint".to_string(), MaterialValue::Vec4(Vec4::new(tint.x, tint.y, tint.z, 1f32)));
+      }
+      // WorldVertexTransition materials blend to a second layer using the vertex alpha of displacements,
+      // the blend modulate texture sharpens the transition. Multiblend materials mix four layers with the multiblend weights.
+      for (key, property) in [("basetexture2", "albedo2"), ("basetexture3", "albedo3"), ("basetexture4", "albedo4"), ("blendmodulatetexture", "blend_modulate")] {
+        if let Some(texture) = vmt_material.get_value(key) {
+          let path = texture_path(texture);
+          manager.request_asset_with_progress(&path, AssetType::Texture, priority, progress);
+          material.properties.insert(property.to_string(), MaterialValue::Texture(path));
+        }
+      }
+
+      // Self shadowed bump maps store the lighting along the bump basis instead of a normal.
+      if !is_flag_set(&vmt_material, "ssbump") {
+        for (key, property) in [("bumpmap", "normal"), ("bumpmap2", "normal2")] {
+          if let Some(bump_map) = vmt_material.get_value(key) {
+            let bump_map_path = texture_path(bump_map);
+            manager.request_asset_with_progress(&bump_map_path, AssetType::Texture, priority, progress);
+            material.properties.insert(property.to_string(), MaterialValue::Texture(bump_map_path));
+          }
+        }
+      }
+      // Self illumination multiplies the albedo with a mask, either in the alpha channel of the base texture
+      // or in a separate mask texture.
+      if is_flag_set(&vmt_material, "selfillum") {
+        let (emissive_path, mask_channels) = if let Some(mask) = vmt_material.get_value("selfillummask") {
+          let mask_path = texture_path(mask);
+          manager.request_asset_with_progress(&mask_path, AssetType::Texture, priority, progress);
+          (mask_path, Vec4::new(1f32, 0f32, 0f32, 0f32))
+        } else {
+          (albedo_path.clone(), Vec4::new(0f32, 0f32, 0f32, 1f32))
+        };
+        let tint = vmt_material.get_value("selfillumtint").and_then(parse_color).unwrap_or_else(|| Vec3::new(1f32, 1f32, 1f32));
+        material.properties.insert("emissive".to_string(), MaterialValue::Texture(emissive_path));
+        material.properties.insert("emissive_factor".to_string(), MaterialValue::Vec4(Vec4::new(tint.x, tint.y, tint.z, 1f32)));
+        material.properties.insert("emissive_mask_channels".to_string(), MaterialValue::Vec4(mask_channels));
+      }
+      // Translucent materials blend using the alpha of the base texture, alpha tested ones discard below the reference.
+      if is_flag_set(&vmt_material, "translucent") {
+        material.properties.insert("alpha_blend".to_string(), MaterialValue::Float(1f32));
+      } else if is_flag_set(&vmt_material, "alphatest") {
+        let reference = vmt_material.get_value("alphatestreference").and_then(|value| value.parse::<f32>().ok()).unwrap_or(DEFAULT_ALPHA_TEST_REFERENCE);
+        material.properties.insert("alpha_cutoff".to_string(), MaterialValue::Float(reference));
+      }
 
       manager.request_asset_with_progress(&albedo_path, AssetType::Texture, priority, progress);
       manager.add_asset_with_progress(&path, Asset::Material(material), Some(progress), priority);
@@ -61,10 +139,58 @@ impl<P: Platform> AssetLoader<P> for VMTMaterialLoader {
         return Err(());
       }
 
-      let material = Material::new_pbr_color(Vec4::new(0f32, 0f32, 0f32, 1f32), 0f32, 1f32);
+      let mut material = Material {
+        shader_name: "water".to_string(),
+        properties: HashMap::new()
+      };
+      if let Some(normal_map) = vmt_material.get_value("normalmap") {
+        let normal_map_path = texture_path(normal_map);
+        manager.request_asset_with_progress(&normal_map_path, AssetType::Texture, priority, progress);
+        material.properties.insert("normal".to_string(), MaterialValue::Texture(normal_map_path));
+      }
+      let fog_color = vmt_material.get_value("fogcolor").and_then(parse_color).unwrap_or_else(|| Vec3::new(0f32, 0f32, 0f32));
+      material.properties.insert("fog_color".to_string(), MaterialValue::Vec4(Vec4::new(fog_color.x, fog_color.y, fog_color.z, 1f32)));
+      // The fog distances are in Source units.
+      let fog_start = vmt_material.get_value("fogstart").and_then(|value| value.parse::<f32>().ok()).unwrap_or(0f32);
+      material.properties.insert("fog_start".to_string(), MaterialValue::Float(fog_start * SCALING_FACTOR));
+      let fog_end = vmt_material.get_value("fogend").and_then(|value| value.parse::<f32>().ok()).unwrap_or(fog_start + 1f32);
+      material.properties.insert("fog_end".to_string(), MaterialValue::Float(fog_end * SCALING_FACTOR));
+      let reflect_tint = vmt_material.get_value("reflecttint").and_then(parse_color).unwrap_or_else(|| Vec3::new(1f32, 1f32, 1f32));
+      material.properties.insert("reflect_tint".to_string(), MaterialValue::Vec4(Vec4::new(reflect_tint.x, reflect_tint.y, reflect_tint.z, 1f32)));
+      let refract_tint = vmt_material.get_value("refracttint").and_then(parse_color).unwrap_or_else(|| Vec3::new(1f32, 1f32, 1f32));
+      material.properties.insert("refract_tint".to_string(), MaterialValue::Vec4(Vec4::new(refract_tint.x, refract_tint.y, refract_tint.z, 1f32)));
       manager.add_asset_with_progress(&path, Asset::Material(material), Some(progress), priority);
     }
 
     Ok(AssetLoaderResult::None)
   }
 }
+
+fn texture_path(name: &str) -> String {
+  "materials/".to_string() + name.to_lowercase().replace('\\', "/").as_str().trim_matches('/').trim_end_matches(".vtf") + ".vtf"
+}
+
+fn is_flag_set(material: &VMTMaterial, key: &str) -> bool {
+  material.get_value(key).is_some_and(|value| value.trim() != "0" && !value.trim().eq_ignore_ascii_case("false"))
+}
+
+/// Converts a Blinn-Phong exponent to the perceptual GGX roughness with a similar highlight.
+fn phong_exponent_to_roughness(exponent: f32) -> f32 {
+  (2f32 / (exponent.max(0f32) + 2f32)).powf(0.25f32)
+}
+
+/// Parses colors like `[1 0.5 0.5]`, `{255 128 128}` or a single value for all channels.
+fn parse_color(value: &str) -> Option<Vec3> {
+  let value = value.trim();
+  let is_integer_color = value.starts_with('{');
+  let components: Vec<f32> = value.trim_matches(&['[', ']', '{', '}'][..])
+    .split_whitespace()
+    .map(|component| component.parse::<f32>().ok())
+    .collect::<Option<Vec<f32>>>()?;
+  let color = match components.len() {
+    1 => Vec3::new(components[0], components[0], components[0]),
+    3 => Vec3::new(components[0], components[1], components[2]),
+    _ => return None
+  };
+  Some(if is_integer_color { color / 255f32 } else { color })
+}
@@ -4,7 +4,6 @@ use sourcerenderer_vpk::{Package, PackageError};
 use crate::asset::{AssetLoader, AssetManager, AssetLoaderProgress};
 use crate::asset::asset_manager::{AssetLoaderResult, AssetFile, AssetContainer, AssetLoadPriority};
 use sourcerenderer_core::Platform;
-use regex::Regex;
 use std::path::Path;
 use std::sync::Arc;
 
@@ -46,22 +45,18 @@ impl AssetContainer for VPKContainer {
   }
 }
 
-pub struct VPKContainerLoader {
-  pak_name_regex: Regex
-}
+pub struct VPKContainerLoader {}
 
 impl VPKContainerLoader {
   pub fn new() -> Self {
-    Self {
-      pak_name_regex: Regex::new(CSGO_PRIMARY_PAK_NAME_PATTERN).unwrap()
-    }
+    Self {}
   }
 }
 
 impl<P: Platform> AssetLoader<P> for VPKContainerLoader {
   fn matches(&self, file: &mut AssetFile) -> bool {
     let file_name = Path::new(&file.path).file_stem();
-    file_name.and_then(|file_name| file_name.to_str()).map_or(false, |file_name| self.pak_name_regex.is_match(file_name))
+    file_name.and_then(|file_name| file_name.to_str()).is_some_and(|file_name| file_name.ends_with("_dir"))
   }
 
   fn load(&self, file: AssetFile, manager: &Arc<AssetManager<P>>, _priority: AssetLoadPriority, progress: &Arc<AssetLoaderProgress>) -> Result<AssetLoaderResult, ()> {
@@ -1,11 +1,13 @@
 use crate::asset::{AssetLoader, Asset, AssetManager};
 use sourcerenderer_core::Platform;
 use crate::asset::asset_manager::{AssetFile, AssetLoaderResult, AssetLoaderProgress, AssetLoadPriority, Texture};
-use std::io::BufReader;
+use std::io::{BufReader, Read, Seek};
 use sourcerenderer_vtf::{VtfTexture, ImageFormat as VTFTextureFormat};
 use sourcerenderer_core::graphics::{SampleCount, TextureInfo, TextureUsage, TextureDimension};
 use sourcerenderer_core::graphics::Format;
 use std::sync::Arc;
+use half::f16;
+use log::warn;
 
 pub struct VTFTextureLoader {
 
@@ -27,43 +29,207 @@ impl<P: Platform> AssetLoader<P> for VTFTextureLoader {
 
   fn load(&self, file: AssetFile, manager: &Arc<AssetManager<P>>, priority: AssetLoadPriority, progress: &Arc<AssetLoaderProgress>) -> Result<AssetLoaderResult, ()> {
     let path = file.path.clone();
-    let mut vtf_texture = VtfTexture::new(BufReader::new(file)).unwrap();
-    let mut data = Vec::<Box<[u8]>>::new();
-    for i in 0..vtf_texture.header().mipmap_count {
-      let reversed_mip = vtf_texture.header().mipmap_count - 1 - i;
-      let mipmap = &vtf_texture.read_mip_map(reversed_mip as u32).unwrap();
-      data.push(mipmap.frames[0].faces[0].slices[0].data.clone());
-    }
-    let mipmap = &vtf_texture.read_mip_map(vtf_texture.header().mipmap_count as u32 - 1).unwrap();
-    let texture = Texture {
-      info: TextureInfo {
-        dimension: TextureDimension::Dim2D,
-        format: convert_vtf_texture_format(mipmap.format),
-        width: mipmap.width,
-        height: mipmap.height,
-        depth: 1,
-        mip_levels: vtf_texture.header().mipmap_count as u32,
-        array_length: 1,
-        samples: SampleCount::Samples1,
-        usage: TextureUsage::SAMPLED | TextureUsage::BLIT_DST,
-        supports_srgb: false,
-      },
-      data: data.into_boxed_slice(),
-    };
-
+    let texture = read_vtf_texture(file, &path)?;
     manager.add_asset_with_progress(&path, Asset::Texture(texture), Some(progress), priority);
 
     Ok(AssetLoaderResult::None)
   }
 }
 
-fn convert_vtf_texture_format(texture_format: VTFTextureFormat) -> Format {
+/// Decodes a VTF file into a texture with all of its mip maps, frames and cube faces.
+pub(super) fn read_vtf_texture<R: Read + Seek>(reader: R, path: &str) -> Result<Texture, ()> {
+  let vtf_texture = VtfTexture::new(BufReader::new(reader));
+  if let Err(e) = &vtf_texture {
+    warn!("Failed to load VTF texture {}: {:?}", path, e);
+    return Err(());
+  }
+  let mut vtf_texture = vtf_texture.unwrap();
+
+  let vtf_format = vtf_texture.header().high_res_image_format;
+  let (format, needs_conversion) = if let Some(format) = convert_vtf_texture_format(vtf_format) {
+    (format, false)
+  } else if let Some(format) = decoded_vtf_texture_format(vtf_format) {
+    (format, true)
+  } else {
+    warn!("VTF texture {} uses unsupported format {:?}", path, vtf_format);
+    return Err(());
+  };
+
+  let mipmap_count = vtf_texture.header().mipmap_count as u32;
+  let frames_count = vtf_texture.frames_count();
+  let is_cube = vtf_texture.faces_count() >= 6;
+  let is_volume = vtf_texture.depth() > 1;
+  let (dimension, array_length) = if is_cube {
+    // Cube maps ignore the sphere map face of older VTF files.
+    (TextureDimension::DimCube, 6 * frames_count)
+  } else if is_volume {
+    // Volume textures can't be texture arrays, so only the first frame gets used.
+    (TextureDimension::Dim3D, 1)
+  } else {
+    (TextureDimension::Dim2D, frames_count)
+  };
+
+  // The texture data is ordered by array layer first and mip level second.
+  let mut layers = vec![Vec::<Box<[u8]>>::with_capacity(mipmap_count as usize); array_length as usize];
+  let mut width = 0u32;
+  let mut height = 0u32;
+  let mut depth = 1u32;
+  for i in 0..mipmap_count {
+    let reversed_mip = mipmap_count - 1 - i;
+    let mipmap = vtf_texture.read_mip_map(reversed_mip);
+    if mipmap.is_none() {
+      warn!("Failed to read mip map {} of VTF texture {}", reversed_mip, path);
+      return Err(());
+    }
+    let mipmap = mipmap.unwrap();
+    if i == 0 {
+      width = mipmap.width;
+      height = mipmap.height;
+      depth = if is_volume { mipmap.depth } else { 1 };
+    }
+
+    for (layer_index, layer) in layers.iter_mut().enumerate() {
+      let (frame, face) = if is_cube {
+        (layer_index / 6, layer_index % 6)
+      } else {
+        (layer_index, 0)
+      };
+      let slices = &mipmap.frames[frame].faces[face].slices;
+      let mut mip_data = Vec::<u8>::with_capacity(slices.iter().map(|slice| slice.data.len()).sum());
+      for slice in slices {
+        mip_data.extend_from_slice(&slice.data);
+      }
+      if needs_conversion {
+        layer.push(decode_vtf_texture(vtf_format, &mip_data));
+      } else {
+        layer.push(mip_data.into_boxed_slice());
+      }
+    }
+  }
+  let data: Vec<Box<[u8]>> = layers.into_iter().flatten().collect();
+
+  let texture = Texture {
+    info: TextureInfo {
+      dimension,
+      format,
+      width,
+      height,
+      depth,
+      mip_levels: mipmap_count,
+      array_length,
+      samples: SampleCount::Samples1,
+      usage: TextureUsage::SAMPLED | TextureUsage::BLIT_DST,
+      supports_srgb: false,
+    },
+    data: data.into_boxed_slice(),
+  };
+
+  Ok(texture)
+}
+
+/// Formats that can be uploaded without any conversion.
+fn convert_vtf_texture_format(texture_format: VTFTextureFormat) -> Option<Format> {
   match texture_format {
-    VTFTextureFormat::DXT1 => Format::DXT1,
-    VTFTextureFormat::DXT1OneBitAlpha => Format::DXT1Alpha,
-    VTFTextureFormat::DXT3 => Format::DXT3,
-    VTFTextureFormat::DXT5 => Format::DXT5,
-    VTFTextureFormat::RGBA8888 => Format::RGBA8UNorm,
-    _ => panic!("VTF format {:?} is not supported", texture_format)
+    VTFTextureFormat::DXT1 => Some(Format::DXT1),
+    VTFTextureFormat::DXT1OneBitAlpha => Some(Format::DXT1Alpha),
+    VTFTextureFormat::DXT3 => Some(Format::DXT3),
+    VTFTextureFormat::DXT5 => Some(Format::DXT5),
+    VTFTextureFormat::RGBA8888 | VTFTextureFormat::UVWQ8888 | VTFTextureFormat::UV1X8888 => Some(Format::RGBA8UNorm),
+    VTFTextureFormat::BGRA8888 => Some(Format::BGRA8UNorm),
+    VTFTextureFormat::UV88 => Some(Format::RG8SNorm),
+    VTFTextureFormat::RGBA16161616F => Some(Format::RGBA16Float),
+    _ => None
   }
-}
\ No newline at end of file
+}
+
+/// Formats that get converted on the CPU by `decode_vtf_texture`.
+fn decoded_vtf_texture_format(texture_format: VTFTextureFormat) -> Option<Format> {
+  match texture_format {
+    VTFTextureFormat::RGBA16161616 => Some(Format::RGBA16Float),
+    VTFTextureFormat::ABGR8888
+    | VTFTextureFormat::ARGB8888
+    | VTFTextureFormat::BGRX8888
+    | VTFTextureFormat::RGB8888
+    | VTFTextureFormat::BGR888
+    | VTFTextureFormat::RGB888Bluescreen
+    | VTFTextureFormat::BGR888Bluescreen
+    | VTFTextureFormat::RGB565
+    | VTFTextureFormat::BGR565
+    | VTFTextureFormat::BGRX5551
+    | VTFTextureFormat::BGRA5551
+    | VTFTextureFormat::BGRA4444
+    | VTFTextureFormat::I8
+    | VTFTextureFormat::IA88
+    | VTFTextureFormat::A8 => Some(Format::RGBA8UNorm),
+    _ => None
+  }
+}
+
+fn expand_bits(value: u16, bits: u32) -> u8 {
+  let max = (1u32 << bits) - 1;
+  ((value as u32 * 255 + max / 2) / max) as u8
+}
+
+fn decode_vtf_texture(texture_format: VTFTextureFormat, data: &[u8]) -> Box<[u8]> {
+  if texture_format == VTFTextureFormat::RGBA16161616 {
+    let mut decoded = Vec::<u8>::with_capacity(data.len());
+    for channel in data.chunks_exact(2) {
+      let value = u16::from_le_bytes([channel[0], channel[1]]) as f32 / 65535f32;
+      decoded.extend_from_slice(&f16::from_f32(value).to_le_bytes());
+    }
+    return decoded.into_boxed_slice();
+  }
+
+  let bytes_per_pixel = match texture_format {
+    VTFTextureFormat::I8 | VTFTextureFormat::A8 => 1,
+    VTFTextureFormat::IA88
+    | VTFTextureFormat::RGB565
+    | VTFTextureFormat::BGR565
+    | VTFTextureFormat::BGRX5551
+    | VTFTextureFormat::BGRA5551
+    | VTFTextureFormat::BGRA4444 => 2,
+    VTFTextureFormat::RGB8888
+    | VTFTextureFormat::BGR888
+    | VTFTextureFormat::RGB888Bluescreen
+    | VTFTextureFormat::BGR888Bluescreen => 3,
+    _ => 4
+  };
+
+  let mut decoded = Vec::<u8>::with_capacity(data.len() / bytes_per_pixel * 4);
+  for pixel in data.chunks_exact(bytes_per_pixel) {
+    let packed = if bytes_per_pixel == 2 { u16::from_le_bytes([pixel[0], pixel[1]]) } else { 0 };
+    let rgba: [u8; 4] = match texture_format {
+      VTFTextureFormat::ABGR8888 => [pixel[3], pixel[2], pixel[1], pixel[0]],
+      VTFTextureFormat::ARGB8888 => [pixel[1], pixel[2], pixel[3], pixel[0]],
+      VTFTextureFormat::BGRX8888 => [pixel[2], pixel[1], pixel[0], 255],
+      VTFTextureFormat::RGB8888 => [pixel[0], pixel[1], pixel[2], 255],
+      VTFTextureFormat::BGR888 => [pixel[2], pixel[1], pixel[0], 255],
+      VTFTextureFormat::RGB888Bluescreen | VTFTextureFormat::BGR888Bluescreen => {
+        let (r, g, b) = if texture_format == VTFTextureFormat::RGB888Bluescreen {
+          (pixel[0], pixel[1], pixel[2])
+        } else {
+          (pixel[2], pixel[1], pixel[0])
+        };
+        // Pure blue marks transparent pixels.
+        if r == 0 && g == 0 && b == 255 {
+          [0, 0, 0, 0]
+        } else {
+          [r, g, b, 255]
+        }
+      }
+      VTFTextureFormat::RGB565 => [expand_bits(packed & 0x1F, 5), expand_bits((packed >> 5) & 0x3F, 6), expand_bits(packed >> 11, 5), 255],
+      VTFTextureFormat::BGR565 => [expand_bits(packed >> 11, 5), expand_bits((packed >> 5) & 0x3F, 6), expand_bits(packed & 0x1F, 5), 255],
+      VTFTextureFormat::BGRX5551 | VTFTextureFormat::BGRA5551 => {
+        let alpha = if texture_format == VTFTextureFormat::BGRX5551 || packed >> 15 != 0 { 255 } else { 0 };
+        [expand_bits((packed >> 10) & 0x1F, 5), expand_bits((packed >> 5) & 0x1F, 5), expand_bits(packed & 0x1F, 5), alpha]
+      }
+      VTFTextureFormat::BGRA4444 => [expand_bits((packed >> 8) & 0xF, 4), expand_bits((packed >> 4) & 0xF, 4), expand_bits(packed & 0xF, 4), expand_bits(packed >> 12, 4)],
+      VTFTextureFormat::I8 => [pixel[0], pixel[0], pixel[0], 255],
+      VTFTextureFormat::IA88 => [pixel[0], pixel[0], pixel[0], pixel[1]],
+      VTFTextureFormat::A8 => [0, 0, 0, pixel[0]],
+      _ => unreachable!()
+    };
+    decoded.extend_from_slice(&rgba);
+  }
+  decoded.into_boxed_slice()
+}
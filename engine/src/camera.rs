@@ -6,3 +6,6 @@ pub struct Camera {
 }
 
 pub struct ActiveCamera(pub Entity);
+
+/// Marks the position the active camera starts at when a level is loaded.
+pub struct PlayerStart {}
@@ -3,15 +3,18 @@ use sourcerenderer_core::input::Key;
 use crate::input::InputState;
 use crate::Transform;
 use crate::Camera;
+use crate::camera::{ActiveCamera, PlayerStart};
 use sourcerenderer_core::{Quaternion, Vec3, Platform, Vec2};
 use legion::systems::Builder;
-use legion::{component, World};
+use legion::{component, World, IntoQuery, maybe_changed, EntityStore};
+use legion::world::SubWorld;
 
 use crate::game::TickRate;
 
 pub fn install<P: Platform>(_world: &mut World, systems: &mut Builder) {
   systems.add_system(retrieve_fps_camera_rotation_system::<P>());
   systems.add_system(fps_camera_movement_system::<P>());
+  systems.add_system(fps_camera_player_start_system());
 }
 
 pub struct FPSCameraComponent {
@@ -109,3 +112,33 @@ fn fps_camera_movement<P: Platform>(#[resource] input: &InputState, transform: &
     transform.position += movement_vector * 8f32 / (tick_rate.0 as f32);
   }
 }
+
+#[system]
+#[read_component(PlayerStart)]
+#[write_component(Transform)]
+#[write_component(FPSCameraComponent)]
+fn fps_camera_player_start(world: &mut SubWorld, #[resource] active_camera: &ActiveCamera) {
+  let mut player_start_query = <(&Transform,)>::query()
+    .filter(component::<PlayerStart>() & maybe_changed::<PlayerStart>());
+  let start = player_start_query.iter(world).next().map(|(transform,)| (transform.position, transform.rotation));
+  if start.is_none() {
+    return;
+  }
+  let (start_position, start_rotation) = start.unwrap();
+
+  let entry = world.entry_mut(active_camera.0);
+  if entry.is_err() {
+    return;
+  }
+  let mut entry = entry.unwrap();
+  if let Ok(transform) = entry.get_component_mut::<Transform>() {
+    transform.position = start_position;
+    transform.rotation = start_rotation;
+  }
+  if let Ok(fps_camera) = entry.get_component_mut::<FPSCameraComponent>() {
+    // The rotation gets recalculated from pitch and yaw every tick.
+    let (pitch, yaw, _) = start_rotation.euler_angles();
+    fps_camera.fps_camera.pitch = pitch;
+    fps_camera.fps_camera.yaw = yaw;
+  }
+}
@@ -6,7 +6,7 @@ use sourcerenderer_core::{Platform, atomic_refcell::AtomicRefCell, platform::Thr
 
 use crate::{asset::loaders::{GltfLoader, FSContainer}, game_internal::GameInternal, input::Input, renderer::*};
 use crate::asset::AssetManager;
-use crate::asset::loaders::{BspLevelLoader, VPKContainerLoader, VTFTextureLoader, VMTMaterialLoader, MDLModelLoader};
+use crate::asset::loaders::{BspLevelLoader, Q3BspLevelLoader, VPKContainerLoader, VTFTextureLoader, VMTMaterialLoader, MDLModelLoader};
 use legion::query::{FilterResult, LayoutFilter};
 use legion::storage::ComponentTypeId;
 use crate::input::InputState;
@@ -66,6 +66,7 @@ impl<P: Platform> Game<P> {
     asset_manager: &Arc<AssetManager<P>>,
     tick_rate: u32) -> Arc<Self> {
 
+    asset_manager.add_loader(Box::new(Q3BspLevelLoader::new()));
     asset_manager.add_loader(Box::new(BspLevelLoader::new()));
     asset_manager.add_loader(Box::new(VPKContainerLoader::new()));
     asset_manager.add_loader(Box::new(VTFTextureLoader::new()));
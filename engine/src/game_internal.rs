@@ -3,15 +3,16 @@ use std::time::Duration;
 
 use legion::{Resources, Schedule, World};
 
-use log::trace;
+use log::{trace, warn};
 use nalgebra::UnitQuaternion;
 use sourcerenderer_core::{Platform, Vec3};
 
-use crate::asset::loaders::{GltfLoader, ImageLoader, ShaderLoader};
+use crate::asset::loaders::{GameInfoContainer, GltfLoader, ImageLoader, ShaderLoader};
 use crate::{DeltaTime, Tick, TickDelta, TickDuration, TickRate, Transform, asset::loaders::GltfContainer, game::FilterAll, renderer::*};
 use crate::transform;
 use crate::asset::AssetManager;
 use crate::fps_camera;
+use crate::animation;
 use crate::renderer::RendererInterface;
 use instant::Instant;
 use crate::game::Game;
@@ -70,6 +71,16 @@ impl GameInternal {
 
     trace!("Csgo path: {:?}", csgo_path);
 
+    // Mounts the game directory in SOURCE_GAME_PATH (for example ".../Half-Life 2/hl2")
+    // and everything listed in the search paths of its gameinfo.txt.
+    match std::env::var("SOURCE_GAME_PATH") {
+      Ok(source_game_path) => match GameInfoContainer::<P>::new(&source_game_path) {
+        Ok(container) => asset_manager.add_container(Box::new(container)),
+        Err(e) => warn!("Could not mount Source game {:?}: {:?}", source_game_path, e)
+      },
+      Err(_) => trace!("SOURCE_GAME_PATH is not set, not mounting a Source game")
+    }
+
     /*let mut level = {
       asset_manager.add_container(Box::new(CSGODirectoryContainer::new::<P>(csgo_path).unwrap()));
       let progress = asset_manager.request_asset("pak01_dir", AssetType::Container, AssetLoadPriority::Normal);
@@ -84,6 +95,7 @@ impl GameInternal {
     PhysicsWorld::install(&mut world, &mut resources, &mut fixed_schedule, tick_duration);
     crate::spinning_cube::install(&mut world, &mut resources, &mut fixed_schedule, asset_manager);
     fps_camera::install::<P>(&mut world, &mut fixed_schedule);
+    animation::install::<P>(&mut fixed_schedule, asset_manager);
     transform::interpolation::install(&mut fixed_schedule, &mut schedule);
     transform::install(&mut fixed_schedule);
     renderer.install(&mut world, &mut resources, &mut schedule);
@@ -92,7 +104,12 @@ impl GameInternal {
       position: Vec3::new(0f32, 0f32, 0f32),
       rotation: UnitQuaternion::default(),
       scale: Vec3::new(1f32, 1f32, 1f32),
-    }, PointLightComponent { intensity: 1.0f32 }));
+    }, PointLightComponent {
+      intensity: 1.0f32,
+      color: Vec3::new(1f32, 1f32, 1f32),
+      range: LightAttenuation::INVERSE_SQUARE.cutoff_range(1.0f32),
+      attenuation: LightAttenuation::INVERSE_SQUARE
+    }));
 
     trace!("Point Light: {:?}", point_light_entity);
 
@@ -11,6 +11,7 @@ extern crate sourcerenderer_vmt;
 extern crate sourcerenderer_mdl;
 extern crate sourcerenderer_vvd;
 extern crate sourcerenderer_vtx;
+extern crate sourcerenderer_vhv;
 #[macro_use]
 extern crate legion;
 extern crate regex;
@@ -29,6 +30,8 @@ pub use transform::Transform;
 pub use transform::Parent;
 pub use camera::Camera;
 pub use camera::ActiveCamera;
+pub use camera::PlayerStart;
+pub use animation::AnimatedModelComponent;
 
 pub use self::game::{DeltaTime, TickDelta, TickDuration, TickRate, Tick};
 
@@ -40,6 +43,7 @@ mod spinning_cube;
 pub mod transform;
 mod camera;
 pub mod fps_camera;
+mod animation;
 mod math;
 
 pub mod renderer;
@@ -143,7 +143,7 @@ impl<B: Backend> AssetBufferSlice<B> {
   }
 
   pub fn offset(&self) -> u32 {
-    self.range.offset
+    self.range.aligned_offset
   }
 
   pub fn size(&self) -> u32 {
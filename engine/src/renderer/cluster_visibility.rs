@@ -0,0 +1,84 @@
+use sourcerenderer_core::{Vec3, Vec4};
+
+#[derive(Clone, Debug)]
+pub struct ClusterVisibilityNode {
+  /// Plane normal in xyz, distance in w.
+  pub plane: Vec4,
+  /// Negative child indices reference leafs: leaf index = -child - 1
+  pub children: [i32; 2]
+}
+
+/// Precomputed cluster visibility of a level in engine space.
+pub struct ClusterVisibility {
+  nodes: Box<[ClusterVisibilityNode]>,
+  leaf_clusters: Box<[i16]>,
+  cluster_count: u32,
+  words_per_cluster: u32,
+  pvs: Box<[u32]>
+}
+
+impl ClusterVisibility {
+  /// `pvs` is called for every cluster and has to return a decompressed bit set of the clusters that are visible from it.
+  pub fn new<F: Fn(u32) -> Box<[u8]>>(nodes: Box<[ClusterVisibilityNode]>, leaf_clusters: Box<[i16]>, cluster_count: u32, pvs: F) -> Self {
+    let words_per_cluster = (cluster_count + 31) / 32;
+    let mut pvs_words = vec![0u32; (words_per_cluster * cluster_count) as usize];
+    for cluster in 0..cluster_count {
+      let bytes = pvs(cluster);
+      let offset = (cluster * words_per_cluster) as usize;
+      for (index, byte) in bytes.iter().enumerate().take(words_per_cluster as usize * 4) {
+        pvs_words[offset + index / 4] |= (*byte as u32) << ((index % 4) * 8);
+      }
+    }
+
+    Self {
+      nodes,
+      leaf_clusters,
+      cluster_count,
+      words_per_cluster,
+      pvs: pvs_words.into_boxed_slice()
+    }
+  }
+
+  pub fn find_leaf(&self, position: &Vec3) -> Option<usize> {
+    if self.nodes.is_empty() {
+      return None;
+    }
+
+    let mut index = 0i32;
+    while index >= 0 {
+      let node = &self.nodes[index as usize];
+      let distance = node.plane.xyz().dot(position) - node.plane.w;
+      index = if distance >= 0f32 {
+        node.children[0]
+      } else {
+        node.children[1]
+      };
+    }
+    Some((-index - 1) as usize)
+  }
+
+  /// Returns the cluster that contains the position or None if the position is outside of the level.
+  pub fn find_cluster(&self, position: &Vec3) -> Option<u32> {
+    self.find_leaf(position)
+      .and_then(|leaf| self.leaf_clusters.get(leaf).copied())
+      .and_then(|cluster| if cluster >= 0 && (cluster as u32) < self.cluster_count { Some(cluster as u32) } else { None })
+  }
+
+  pub fn visible_clusters(&self, cluster: u32) -> &[u32] {
+    let offset = (cluster * self.words_per_cluster) as usize;
+    &self.pvs[offset .. offset + self.words_per_cluster as usize]
+  }
+
+  pub fn is_visible(visible_clusters: &[u32], cluster: u16) -> bool {
+    let word = visible_clusters.get(cluster as usize / 32).copied().unwrap_or(0);
+    (word & (1 << (cluster % 32))) != 0
+  }
+
+  pub fn leaf_cluster(&self, leaf: usize) -> Option<u16> {
+    self.leaf_clusters.get(leaf).and_then(|cluster| if *cluster >= 0 { Some(*cluster as u16) } else { None })
+  }
+
+  pub fn cluster_count(&self) -> u32 {
+    self.cluster_count
+  }
+}
@@ -1,5 +1,9 @@
+use std::sync::Arc;
+
 use legion::Entity;
-use sourcerenderer_core::Matrix4;
+use sourcerenderer_core::{Matrix4, Vec3};
+
+use super::{ClusterVisibility, LightAttenuation, LightStyles, Decals, WaterSurfaces, WaterVolumes, DetailProps};
 
 pub enum RendererCommand {
   RegisterStatic {
@@ -8,23 +12,52 @@ pub enum RendererCommand {
     model_path: String,
     receive_shadows: bool,
     cast_shadows: bool,
-    can_move: bool
+    can_move: bool,
+    clusters: Option<Box<[u16]>>,
+    cubemap: Option<u32>,
+    ambient_cube: Option<[Vec3; 6]>,
+    vertex_colors_path: Option<String>
   },
   UnregisterStatic(Entity),
   RegisterPointLight {
     entity: Entity,
     transform: Matrix4,
-    intensity: f32
+    intensity: f32,
+    color: Vec3,
+    range: f32,
+    attenuation: LightAttenuation
   },
   UnregisterPointLight(Entity),
   RegisterDirectionalLight {
     entity: Entity,
     transform: Matrix4,
-    intensity: f32
+    intensity: f32,
+    color: Vec3
   },
   UnregisterDirectionalLight(Entity),
+  RegisterSpotLight {
+    entity: Entity,
+    transform: Matrix4,
+    intensity: f32,
+    color: Vec3,
+    range: f32,
+    attenuation: LightAttenuation,
+    inner_cone_angle: f32,
+    outer_cone_angle: f32
+  },
+  UnregisterSpotLight(Entity),
   UpdateTransform{ entity: Entity, transform_mat: Matrix4 },
+  UpdateBonePalette { entity: Entity, bone_palette: Box<[Matrix4]> },
   UpdateCameraTransform { camera_transform_mat: Matrix4, fov: f32 },
   SetLightmap(String),
+  SetLightStyles(LightStyles),
+  SetSkybox(String),
+  SetDecals(Decals),
+  SetWaterSurfaces(WaterSurfaces),
+  SetWaterVolumes(WaterVolumes),
+  SetDetailProps(DetailProps),
+  SetSkyCamera { position: Vec3, scale: f32 },
+  SetCubemaps(Box<[String]>),
+  SetPotentiallyVisibleSet(Arc<ClusterVisibility>),
   EndFrame
 }
@@ -13,7 +13,16 @@ pub struct RendererStaticDrawable {
   pub model: ModelHandle,
   pub receive_shadows: bool,
   pub cast_shadows: bool,
-  pub can_move: bool
+  pub can_move: bool,
+  pub clusters: Option<Box<[u16]>>,
+  /// Index of the scene cubemap used for reflections.
+  pub cubemap: Option<u32>,
+  /// Indirect lighting for drawables without lightmap coverage.
+  pub ambient_cube: Option<[Vec3; 6]>,
+  /// Baked lighting for every vertex of the model, takes precedence over the ambient cube.
+  pub vertex_colors: Option<VertexColorsHandle>,
+  /// Skinning matrices of animated models, indexed by bone.
+  pub bone_palette: Option<Box<[Matrix4]>>
 }
 
 #[derive(Clone)]
@@ -30,7 +39,9 @@ pub struct View {
   pub aspect_ratio: f32,
   pub old_visible_drawables_bitset: Vec<u32>,
   pub visible_drawables_bitset: Vec<u32>,
-  pub drawable_parts: Vec<DrawablePart>
+  pub drawable_parts: Vec<DrawablePart>,
+  /// Parts with blended materials, sorted back to front.
+  pub translucent_parts: Vec<DrawablePart>
 }
 
 impl Default for View {
@@ -47,6 +58,7 @@ impl Default for View {
       far_plane: 100f32,
       aspect_ratio: 16.0f32 / 9.0f32,
       drawable_parts: Vec::new(),
+      translucent_parts: Vec::new(),
       old_visible_drawables_bitset: Vec::new(),
       visible_drawables_bitset: Vec::new()
     }
@@ -69,3 +81,13 @@ pub(crate) fn make_camera_proj(fov: f32, aspect_ratio: f32, z_near: f32, z_far:
   let vertical_fov = 2f32 * ((fov / 2f32).tan() * (1f32 / aspect_ratio)).atan();
   nalgebra_glm::perspective_lh_zo(aspect_ratio, vertical_fov, z_near, z_far)
 }
+
+/// The pixel height of a sphere with a diameter of one world unit at the origin of the transform.
+pub(crate) fn projected_screen_size(view: &View, transform: &Matrix4, rt_height: u32) -> f32 {
+  let position = Vec3::new(transform[(0, 3)], transform[(1, 3)], transform[(2, 3)]);
+  let scale = transform.fixed_slice::<3, 3>(0, 0).column_iter()
+    .map(|axis| axis.magnitude())
+    .fold(0f32, f32::max);
+  let distance = (position - view.camera_position).magnitude().max(view.near_plane);
+  view.proj_matrix[(1, 1)].abs() * scale * rt_height as f32 * 0.5f32 / distance
+}
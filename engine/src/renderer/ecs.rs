@@ -1,4 +1,5 @@
 use std::collections::HashSet;
+use std::sync::Arc;
 use instant::Duration;
 use legion::{Entity, IntoQuery, maybe_changed, EntityStore};
 
@@ -7,19 +8,34 @@ use legion::component;
 use legion::world::SubWorld;
 use crate::transform::GlobalTransform;
 use crate::{ActiveCamera, Camera};
-use sourcerenderer_core::{Matrix4, Platform};
+use sourcerenderer_core::{Matrix4, Platform, Vec3, Vec4};
 use crate::transform::interpolation::InterpolatedTransform;
+use super::ClusterVisibility;
+use crate::animation::AnimatedModelComponent;
 
 pub trait RendererInterface {
-  fn register_static_renderable(&self, entity: Entity, transform: &InterpolatedTransform, renderable: &StaticRenderableComponent);
+  #[allow(clippy::too_many_arguments)]
+  fn register_static_renderable(&self, entity: Entity, transform: &InterpolatedTransform, renderable: &StaticRenderableComponent, clusters: Option<&VisibilityClusters>, cubemap: Option<&CubemapIndex>, ambient_cube: Option<&AmbientLightCube>, vertex_lighting: Option<&VertexLighting>);
   fn unregister_static_renderable(&self, entity: Entity);
   fn register_point_light(&self, entity: Entity, transform: &InterpolatedTransform, point_light: &PointLightComponent);
   fn unregister_point_light(&self, entity: Entity);
   fn register_directional_light(&self, entity: Entity, transform: &InterpolatedTransform, directional_light: &DirectionalLightComponent);
   fn unregister_directional_light(&self, entity: Entity);
+  fn register_spot_light(&self, entity: Entity, transform: &InterpolatedTransform, spot_light: &SpotLightComponent);
+  fn unregister_spot_light(&self, entity: Entity);
   fn update_camera_transform(&self, camera_transform_mat: Matrix4, fov: f32);
   fn update_transform(&self, entity: Entity, transform: Matrix4);
+  fn update_bone_palette(&self, entity: Entity, bone_palette: &[Matrix4]);
   fn update_lightmap(&self, path: &str);
+  fn update_light_styles(&self, light_styles: &LightStyles);
+  fn update_skybox(&self, path: &str);
+  fn update_decals(&self, decals: &Decals);
+  fn update_water_surfaces(&self, water_surfaces: &WaterSurfaces);
+  fn update_water_volumes(&self, water_volumes: &WaterVolumes);
+  fn update_detail_props(&self, detail_props: &DetailProps);
+  fn update_sky_camera(&self, position: Vec3, scale: f32);
+  fn update_cubemaps(&self, texture_paths: &[String]);
+  fn update_potentially_visible_set(&self, pvs: &Arc<ClusterVisibility>);
   fn end_frame(&self);
   fn is_saturated(&self) -> bool;
   fn wait_until_available(&self, timeout: Duration);
@@ -34,14 +50,67 @@ pub struct StaticRenderableComponent {
   pub can_move: bool
 }
 
+/// Intensity below which a light no longer contributes anything visible.
+pub(super) const LIGHT_CUTOFF: f32 = 0.05f32;
+
+/// The light falls off with `1 / (constant + linear * d + quadratic * d²)`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightAttenuation {
+  pub constant: f32,
+  pub linear: f32,
+  pub quadratic: f32
+}
+
+impl LightAttenuation {
+  /// Physically based falloff.
+  pub const INVERSE_SQUARE: Self = Self {
+    constant: 0f32,
+    linear: 0f32,
+    quadratic: 1f32
+  };
+
+  /// Distance at which a light with the given intensity falls below the cutoff, for lights that don't specify a range.
+  pub fn cutoff_range(&self, intensity: f32) -> f32 {
+    let threshold = (intensity / LIGHT_CUTOFF - self.constant).max(0f32);
+    if self.quadratic > 0f32 {
+      (-self.linear + (self.linear * self.linear + 4f32 * self.quadratic * threshold).sqrt()) / (2f32 * self.quadratic)
+    } else if self.linear > 0f32 {
+      threshold / self.linear
+    } else {
+      f32::INFINITY
+    }
+  }
+}
+
+/// The color is linear RGB and gets scaled by the intensity.
+/// The light gets faded out smoothly towards the range, lights are only binned into the clusters within it.
 #[derive(Clone, Debug, PartialEq)]
 pub struct PointLightComponent {
-  pub intensity: f32
+  pub intensity: f32,
+  pub color: Vec3,
+  pub range: f32,
+  pub attenuation: LightAttenuation
 }
 
+/// The color is linear RGB and gets scaled by the intensity.
 #[derive(Clone, Debug, PartialEq)]
 pub struct DirectionalLightComponent {
-  pub intensity: f32
+  pub intensity: f32,
+  pub color: Vec3
+}
+
+/// Shines along the up axis of the transform, like `DirectionalLightComponent`.
+/// The intensity falls off between the inner and the outer cone, both angles are in radians and measured from the center of the cone.
+/// Color, range and attenuation work like they do for `PointLightComponent`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpotLightComponent {
+  pub intensity: f32,
+  pub color: Vec3,
+  pub range: f32,
+  pub attenuation: LightAttenuation,
+  pub inner_cone_angle: f32,
+  pub outer_cone_angle: f32
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -49,6 +118,159 @@ pub struct Lightmap {
   pub path: String
 }
 
+pub const LIGHT_STYLE_COUNT: usize = 64;
+
+/// Intensities of the light styles baked into the lightmap.
+/// Game code can change them to make lights flicker or switch them on and off.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LightStyles {
+  pub intensities: [f32; LIGHT_STYLE_COUNT]
+}
+
+impl Default for LightStyles {
+  fn default() -> Self {
+    Self {
+      intensities: [1f32; LIGHT_STYLE_COUNT]
+    }
+  }
+}
+
+/// The cube map that gets drawn behind all geometry.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Skybox {
+  pub texture_path: String
+}
+
+/// The overlays and decals of the level, clipped to the surfaces they cover and merged into a single model.
+/// Every part of the model is one material, the parts are ordered by their render order.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decals {
+  pub model_path: String,
+  /// One entry per part of the model.
+  pub part_fades: Box<[DecalFade]>
+}
+
+/// Decals fade out between the start and the end distance to the camera.
+/// An end distance of zero means that the decal never fades out.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DecalFade {
+  pub start: f32,
+  pub end: f32
+}
+
+/// The water surfaces of a brush model merged into a single model, every part of the model uses a water material.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WaterSurface {
+  pub model_path: String,
+  pub transform: Matrix4
+}
+
+/// The water surfaces of the level, they get drawn after the opaque geometry.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WaterSurfaces {
+  pub surfaces: Box<[WaterSurface]>
+}
+
+/// A body of water, the camera gets fogged with the material of the surface when it's below it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WaterVolume {
+  pub surface_height: f32,
+  pub material_path: String
+}
+
+/// The water volumes of the level.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WaterVolumes {
+  /// The index of the water volume of every leaf of the `PotentiallyVisibleSet`, -1 for leaves without water.
+  pub leaf_volumes: Box<[i16]>,
+  pub volumes: Box<[WaterVolume]>
+}
+
+/// How a detail sprite faces the camera.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DetailSpriteOrientation {
+  /// The quad is spanned by `right` and `up`.
+  Fixed,
+  ScreenAligned,
+  /// Faces the camera but only rotates around the up axis.
+  ScreenAlignedVertical
+}
+
+/// A quad that shows a region of the detail sprite texture.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DetailSprite {
+  pub position: Vec3,
+  pub orientation: DetailSpriteOrientation,
+  pub right: Vec3,
+  pub up: Vec3,
+  /// The corners of the quad relative to the position along the right and up axes: min right, min up, max right, max up.
+  pub extents: Vec4,
+  /// The texture coordinates of the upper left and the lower right corner.
+  pub tex_coords: Vec4,
+  pub color: Vec3
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DetailModelInstance {
+  pub transform: Matrix4,
+  pub color: Vec3
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DetailModel {
+  pub model_path: String,
+  pub instances: Box<[DetailModelInstance]>
+}
+
+/// The grass and foliage of the level. Detail props fade out between `fade_start` and `fade_end`
+/// and get drawn in a few instanced batches instead of one drawable each.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DetailProps {
+  /// All sprites share a single material.
+  pub sprite_material_path: String,
+  pub sprites: Box<[DetailSprite]>,
+  pub models: Box<[DetailModel]>,
+  pub fade_start: f32,
+  pub fade_end: f32
+}
+
+/// The origin of the 3D skybox. The area around it gets drawn behind the level,
+/// scaled up by `scale` and moving along with the camera.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SkyCamera {
+  pub scale: f32
+}
+
+/// The cube maps baked into the level for reflections.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cubemaps {
+  pub texture_paths: Box<[String]>
+}
+
+/// Selects the entry of `Cubemaps` that a renderable reflects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CubemapIndex(pub u32);
+
+/// Baked indirect lighting of renderables that aren't covered by the lightmap.
+/// Holds one color for each axis direction in the order +X, -X, +Y, -Y, +Z, -Z.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AmbientLightCube(pub [Vec3; 6]);
+
+/// Baked per-vertex lighting of a static renderable, it replaces the lightmap and the ambient cube.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VertexLighting {
+  pub vertex_colors_path: String
+}
+
+/// Precomputed cluster visibility of the loaded level.
+#[derive(Clone)]
+pub struct PotentiallyVisibleSet(pub Arc<ClusterVisibility>);
+
+/// The visibility clusters a renderable touches.
+/// Renderables without it are never culled using the potentially visible set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VisibilityClusters(pub Box<[u16]>);
+
 #[derive(Clone, Default, Debug)]
 pub struct ActiveStaticRenderables(HashSet<Entity>);
 #[derive(Clone, Default, Debug)]
@@ -61,9 +283,13 @@ pub struct RegisteredPointLights(HashSet<Entity>);
 pub struct ActiveDirectionalLights(HashSet<Entity>);
 #[derive(Clone, Default, Debug)]
 pub struct RegisteredDirectionalLights(HashSet<Entity>);
+#[derive(Clone, Default, Debug)]
+pub struct ActiveSpotLights(HashSet<Entity>);
+#[derive(Clone, Default, Debug)]
+pub struct RegisteredSpotLights(HashSet<Entity>);
 
 pub fn install<P: Platform, R: RendererInterface + Send + Sync + 'static>(systems: &mut Builder, renderer: R) {
-  systems.add_system(renderer_system::<P, R>(renderer, ActiveStaticRenderables(HashSet::new()), RegisteredStaticRenderables(HashSet::new()), ActivePointLights(HashSet::new()), RegisteredPointLights(HashSet::new()), ActiveDirectionalLights(HashSet::new()), RegisteredDirectionalLights(HashSet::new())));
+  systems.add_system(renderer_system::<P, R>(renderer, ActiveStaticRenderables(HashSet::new()), RegisteredStaticRenderables(HashSet::new()), ActivePointLights(HashSet::new()), RegisteredPointLights(HashSet::new()), ActiveDirectionalLights(HashSet::new()), RegisteredDirectionalLights(HashSet::new()), ActiveSpotLights(HashSet::new()), RegisteredSpotLights(HashSet::new())));
 }
 
 #[system]
@@ -71,9 +297,24 @@ pub fn install<P: Platform, R: RendererInterface + Send + Sync + 'static>(system
 #[read_component(InterpolatedTransform)]
 #[read_component(PointLightComponent)]
 #[read_component(DirectionalLightComponent)]
+#[read_component(SpotLightComponent)]
 #[read_component(GlobalTransform)]
 #[read_component(Camera)]
 #[read_component(Lightmap)]
+#[read_component(LightStyles)]
+#[read_component(Skybox)]
+#[read_component(Decals)]
+#[read_component(WaterSurfaces)]
+#[read_component(WaterVolumes)]
+#[read_component(DetailProps)]
+#[read_component(SkyCamera)]
+#[read_component(Cubemaps)]
+#[read_component(CubemapIndex)]
+#[read_component(AmbientLightCube)]
+#[read_component(VertexLighting)]
+#[read_component(VisibilityClusters)]
+#[read_component(PotentiallyVisibleSet)]
+#[read_component(AnimatedModelComponent)]
 fn renderer<P: Platform, R: RendererInterface + 'static>(world: &mut SubWorld,
             #[state] renderer: &R,
             #[state] active_static_renderables: &mut ActiveStaticRenderables,
@@ -82,6 +323,8 @@ fn renderer<P: Platform, R: RendererInterface + 'static>(world: &mut SubWorld,
             #[state] registered_point_lights: &mut RegisteredPointLights,
             #[state] active_directional_lights: &mut ActiveDirectionalLights,
             #[state] registered_directional_lights: &mut RegisteredDirectionalLights,
+            #[state] active_spot_lights: &mut ActiveSpotLights,
+            #[state] registered_spot_lights: &mut RegisteredSpotLights,
             #[resource] active_camera: &ActiveCamera) {
   if renderer.is_saturated() {
     return;
@@ -103,14 +346,14 @@ fn renderer<P: Platform, R: RendererInterface + 'static>(world: &mut SubWorld,
   }
 
   active_static_renderables.0.clear();
-  let mut static_components_query = <(Entity, &StaticRenderableComponent, &InterpolatedTransform)>::query();
-  for (entity, component, transform) in static_components_query.iter(world) {
+  let mut static_components_query = <(Entity, &StaticRenderableComponent, &InterpolatedTransform, Option<&VisibilityClusters>, Option<&CubemapIndex>, Option<&AmbientLightCube>, Option<&VertexLighting>)>::query();
+  for (entity, component, transform, clusters, cubemap, ambient_cube, vertex_lighting) in static_components_query.iter(world) {
     if active_static_renderables.0.contains(entity) {
       continue;
     }
 
     if !registered_static_renderables.0.contains(entity) {
-      renderer.register_static_renderable(*entity, transform, component);
+      renderer.register_static_renderable(*entity, transform, component, clusters, cubemap, ambient_cube, vertex_lighting);
 
       registered_static_renderables.0.insert(*entity);
     }
@@ -125,6 +368,15 @@ fn renderer<P: Platform, R: RendererInterface + 'static>(world: &mut SubWorld,
     renderer.update_transform(*entity, transform.0);
   }
 
+  let mut bone_palettes_query = <(Entity, &AnimatedModelComponent)>::query()
+    .filter(component::<StaticRenderableComponent>() & maybe_changed::<AnimatedModelComponent>());
+
+  for (entity, animated_model) in bone_palettes_query.iter(world) {
+    if !animated_model.bone_palette().is_empty() {
+      renderer.update_bone_palette(*entity, animated_model.bone_palette());
+    }
+  }
+
   registered_static_renderables.0.retain(|entity| {
     if !active_static_renderables.0.contains(entity) {
       renderer.unregister_static_renderable(*entity);
@@ -196,6 +448,34 @@ fn renderer<P: Platform, R: RendererInterface + 'static>(world: &mut SubWorld,
     }
   });
 
+  active_spot_lights.0.clear();
+  let mut spot_lights_query = <(Entity, &SpotLightComponent, &InterpolatedTransform)>::query();
+  for (entity, component, transform) in spot_lights_query.iter(world) {
+    if !registered_spot_lights.0.contains(entity) {
+      renderer.register_spot_light(*entity, transform, component);
+
+      registered_spot_lights.0.insert(*entity);
+    }
+
+    active_spot_lights.0.insert(*entity);
+  }
+
+  let mut spot_lights_update_transforms_query = <(Entity, &InterpolatedTransform)>::query()
+    .filter(component::<SpotLightComponent>() & maybe_changed::<InterpolatedTransform>());
+
+  for (entity, transform) in spot_lights_update_transforms_query.iter(world) {
+    renderer.update_transform(*entity, transform.0);
+  }
+
+  registered_spot_lights.0.retain(|entity| {
+    if !active_spot_lights.0.contains(entity) {
+      renderer.unregister_spot_light(*entity);
+      false
+    } else {
+      true
+    }
+  });
+
   let mut lightmap_query = <(&Lightmap,)>::query()
     .filter(maybe_changed::<Lightmap>());
 
@@ -204,5 +484,77 @@ fn renderer<P: Platform, R: RendererInterface + 'static>(world: &mut SubWorld,
     break;
   }
 
+  let mut light_styles_query = <(&LightStyles,)>::query()
+    .filter(maybe_changed::<LightStyles>());
+
+  for (light_styles,) in light_styles_query.iter(world) {
+    renderer.update_light_styles(light_styles);
+    break;
+  }
+
+  let mut skybox_query = <(&Skybox,)>::query()
+    .filter(maybe_changed::<Skybox>());
+
+  for (skybox,) in skybox_query.iter(world) {
+    renderer.update_skybox(&skybox.texture_path);
+    break;
+  }
+
+  let mut decals_query = <(&Decals,)>::query()
+    .filter(maybe_changed::<Decals>());
+
+  for (decals,) in decals_query.iter(world) {
+    renderer.update_decals(decals);
+    break;
+  }
+
+  let mut water_surfaces_query = <(&WaterSurfaces,)>::query()
+    .filter(maybe_changed::<WaterSurfaces>());
+
+  for (water_surfaces,) in water_surfaces_query.iter(world) {
+    renderer.update_water_surfaces(water_surfaces);
+    break;
+  }
+
+  let mut water_volumes_query = <(&WaterVolumes,)>::query()
+    .filter(maybe_changed::<WaterVolumes>());
+
+  for (water_volumes,) in water_volumes_query.iter(world) {
+    renderer.update_water_volumes(water_volumes);
+    break;
+  }
+
+  let mut detail_props_query = <(&DetailProps,)>::query()
+    .filter(maybe_changed::<DetailProps>());
+
+  for (detail_props,) in detail_props_query.iter(world) {
+    renderer.update_detail_props(detail_props);
+    break;
+  }
+
+  let mut sky_camera_query = <(&SkyCamera, &InterpolatedTransform)>::query()
+    .filter(maybe_changed::<SkyCamera>() | maybe_changed::<InterpolatedTransform>());
+
+  for (sky_camera, transform) in sky_camera_query.iter(world) {
+    renderer.update_sky_camera(transform.0.column(3).xyz(), sky_camera.scale);
+    break;
+  }
+
+  let mut cubemaps_query = <(&Cubemaps,)>::query()
+    .filter(maybe_changed::<Cubemaps>());
+
+  for (cubemaps,) in cubemaps_query.iter(world) {
+    renderer.update_cubemaps(&cubemaps.texture_paths);
+    break;
+  }
+
+  let mut pvs_query = <(&PotentiallyVisibleSet,)>::query()
+    .filter(maybe_changed::<PotentiallyVisibleSet>());
+
+  for (pvs,) in pvs_query.iter(world) {
+    renderer.update_potentially_visible_set(&pvs.0);
+    break;
+  }
+
   renderer.end_frame();
 }
@@ -2,18 +2,39 @@ use std::sync::Arc;
 
 use sourcerenderer_core::{Vec3, atomic_refcell::AtomicRefCell, graphics::Backend};
 
+use super::LightAttenuation;
+
 #[repr(C)]
 #[derive(Debug, Clone)]
 pub struct PointLight {
   pub position: Vec3,
-  pub intensity: f32
+  pub intensity: f32,
+  pub color: Vec3,
+  pub range: f32,
+  pub attenuation: LightAttenuation
 }
 
 #[repr(C)]
 #[derive(Debug, Clone)]
 pub struct DirectionalLight {
   pub direction: Vec3,
-  pub intensity: f32
+  pub intensity: f32,
+  pub color: Vec3
+}
+
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct SpotLight {
+  pub position: Vec3,
+  pub intensity: f32,
+  pub color: Vec3,
+  pub range: f32,
+  pub attenuation: LightAttenuation,
+  pub direction: Vec3,
+  /// Angle between the direction and the edge of the fully lit cone in radians.
+  pub inner_cone_angle: f32,
+  /// Angle between the direction and the edge of the cone in radians.
+  pub outer_cone_angle: f32
 }
 
 #[repr(C)]
@@ -27,14 +48,16 @@ pub struct CullingPointLight {
 pub struct RendererDirectionalLight<B: Backend> {
   pub direction: Vec3,
   pub intensity: f32,
+  pub color: Vec3,
   pub shadow_map: AtomicRefCell<Option<Arc<B::Texture>>>
 }
 
 impl<B: Backend> RendererDirectionalLight<B> {
-  pub fn new(direction: Vec3, intensity: f32) -> Self {
+  pub fn new(light: DirectionalLight) -> Self {
     Self {
-      direction,
-      intensity,
+      direction: light.direction,
+      intensity: light.intensity,
+      color: light.color,
       shadow_map: AtomicRefCell::new(None)
     }
   }
@@ -45,14 +68,49 @@ impl<B: Backend> RendererDirectionalLight<B> {
 pub struct RendererPointLight<B: Backend> {
   pub position: Vec3,
   pub intensity: f32,
+  pub color: Vec3,
+  pub range: f32,
+  pub attenuation: LightAttenuation,
   pub shadow_map: AtomicRefCell<Option<Arc<B::Texture>>>
 }
 
 impl<B: Backend> RendererPointLight<B> {
-  pub fn new(position: Vec3, intensity: f32) -> Self {
+  pub fn new(light: PointLight) -> Self {
+    Self {
+      position: light.position,
+      intensity: light.intensity,
+      color: light.color,
+      range: light.range,
+      attenuation: light.attenuation,
+      shadow_map: AtomicRefCell::new(None)
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+pub struct RendererSpotLight<B: Backend> {
+  pub position: Vec3,
+  pub direction: Vec3,
+  pub intensity: f32,
+  pub color: Vec3,
+  pub range: f32,
+  pub attenuation: LightAttenuation,
+  pub inner_cone_angle: f32,
+  pub outer_cone_angle: f32,
+  pub shadow_map: AtomicRefCell<Option<Arc<B::Texture>>>
+}
+
+impl<B: Backend> RendererSpotLight<B> {
+  pub fn new(light: SpotLight) -> Self {
     Self {
-      position,
-      intensity,
+      position: light.position,
+      direction: light.direction,
+      intensity: light.intensity,
+      color: light.color,
+      range: light.range,
+      attenuation: light.attenuation,
+      inner_cone_angle: light.inner_cone_angle,
+      outer_cone_angle: light.outer_cone_angle,
       shadow_map: AtomicRefCell::new(None)
     }
   }
@@ -18,6 +18,7 @@ mod asset_buffer;
 mod late_latching;
 mod vertex;
 mod shader_manager;
+mod cluster_visibility;
 
 #[cfg(feature = "threading")]
 pub use self::renderer::Renderer;
@@ -25,16 +26,19 @@ pub use self::renderer::Renderer;
 pub use self::ecs::StaticRenderableComponent;
 pub use self::ecs::PointLightComponent;
 pub use self::ecs::DirectionalLightComponent;
+pub use self::ecs::LightAttenuation;
+pub use self::ecs::SpotLightComponent;
 pub use self::drawable::DrawablePart;
 pub use self::late_latch_camera::LateLatchCamera;
 use self::drawable::View;
-pub use self::ecs::{RendererInterface, Lightmap};
+pub use self::ecs::{RendererInterface, Lightmap, LightStyles, LIGHT_STYLE_COUNT, Skybox, Decals, DecalFade, WaterSurface, WaterSurfaces, WaterVolumes, WaterVolume, DetailProps, DetailSprite, DetailSpriteOrientation, DetailModel, DetailModelInstance, SkyCamera, Cubemaps, CubemapIndex, AmbientLightCube, VertexLighting, PotentiallyVisibleSet, VisibilityClusters};
+pub use self::cluster_visibility::{ClusterVisibility, ClusterVisibilityNode};
 pub use self::command::RendererCommand;
 pub use self::light::PointLight;
 use self::drawable::RendererStaticDrawable;
 use self::renderer_scene::RendererScene;
 pub use self::late_latching::LateLatching;
-pub use self::vertex::Vertex;
+pub use self::vertex::{Vertex, VertexAttribute, SkinningVertex};
 
 #[cfg(feature = "threading")]
 use self::renderer_internal::RendererInternal;
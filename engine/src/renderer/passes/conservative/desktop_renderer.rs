@@ -4,7 +4,7 @@ use nalgebra::Vector3;
 use smallvec::SmallVec;
 use sourcerenderer_core::{Matrix4, Platform, Vec2UI, graphics::{Backend, Barrier, CommandBuffer, Device, Queue, Swapchain, SwapchainError, TextureRenderTargetView, BarrierSync, BarrierAccess, TextureLayout, BarrierTextureRange, BindingFrequency, WHOLE_BUFFER, BufferUsage, MemoryUsage, BufferInfo}, Vec2, Vec3};
 
-use crate::{input::Input, renderer::{LateLatching, drawable::View, render_path::{RenderPath, SceneInfo, ZeroTextures, FrameInfo}, renderer_resources::{RendererResources, HistoryResourceEntry}, renderer_scene::RendererScene, passes::blue_noise::BlueNoise, shader_manager::ShaderManager, renderer_assets::RendererAssets}};
+use crate::{input::Input, renderer::{LateLatching, drawable::View, render_path::{RenderPath, SceneInfo, ZeroTextures, FrameInfo}, renderer_resources::{RendererResources, HistoryResourceEntry}, renderer_scene::RendererScene, passes::{blue_noise::BlueNoise, skybox::SkyboxPass, decal::DecalPass, detail_props::DetailPropPass, water::WaterPass, translucent::TranslucentPass, shadow_map::ShadowMapPass}, shader_manager::ShaderManager, renderer_assets::RendererAssets}};
 
 use super::{clustering::ClusteringPass, geometry::GeometryPass, light_binning::LightBinningPass, prepass::Prepass, sharpen::SharpenPass, ssao::SsaoPass, taa::TAAPass, occlusion::OcclusionPass, acceleration_structure_update::AccelerationStructureUpdatePass, rt_shadows::RTShadowPass};
 
@@ -15,12 +15,18 @@ pub struct ConservativeRenderer<P: Platform> {
   clustering_pass: ClusteringPass,
   light_binning_pass: LightBinningPass,
   prepass: Prepass,
+  skybox: SkyboxPass,
   geometry: GeometryPass<P>,
+  decals: DecalPass,
+  detail_props: DetailPropPass,
+  water: WaterPass,
+  translucent: TranslucentPass,
   taa: TAAPass,
   sharpen: SharpenPass,
   ssao: SsaoPass<P>,
   occlusion: OcclusionPass<P>,
   rt_passes: Option<RTPasses<P>>,
+  shadow_map: ShadowMapPass<P>,
   blue_noise: BlueNoise<P::GraphicsBackend>
 }
 
@@ -38,6 +44,8 @@ pub struct FrameBindings<B: Backend> {
   directional_lights: Arc<B::Buffer>,
   point_lights: Arc<B::Buffer>,
   setup_buffer: Arc<B::Buffer>,
+  light_styles: Arc<B::Buffer>,
+  spot_lights: Arc<B::Buffer>,
 }
 
 impl<P: Platform> ConservativeRenderer<P> {
@@ -52,7 +60,13 @@ impl<P: Platform> ConservativeRenderer<P> {
     let clustering = ClusteringPass::new::<P>(&mut barriers, shader_manager);
     let light_binning = LightBinningPass::new::<P>(&mut barriers, shader_manager);
     let prepass = Prepass::new::<P>(&mut barriers, shader_manager, resolution);
+    let skybox = SkyboxPass::new::<P>(&mut barriers, shader_manager, resolution);
     let geometry = GeometryPass::<P>::new(device, resolution, &mut barriers, shader_manager);
+    let decals = DecalPass::new::<P>(shader_manager, barriers.texture_info(GeometryPass::<P>::GEOMETRY_PASS_TEXTURE_NAME).format);
+    let detail_props = DetailPropPass::new::<P>(shader_manager, barriers.texture_info(GeometryPass::<P>::GEOMETRY_PASS_TEXTURE_NAME).format);
+    let geometry_format = barriers.texture_info(GeometryPass::<P>::GEOMETRY_PASS_TEXTURE_NAME).format;
+    let water = WaterPass::new::<P>(&mut barriers, shader_manager, resolution, geometry_format);
+    let translucent = TranslucentPass::new::<P>(shader_manager, geometry_format);
     let taa = TAAPass::new::<P>(resolution, &mut barriers, shader_manager, false);
     let sharpen = SharpenPass::new::<P>(resolution, &mut barriers, shader_manager);
     let ssao = SsaoPass::<P>::new(device, resolution, &mut barriers, shader_manager, false);
@@ -61,6 +75,7 @@ impl<P: Platform> ConservativeRenderer<P> {
       acceleration_structure_update: AccelerationStructureUpdatePass::<P>::new(device, &mut init_cmd_buffer),
       shadows: RTShadowPass::new::<P>(resolution, &mut barriers, shader_manager)
     });
+    let shadow_map = ShadowMapPass::<P>::new(device, &mut barriers, shader_manager);
     init_cmd_buffer.flush_barriers();
     device.flush_transfers();
 
@@ -75,12 +90,18 @@ impl<P: Platform> ConservativeRenderer<P> {
       clustering_pass: clustering,
       light_binning_pass: light_binning,
       prepass,
+      skybox,
       geometry,
+      decals,
+      detail_props,
+      water,
+      translucent,
       taa,
       sharpen,
       ssao,
       occlusion,
       rt_passes,
+      shadow_map,
       blue_noise,
     }
   }
@@ -110,7 +131,7 @@ impl<P: Platform> ConservativeRenderer<P> {
       cluster_z_bias: f32,
       cluster_z_scale: f32,
       cluster_count: Vector3<u32>,
-      _padding: u32,
+      spot_light_count: u32,
       swapchain_transform: Matrix4,
       halton_point: Vec2,
       rt_size: Vec2UI,
@@ -121,7 +142,7 @@ impl<P: Platform> ConservativeRenderer<P> {
       cluster_z_bias,
       cluster_z_scale,
       cluster_count,
-      _padding: 0,
+      spot_light_count: scene.spot_lights().len() as u32,
       swapchain_transform: swapchain.transform(),
       halton_point: super::taa::scaled_halton_point(rendering_resolution.x, rendering_resolution.y, (frame % 8) as u32 + 1),
       rt_size: *rendering_resolution
@@ -130,24 +151,64 @@ impl<P: Platform> ConservativeRenderer<P> {
     #[derive(Debug, Clone)]
     struct PointLight {
       position: Vec3,
-      intensity: f32
+      intensity: f32,
+      color: Vec3,
+      range: f32,
+      attenuation: Vec3,
+      _padding: f32
     }
     let point_lights: SmallVec<[PointLight; 16]> = scene.point_lights().iter().map(|l| PointLight {
       position: l.position,
-      intensity: l.intensity
+      intensity: l.intensity,
+      color: l.color,
+      range: l.range,
+      attenuation: Vec3::new(l.attenuation.constant, l.attenuation.linear, l.attenuation.quadratic),
+      _padding: 0f32
     }).collect();
     let point_lights_buffer = cmd_buf.upload_dynamic_data(&point_lights, BufferUsage::CONSTANT);
     #[repr(C)]
     #[derive(Debug, Clone)]
     struct DirectionalLight {
       direction: Vec3,
-      intensity: f32
+      intensity: f32,
+      color: Vec3,
+      _padding: f32
     }
     let directional_lights: SmallVec<[DirectionalLight; 16]> = scene.directional_lights().iter().map(|l| DirectionalLight {
       direction: l.direction,
-      intensity: l.intensity
+      intensity: l.intensity,
+      color: l.color,
+      _padding: 0f32
     }).collect();
     let directional_lights_buffer = cmd_buf.upload_dynamic_data(&directional_lights, BufferUsage::CONSTANT);
+    let light_styles_buffer = cmd_buf.upload_dynamic_data(&scene.light_styles().intensities, BufferUsage::CONSTANT);
+    #[repr(C)]
+    #[derive(Debug, Clone)]
+    struct SpotLight {
+      position: Vec3,
+      intensity: f32,
+      direction: Vec3,
+      angle_scale: f32,
+      color: Vec3,
+      range: f32,
+      attenuation: Vec3,
+      angle_offset: f32
+    }
+    let spot_lights: SmallVec<[SpotLight; 16]> = scene.spot_lights().iter().map(|l| {
+      let cos_outer = l.outer_cone_angle.cos();
+      let angle_scale = 1f32 / (l.inner_cone_angle.cos() - cos_outer).max(0.001f32);
+      SpotLight {
+        position: l.position,
+        intensity: l.intensity,
+        direction: l.direction,
+        angle_scale,
+        color: l.color,
+        range: l.range,
+        attenuation: Vec3::new(l.attenuation.constant, l.attenuation.linear, l.attenuation.quadratic),
+        angle_offset: -cos_outer * angle_scale
+      }
+    }).collect();
+    let spot_lights_buffer = cmd_buf.upload_dynamic_data(&spot_lights, BufferUsage::CONSTANT);
 
     FrameBindings {
       gpu_scene_buffer: gpu_scene_buffer.clone(),
@@ -158,6 +219,8 @@ impl<P: Platform> ConservativeRenderer<P> {
       directional_lights: directional_lights_buffer,
       point_lights: point_lights_buffer,
       setup_buffer,
+      light_styles: light_styles_buffer,
+      spot_lights: spot_lights_buffer,
     }
   }
 }
@@ -225,8 +288,15 @@ impl<P: Platform> RenderPath<P> for ConservativeRenderer<P> {
     self.ssao.execute(&mut cmd_buf, &self.barriers, Prepass::DEPTH_TEXTURE_NAME, Some(Prepass::MOTION_TEXTURE_NAME), &late_latching_buffer, self.blue_noise.frame(frame_info.frame), self.blue_noise.sampler(), shader_manager, false);
     if let Some(rt_passes) = self.rt_passes.as_mut() {
       rt_passes.shadows.execute(&mut cmd_buf, &self.barriers, shader_manager, Prepass::DEPTH_TEXTURE_NAME, rt_passes.acceleration_structure_update.acceleration_structure(), &self.blue_noise.frame(frame_info.frame), &self.blue_noise.sampler());
+    } else {
+      self.shadow_map.execute(&mut cmd_buf, scene, &self.barriers, shader_manager, assets);
     }
-    self.geometry.execute(&mut cmd_buf, &self.barriers, shader_manager, &self.device, Prepass::DEPTH_TEXTURE_NAME, scene, &frame_bindings, zero_textures, scene.lightmap.unwrap(), assets);
+    self.skybox.execute(&mut cmd_buf, scene, &self.barriers, shader_manager, assets);
+    self.geometry.execute(&mut cmd_buf, &self.barriers, shader_manager, &self.device, Prepass::DEPTH_TEXTURE_NAME, scene, &frame_bindings, zero_textures, scene.lightmap.unwrap(), &self.shadow_map, assets);
+    self.decals.execute(&mut cmd_buf, scene, &self.barriers, shader_manager, assets, GeometryPass::<P>::GEOMETRY_PASS_TEXTURE_NAME, Prepass::DEPTH_TEXTURE_NAME);
+    self.detail_props.execute(&mut cmd_buf, scene, &self.barriers, shader_manager, assets, GeometryPass::<P>::GEOMETRY_PASS_TEXTURE_NAME, Prepass::DEPTH_TEXTURE_NAME);
+    self.water.execute(&mut cmd_buf, scene, &self.barriers, shader_manager, assets, GeometryPass::<P>::GEOMETRY_PASS_TEXTURE_NAME, Prepass::DEPTH_TEXTURE_NAME, false);
+    self.translucent.execute(&mut cmd_buf, scene, &self.barriers, shader_manager, zero_textures, &self.shadow_map, assets, GeometryPass::<P>::GEOMETRY_PASS_TEXTURE_NAME, Prepass::DEPTH_TEXTURE_NAME);
     self.taa.execute(&mut cmd_buf, &self.barriers, shader_manager, GeometryPass::<P>::GEOMETRY_PASS_TEXTURE_NAME, Prepass::DEPTH_TEXTURE_NAME, Some(Prepass::MOTION_TEXTURE_NAME), false);
     self.sharpen.execute(&mut cmd_buf, &self.barriers, shader_manager);
 
@@ -310,4 +380,6 @@ pub fn setup_frame<B: Backend>(
   cmd_buf.bind_uniform_buffer(BindingFrequency::Frame, 5, &frame_bindings.setup_buffer, 0, WHOLE_BUFFER);
   cmd_buf.bind_uniform_buffer(BindingFrequency::Frame, 6, &frame_bindings.point_lights, 0, WHOLE_BUFFER);
   cmd_buf.bind_uniform_buffer(BindingFrequency::Frame, 7, &frame_bindings.directional_lights, 0, WHOLE_BUFFER);
+  cmd_buf.bind_uniform_buffer(BindingFrequency::Frame, 8, &frame_bindings.light_styles, 0, WHOLE_BUFFER);
+  cmd_buf.bind_uniform_buffer(BindingFrequency::Frame, 9, &frame_bindings.spot_lights, 0, WHOLE_BUFFER);
 }
@@ -1,12 +1,14 @@
 use nalgebra::Vector2;
 use smallvec::SmallVec;
-use sourcerenderer_core::{Matrix4, Vec4, graphics::{AddressMode, AttachmentBlendInfo, AttachmentInfo, Backend as GraphicsBackend, BindingFrequency, BlendInfo, BufferUsage, CommandBuffer, CompareFunc, CullMode, DepthStencilAttachmentRef, DepthStencilInfo, Device, FillMode, Filter, Format, FrontFace, InputAssemblerElement, InputRate, LoadOp, LogicOp, OutputAttachmentRef, PipelineBinding, PrimitiveType, Queue, RasterizerInfo, RenderPassAttachment, RenderPassAttachmentView, RenderPassBeginInfo, RenderPassInfo, RenderpassRecordingMode, SampleCount, SamplerInfo, Scissor, ShaderInputElement, ShaderType, StencilInfo, StoreOp, SubpassInfo, TextureInfo, TextureViewInfo, TextureUsage, VertexLayoutInfo, Viewport, TextureLayout, BarrierSync, BarrierAccess, IndexFormat, WHOLE_BUFFER, TextureDimension}};
+use sourcerenderer_core::{Matrix4, Vec4, graphics::{AddressMode, AttachmentBlendInfo, AttachmentInfo, Backend as GraphicsBackend, BindingFrequency, BlendInfo, BufferUsage, CommandBuffer, CompareFunc, CullMode, DepthStencilAttachmentRef, DepthStencilInfo, Device, FillMode, Filter, Format, FrontFace, LoadOp, LogicOp, OutputAttachmentRef, PipelineBinding, PrimitiveType, Queue, RasterizerInfo, RenderPassAttachment, RenderPassAttachmentView, RenderPassBeginInfo, RenderPassInfo, RenderpassRecordingMode, SampleCount, SamplerInfo, Scissor, ShaderType, StencilInfo, StoreOp, SubpassInfo, TextureInfo, TextureViewInfo, TextureUsage, VertexLayoutInfo, Viewport, TextureLayout, BarrierSync, BarrierAccess, IndexFormat, WHOLE_BUFFER, TextureDimension}};
 use std::{sync::Arc, cell::Ref};
-use crate::renderer::{renderer_resources::{RendererResources, HistoryResourceEntry}, passes::{light_binning, ssao::SsaoPass, rt_shadows::RTShadowPass, conservative::desktop_renderer::setup_frame}, shader_manager::{ShaderManager, GraphicsPipelineInfo, GraphicsPipelineHandle}};
+use crate::renderer::{renderer_resources::{RendererResources, HistoryResourceEntry}, passes::{light_binning, ssao::SsaoPass, skybox::SkyboxPass, rt_shadows::RTShadowPass, shadow_map::ShadowMapPass, conservative::desktop_renderer::setup_frame}, shader_manager::{ShaderManager, GraphicsPipelineInfo, GraphicsPipelineHandle}};
 use sourcerenderer_core::{Platform, Vec2, Vec2I, Vec2UI};
 use crate::renderer::renderer_assets::*;
 use rayon::prelude::*;
 use crate::renderer::render_path::{SceneInfo, ZeroTextures};
+use crate::renderer::{renderer_scene::RendererScene, drawable::RendererStaticDrawable};
+use crate::renderer::{Vertex, VertexAttribute};
 
 use super::desktop_renderer::FrameBindings;
 
@@ -27,7 +29,9 @@ struct FrameData {
 
 pub struct GeometryPass<P: Platform> {
   sampler: Arc<<P::GraphicsBackend as GraphicsBackend>::Sampler>,
-  pipeline: GraphicsPipelineHandle
+  pipeline: GraphicsPipelineHandle,
+  skinned_pipeline: GraphicsPipelineHandle,
+  skybox_pipeline: GraphicsPipelineHandle
 }
 
 impl<P: Platform> GeometryPass<P> {
@@ -69,55 +73,18 @@ impl<P: Platform> GeometryPass<P> {
       fs: Some("shaders/textured.frag.spv"),
       primitive_type: PrimitiveType::Triangles,
       vertex_layout: VertexLayoutInfo {
-        input_assembler: &[
-          InputAssemblerElement {
-            binding: 0,
-            stride: 64,
-            input_rate: InputRate::PerVertex
-          }
-        ],
-        shader_inputs: &[
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 0,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 0,
-            format: Format::RGB32Float
-          },
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 1,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 16,
-            format: Format::RGB32Float
-          },
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 2,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 32,
-            format: Format::RG32Float
-          },
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 3,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 40,
-            format: Format::RG32Float
-          },
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 4,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 48,
-            format: Format::R32Float
-          }
-        ]
+        input_assembler: &Vertex::INPUT_ASSEMBLER,
+        shader_inputs: &Vertex::shader_inputs(&[
+          VertexAttribute::Position,
+          VertexAttribute::Normal,
+          VertexAttribute::Uv,
+          VertexAttribute::LightmapUv,
+          VertexAttribute::Alpha,
+          VertexAttribute::LightmapStyles,
+          VertexAttribute::Multiblend,
+          VertexAttribute::LightmapBumpPageWidth,
+          VertexAttribute::Tangent
+        ])
       },
       rasterizer: RasterizerInfo {
         fill_mode: FillMode::Fill,
@@ -145,7 +112,7 @@ impl<P: Platform> GeometryPass<P> {
         ]
       }
     };
-    let pipeline = shader_manager.request_graphics_pipeline(&pipeline_info, &RenderPassInfo {
+    let render_pass_info = RenderPassInfo {
       attachments: &[
         AttachmentInfo {
           format: texture_info.format,
@@ -171,11 +138,35 @@ impl<P: Platform> GeometryPass<P> {
           }),
         }
       ]
-    }, 0);
+    };
+    let pipeline = shader_manager.request_graphics_pipeline(&pipeline_info, &render_pass_info, 0);
+    let skinned_pipeline_info = GraphicsPipelineInfo {
+      vs: "shaders/textured_skinned.vert.spv",
+      ..pipeline_info
+    };
+    let skinned_pipeline = shader_manager.request_graphics_pipeline(&skinned_pipeline_info, &render_pass_info, 0);
+    let skybox_pipeline_info = GraphicsPipelineInfo {
+      vs: "shaders/skybox_composite.vert.spv",
+      fs: Some("shaders/skybox_composite.frag.spv"),
+      vertex_layout: VertexLayoutInfo {
+        input_assembler: &[],
+        shader_inputs: &[]
+      },
+      rasterizer: RasterizerInfo {
+        fill_mode: FillMode::Fill,
+        cull_mode: CullMode::None,
+        front_face: FrontFace::Clockwise,
+        sample_count: SampleCount::Samples1
+      },
+      ..skinned_pipeline_info
+    };
+    let skybox_pipeline = shader_manager.request_graphics_pipeline(&skybox_pipeline_info, &render_pass_info, 0);
 
     Self {
       sampler,
-      pipeline
+      pipeline,
+      skinned_pipeline,
+      skybox_pipeline
     }
   }
 
@@ -191,6 +182,7 @@ impl<P: Platform> GeometryPass<P> {
     bindings: &FrameBindings<P::GraphicsBackend>,
     zero_textures: &ZeroTextures<P::GraphicsBackend>,
     lightmap: &RendererTexture<P::GraphicsBackend>,
+    shadow_map: &ShadowMapPass<P>,
     assets: &RendererAssets<P>
   ) {
     cmd_buffer.begin_label("Geometry pass");
@@ -262,6 +254,31 @@ impl<P: Platform> GeometryPass<P> {
       zero_textures.zero_texture_view
     };
 
+    let shadow_map_ref = barriers.access_sampling_view(
+      cmd_buffer,
+      ShadowMapPass::<P>::SHADOW_MAP_NAME,
+      BarrierSync::FRAGMENT_SHADER,
+      BarrierAccess::SAMPLING_READ,
+      TextureLayout::Sampled,
+      false,
+      &ShadowMapPass::<P>::sampling_view_info(),
+      HistoryResourceEntry::Current
+    );
+    let shadow_map_view = &*shadow_map_ref;
+    let shadow_cascades = *shadow_map.cascades();
+
+    let skybox_ref = barriers.access_sampling_view(
+      cmd_buffer,
+      SkyboxPass::SKYBOX_TEXTURE_NAME,
+      BarrierSync::FRAGMENT_SHADER,
+      BarrierAccess::SAMPLING_READ,
+      TextureLayout::Sampled,
+      false,
+      &TextureViewInfo::default(),
+      HistoryResourceEntry::Current
+    );
+    let skybox = &*skybox_ref;
+
     /*let clusters = barriers.access_buffer(
       cmd_buffer,
       ClusteringPass::<P::GraphicsBackend>::CLUSTERS_BUFFER_NAME,
@@ -305,7 +322,8 @@ impl<P: Platform> GeometryPass<P> {
     let view = &scene.views[scene.active_view_index];
     let chunks = view.drawable_parts.par_chunks(CHUNK_SIZE);
     let pipeline = shader_manager.get_graphics_pipeline(self.pipeline);
-    let inner_cmd_buffers: Vec::<<P::GraphicsBackend as GraphicsBackend>::CommandBufferSubmission> = chunks.map(|chunk| {
+    let skinned_pipeline = shader_manager.get_graphics_pipeline(self.skinned_pipeline);
+    let mut inner_cmd_buffers: Vec::<<P::GraphicsBackend as GraphicsBackend>::CommandBufferSubmission> = chunks.map(|chunk| {
       let mut command_buffer = device.graphics_queue().create_inner_command_buffer(inheritance);
 
       command_buffer.set_pipeline(PipelineBinding::Graphics(&pipeline));
@@ -326,11 +344,15 @@ impl<P: Platform> GeometryPass<P> {
       command_buffer.bind_storage_buffer(BindingFrequency::Frequent, 3, &light_bitmask_buffer, 0, WHOLE_BUFFER);
       command_buffer.bind_sampling_view_and_sampler(BindingFrequency::Frequent, 4, &ssao, &self.sampler);
       // command_buffer.bind_storage_buffer(BindingFrequency::Frequent, 5, &clusters, 0, WHOLE_BUFFER);
+      command_buffer.bind_sampling_view_and_sampler(BindingFrequency::Frequent, 6, shadow_map_view, shadow_map.sampler());
+      let shadow_cascades_buffer = command_buffer.upload_dynamic_data(&[shadow_cascades], BufferUsage::CONSTANT);
+      command_buffer.bind_uniform_buffer(BindingFrequency::Frequent, 7, &shadow_cascades_buffer, 0, WHOLE_BUFFER);
 
       command_buffer.track_texture_view(zero_textures.zero_texture_view);
       command_buffer.track_texture_view(zero_textures.zero_texture_view_black);
 
       let mut last_material = Option::<&RendererMaterial>::None;
+      let mut is_skinned_pipeline = false;
 
       for part in chunk.iter() {
         let drawable = &static_drawables[part.drawable_index];
@@ -338,10 +360,6 @@ impl<P: Platform> GeometryPass<P> {
           command_buffer.begin_label(&format!("Drawable {}", part.drawable_index));
         }
 
-        setup_frame::<P::GraphicsBackend>(&mut command_buffer, bindings);
-
-        command_buffer.upload_dynamic_data_inline(&[drawable.transform], ShaderType::VertexShader);
-
         let model = assets.get_model(drawable.model);
         if model.is_none() {
           log::info!("Skipping draw because of missing model");
@@ -354,6 +372,22 @@ impl<P: Platform> GeometryPass<P> {
           continue;
         }
         let mesh = mesh.unwrap();
+
+        // The pipeline has to be set before the push constants get uploaded.
+        let skinning = mesh.skinning.as_ref().zip(drawable.bone_palette.as_ref());
+        if skinning.is_some() != is_skinned_pipeline {
+          is_skinned_pipeline = skinning.is_some();
+          command_buffer.set_pipeline(PipelineBinding::Graphics(if is_skinned_pipeline { &skinned_pipeline } else { &pipeline }));
+        }
+        if let Some((skinning, bone_palette)) = skinning {
+          command_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 4, skinning.buffer(), skinning.offset() as usize, skinning.size() as usize);
+          let bone_palette_buffer = command_buffer.upload_dynamic_data(&bone_palette[..], BufferUsage::STORAGE);
+          command_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 5, &bone_palette_buffer, 0, WHOLE_BUFFER);
+        }
+
+        setup_frame::<P::GraphicsBackend>(&mut command_buffer, bindings);
+
+        command_buffer.upload_dynamic_data_inline(&[drawable.transform], ShaderType::VertexShader);
         let materials: SmallVec<[&RendererMaterial; 8]> = model.material_handles().iter().map(|handle| assets.get_material(*handle)).collect();
 
         command_buffer.set_vertex_buffer(mesh.vertices.buffer(), mesh.vertices.offset() as usize);
@@ -365,67 +399,12 @@ impl<P: Platform> GeometryPass<P> {
         let material = &materials[part.part_index];
 
         if last_material.as_ref() != Some(material) {
-          #[repr(C)]
-          #[derive(Clone, Copy)]
-          struct MaterialInfo {
-            albedo: Vec4,
-            roughness_factor: f32,
-            metalness_factor: f32,
-            albedo_texture_index: u32
-          }
-          let mut material_info = MaterialInfo {
-            albedo: Vec4::new(1f32, 1f32, 1f32, 1f32),
-            roughness_factor: 0f32,
-            metalness_factor: 0f32,
-            albedo_texture_index: 0u32
-          };
-
-          command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 0, zero_textures.zero_texture_view, &self.sampler);
-          command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 1, zero_textures.zero_texture_view, &self.sampler);
-          command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 2, zero_textures.zero_texture_view, &self.sampler);
-
-          let albedo_value = material.get("albedo").unwrap();
-          match albedo_value {
-            RendererMaterialValue::Texture(handle) => {
-              let albedo_view = &assets.get_texture(*handle).view;
-              command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 0, albedo_view, &self.sampler);
-              command_buffer.track_texture_view(albedo_view);
-              material_info.albedo_texture_index = 0;
-            },
-            RendererMaterialValue::Vec4(val) => {
-              material_info.albedo = *val
-            },
-            RendererMaterialValue::Float(_) => unimplemented!()
-          }
-          let roughness_value = material.get("roughness");
-          match roughness_value {
-            Some(RendererMaterialValue::Texture(handle)) => {
-              let roughness_view = &assets.get_texture(*handle).view;
-              command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 1, roughness_view, &self.sampler);
-            }
-            Some(RendererMaterialValue::Vec4(_)) => unimplemented!(),
-            Some(RendererMaterialValue::Float(val)) => {
-              material_info.roughness_factor = *val;
-            },
-            None => {}
-          }
-          let metalness_value = material.get("metalness");
-          match metalness_value {
-            Some(RendererMaterialValue::Texture(handle)) => {
-              let metalness_view = &assets.get_texture(*handle).view;
-              command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 2, metalness_view, &self.sampler);
-            }
-            Some(RendererMaterialValue::Vec4(_)) => unimplemented!(),
-            Some(RendererMaterialValue::Float(val)) => {
-              material_info.metalness_factor = *val;
-            },
-            None => {}
-          }
-          let material_info_buffer = command_buffer.upload_dynamic_data(&[material_info], BufferUsage::CONSTANT);
-          command_buffer.bind_uniform_buffer(BindingFrequency::VeryFrequent, 3, &material_info_buffer, 0, WHOLE_BUFFER);
+          bind_material::<P>(&mut command_buffer, material, &self.sampler, zero_textures, assets);
           last_material = Some(material.clone());
         }
 
+        bind_drawable::<P>(&mut command_buffer, scene.scene, drawable, mesh, &self.sampler, assets);
+
         command_buffer.finish_binding();
 
         if mesh.indices.is_some() {
@@ -440,8 +419,232 @@ impl<P: Platform> GeometryPass<P> {
       command_buffer.finish()
     }).collect();
 
+    // The skybox fills every pixel that the depth prepass didn't cover.
+    let mut skybox_cmd_buffer = device.graphics_queue().create_inner_command_buffer(inheritance);
+    let skybox_pipeline = shader_manager.get_graphics_pipeline(self.skybox_pipeline);
+    skybox_cmd_buffer.set_pipeline(PipelineBinding::Graphics(&skybox_pipeline));
+    skybox_cmd_buffer.set_viewports(&[Viewport {
+      position: Vec2::new(0.0f32, 0.0f32),
+      extent: Vec2::new(width as f32, height as f32),
+      min_depth: 0.0f32,
+      max_depth: 1.0f32
+    }]);
+    skybox_cmd_buffer.set_scissors(&[Scissor {
+      position: Vec2I::new(0, 0),
+      extent: Vec2UI::new(9999, 9999),
+    }]);
+    skybox_cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 0, skybox, &self.sampler);
+    skybox_cmd_buffer.finish_binding();
+    skybox_cmd_buffer.draw(3, 0);
+    inner_cmd_buffers.push(skybox_cmd_buffer.finish());
+
     cmd_buffer.execute_inner(inner_cmd_buffers);
     cmd_buffer.end_render_pass();
     cmd_buffer.end_label();
   }
 }
+
+/// Binds the textures and the material buffer of textured.frag.
+pub(crate) fn bind_material<P: Platform>(
+  command_buffer: &mut <P::GraphicsBackend as GraphicsBackend>::CommandBuffer,
+  material: &RendererMaterial,
+  sampler: &Arc<<P::GraphicsBackend as GraphicsBackend>::Sampler>,
+  zero_textures: &ZeroTextures<P::GraphicsBackend>,
+  assets: &RendererAssets<P>
+) {
+  #[repr(C)]
+  #[derive(Clone, Copy)]
+  struct MaterialInfo {
+    albedo: Vec4,
+    environment_map_tint: Vec4,
+    emissive_factor: Vec4,
+    emissive_mask_channels: Vec4,
+    roughness_factor: f32,
+    metalness_factor: f32,
+    occlusion_strength: f32,
+    alpha_cutoff: f32,
+    albedo_texture_index: u32,
+    has_albedo2: u32,
+    has_blend_modulate: u32,
+    has_normal_map: u32,
+    has_orm_map: u32,
+    has_emissive_map: u32,
+    has_normal2: u32,
+    has_multiblend: u32,
+    alpha_mode: u32
+  }
+  let mut material_info = MaterialInfo {
+    albedo: Vec4::new(1f32, 1f32, 1f32, 1f32),
+    environment_map_tint: Vec4::new(0f32, 0f32, 0f32, 0f32),
+    emissive_factor: Vec4::new(0f32, 0f32, 0f32, 0f32),
+    emissive_mask_channels: Vec4::new(0f32, 0f32, 0f32, 0f32),
+    roughness_factor: 0f32,
+    metalness_factor: 0f32,
+    occlusion_strength: 1f32,
+    alpha_cutoff: material.alpha_cutoff(),
+    albedo_texture_index: 0u32,
+    has_albedo2: 0u32,
+    has_blend_modulate: 0u32,
+    has_normal_map: 0u32,
+    has_orm_map: 0u32,
+    has_emissive_map: 0u32,
+    has_normal2: 0u32,
+    has_multiblend: 0u32,
+    alpha_mode: material.alpha_mode() as u32
+  };
+
+  command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 0, zero_textures.zero_texture_view, sampler);
+  command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 1, zero_textures.zero_texture_view, sampler);
+  command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 2, zero_textures.zero_texture_view, sampler);
+  command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 9, zero_textures.zero_texture_view, sampler);
+  command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 10, zero_textures.zero_texture_view, sampler);
+  command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 11, zero_textures.zero_texture_view, sampler);
+  command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 12, zero_textures.zero_texture_view, sampler);
+  command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 13, zero_textures.zero_texture_view, sampler);
+  command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 14, zero_textures.zero_texture_view, sampler);
+  command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 15, zero_textures.zero_texture_view, sampler);
+  command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 16, zero_textures.zero_texture_view, sampler);
+
+  let albedo_value = material.get("albedo").unwrap();
+  match albedo_value {
+    RendererMaterialValue::Texture(handle) => {
+      let frame = match material.get("albedo_frame") {
+        Some(RendererMaterialValue::Float(frame)) => *frame as u32,
+        _ => 0
+      };
+      let albedo_view = assets.get_texture(*handle).frame_view(frame);
+      command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 0, albedo_view, sampler);
+      command_buffer.track_texture_view(albedo_view);
+      material_info.albedo_texture_index = 0;
+    },
+    RendererMaterialValue::Vec4(val) => {
+      material_info.albedo = *val
+    },
+    RendererMaterialValue::Float(_) => unimplemented!()
+  }
+  let roughness_value = material.get("roughness");
+  match roughness_value {
+    Some(RendererMaterialValue::Texture(handle)) => {
+      let roughness_view = &assets.get_texture(*handle).view;
+      command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 1, roughness_view, sampler);
+    }
+    Some(RendererMaterialValue::Vec4(_)) => unimplemented!(),
+    Some(RendererMaterialValue::Float(val)) => {
+      material_info.roughness_factor = *val;
+    },
+    None => {}
+  }
+  let metalness_value = material.get("metalness");
+  match metalness_value {
+    Some(RendererMaterialValue::Texture(handle)) => {
+      let metalness_view = &assets.get_texture(*handle).view;
+      command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 2, metalness_view, sampler);
+    }
+    Some(RendererMaterialValue::Vec4(_)) => unimplemented!(),
+    Some(RendererMaterialValue::Float(val)) => {
+      material_info.metalness_factor = *val;
+    },
+    None => {}
+  }
+  if let Some(RendererMaterialValue::Vec4(tint)) = material.get("environment_map_tint") {
+    material_info.environment_map_tint = *tint;
+  }
+  if let Some(RendererMaterialValue::Texture(handle)) = material.get("albedo2") {
+    let albedo2_view = &assets.get_texture(*handle).view;
+    command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 9, albedo2_view, sampler);
+    command_buffer.track_texture_view(albedo2_view);
+    material_info.has_albedo2 = 1;
+  }
+  if let Some(RendererMaterialValue::Texture(handle)) = material.get("blend_modulate") {
+    let blend_modulate_view = &assets.get_texture(*handle).view;
+    command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 10, blend_modulate_view, sampler);
+    command_buffer.track_texture_view(blend_modulate_view);
+    material_info.has_blend_modulate = 1;
+  }
+  if let Some(RendererMaterialValue::Texture(handle)) = material.get("normal") {
+    let normal_view = &assets.get_texture(*handle).view;
+    command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 11, normal_view, sampler);
+    command_buffer.track_texture_view(normal_view);
+    material_info.has_normal_map = 1;
+  }
+  if let Some(RendererMaterialValue::Texture(handle)) = material.get("normal2") {
+    let normal2_view = &assets.get_texture(*handle).view;
+    command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 14, normal2_view, sampler);
+    command_buffer.track_texture_view(normal2_view);
+    material_info.has_normal2 = 1;
+  }
+  // Multiblend needs all four layers.
+  if let (Some(RendererMaterialValue::Texture(albedo3_handle)), Some(RendererMaterialValue::Texture(albedo4_handle))) = (material.get("albedo3"), material.get("albedo4")) {
+    let albedo3_view = &assets.get_texture(*albedo3_handle).view;
+    let albedo4_view = &assets.get_texture(*albedo4_handle).view;
+    command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 15, albedo3_view, sampler);
+    command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 16, albedo4_view, sampler);
+    command_buffer.track_texture_view(albedo3_view);
+    command_buffer.track_texture_view(albedo4_view);
+    material_info.has_multiblend = material_info.has_albedo2;
+  }
+  if let Some(RendererMaterialValue::Texture(handle)) = material.get("orm") {
+    let orm_view = &assets.get_texture(*handle).view;
+    command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 12, orm_view, sampler);
+    command_buffer.track_texture_view(orm_view);
+    material_info.has_orm_map = 1;
+  }
+  if let Some(RendererMaterialValue::Float(strength)) = material.get("occlusion_strength") {
+    material_info.occlusion_strength = *strength;
+  }
+  if let Some(RendererMaterialValue::Texture(handle)) = material.get("emissive") {
+    let emissive_view = &assets.get_texture(*handle).view;
+    command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 13, emissive_view, sampler);
+    command_buffer.track_texture_view(emissive_view);
+    material_info.has_emissive_map = 1;
+  }
+  if let Some(RendererMaterialValue::Vec4(factor)) = material.get("emissive_factor") {
+    material_info.emissive_factor = *factor;
+  }
+  if let Some(RendererMaterialValue::Vec4(channels)) = material.get("emissive_mask_channels") {
+    material_info.emissive_mask_channels = *channels;
+  }
+  let material_info_buffer = command_buffer.upload_dynamic_data(&[material_info], BufferUsage::CONSTANT);
+  command_buffer.bind_uniform_buffer(BindingFrequency::VeryFrequent, 3, &material_info_buffer, 0, WHOLE_BUFFER);
+}
+
+/// Binds the per drawable resources of textured.vert and textured.frag: the cubemap, the vertex colors and the ambient cube.
+pub(crate) fn bind_drawable<P: Platform>(
+  command_buffer: &mut <P::GraphicsBackend as GraphicsBackend>::CommandBuffer,
+  scene: &RendererScene<P::GraphicsBackend>,
+  drawable: &RendererStaticDrawable,
+  mesh: &RendererMesh<P::GraphicsBackend>,
+  sampler: &Arc<<P::GraphicsBackend as GraphicsBackend>::Sampler>,
+  assets: &RendererAssets<P>
+) {
+  let cubemap = drawable.cubemap
+    .and_then(|index| scene.cubemaps().get(index as usize))
+    .map_or_else(|| assets.placeholder_cube(), |handle| assets.get_cube_texture(*handle));
+  command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 6, &cubemap.view, sampler);
+  command_buffer.track_texture_view(&cubemap.view);
+
+  // The vertex shader reads the colors unconditionally, so the vertices are bound in place of missing ones.
+  let vertex_colors = drawable.vertex_colors.and_then(|handle| assets.get_vertex_colors(handle, mesh));
+  let vertex_colors_slice = vertex_colors.unwrap_or(&mesh.vertices);
+  command_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 8, vertex_colors_slice.buffer(), vertex_colors_slice.offset() as usize, vertex_colors_slice.size() as usize);
+
+  #[repr(C)]
+  #[derive(Clone, Copy)]
+  struct DrawableInfo {
+    ambient_cube: [Vec4; 6],
+    has_ambient_cube: u32,
+    has_vertex_colors: u32
+  }
+  let mut drawable_info = DrawableInfo {
+    ambient_cube: [Vec4::new(0f32, 0f32, 0f32, 0f32); 6],
+    has_ambient_cube: drawable.ambient_cube.is_some() as u32,
+    has_vertex_colors: vertex_colors.is_some() as u32
+  };
+  if let Some(ambient_cube) = drawable.ambient_cube.as_ref() {
+    for (side, color) in ambient_cube.iter().enumerate() {
+      drawable_info.ambient_cube[side] = Vec4::new(color.x, color.y, color.z, 0f32);
+    }
+  }
+  let drawable_info_buffer = command_buffer.upload_dynamic_data(&[drawable_info], BufferUsage::CONSTANT);
+  command_buffer.bind_uniform_buffer(BindingFrequency::VeryFrequent, 7, &drawable_info_buffer, 0, WHOLE_BUFFER);
+}
@@ -0,0 +1,228 @@
+use sourcerenderer_core::graphics::{OutputAttachmentRef, RenderPassAttachment, RenderPassAttachmentView, RenderPassBeginInfo, RenderpassRecordingMode, TextureViewInfo, TextureLayout, BarrierAccess, BarrierSync, IndexFormat, BlendFactor, BlendOp, ColorComponents, ShaderType};
+use sourcerenderer_core::graphics::{AttachmentBlendInfo, AttachmentInfo, Backend as GraphicsBackend, BindingFrequency, BlendInfo, CommandBuffer, CompareFunc, CullMode, DepthStencilAttachmentRef, DepthStencilInfo, FillMode, Format, FrontFace, LoadOp, LogicOp, PipelineBinding, PrimitiveType, RasterizerInfo, RenderPassInfo, SampleCount, Scissor, StencilInfo, StoreOp, SubpassInfo, VertexLayoutInfo, Viewport};
+use crate::renderer::render_path::SceneInfo;
+use crate::renderer::renderer_assets::{RendererAssets, RendererMaterialValue};
+use crate::renderer::renderer_resources::{RendererResources, HistoryResourceEntry};
+use crate::renderer::shader_manager::{ShaderManager, GraphicsPipelineInfo, GraphicsPipelineHandle};
+use crate::renderer::{Vertex, VertexAttribute};
+use sourcerenderer_core::{Platform, Vec2, Vec2I, Vec2UI};
+
+/// Blends the overlays and decals of the level on top of the lit opaque geometry.
+/// The decal geometry is already clipped to the surfaces it covers, so it only needs the depth buffer
+/// of the opaque geometry to get occluded correctly.
+/// Every part gets faded out with the distances of its `DecalFade`.
+pub struct DecalPass {
+  pipeline: GraphicsPipelineHandle
+}
+
+impl DecalPass {
+  pub fn new<P: Platform>(
+    shader_manager: &mut ShaderManager<P>,
+    output_format: Format
+  ) -> Self {
+    let render_pass_info = RenderPassInfo {
+      attachments: &[
+        AttachmentInfo {
+          format: output_format,
+          samples: SampleCount::Samples1,
+        },
+        AttachmentInfo {
+          format: Format::D24,
+          samples: SampleCount::Samples1,
+        }
+      ],
+      subpasses: &[
+        SubpassInfo {
+          input_attachments: &[],
+          output_color_attachments: &[
+            OutputAttachmentRef {
+              index: 0,
+              resolve_attachment_index: None
+            }
+          ],
+          depth_stencil_attachment: Some(DepthStencilAttachmentRef {
+            index: 1,
+            read_only: true
+          })
+        }
+      ]
+    };
+
+    let pipeline_info = GraphicsPipelineInfo {
+      vs: "shaders/decal.vert.spv",
+      fs: Some("shaders/decal.frag.spv"),
+      primitive_type: PrimitiveType::Triangles,
+      vertex_layout: VertexLayoutInfo {
+        input_assembler: &Vertex::INPUT_ASSEMBLER,
+        shader_inputs: &Vertex::shader_inputs(&[
+          VertexAttribute::Position,
+          VertexAttribute::Uv,
+          VertexAttribute::LightmapUv,
+          VertexAttribute::LightmapStyles,
+          VertexAttribute::LightmapBumpPageWidth
+        ])
+      },
+      rasterizer: RasterizerInfo {
+        fill_mode: FillMode::Fill,
+        cull_mode: CullMode::Back,
+        front_face: FrontFace::Clockwise,
+        sample_count: SampleCount::Samples1
+      },
+      depth_stencil: DepthStencilInfo {
+        depth_test_enabled: true,
+        depth_write_enabled: false,
+        depth_func: CompareFunc::LessEqual,
+        stencil_enable: false,
+        stencil_read_mask: 0u8,
+        stencil_write_mask: 0u8,
+        stencil_front: StencilInfo::default(),
+        stencil_back: StencilInfo::default()
+      },
+      blend: BlendInfo {
+        alpha_to_coverage_enabled: false,
+        logic_op_enabled: false,
+        logic_op: LogicOp::And,
+        constants: [0f32, 0f32, 0f32, 0f32],
+        attachments: &[
+          AttachmentBlendInfo {
+            blend_enabled: true,
+            src_color_blend_factor: BlendFactor::SrcAlpha,
+            dst_color_blend_factor: BlendFactor::OneMinusSrcAlpha,
+            color_blend_op: BlendOp::Add,
+            src_alpha_blend_factor: BlendFactor::Zero,
+            dst_alpha_blend_factor: BlendFactor::One,
+            alpha_blend_op: BlendOp::Add,
+            write_mask: ColorComponents::RED | ColorComponents::GREEN | ColorComponents::BLUE
+          }
+        ]
+      }
+    };
+    let pipeline = shader_manager.request_graphics_pipeline(&pipeline_info, &render_pass_info, 0);
+
+    Self {
+      pipeline
+    }
+  }
+
+  #[profiling::function]
+  #[allow(clippy::too_many_arguments)]
+  pub(super) fn execute<P: Platform>(
+    &mut self,
+    cmd_buffer: &mut <P::GraphicsBackend as GraphicsBackend>::CommandBuffer,
+    scene: &SceneInfo<P::GraphicsBackend>,
+    resources: &RendererResources<P::GraphicsBackend>,
+    shader_manager: &ShaderManager<P>,
+    assets: &RendererAssets<P>,
+    output_name: &str,
+    depth_name: &str
+  ) {
+    let decals = scene.scene.decals()
+      .and_then(|decals| assets.get_model(decals.model).map(|model| (decals, model)))
+      .and_then(|(decals, model)| assets.get_mesh(model.mesh_handle()).map(|mesh| (decals, model, mesh)));
+    let lightmap = scene.lightmap;
+    if decals.is_none() || lightmap.is_none() {
+      return;
+    }
+    let (decals, model, mesh) = decals.unwrap();
+    let lightmap = lightmap.unwrap();
+
+    cmd_buffer.begin_label("Decals");
+
+    let rtv = resources.access_render_target_view(
+      cmd_buffer,
+      output_name,
+      BarrierSync::RENDER_TARGET,
+      BarrierAccess::RENDER_TARGET_READ | BarrierAccess::RENDER_TARGET_WRITE,
+      TextureLayout::RenderTarget,
+      false,
+      &TextureViewInfo::default(),
+      HistoryResourceEntry::Current
+    );
+
+    let depth_buffer = resources.access_depth_stencil_view(
+      cmd_buffer,
+      depth_name,
+      BarrierSync::EARLY_DEPTH | BarrierSync::LATE_DEPTH,
+      BarrierAccess::DEPTH_STENCIL_READ,
+      TextureLayout::DepthStencilRead,
+      false,
+      &TextureViewInfo::default(),
+      HistoryResourceEntry::Current
+    );
+
+    cmd_buffer.begin_render_pass(&RenderPassBeginInfo {
+      attachments: &[
+        RenderPassAttachment {
+          view: RenderPassAttachmentView::RenderTarget(&*rtv),
+          load_op: LoadOp::Load,
+          store_op: StoreOp::Store,
+        },
+        RenderPassAttachment {
+          view: RenderPassAttachmentView::DepthStencil(&*depth_buffer),
+          load_op: LoadOp::Load,
+          store_op: StoreOp::Store
+        }
+      ],
+      subpasses: &[
+        SubpassInfo {
+          input_attachments: &[],
+          output_color_attachments: &[
+            OutputAttachmentRef {
+              index: 0,
+              resolve_attachment_index: None
+            }
+          ],
+          depth_stencil_attachment: Some(DepthStencilAttachmentRef {
+            index: 1,
+            read_only: true
+          })
+        }
+      ]
+    }, RenderpassRecordingMode::Commands);
+
+    let (width, height) = {
+      let info = resources.texture_info(output_name);
+      (info.width, info.height)
+    };
+    cmd_buffer.set_viewports(&[Viewport {
+      position: Vec2::new(0.0f32, 0.0f32),
+      extent: Vec2::new(width as f32, height as f32),
+      min_depth: 0.0f32,
+      max_depth: 1.0f32
+    }]);
+    cmd_buffer.set_scissors(&[Scissor {
+      position: Vec2I::new(0, 0),
+      extent: Vec2UI::new(9999, 9999),
+    }]);
+
+    let pipeline = shader_manager.get_graphics_pipeline(self.pipeline);
+    cmd_buffer.set_pipeline(PipelineBinding::Graphics(&pipeline));
+    cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::Frequent, 0, &lightmap.view, resources.linear_sampler());
+    cmd_buffer.set_vertex_buffer(mesh.vertices.buffer(), mesh.vertices.offset() as usize);
+    if let Some(indices) = mesh.indices.as_ref() {
+      cmd_buffer.set_index_buffer(indices.buffer(), indices.offset() as usize, IndexFormat::U32);
+    }
+
+    // The parts are sorted by their render order, so overlapping decals blend in the right order.
+    for (part_index, range) in mesh.parts.iter().enumerate() {
+      let material = assets.get_material(model.material_handles()[part_index]);
+      let albedo_view = match material.get("albedo") {
+        Some(RendererMaterialValue::Texture(handle)) => &assets.get_texture(*handle).view,
+        _ => continue
+      };
+      let fade = decals.part_fades.get(part_index).copied().unwrap_or_default();
+      cmd_buffer.upload_dynamic_data_inline(&[fade.start, fade.end], ShaderType::VertexShader);
+      cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 0, albedo_view, resources.linear_sampler());
+      cmd_buffer.finish_binding();
+
+      if mesh.indices.is_some() {
+        cmd_buffer.draw_indexed(1, 0, range.count, range.start, 0);
+      } else {
+        cmd_buffer.draw(range.count, range.start);
+      }
+    }
+
+    cmd_buffer.end_render_pass();
+    cmd_buffer.end_label();
+  }
+}
@@ -0,0 +1,350 @@
+use sourcerenderer_core::graphics::{OutputAttachmentRef, RenderPassAttachment, RenderPassAttachmentView, RenderPassBeginInfo, RenderpassRecordingMode, TextureViewInfo, TextureLayout, BarrierAccess, BarrierSync, IndexFormat, BufferUsage, WHOLE_BUFFER};
+use sourcerenderer_core::graphics::{AttachmentBlendInfo, AttachmentInfo, Backend as GraphicsBackend, BindingFrequency, BlendInfo, CommandBuffer, CompareFunc, CullMode, DepthStencilAttachmentRef, DepthStencilInfo, FillMode, Format, FrontFace, LoadOp, LogicOp, PipelineBinding, PrimitiveType, RasterizerInfo, RenderPassInfo, SampleCount, Scissor, StencilInfo, StoreOp, SubpassInfo, VertexLayoutInfo, Viewport};
+use crate::renderer::render_path::SceneInfo;
+use crate::renderer::renderer_assets::{RendererAssets, RendererMaterial, RendererMaterialValue, RendererTexture};
+use crate::renderer::renderer_resources::{RendererResources, HistoryResourceEntry};
+use crate::renderer::shader_manager::{ShaderManager, GraphicsPipelineInfo, GraphicsPipelineHandle};
+use crate::renderer::{DetailModelInstance, DetailSprite, DetailSpriteOrientation};
+use crate::renderer::{Vertex, VertexAttribute};
+use crate::math::{BoundingBox, Frustum};
+use sourcerenderer_core::{Matrix4, Platform, Vec2, Vec2I, Vec2UI, Vec3, Vec4};
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct DetailCB {
+  fade_start: f32,
+  fade_end: f32
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct GPUDetailSprite {
+  position_and_orientation: Vec4,
+  right: Vec4,
+  up: Vec4,
+  extents: Vec4,
+  tex_coords: Vec4,
+  color: Vec4
+}
+
+impl GPUDetailSprite {
+  fn new(sprite: &DetailSprite) -> Self {
+    let orientation = match sprite.orientation {
+      DetailSpriteOrientation::Fixed => 0f32,
+      DetailSpriteOrientation::ScreenAligned => 1f32,
+      DetailSpriteOrientation::ScreenAlignedVertical => 2f32
+    };
+    Self {
+      position_and_orientation: Vec4::new(sprite.position.x, sprite.position.y, sprite.position.z, orientation),
+      right: Vec4::new(sprite.right.x, sprite.right.y, sprite.right.z, 0f32),
+      up: Vec4::new(sprite.up.x, sprite.up.y, sprite.up.z, 0f32),
+      extents: sprite.extents,
+      tex_coords: sprite.tex_coords,
+      color: Vec4::new(sprite.color.x, sprite.color.y, sprite.color.z, 1f32)
+    }
+  }
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct GPUDetailModelInstance {
+  transform: Matrix4,
+  color: Vec4
+}
+
+impl GPUDetailModelInstance {
+  fn new(instance: &DetailModelInstance) -> Self {
+    Self {
+      transform: instance.transform,
+      color: Vec4::new(instance.color.x, instance.color.y, instance.color.z, 1f32)
+    }
+  }
+}
+
+/// Draws the grass and other small props that VBSP scatters over the level.
+/// The props within the fade distance and the view frustum get uploaded every frame and drawn with one instanced draw per batch,
+/// sprites get expanded to quads in the vertex shader. They're alpha tested, so they write depth like opaque geometry.
+pub struct DetailPropPass {
+  sprite_pipeline: GraphicsPipelineHandle,
+  model_pipeline: GraphicsPipelineHandle
+}
+
+impl DetailPropPass {
+  pub fn new<P: Platform>(
+    shader_manager: &mut ShaderManager<P>,
+    output_format: Format
+  ) -> Self {
+    let render_pass_info = RenderPassInfo {
+      attachments: &[
+        AttachmentInfo {
+          format: output_format,
+          samples: SampleCount::Samples1,
+        },
+        AttachmentInfo {
+          format: Format::D24,
+          samples: SampleCount::Samples1,
+        }
+      ],
+      subpasses: &[
+        SubpassInfo {
+          input_attachments: &[],
+          output_color_attachments: &[
+            OutputAttachmentRef {
+              index: 0,
+              resolve_attachment_index: None
+            }
+          ],
+          depth_stencil_attachment: Some(DepthStencilAttachmentRef {
+            index: 1,
+            read_only: false
+          })
+        }
+      ]
+    };
+
+    let rasterizer = RasterizerInfo {
+      fill_mode: FillMode::Fill,
+      cull_mode: CullMode::None,
+      front_face: FrontFace::Clockwise,
+      sample_count: SampleCount::Samples1
+    };
+    let depth_stencil = DepthStencilInfo {
+      depth_test_enabled: true,
+      depth_write_enabled: true,
+      depth_func: CompareFunc::LessEqual,
+      stencil_enable: false,
+      stencil_read_mask: 0u8,
+      stencil_write_mask: 0u8,
+      stencil_front: StencilInfo::default(),
+      stencil_back: StencilInfo::default()
+    };
+
+    let sprite_pipeline_info = GraphicsPipelineInfo {
+      vs: "shaders/detail_sprite.vert.spv",
+      fs: Some("shaders/detail_prop.frag.spv"),
+      primitive_type: PrimitiveType::Triangles,
+      vertex_layout: VertexLayoutInfo {
+        input_assembler: &[],
+        shader_inputs: &[]
+      },
+      rasterizer: rasterizer.clone(),
+      depth_stencil: depth_stencil.clone(),
+      blend: BlendInfo {
+        alpha_to_coverage_enabled: false,
+        logic_op_enabled: false,
+        logic_op: LogicOp::And,
+        constants: [0f32, 0f32, 0f32, 0f32],
+        attachments: &[
+          AttachmentBlendInfo::default()
+        ]
+      }
+    };
+    let sprite_pipeline = shader_manager.request_graphics_pipeline(&sprite_pipeline_info, &render_pass_info, 0);
+
+    let model_pipeline_info = GraphicsPipelineInfo {
+      vs: "shaders/detail_model.vert.spv",
+      fs: Some("shaders/detail_prop.frag.spv"),
+      primitive_type: PrimitiveType::Triangles,
+      vertex_layout: VertexLayoutInfo {
+        input_assembler: &Vertex::INPUT_ASSEMBLER,
+        shader_inputs: &Vertex::shader_inputs(&[
+          VertexAttribute::Position,
+          VertexAttribute::Uv
+        ])
+      },
+      rasterizer,
+      depth_stencil,
+      blend: BlendInfo {
+        alpha_to_coverage_enabled: false,
+        logic_op_enabled: false,
+        logic_op: LogicOp::And,
+        constants: [0f32, 0f32, 0f32, 0f32],
+        attachments: &[
+          AttachmentBlendInfo::default()
+        ]
+      }
+    };
+    let model_pipeline = shader_manager.request_graphics_pipeline(&model_pipeline_info, &render_pass_info, 0);
+
+    Self {
+      sprite_pipeline,
+      model_pipeline
+    }
+  }
+
+  #[profiling::function]
+  #[allow(clippy::too_many_arguments)]
+  pub(super) fn execute<P: Platform>(
+    &mut self,
+    cmd_buffer: &mut <P::GraphicsBackend as GraphicsBackend>::CommandBuffer,
+    scene: &SceneInfo<P::GraphicsBackend>,
+    resources: &RendererResources<P::GraphicsBackend>,
+    shader_manager: &ShaderManager<P>,
+    assets: &RendererAssets<P>,
+    output_name: &str,
+    depth_name: &str
+  ) {
+    let detail_props = if let Some(detail_props) = scene.scene.detail_props() {
+      detail_props
+    } else {
+      return;
+    };
+
+    // Everything past the fade distance is invisible anyway.
+    let view = &scene.views[scene.active_view_index];
+    let camera_position = view.camera_position;
+    let max_distance_sqr = detail_props.fade_end * detail_props.fade_end;
+    let is_in_range = |position: &Vec3| (position - camera_position).magnitude_squared() < max_distance_sqr;
+    let frustum = Frustum::new(view.near_plane, view.far_plane, view.camera_fov, view.aspect_ratio);
+    let sprites: Vec<GPUDetailSprite> = detail_props.sprites.iter()
+      .filter(|sprite| is_in_range(&sprite.position))
+      .filter(|sprite| {
+        // Screen aligned sprites can face any direction, so the box has to fit the quad in every orientation.
+        let radius = sprite.extents.abs().max();
+        let bounding_box = BoundingBox::new(sprite.position - Vec3::repeat(radius), sprite.position + Vec3::repeat(radius));
+        frustum.intersects(&bounding_box, &view.view_matrix)
+      })
+      .map(GPUDetailSprite::new)
+      .collect();
+    let models: Vec<_> = detail_props.models.iter().filter_map(|(model_handle, instances)| {
+      let model = assets.get_model(*model_handle)?;
+      let mesh = assets.get_mesh(model.mesh_handle())?;
+      let instances: Vec<GPUDetailModelInstance> = instances.iter()
+        .filter(|instance| is_in_range(&Vec3::new(instance.transform[(0, 3)], instance.transform[(1, 3)], instance.transform[(2, 3)])))
+        .filter(|instance| if let Some(bounding_box) = mesh.bounding_box.as_ref() {
+          frustum.intersects(bounding_box, &(view.view_matrix * instance.transform))
+        } else {
+          true
+        })
+        .map(GPUDetailModelInstance::new)
+        .collect();
+      if instances.is_empty() {
+        return None;
+      }
+      Some((model, mesh, instances))
+    }).collect();
+    if sprites.is_empty() && models.is_empty() {
+      return;
+    }
+
+    cmd_buffer.begin_label("Detail props");
+
+    let rtv = resources.access_render_target_view(
+      cmd_buffer,
+      output_name,
+      BarrierSync::RENDER_TARGET,
+      BarrierAccess::RENDER_TARGET_READ | BarrierAccess::RENDER_TARGET_WRITE,
+      TextureLayout::RenderTarget,
+      false,
+      &TextureViewInfo::default(),
+      HistoryResourceEntry::Current
+    );
+
+    let depth_buffer = resources.access_depth_stencil_view(
+      cmd_buffer,
+      depth_name,
+      BarrierSync::EARLY_DEPTH | BarrierSync::LATE_DEPTH,
+      BarrierAccess::DEPTH_STENCIL_READ | BarrierAccess::DEPTH_STENCIL_WRITE,
+      TextureLayout::DepthStencilReadWrite,
+      false,
+      &TextureViewInfo::default(),
+      HistoryResourceEntry::Current
+    );
+
+    cmd_buffer.begin_render_pass(&RenderPassBeginInfo {
+      attachments: &[
+        RenderPassAttachment {
+          view: RenderPassAttachmentView::RenderTarget(&*rtv),
+          load_op: LoadOp::Load,
+          store_op: StoreOp::Store,
+        },
+        RenderPassAttachment {
+          view: RenderPassAttachmentView::DepthStencil(&*depth_buffer),
+          load_op: LoadOp::Load,
+          store_op: StoreOp::Store
+        }
+      ],
+      subpasses: &[
+        SubpassInfo {
+          input_attachments: &[],
+          output_color_attachments: &[
+            OutputAttachmentRef {
+              index: 0,
+              resolve_attachment_index: None
+            }
+          ],
+          depth_stencil_attachment: Some(DepthStencilAttachmentRef {
+            index: 1,
+            read_only: false
+          })
+        }
+      ]
+    }, RenderpassRecordingMode::Commands);
+
+    let (width, height) = {
+      let info = resources.texture_info(output_name);
+      (info.width, info.height)
+    };
+    cmd_buffer.set_viewports(&[Viewport {
+      position: Vec2::new(0.0f32, 0.0f32),
+      extent: Vec2::new(width as f32, height as f32),
+      min_depth: 0.0f32,
+      max_depth: 1.0f32
+    }]);
+    cmd_buffer.set_scissors(&[Scissor {
+      position: Vec2I::new(0, 0),
+      extent: Vec2UI::new(9999, 9999),
+    }]);
+
+    let detail_cb = cmd_buffer.upload_dynamic_data(&[DetailCB {
+      fade_start: detail_props.fade_start,
+      fade_end: detail_props.fade_end
+    }], BufferUsage::CONSTANT);
+
+    if !sprites.is_empty() {
+      let material = assets.get_material(detail_props.sprite_material);
+      let pipeline = shader_manager.get_graphics_pipeline(self.sprite_pipeline);
+      cmd_buffer.set_pipeline(PipelineBinding::Graphics(&pipeline));
+      let sprite_buffer = cmd_buffer.upload_dynamic_data(&sprites[..], BufferUsage::STORAGE);
+      cmd_buffer.bind_storage_buffer(BindingFrequency::Frequent, 0, &sprite_buffer, 0, WHOLE_BUFFER);
+      cmd_buffer.bind_uniform_buffer(BindingFrequency::Frequent, 1, &detail_cb, 0, WHOLE_BUFFER);
+      cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 0, &Self::albedo(assets, material).view, resources.linear_sampler());
+      cmd_buffer.finish_binding();
+      cmd_buffer.draw(6 * sprites.len() as u32, 0);
+    }
+
+    if !models.is_empty() {
+      let pipeline = shader_manager.get_graphics_pipeline(self.model_pipeline);
+      cmd_buffer.set_pipeline(PipelineBinding::Graphics(&pipeline));
+      for (model, mesh, instances) in &models {
+        // Instancing needs an index buffer, models always have one.
+        let indices = if let Some(indices) = mesh.indices.as_ref() {
+          indices
+        } else {
+          continue;
+        };
+        let instance_buffer = cmd_buffer.upload_dynamic_data(&instances[..], BufferUsage::STORAGE);
+        cmd_buffer.bind_storage_buffer(BindingFrequency::Frequent, 0, &instance_buffer, 0, WHOLE_BUFFER);
+        cmd_buffer.bind_uniform_buffer(BindingFrequency::Frequent, 1, &detail_cb, 0, WHOLE_BUFFER);
+        cmd_buffer.set_vertex_buffer(mesh.vertices.buffer(), mesh.vertices.offset() as usize);
+        cmd_buffer.set_index_buffer(indices.buffer(), indices.offset() as usize, IndexFormat::U32);
+        for (part_index, range) in mesh.parts.iter().enumerate() {
+          let material = assets.get_material(model.material_handles()[part_index]);
+          cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 0, &Self::albedo(assets, material).view, resources.linear_sampler());
+          cmd_buffer.finish_binding();
+          cmd_buffer.draw_indexed(instances.len() as u32, 0, range.count, range.start, 0);
+        }
+      }
+    }
+
+    cmd_buffer.end_render_pass();
+    cmd_buffer.end_label();
+  }
+
+  fn albedo<'a, P: Platform>(assets: &'a RendererAssets<P>, material: &RendererMaterial) -> &'a RendererTexture<P::GraphicsBackend> {
+    match material.get("albedo") {
+      Some(RendererMaterialValue::Texture(handle)) => assets.get_texture(*handle),
+      _ => assets.placeholder_texture()
+    }
+  }
+}
@@ -297,7 +297,7 @@ unsafe fn texture_into_ffx<B: Backend>(texture: &Arc<B::Texture>, is_uav: bool,
     description: FfxResourceDescription {
       type_: match info.dimension {
         TextureDimension::Dim1D => FfxResourceType_FFX_RESOURCE_TYPE_TEXTURE1D,
-        TextureDimension::Dim2D => FfxResourceType_FFX_RESOURCE_TYPE_TEXTURE2D,
+        TextureDimension::Dim2D | TextureDimension::Dim2DArray | TextureDimension::DimCube => FfxResourceType_FFX_RESOURCE_TYPE_TEXTURE2D,
         TextureDimension::Dim3D => FfxResourceType_FFX_RESOURCE_TYPE_TEXTURE3D,
       },
       format: format_to_ffx(info.format).unwrap_or(FfxSurfaceFormat_FFX_SURFACE_FORMAT_UNKNOWN),
@@ -653,7 +653,7 @@ unsafe extern "C" fn get_resource_description<B: Backend>(
       FfxResourceDescription {
         type_: match info.dimension {
           TextureDimension::Dim1D => FfxResourceType_FFX_RESOURCE_TYPE_TEXTURE1D,
-          TextureDimension::Dim2D => FfxResourceType_FFX_RESOURCE_TYPE_TEXTURE2D,
+          TextureDimension::Dim2D | TextureDimension::Dim2DArray | TextureDimension::DimCube => FfxResourceType_FFX_RESOURCE_TYPE_TEXTURE2D,
           TextureDimension::Dim3D => FfxResourceType_FFX_RESOURCE_TYPE_TEXTURE3D,
         },
         format: format_to_ffx(info.format).unwrap_or_else(|| panic!("Unsupported format: {:?}", info.format)),
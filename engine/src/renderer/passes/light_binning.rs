@@ -11,7 +11,8 @@ use super::clustering::ClusteringPass;
 #[derive(Debug, Clone, Copy)]
 pub struct SetupInfo {
   cluster_count: u32,
-  point_light_count: u32
+  point_light_count: u32,
+  spot_light_count: u32
 }
 
 #[repr(C)]
@@ -21,7 +22,14 @@ pub struct CullingPointLight {
   radius: f32
 }
 
-const LIGHT_CUTOFF: f32 = 0.05f32;
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct CullingSpotLight {
+  position: Vec3,
+  radius: f32,
+  direction: Vec3,
+  outer_cone_angle: f32
+}
 
 pub struct LightBinningPass {
   light_binning_pipeline: ComputePipelineHandle
@@ -55,15 +63,23 @@ impl LightBinningPass {
     let cluster_count = Vector3::<u32>::new(16, 9, 24);
     let setup_info = SetupInfo {
       point_light_count: scene.point_lights().len() as u32,
+      spot_light_count: scene.spot_lights().len() as u32,
       cluster_count: cluster_count.x * cluster_count.y * cluster_count.z
     };
     let point_lights: Vec<CullingPointLight> = scene.point_lights().iter().map(|l| CullingPointLight {
       position: l.position,
-      radius: (l.intensity / LIGHT_CUTOFF).sqrt()
+      radius: l.range
+    }).collect();
+    let spot_lights: Vec<CullingSpotLight> = scene.spot_lights().iter().map(|l| CullingSpotLight {
+      position: l.position,
+      radius: l.range,
+      direction: l.direction,
+      outer_cone_angle: l.outer_cone_angle
     }).collect();
 
     let light_info_buffer = cmd_buffer.upload_dynamic_data(&[setup_info], BufferUsage::STORAGE);
     let point_lights_buffer = cmd_buffer.upload_dynamic_data(&point_lights[..], BufferUsage::STORAGE);
+    let spot_lights_buffer = cmd_buffer.upload_dynamic_data(&spot_lights[..], BufferUsage::STORAGE);
 
     cmd_buffer.barrier(&[
       Barrier::BufferBarrier {
@@ -85,6 +101,7 @@ impl LightBinningPass {
     cmd_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 2, &light_info_buffer, 0, WHOLE_BUFFER);
     cmd_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 3, &point_lights_buffer, 0, WHOLE_BUFFER);
     cmd_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 4, &*light_bitmask_buffer, 0, WHOLE_BUFFER);
+    cmd_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 5, &spot_lights_buffer, 0, WHOLE_BUFFER);
     cmd_buffer.finish_binding();
     cmd_buffer.dispatch((cluster_count.x * cluster_count.y * cluster_count.z + 63) / 64, 1, 1);
     cmd_buffer.end_label();
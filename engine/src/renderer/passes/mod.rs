@@ -8,6 +8,12 @@ pub(crate) mod taa;
 pub(crate) mod light_binning;
 pub(crate) mod clustering;
 pub(crate) mod prepass;
+pub(crate) mod skybox;
+pub(crate) mod decal;
+pub(crate) mod detail_props;
+pub(crate) mod water;
+pub(crate) mod translucent;
+pub(crate) mod shadow_map;
 pub(crate) mod ssr;
 pub(crate) mod compositing;
 #[cfg(not(target_arch = "wasm32"))]
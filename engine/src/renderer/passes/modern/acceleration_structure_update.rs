@@ -74,7 +74,8 @@ impl<P: Platform> AccelerationStructureUpdatePass<P> {
           let mesh = mesh.unwrap();
 
           let blas = {
-            let parts: Vec<AccelerationStructureMeshRange> = mesh.parts.iter().map(|p| {
+            // Ray traced effects always use the most detailed LOD.
+            let parts: Vec<AccelerationStructureMeshRange> = mesh.parts[mesh.lod_parts(0)].iter().map(|p| {
               debug_assert_eq!(p.start % 3, 0);
               debug_assert_eq!(p.count % 3, 0);
               AccelerationStructureMeshRange {
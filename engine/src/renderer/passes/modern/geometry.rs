@@ -1,11 +1,12 @@
 use nalgebra::Vector2;
 use smallvec::SmallVec;
-use sourcerenderer_core::{Matrix4, graphics::{AddressMode, AttachmentBlendInfo, AttachmentInfo, Backend as GraphicsBackend, BindingFrequency, BlendInfo, BufferUsage, CommandBuffer, CompareFunc, CullMode, DepthStencilAttachmentRef, DepthStencilInfo, Device, FillMode, Filter, Format, FrontFace, InputAssemblerElement, InputRate, LoadOp, LogicOp, OutputAttachmentRef, PipelineBinding, PrimitiveType, RasterizerInfo, RenderPassAttachment, RenderPassAttachmentView, RenderPassBeginInfo, RenderPassInfo, RenderpassRecordingMode, SampleCount, SamplerInfo, Scissor, ShaderInputElement, StencilInfo, StoreOp, SubpassInfo, Swapchain, Texture, TextureInfo, TextureRenderTargetView, TextureViewInfo, TextureUsage, VertexLayoutInfo, Viewport, TextureLayout, BarrierSync, BarrierAccess, IndexFormat, WHOLE_BUFFER, TextureDimension}};
+use sourcerenderer_core::{Matrix4, graphics::{AddressMode, AttachmentBlendInfo, AttachmentInfo, Backend as GraphicsBackend, BindingFrequency, BlendInfo, BufferUsage, CommandBuffer, CompareFunc, CullMode, DepthStencilAttachmentRef, DepthStencilInfo, Device, FillMode, Filter, Format, FrontFace, LoadOp, LogicOp, OutputAttachmentRef, PipelineBinding, PrimitiveType, RasterizerInfo, RenderPassAttachment, RenderPassAttachmentView, RenderPassBeginInfo, RenderPassInfo, RenderpassRecordingMode, SampleCount, SamplerInfo, Scissor, StencilInfo, StoreOp, SubpassInfo, Swapchain, Texture, TextureInfo, TextureRenderTargetView, TextureViewInfo, TextureUsage, VertexLayoutInfo, Viewport, TextureLayout, BarrierSync, BarrierAccess, IndexFormat, WHOLE_BUFFER, TextureDimension}};
 use std::{sync::Arc, cell::Ref};
 use crate::renderer::{PointLight, drawable::View, light::DirectionalLight, renderer_scene::RendererScene, renderer_resources::{RendererResources, HistoryResourceEntry}, passes::{light_binning, ssao::SsaoPass, rt_shadows::RTShadowPass}, shader_manager::{ShaderManager, GraphicsPipelineHandle, GraphicsPipelineInfo}};
 use sourcerenderer_core::{Platform, Vec2, Vec2I, Vec2UI, graphics::Backend};
 use crate::renderer::passes::taa::scaled_halton_point;
 use crate::renderer::renderer_assets::*;
+use crate::renderer::{Vertex, VertexAttribute};
 
 use super::{draw_prep::DrawPrepPass, gpu_scene::DRAW_CAPACITY};
 
@@ -66,55 +67,17 @@ impl<P: Platform> GeometryPass<P> {
       fs: Some("shaders/geometry_bindless.frag.spv"),
       primitive_type: PrimitiveType::Triangles,
       vertex_layout: VertexLayoutInfo {
-        input_assembler: &[
-          InputAssemblerElement {
-            binding: 0,
-            stride: 64,
-            input_rate: InputRate::PerVertex
-          }
-        ],
-        shader_inputs: &[
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 0,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 0,
-            format: Format::RGB32Float
-          },
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 1,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 16,
-            format: Format::RGB32Float
-          },
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 2,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 32,
-            format: Format::RG32Float
-          },
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 3,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 40,
-            format: Format::RG32Float
-          },
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 4,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 48,
-            format: Format::R32Float
-          }
-        ]
+        input_assembler: &Vertex::INPUT_ASSEMBLER,
+        shader_inputs: &Vertex::shader_inputs(&[
+          VertexAttribute::Position,
+          VertexAttribute::Normal,
+          VertexAttribute::Uv,
+          VertexAttribute::LightmapUv,
+          VertexAttribute::Alpha,
+          VertexAttribute::LightmapStyles,
+          VertexAttribute::LightmapBumpPageWidth,
+          VertexAttribute::Tangent
+        ])
       },
       rasterizer: RasterizerInfo {
         fill_mode: FillMode::Fill,
@@ -319,14 +282,18 @@ impl<P: Platform> GeometryPass<P> {
     for point_light in scene.point_lights() {
       point_lights.push(PointLight {
         position: point_light.position,
-        intensity: point_light.intensity
+        intensity: point_light.intensity,
+        color: point_light.color,
+        range: point_light.range,
+        attenuation: point_light.attenuation
       });
     }
     let mut directional_lights = SmallVec::<[DirectionalLight; 16]>::new();
     for directional_light in scene.directional_lights() {
       directional_lights.push(DirectionalLight {
         direction: directional_light.direction,
-        intensity: directional_light.intensity
+        intensity: directional_light.intensity,
+        color: directional_light.color
       });
     }
     let per_frame_buffer = cmd_buffer.upload_dynamic_data(&[per_frame], BufferUsage::CONSTANT);
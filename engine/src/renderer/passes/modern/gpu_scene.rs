@@ -4,13 +4,17 @@ use field_offset::offset_of;
 use smallvec::SmallVec;
 use sourcerenderer_core::{Vec4, Matrix4, graphics::{Backend, CommandBuffer, BufferInfo, BufferUsage, MemoryUsage, Buffer}, Vec3, Platform};
 
-use crate::renderer::{renderer_scene::RendererScene, renderer_assets::{RendererMaterial, RendererMaterialValue, RendererAssets, ModelHandle, MaterialHandle}};
+use crate::renderer::{ClusterVisibility, renderer_scene::RendererScene, renderer_assets::{RendererMaterial, RendererMaterialValue, RendererAssets, ModelHandle, MaterialHandle, AlphaMode}};
 
 pub const DRAWABLE_CAPACITY: u32 = 4096;
 pub const PART_CAPACITY: u32 = 4096;
 pub const DRAW_CAPACITY: u32 = 4096;
 pub const MATERIAL_CAPACITY: u32 = 4096;
 pub const MESH_CAPACITY: u32 = 4096;
+pub const CUBEMAP_CAPACITY: u32 = 128;
+const NO_CUBEMAP: u32 = u32::MAX;
+const NO_VERTEX_COLORS: u32 = u32::MAX;
+const NO_SKINNING: u32 = u32::MAX;
 
 #[repr(C)]
 #[derive(Debug, Clone)]
@@ -44,16 +48,41 @@ struct GPUMeshPart {
   mesh_first_index: u32,
   mesh_index_count: u32,
   mesh_vertex_offset: u32,
+  lod_min_screen_size: f32,
+  lod_max_screen_size: f32,
 }
 
 #[repr(C)]
 #[derive(Debug, Clone)]
 struct GPUMaterial {
   albedo: Vec4,
+  environment_map_tint: Vec4,
+  emissive_factor: Vec4,
+  emissive_mask_channels: Vec4,
   roughness_factor: f32,
   metalness_factor: f32,
+  occlusion_strength: f32,
+  alpha_cutoff: f32,
   albedo_texture_index: u32,
+  albedo2_texture_index: u32,
+  blend_modulate_texture_index: u32,
+  normal_texture_index: u32,
+  orm_texture_index: u32,
+  emissive_texture_index: u32,
+  normal2_texture_index: u32,
+  albedo3_texture_index: u32,
+  albedo4_texture_index: u32,
+  has_albedo2: u32,
+  has_blend_modulate: u32,
+  has_normal_map: u32,
+  has_orm_map: u32,
+  has_emissive_map: u32,
+  has_normal2: u32,
+  has_multiblend: u32,
+  alpha_mode: u32,
   _padding: u32,
+  _padding1: u32,
+  _padding2: u32
 }
 
 #[repr(C)]
@@ -62,9 +91,18 @@ struct GPUDrawable {
   transform: Matrix4,
   old_transform: Matrix4,
   mesh_index: u32,
+  cubemap_index: u32,
+  has_ambient_cube: u32,
+  /// Index of the first baked vertex color in the vertex buffer.
+  vertex_colors_offset: u32,
+  /// Index of the first 32 bit word of the skinning stream in the vertex buffer.
+  skinning_offset: u32,
+  /// Index of the first bone of the drawable in the bone palette buffer.
+  bone_palette_offset: u32,
+  /// Drawables in the 3D skybox only get drawn by the sky view.
+  is_in_sky_area: u32,
   _padding: u32,
-  _padding1: u32,
-  _padding2: u32,
+  ambient_cube: [Vec4; 6],
 }
 
 #[repr(C)]
@@ -94,9 +132,13 @@ pub(crate) fn upload<P: Platform>(
   scene: &RendererScene<P::GraphicsBackend>,
   zero_view_index: u32,
   assets: &RendererAssets<P>
-) -> Arc<<P::GraphicsBackend as Backend>::Buffer> {
+) -> (Arc<<P::GraphicsBackend as Backend>::Buffer>, Arc<<P::GraphicsBackend as Backend>::Buffer>) {
   let mut scene_box = Box::new(MaybeUninit::<GPUScene>::uninit()); // TODO reuse the same allocation
   let local = unsafe { scene_box.assume_init_mut() };
+  let mut bone_palettes = Vec::<Matrix4>::new();
+  // The 3D skybox consists of the clusters that can be seen from the sky camera.
+  let sky_clusters = scene.sky_camera().zip(scene.potentially_visible_set())
+    .and_then(|((sky_camera_position, _scale), pvs)| pvs.find_cluster(&sky_camera_position).map(|cluster| pvs.visible_clusters(cluster)));
   {
     profiling::scope!("CollectingSceneData");
 
@@ -140,19 +182,46 @@ pub(crate) fn upload<P: Platform>(
             let material_index = local.material_count;
             let mut gpu_material = GPUMaterial {
               albedo: Vec4::new(1f32, 1f32, 1f32, 1f32),
+              environment_map_tint: Vec4::new(0f32, 0f32, 0f32, 0f32),
+              emissive_factor: Vec4::new(0f32, 0f32, 0f32, 0f32),
+              emissive_mask_channels: Vec4::new(0f32, 0f32, 0f32, 0f32),
               roughness_factor: 1f32,
               metalness_factor: 0f32,
+              occlusion_strength: 1f32,
+              alpha_cutoff: 0f32,
               albedo_texture_index: zero_view_index,
-              _padding: 0
+              albedo2_texture_index: zero_view_index,
+              blend_modulate_texture_index: zero_view_index,
+              normal_texture_index: zero_view_index,
+              orm_texture_index: zero_view_index,
+              emissive_texture_index: zero_view_index,
+              normal2_texture_index: zero_view_index,
+              albedo3_texture_index: zero_view_index,
+              albedo4_texture_index: zero_view_index,
+              has_albedo2: 0,
+              has_blend_modulate: 0,
+              has_normal_map: 0,
+              has_orm_map: 0,
+              has_emissive_map: 0,
+              has_normal2: 0,
+              has_multiblend: 0,
+              alpha_mode: AlphaMode::Opaque as u32,
+              _padding: 0,
+              _padding1: 0,
+              _padding2: 0
             };
 
             let albedo_value = material.get("albedo").unwrap();
             match albedo_value {
               RendererMaterialValue::Texture(handle) => {
+                let frame = match material.get("albedo_frame") {
+                  Some(RendererMaterialValue::Float(frame)) => *frame as u32,
+                  _ => 0
+                };
                 let texture = assets.get_texture(*handle);
-                let albedo_view = &texture.view;
+                let albedo_view = texture.frame_view(frame);
                 cmd_buffer.track_texture_view(albedo_view);
-                gpu_material.albedo_texture_index = texture.bindless_index.unwrap();
+                gpu_material.albedo_texture_index = texture.frame_bindless_index(frame).unwrap();
               },
               RendererMaterialValue::Vec4(val) => {
                 gpu_material.albedo = *val
@@ -181,6 +250,66 @@ pub(crate) fn upload<P: Platform>(
               },
               None => {}
             }
+            if let Some(RendererMaterialValue::Vec4(tint)) = material.get("environment_map_tint") {
+              gpu_material.environment_map_tint = *tint;
+            }
+            if let Some(RendererMaterialValue::Texture(handle)) = material.get("albedo2") {
+              let texture = assets.get_texture(*handle);
+              cmd_buffer.track_texture_view(&texture.view);
+              gpu_material.albedo2_texture_index = texture.bindless_index.unwrap();
+              gpu_material.has_albedo2 = 1;
+            }
+            if let Some(RendererMaterialValue::Texture(handle)) = material.get("blend_modulate") {
+              let texture = assets.get_texture(*handle);
+              cmd_buffer.track_texture_view(&texture.view);
+              gpu_material.blend_modulate_texture_index = texture.bindless_index.unwrap();
+              gpu_material.has_blend_modulate = 1;
+            }
+            if let Some(RendererMaterialValue::Texture(handle)) = material.get("normal") {
+              let texture = assets.get_texture(*handle);
+              cmd_buffer.track_texture_view(&texture.view);
+              gpu_material.normal_texture_index = texture.bindless_index.unwrap();
+              gpu_material.has_normal_map = 1;
+            }
+            if let Some(RendererMaterialValue::Texture(handle)) = material.get("normal2") {
+              let texture = assets.get_texture(*handle);
+              cmd_buffer.track_texture_view(&texture.view);
+              gpu_material.normal2_texture_index = texture.bindless_index.unwrap();
+              gpu_material.has_normal2 = 1;
+            }
+            // Multiblend needs all four layers.
+            if let (Some(RendererMaterialValue::Texture(albedo3_handle)), Some(RendererMaterialValue::Texture(albedo4_handle))) = (material.get("albedo3"), material.get("albedo4")) {
+              let albedo3_texture = assets.get_texture(*albedo3_handle);
+              let albedo4_texture = assets.get_texture(*albedo4_handle);
+              cmd_buffer.track_texture_view(&albedo3_texture.view);
+              cmd_buffer.track_texture_view(&albedo4_texture.view);
+              gpu_material.albedo3_texture_index = albedo3_texture.bindless_index.unwrap();
+              gpu_material.albedo4_texture_index = albedo4_texture.bindless_index.unwrap();
+              gpu_material.has_multiblend = gpu_material.has_albedo2;
+            }
+            if let Some(RendererMaterialValue::Texture(handle)) = material.get("orm") {
+              let texture = assets.get_texture(*handle);
+              cmd_buffer.track_texture_view(&texture.view);
+              gpu_material.orm_texture_index = texture.bindless_index.unwrap();
+              gpu_material.has_orm_map = 1;
+            }
+            if let Some(RendererMaterialValue::Float(strength)) = material.get("occlusion_strength") {
+              gpu_material.occlusion_strength = *strength;
+            }
+            if let Some(RendererMaterialValue::Texture(handle)) = material.get("emissive") {
+              let texture = assets.get_texture(*handle);
+              cmd_buffer.track_texture_view(&texture.view);
+              gpu_material.emissive_texture_index = texture.bindless_index.unwrap();
+              gpu_material.has_emissive_map = 1;
+            }
+            if let Some(RendererMaterialValue::Vec4(factor)) = material.get("emissive_factor") {
+              gpu_material.emissive_factor = *factor;
+            }
+            if let Some(RendererMaterialValue::Vec4(channels)) = material.get("emissive_mask_channels") {
+              gpu_material.emissive_mask_channels = *channels;
+            }
+            gpu_material.alpha_mode = material.alpha_mode() as u32;
+            gpu_material.alpha_cutoff = material.alpha_cutoff();
             local.materials[material_index as usize] = gpu_material;
             debug_assert!(local.material_count < local.materials.len() as u32);
             material_map.insert(material_handle, material_index);
@@ -199,6 +328,15 @@ pub(crate) fn upload<P: Platform>(
           gpu_part.mesh_first_index = part.start + indices.offset() / std::mem::size_of::<u32>() as u32;
           gpu_part.mesh_index_count = part.count;
           gpu_part.mesh_vertex_offset = vertices.offset() / (std::mem::size_of::<crate::renderer::Vertex>() as u32); // TODO: hardcoded vertex size
+
+          // The part gets drawn while the screen size is between the switch points of its LOD and the next one.
+          let (lod_min_screen_size, lod_max_screen_size) = mesh.lods.iter()
+            .enumerate()
+            .find(|(_, lod)| (lod.first_part as usize .. (lod.first_part + lod.part_count) as usize).contains(&index))
+            .map(|(lod_index, lod)| (mesh.lods.get(lod_index + 1).map_or(0f32, |next_lod| next_lod.max_screen_size), lod.max_screen_size))
+            .unwrap_or((0f32, f32::INFINITY));
+          gpu_part.lod_min_screen_size = lod_min_screen_size;
+          gpu_part.lod_max_screen_size = lod_max_screen_size;
           local.part_count += 1;
         }
 
@@ -241,10 +379,38 @@ pub(crate) fn upload<P: Platform>(
         gpu_drawable.transform = drawable.transform;
         gpu_drawable.old_transform = drawable.old_transform;
         gpu_drawable.mesh_index = model_entry.mesh_index;
+        gpu_drawable.cubemap_index = drawable.cubemap
+          .filter(|cubemap| *cubemap < (scene.cubemaps().len() as u32).min(CUBEMAP_CAPACITY))
+          .unwrap_or(NO_CUBEMAP);
+        gpu_drawable.has_ambient_cube = drawable.ambient_cube.is_some() as u32;
+        gpu_drawable.is_in_sky_area = sky_clusters.zip(drawable.clusters.as_ref())
+          .is_some_and(|(sky_clusters, clusters)| clusters.iter().any(|cluster| ClusterVisibility::is_visible(sky_clusters, *cluster))) as u32;
+        let mesh = assets.get_model(drawable.model).and_then(|model| assets.get_mesh(model.mesh_handle()));
+        gpu_drawable.vertex_colors_offset = drawable.vertex_colors.zip(mesh)
+          .and_then(|(handle, mesh)| assets.get_vertex_colors(handle, mesh))
+          .map_or(NO_VERTEX_COLORS, |colors| colors.offset() / std::mem::size_of::<u32>() as u32);
+        gpu_drawable.skinning_offset = NO_SKINNING;
+        gpu_drawable.bone_palette_offset = 0;
+        let skinning = mesh.and_then(|mesh| mesh.skinning.as_ref()).zip(drawable.bone_palette.as_ref());
+        if let Some((skinning, bone_palette)) = skinning {
+          gpu_drawable.skinning_offset = skinning.offset() / std::mem::size_of::<u32>() as u32;
+          gpu_drawable.bone_palette_offset = bone_palettes.len() as u32;
+          bone_palettes.extend_from_slice(&bone_palette[..]);
+        }
+        if let Some(ambient_cube) = drawable.ambient_cube.as_ref() {
+          for (side, color) in ambient_cube.iter().enumerate() {
+            gpu_drawable.ambient_cube[side] = Vec4::new(color.x, color.y, color.z, 0f32);
+          }
+        }
         local.drawable_count += 1;
       }
 
       for part_index in model_entry.part_start .. (model_entry.part_start + model_entry.part_count) {
+        // Translucent parts get sorted on the CPU and drawn in the translucent pass.
+        let material_index = local.parts[part_index as usize].material_index;
+        if local.materials[material_index as usize].alpha_mode == AlphaMode::Blend as u32 {
+          continue;
+        }
         let draw_index = local.draw_count;
         let gpu_draw = &mut local.draws[draw_index as usize];
         gpu_draw.drawable_index = drawable_index;
@@ -277,7 +443,13 @@ pub(crate) fn upload<P: Platform>(
     std::ptr::copy(src_base.add(meshes_offset), dst_base.add(meshes_offset), align_up_to_cache_line(local.mesh_count as usize * std::mem::size_of::<GPUMesh>()));
     buffer.unmap_unsafe(true);
   }
-  buffer
+
+  // Bind a valid buffer even if there are no skinned drawables.
+  if bone_palettes.is_empty() {
+    bone_palettes.push(Matrix4::identity());
+  }
+  let bone_palette_buffer = cmd_buffer.upload_dynamic_data(&bone_palettes[..], BufferUsage::STORAGE);
+  (buffer, bone_palette_buffer)
 }
 
 fn align_up_to_cache_line(value: usize) -> usize {
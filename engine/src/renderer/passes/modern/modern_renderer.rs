@@ -4,7 +4,7 @@ use nalgebra::Vector3;
 use smallvec::SmallVec;
 use sourcerenderer_core::{Matrix4, Platform, Vec2UI, graphics::{Backend, Barrier, CommandBuffer, Device, Queue, Swapchain, SwapchainError, TextureRenderTargetView, BarrierSync, BarrierAccess, TextureLayout, BarrierTextureRange, BindingFrequency, WHOLE_BUFFER, BufferUsage}, Vec2, Vec3};
 
-use crate::{input::Input, renderer::{LateLatching, drawable::View, render_path::{RenderPath, SceneInfo, ZeroTextures, FrameInfo}, renderer_resources::{RendererResources, HistoryResourceEntry}, renderer_scene::RendererScene, passes::{blue_noise::BlueNoise, ssr::SsrPass, compositing::CompositingPass}, shader_manager::ShaderManager, renderer_assets::RendererAssets}};
+use crate::{input::Input, renderer::{LateLatching, drawable::View, render_path::{RenderPath, SceneInfo, ZeroTextures, FrameInfo}, renderer_resources::{RendererResources, HistoryResourceEntry}, renderer_scene::RendererScene, passes::{blue_noise::BlueNoise, ssr::SsrPass, compositing::CompositingPass, skybox::SkyboxPass, decal::DecalPass, detail_props::DetailPropPass, water::WaterPass, translucent::TranslucentPass, shadow_map::ShadowMapPass}, shader_manager::ShaderManager, renderer_assets::RendererAssets}};
 use crate::renderer::passes::fsr2::Fsr2Pass;
 use crate::renderer::passes::modern::motion_vectors::MotionVectorPass;
 
@@ -19,11 +19,17 @@ pub struct ModernRenderer<P: Platform> {
   geometry_draw_prep: DrawPrepPass,
   ssao: SsaoPass<P>,
   rt_passes: Option<RTPasses<P>>,
+  shadow_map: ShadowMapPass<P>,
   blue_noise: BlueNoise<P::GraphicsBackend>,
   hi_z_pass: HierarchicalZPass<P>,
   ssr_pass: SsrPass,
   visibility_buffer: VisibilityBufferPass,
+  skybox: SkyboxPass,
   shading_pass: ShadingPass<P>,
+  decals: DecalPass,
+  detail_props: DetailPropPass,
+  water: WaterPass,
+  translucent: TranslucentPass,
   compositing_pass: CompositingPass,
   motion_vector_pass: MotionVectorPass,
   anti_aliasing: AntiAliasing<P::GraphicsBackend>,
@@ -66,11 +72,18 @@ impl<P: Platform> ModernRenderer<P> {
       acceleration_structure_update: AccelerationStructureUpdatePass::<P>::new(device, &mut init_cmd_buffer),
       shadows: RTShadowPass::new::<P>(resolution, &mut barriers, shader_manager)
     });
+    let shadow_map = ShadowMapPass::<P>::new(device, &mut barriers, shader_manager);
     let visibility_buffer = VisibilityBufferPass::new::<P>(resolution, &mut barriers, shader_manager);
     let draw_prep = DrawPrepPass::new::<P>(&mut barriers, shader_manager);
     let hi_z_pass = HierarchicalZPass::<P>::new(device, &mut barriers, shader_manager, &mut init_cmd_buffer, VisibilityBufferPass::DEPTH_TEXTURE_NAME);
     let ssr_pass = SsrPass::new::<P>(resolution, &mut barriers, shader_manager, true);
+    let skybox = SkyboxPass::new::<P>(&mut barriers, shader_manager, resolution);
     let shading_pass = ShadingPass::<P>::new(device, resolution, &mut barriers, shader_manager, &mut init_cmd_buffer);
+    let decals = DecalPass::new::<P>(shader_manager, barriers.texture_info(ShadingPass::<P>::SHADING_TEXTURE_NAME).format);
+    let detail_props = DetailPropPass::new::<P>(shader_manager, barriers.texture_info(ShadingPass::<P>::SHADING_TEXTURE_NAME).format);
+    let shading_format = barriers.texture_info(ShadingPass::<P>::SHADING_TEXTURE_NAME).format;
+    let water = WaterPass::new::<P>(&mut barriers, shader_manager, resolution, shading_format);
+    let translucent = TranslucentPass::new::<P>(shader_manager, shading_format);
     let compositing_pass = CompositingPass::new::<P>(resolution, &mut barriers, shader_manager);
     let motion_vector_pass = MotionVectorPass::new::<P>(&mut barriers, resolution, shader_manager);
 
@@ -99,11 +112,17 @@ impl<P: Platform> ModernRenderer<P> {
       geometry_draw_prep: draw_prep,
       ssao,
       rt_passes,
+      shadow_map,
       blue_noise,
       hi_z_pass,
       ssr_pass,
       visibility_buffer,
+      skybox,
       shading_pass,
+      decals,
+      detail_props,
+      water,
+      translucent,
       compositing_pass,
       motion_vector_pass,
       anti_aliasing,
@@ -140,7 +159,7 @@ impl<P: Platform> ModernRenderer<P> {
       cluster_z_bias: f32,
       cluster_z_scale: f32,
       cluster_count: Vector3<u32>,
-      _padding: u32,
+      spot_light_count: u32,
       swapchain_transform: Matrix4,
       halton_point: Vec2,
       rt_size: Vec2UI,
@@ -151,7 +170,7 @@ impl<P: Platform> ModernRenderer<P> {
       cluster_z_bias,
       cluster_z_scale,
       cluster_count,
-      _padding: 0,
+      spot_light_count: scene.spot_lights().len() as u32,
       swapchain_transform: swapchain.transform(),
       halton_point: super::taa::scaled_halton_point(rendering_resolution.x, rendering_resolution.y, (frame % 8) as u32 + 1),
       rt_size: *rendering_resolution
@@ -161,11 +180,19 @@ impl<P: Platform> ModernRenderer<P> {
     #[derive(Debug, Clone)]
     struct PointLight {
       position: Vec3,
-      intensity: f32
+      intensity: f32,
+      color: Vec3,
+      range: f32,
+      attenuation: Vec3,
+      _padding: f32
     }
     let point_lights: SmallVec<[PointLight; 16]> = scene.point_lights().iter().map(|l| PointLight {
       position: l.position,
-      intensity: l.intensity
+      intensity: l.intensity,
+      color: l.color,
+      range: l.range,
+      attenuation: Vec3::new(l.attenuation.constant, l.attenuation.linear, l.attenuation.quadratic),
+      _padding: 0f32
     }).collect();
     let point_lights_buffer = cmd_buf.upload_dynamic_data(&point_lights, BufferUsage::CONSTANT);
     cmd_buf.bind_uniform_buffer(BindingFrequency::Frame, 6, &point_lights_buffer, 0, WHOLE_BUFFER);
@@ -173,14 +200,48 @@ impl<P: Platform> ModernRenderer<P> {
     #[derive(Debug, Clone)]
     struct DirectionalLight {
       direction: Vec3,
-      intensity: f32
+      intensity: f32,
+      color: Vec3,
+      _padding: f32
     }
     let directional_lights: SmallVec<[DirectionalLight; 16]> = scene.directional_lights().iter().map(|l| DirectionalLight {
       direction: l.direction,
-      intensity: l.intensity
+      intensity: l.intensity,
+      color: l.color,
+      _padding: 0f32
     }).collect();
     let directional_lights_buffer = cmd_buf.upload_dynamic_data(&directional_lights, BufferUsage::CONSTANT);
     cmd_buf.bind_uniform_buffer(BindingFrequency::Frame, 7, &directional_lights_buffer, 0, WHOLE_BUFFER);
+    let light_styles_buffer = cmd_buf.upload_dynamic_data(&scene.light_styles().intensities, BufferUsage::CONSTANT);
+    #[repr(C)]
+    #[derive(Debug, Clone)]
+    struct SpotLight {
+      position: Vec3,
+      intensity: f32,
+      direction: Vec3,
+      angle_scale: f32,
+      color: Vec3,
+      range: f32,
+      attenuation: Vec3,
+      angle_offset: f32
+    }
+    let spot_lights: SmallVec<[SpotLight; 16]> = scene.spot_lights().iter().map(|l| {
+      let cos_outer = l.outer_cone_angle.cos();
+      let angle_scale = 1f32 / (l.inner_cone_angle.cos() - cos_outer).max(0.001f32);
+      SpotLight {
+        position: l.position,
+        intensity: l.intensity,
+        direction: l.direction,
+        angle_scale,
+        color: l.color,
+        range: l.range,
+        attenuation: Vec3::new(l.attenuation.constant, l.attenuation.linear, l.attenuation.quadratic),
+        angle_offset: -cos_outer * angle_scale
+      }
+    }).collect();
+    let spot_lights_buffer = cmd_buf.upload_dynamic_data(&spot_lights, BufferUsage::CONSTANT);
+    cmd_buf.bind_uniform_buffer(BindingFrequency::Frame, 9, &spot_lights_buffer, 0, WHOLE_BUFFER);
+    cmd_buf.bind_uniform_buffer(BindingFrequency::Frame, 8, &light_styles_buffer, 0, WHOLE_BUFFER);
   }
 }
 
@@ -215,7 +276,7 @@ impl<P: Platform> RenderPath<P> for ModernRenderer<P> {
     let camera_buffer = late_latching.unwrap().buffer();
     let camera_history_buffer = late_latching.unwrap().history_buffer().unwrap();
 
-    let gpu_scene_buffer = super::gpu_scene::upload(&mut cmd_buf, scene.scene, 0 /* TODO */, assets);
+    let (gpu_scene_buffer, bone_palette_buffer) = super::gpu_scene::upload(&mut cmd_buf, scene.scene, 0 /* TODO */, assets);
 
     self.setup_frame(
       &mut cmd_buf,
@@ -241,8 +302,8 @@ impl<P: Platform> RenderPath<P> for ModernRenderer<P> {
     }
     self.hi_z_pass.execute(&mut cmd_buf, &self.barriers, shader_manager, VisibilityBufferPass::DEPTH_TEXTURE_NAME);
     self.geometry_draw_prep.execute(&mut cmd_buf, &self.barriers, scene.scene, main_view, shader_manager, assets);
-    self.visibility_buffer.execute(&mut cmd_buf, &self.barriers, scene.vertex_buffer, scene.index_buffer, shader_manager);
-    self.motion_vector_pass.execute(&mut cmd_buf, &self.barriers, shader_manager);
+    self.visibility_buffer.execute(&mut cmd_buf, &self.barriers, scene.vertex_buffer, scene.index_buffer, &bone_palette_buffer, shader_manager);
+    self.motion_vector_pass.execute(&mut cmd_buf, &self.barriers, scene.vertex_buffer, &bone_palette_buffer, shader_manager);
     self.clustering_pass.execute(&mut cmd_buf, resolution, main_view, &camera_buffer, &mut self.barriers, shader_manager);
     self.light_binning_pass.execute(&mut cmd_buf, scene.scene, &camera_buffer, &mut self.barriers, shader_manager);
     self.ssao.execute(&mut cmd_buf, &self.barriers, VisibilityBufferPass::DEPTH_TEXTURE_NAME, None, &camera_buffer, self.blue_noise.frame(frame_info.frame), self.blue_noise.sampler(), shader_manager, true);
@@ -251,8 +312,16 @@ impl<P: Platform> RenderPath<P> for ModernRenderer<P> {
       let blue_noise_sampler = &self.blue_noise.sampler();
       let acceleration_structure = rt_passes.acceleration_structure_update.acceleration_structure();
       rt_passes.shadows.execute(&mut cmd_buf, &self.barriers, shader_manager, VisibilityBufferPass::DEPTH_TEXTURE_NAME, acceleration_structure, blue_noise, blue_noise_sampler);
+    } else {
+      self.shadow_map.execute(&mut cmd_buf, scene, &self.barriers, shader_manager, assets);
     }
-    self.shading_pass.execute(&mut cmd_buf,  &self.device, scene.lightmap.unwrap(), zero_textures.zero_texture_view, &self.barriers, shader_manager);
+    self.skybox.execute(&mut cmd_buf, scene, &self.barriers, shader_manager, assets);
+    self.shading_pass.execute(&mut cmd_buf,  &self.device, scene.scene, scene.lightmap.unwrap(), zero_textures.zero_texture_view, &self.shadow_map, &bone_palette_buffer, &self.barriers, shader_manager, assets);
+    self.decals.execute(&mut cmd_buf, scene, &self.barriers, shader_manager, assets, ShadingPass::<P>::SHADING_TEXTURE_NAME, VisibilityBufferPass::DEPTH_TEXTURE_NAME);
+    self.detail_props.execute(&mut cmd_buf, scene, &self.barriers, shader_manager, assets, ShadingPass::<P>::SHADING_TEXTURE_NAME, VisibilityBufferPass::DEPTH_TEXTURE_NAME);
+    // The water doesn't write to the visibility buffer that the SSR pass reads, so it reflects the skybox instead.
+    self.water.execute(&mut cmd_buf, scene, &self.barriers, shader_manager, assets, ShadingPass::<P>::SHADING_TEXTURE_NAME, VisibilityBufferPass::DEPTH_TEXTURE_NAME, false);
+    self.translucent.execute(&mut cmd_buf, scene, &self.barriers, shader_manager, zero_textures, &self.shadow_map, assets, ShadingPass::<P>::SHADING_TEXTURE_NAME, VisibilityBufferPass::DEPTH_TEXTURE_NAME);
     self.ssr_pass.execute(&mut cmd_buf, &self.barriers, shader_manager, ShadingPass::<P>::SHADING_TEXTURE_NAME, VisibilityBufferPass::DEPTH_TEXTURE_NAME, true);
     self.compositing_pass.execute(&mut cmd_buf, &self.barriers, ShadingPass::<P>::SHADING_TEXTURE_NAME, shader_manager);
 
@@ -2,7 +2,8 @@ use sourcerenderer_core::graphics::{Backend, BarrierAccess, BarrierSync, Binding
                                     CommandBuffer, Format, PipelineBinding, TextureInfo,
                                     TextureLayout, TextureStorageView, TextureUsage,
                                     TextureViewInfo, Texture, TextureDimension,
-                                    SampleCount};
+                                    SampleCount, WHOLE_BUFFER};
+use std::sync::Arc;
 use sourcerenderer_core::{Platform, Vec2UI};
 use crate::renderer::passes::modern::VisibilityBufferPass;
 use crate::renderer::renderer_resources::{HistoryResourceEntry, RendererResources};
@@ -39,7 +40,14 @@ impl MotionVectorPass {
     }
   }
 
-  pub fn execute<P: Platform>(&mut self, cmd_buffer: &mut <P::GraphicsBackend as Backend>::CommandBuffer, resources: &RendererResources<P::GraphicsBackend>, shader_manager: &ShaderManager<P>) {
+  pub fn execute<P: Platform>(
+    &mut self,
+    cmd_buffer: &mut <P::GraphicsBackend as Backend>::CommandBuffer,
+    resources: &RendererResources<P::GraphicsBackend>,
+    vertex_buffer: &Arc<<P::GraphicsBackend as Backend>::Buffer>,
+    bone_palette_buffer: &Arc<<P::GraphicsBackend as Backend>::Buffer>,
+    shader_manager: &ShaderManager<P>
+  ) {
     let pipeline = shader_manager.get_compute_pipeline(self.pipeline);
 
     cmd_buffer.begin_label("Motion Vectors");
@@ -86,6 +94,8 @@ impl MotionVectorPass {
     cmd_buffer.bind_storage_texture(BindingFrequency::VeryFrequent, 0, &output_srv);
     cmd_buffer.bind_storage_texture(BindingFrequency::VeryFrequent, 1, &ids);
     cmd_buffer.bind_storage_texture(BindingFrequency::VeryFrequent, 2, &barycentrics);
+    cmd_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 3, vertex_buffer, 0, WHOLE_BUFFER);
+    cmd_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 4, bone_palette_buffer, 0, WHOLE_BUFFER);
     cmd_buffer.flush_barriers();
     cmd_buffer.finish_binding();
     cmd_buffer.dispatch((width + 7) / 8, (height + 7) / 8, 1);
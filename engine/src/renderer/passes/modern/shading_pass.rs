@@ -1,10 +1,10 @@
 use std::{sync::Arc, cell::Ref};
 
-use sourcerenderer_core::{graphics::{Backend, Device, TextureInfo, Format, SampleCount, TextureUsage, BarrierAccess, TextureLayout, TextureViewInfo, BarrierSync, CommandBuffer, PipelineBinding, WHOLE_BUFFER, BindingFrequency, Filter, AddressMode, SamplerInfo, TextureDimension}, Platform, Vec2UI};
+use sourcerenderer_core::{graphics::{Backend, Device, TextureInfo, Format, SampleCount, TextureUsage, BarrierAccess, TextureLayout, TextureViewInfo, BarrierSync, CommandBuffer, PipelineBinding, WHOLE_BUFFER, BindingFrequency, Filter, AddressMode, SamplerInfo, TextureDimension, BufferUsage}, Platform, Vec2UI};
 
-use crate::renderer::{renderer_resources::{RendererResources, HistoryResourceEntry}, renderer_assets::RendererTexture, passes::ssao::SsaoPass, shader_manager::{ComputePipelineHandle, ShaderManager}};
+use crate::renderer::{renderer_resources::{RendererResources, HistoryResourceEntry}, renderer_assets::{RendererAssets, RendererTexture}, renderer_scene::RendererScene, passes::{ssao::SsaoPass, skybox::SkyboxPass, shadow_map::ShadowMapPass}, shader_manager::{ComputePipelineHandle, ShaderManager}};
 
-use super::{visibility_buffer::VisibilityBufferPass, rt_shadows::RTShadowPass};
+use super::{visibility_buffer::VisibilityBufferPass, rt_shadows::RTShadowPass, gpu_scene::CUBEMAP_CAPACITY};
 
 
 pub struct ShadingPass<P: Platform> {
@@ -41,7 +41,8 @@ impl<P: Platform> ShadingPass<P> {
       mip_levels: 1,
       array_length: 1,
       samples: SampleCount::Samples1,
-      usage: TextureUsage::STORAGE | TextureUsage::SAMPLED,
+      // The decals and the water get drawn on top of the shaded image, the water pass copies it for refraction.
+      usage: TextureUsage::STORAGE | TextureUsage::SAMPLED | TextureUsage::RENDER_TARGET | TextureUsage::BLIT_SRC,
       supports_srgb: true,
     }, false);
 
@@ -55,10 +56,14 @@ impl<P: Platform> ShadingPass<P> {
     &mut self,
     cmd_buffer: &mut <P::GraphicsBackend as Backend>::CommandBuffer,
     device: &<P::GraphicsBackend as Backend>::Device,
+    scene: &RendererScene<P::GraphicsBackend>,
     lightmap: &RendererTexture<P::GraphicsBackend>,
     zero_texture_view: &Arc<<P::GraphicsBackend as Backend>::TextureSamplingView>,
+    shadow_map: &ShadowMapPass<P>,
+    bone_palette_buffer: &Arc<<P::GraphicsBackend as Backend>::Buffer>,
     resources: &RendererResources<P::GraphicsBackend>,
-    shader_manager: &ShaderManager<P>
+    shader_manager: &ShaderManager<P>,
+    assets: &RendererAssets<P>
   ) {
     let (width, height) = {
       let info = resources.texture_info(Self::SHADING_TEXTURE_NAME);
@@ -136,6 +141,51 @@ impl<P: Platform> ShadingPass<P> {
       zero_texture_view
     };
 
+    let shadow_map_view = resources.access_sampling_view(
+      cmd_buffer,
+      ShadowMapPass::<P>::SHADOW_MAP_NAME,
+      BarrierSync::COMPUTE_SHADER,
+      BarrierAccess::SAMPLING_READ,
+      TextureLayout::Sampled,
+      false,
+      &ShadowMapPass::<P>::sampling_view_info(),
+      HistoryResourceEntry::Current
+    );
+
+    let depth = resources.access_sampling_view(
+      cmd_buffer,
+      VisibilityBufferPass::DEPTH_TEXTURE_NAME,
+      BarrierSync::COMPUTE_SHADER,
+      BarrierAccess::SAMPLING_READ,
+      TextureLayout::Sampled,
+      false,
+      &TextureViewInfo::default(),
+      HistoryResourceEntry::Current
+    );
+
+    let skybox = resources.access_sampling_view(
+      cmd_buffer,
+      SkyboxPass::SKYBOX_TEXTURE_NAME,
+      BarrierSync::COMPUTE_SHADER,
+      BarrierAccess::SAMPLING_READ,
+      TextureLayout::Sampled,
+      false,
+      &TextureViewInfo::default(),
+      HistoryResourceEntry::Current
+    );
+
+    // The unused entries of the array get filled with a black cube map.
+    let cubemaps: Vec<_> = (0 .. CUBEMAP_CAPACITY as usize)
+      .map(|index| {
+        let texture = scene.cubemaps().get(index)
+          .map_or_else(|| assets.placeholder_cube(), |handle| assets.get_cube_texture(*handle));
+        (&texture.view, resources.linear_sampler())
+      })
+      .collect();
+    for (view, _) in &cubemaps {
+      cmd_buffer.track_texture_view(view);
+    }
+
     let pipeline = shader_manager.get_compute_pipeline(self.pipeline);
     cmd_buffer.set_pipeline(PipelineBinding::Compute(&pipeline));
     cmd_buffer.bind_storage_texture(BindingFrequency::VeryFrequent, 1, &ids);
@@ -146,6 +196,14 @@ impl<P: Platform> ShadingPass<P> {
     cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 6, &lightmap.view, resources.linear_sampler());
     cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 7, shadows, resources.linear_sampler());
     cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 8, &ssao, resources.linear_sampler());
+    cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 9, &depth, resources.linear_sampler());
+    cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 10, &skybox, resources.linear_sampler());
+    cmd_buffer.bind_sampling_view_and_sampler_array(BindingFrequency::VeryFrequent, 11, &cubemaps);
+    cmd_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 12, assets.vertex_buffer(), 0, WHOLE_BUFFER);
+    cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 13, &shadow_map_view, shadow_map.sampler());
+    let shadow_cascades_buffer = cmd_buffer.upload_dynamic_data(&[*shadow_map.cascades()], BufferUsage::CONSTANT);
+    cmd_buffer.bind_uniform_buffer(BindingFrequency::VeryFrequent, 14, &shadow_cascades_buffer, 0, WHOLE_BUFFER);
+    cmd_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 15, bone_palette_buffer, 0, WHOLE_BUFFER);
 
     cmd_buffer.flush_barriers();
     cmd_buffer.finish_binding();
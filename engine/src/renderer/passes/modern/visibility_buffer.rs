@@ -1,6 +1,7 @@
-use sourcerenderer_core::{graphics::{AttachmentBlendInfo, AttachmentInfo, Backend as GraphicsBackend, BlendInfo, CommandBuffer, CompareFunc, CullMode, DepthStencilAttachmentRef, DepthStencilInfo, FillMode, Format, FrontFace, InputAssemblerElement, InputRate, LoadOp, LogicOp, OutputAttachmentRef, PipelineBinding, PrimitiveType, RasterizerInfo, RenderPassAttachment, RenderPassAttachmentView, RenderPassBeginInfo, RenderPassInfo, RenderpassRecordingMode, SampleCount, Scissor, ShaderInputElement, StencilInfo, StoreOp, SubpassInfo, Texture, TextureInfo, TextureRenderTargetView, TextureViewInfo, TextureUsage, VertexLayoutInfo, Viewport, TextureLayout, BarrierSync, BarrierAccess, IndexFormat, TextureDimension}};
+use sourcerenderer_core::{graphics::{AttachmentBlendInfo, AttachmentInfo, Backend as GraphicsBackend, BindingFrequency, BlendInfo, CommandBuffer, CompareFunc, CullMode, DepthStencilAttachmentRef, DepthStencilInfo, FillMode, Format, FrontFace, LoadOp, LogicOp, OutputAttachmentRef, PipelineBinding, PrimitiveType, RasterizerInfo, RenderPassAttachment, RenderPassAttachmentView, RenderPassBeginInfo, RenderPassInfo, RenderpassRecordingMode, SampleCount, Scissor, StencilInfo, StoreOp, SubpassInfo, Texture, TextureInfo, TextureRenderTargetView, TextureViewInfo, TextureUsage, VertexLayoutInfo, Viewport, TextureLayout, BarrierSync, BarrierAccess, IndexFormat, TextureDimension, WHOLE_BUFFER}};
 use std::sync::Arc;
 use crate::renderer::{renderer_resources::{RendererResources, HistoryResourceEntry}, shader_manager::{GraphicsPipelineInfo, ShaderManager, GraphicsPipelineHandle}};
+use crate::renderer::{Vertex, VertexAttribute};
 use sourcerenderer_core::{Platform, Vec2, Vec2I, Vec2UI};
 
 use super::{draw_prep::DrawPrepPass, gpu_scene::DRAW_CAPACITY};
@@ -52,7 +53,7 @@ impl VisibilityBufferPass {
       mip_levels: 1,
       array_length: 1,
       samples: SampleCount::Samples1,
-      usage: TextureUsage::SAMPLED | TextureUsage::DEPTH_STENCIL,
+      usage: TextureUsage::SAMPLED | TextureUsage::DEPTH_STENCIL | TextureUsage::BLIT_SRC,
       supports_srgb: false,
     };
     resources.create_texture(Self::DEPTH_TEXTURE_NAME, &depth_texture_info, true);
@@ -62,23 +63,11 @@ impl VisibilityBufferPass {
       fs: Some("shaders/visibility_buffer.frag.spv"),
       primitive_type: PrimitiveType::Triangles,
       vertex_layout: VertexLayoutInfo {
-        input_assembler: &[
-          InputAssemblerElement {
-            binding: 0,
-            stride: 64,
-            input_rate: InputRate::PerVertex
-          }
-        ],
-        shader_inputs: &[
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 0,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 0,
-            format: Format::RGB32Float
-          },
-        ]
+        input_assembler: &Vertex::INPUT_ASSEMBLER,
+        shader_inputs: &Vertex::shader_inputs(&[
+          VertexAttribute::Position,
+          VertexAttribute::Uv
+        ])
       },
       rasterizer: RasterizerInfo {
         fill_mode: FillMode::Fill,
@@ -155,6 +144,7 @@ impl VisibilityBufferPass {
     resources: &RendererResources<P::GraphicsBackend>,
     vertex_buffer: &Arc<<P::GraphicsBackend as GraphicsBackend>::Buffer>,
     index_buffer: &Arc<<P::GraphicsBackend as GraphicsBackend>::Buffer>,
+    bone_palette_buffer: &Arc<<P::GraphicsBackend as GraphicsBackend>::Buffer>,
     shader_manager: &ShaderManager<P>
   ) {
     cmd_buffer.begin_label("Visibility Buffer pass");
@@ -252,6 +242,10 @@ impl VisibilityBufferPass {
     cmd_buffer.set_vertex_buffer(vertex_buffer, 0);
     cmd_buffer.set_index_buffer(index_buffer, 0, IndexFormat::U32);
 
+    // Alpha tested materials sample their albedo to discard.
+    cmd_buffer.bind_sampler(BindingFrequency::VeryFrequent, 0, resources.repeat_sampler());
+    cmd_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 1, vertex_buffer, 0, WHOLE_BUFFER);
+    cmd_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 2, bone_palette_buffer, 0, WHOLE_BUFFER);
     cmd_buffer.finish_binding();
     cmd_buffer.draw_indexed_indirect(&draw_buffer, 4, &draw_buffer, 0, DRAW_CAPACITY, 20);
 
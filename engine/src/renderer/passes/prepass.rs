@@ -1,12 +1,13 @@
 use sourcerenderer_core::graphics::{OutputAttachmentRef, Queue, RenderPassAttachment, RenderPassAttachmentView, RenderPassBeginInfo, RenderpassRecordingMode, TextureViewInfo, TextureLayout, BarrierAccess, BarrierSync, IndexFormat, TextureRenderTargetView, Texture, WHOLE_BUFFER, TextureDimension};
-use sourcerenderer_core::graphics::{AttachmentBlendInfo, AttachmentInfo, Backend as GraphicsBackend, BindingFrequency, BlendInfo, BufferUsage, CommandBuffer, CompareFunc, CullMode, DepthStencilAttachmentRef, DepthStencilInfo, Device, FillMode, Format, FrontFace, InputAssemblerElement, InputRate, LoadOp, LogicOp, PipelineBinding, PrimitiveType, RasterizerInfo, RenderPassInfo, SampleCount, Scissor, ShaderInputElement, ShaderType, StencilInfo, StoreOp, SubpassInfo, TextureInfo, TextureUsage, VertexLayoutInfo, Viewport};
+use sourcerenderer_core::graphics::{AttachmentBlendInfo, AttachmentInfo, Backend as GraphicsBackend, BindingFrequency, BlendInfo, BufferUsage, CommandBuffer, CompareFunc, CullMode, DepthStencilAttachmentRef, DepthStencilInfo, Device, FillMode, Format, FrontFace, LoadOp, LogicOp, PipelineBinding, PrimitiveType, RasterizerInfo, RenderPassInfo, SampleCount, Scissor, ShaderType, StencilInfo, StoreOp, SubpassInfo, TextureInfo, TextureUsage, VertexLayoutInfo, Viewport};
 use std::sync::Arc;
 use crate::renderer::passes::taa::scaled_halton_point;
-use crate::renderer::renderer_assets::RendererAssets;
+use crate::renderer::renderer_assets::{RendererAssets, RendererMaterialValue, AlphaMode};
 use crate::renderer::renderer_resources::{RendererResources, HistoryResourceEntry};
 use crate::renderer::shader_manager::{ShaderManager, GraphicsPipelineInfo, GraphicsPipelineHandle};
 use crate::renderer::{RendererScene, drawable::View};
-use sourcerenderer_core::{Matrix4, Platform, Vec2, Vec2I, Vec2UI};
+use crate::renderer::{Vertex, VertexAttribute};
+use sourcerenderer_core::{Matrix4, Platform, Vec2, Vec2I, Vec2UI, Vec4};
 use rayon::prelude::*;
 
 #[derive(Clone, Copy)]
@@ -21,6 +22,12 @@ struct PrepassModelCB {
   model: Matrix4,
   old_model: Matrix4
 }
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct PrepassAlphaTestCB {
+  albedo_color: Vec4,
+  alpha_cutoff: f32
+}
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 struct FrameData {
@@ -29,7 +36,10 @@ struct FrameData {
 }
 
 pub struct Prepass {
-  pipeline: GraphicsPipelineHandle
+  pipeline: GraphicsPipelineHandle,
+  skinned_pipeline: GraphicsPipelineHandle,
+  alpha_test_pipeline: GraphicsPipelineHandle,
+  skinned_alpha_test_pipeline: GraphicsPipelineHandle
 }
 
 impl Prepass {
@@ -53,7 +63,7 @@ impl Prepass {
       mip_levels: 1,
       array_length: 1,
       samples: SampleCount::Samples1,
-      usage: TextureUsage::DEPTH_STENCIL | TextureUsage::SAMPLED,
+      usage: TextureUsage::DEPTH_STENCIL | TextureUsage::SAMPLED | TextureUsage::BLIT_SRC,
       supports_srgb: false,
     };
     resources.create_texture(Self::DEPTH_TEXTURE_NAME, &depth_info, true);
@@ -89,31 +99,12 @@ impl Prepass {
       fs: Some("shaders/prepass.frag.spv"),
       primitive_type: PrimitiveType::Triangles,
       vertex_layout: VertexLayoutInfo {
-        input_assembler: &[
-          InputAssemblerElement {
-            binding: 0,
-            stride: 64,
-            input_rate: InputRate::PerVertex
-          }
-        ],
-        shader_inputs: &[
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 0,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 0,
-            format: Format::RGB32Float
-          },
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 1,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 16,
-            format: Format::RGB32Float
-          }
-        ]
+        input_assembler: &Vertex::INPUT_ASSEMBLER,
+        shader_inputs: &Vertex::shader_inputs(&[
+          VertexAttribute::Position,
+          VertexAttribute::Normal,
+          VertexAttribute::Uv
+        ])
       },
       rasterizer: RasterizerInfo {
         fill_mode: FillMode::Fill,
@@ -142,7 +133,7 @@ impl Prepass {
         ]
       }
     };
-    let pipeline = shader_manager.request_graphics_pipeline(&pipeline_info, &RenderPassInfo {
+    let render_pass_info = RenderPassInfo {
       attachments: &[
         AttachmentInfo {
           format: Format::RG32Float,
@@ -176,10 +167,30 @@ impl Prepass {
           })
         }
       ],
-    }, 0);
+    };
+    let pipeline = shader_manager.request_graphics_pipeline(&pipeline_info, &render_pass_info, 0);
+    let skinned_pipeline_info = GraphicsPipelineInfo {
+      vs: "shaders/prepass_skinned.vert.spv",
+      ..pipeline_info
+    };
+    let skinned_pipeline = shader_manager.request_graphics_pipeline(&skinned_pipeline_info, &render_pass_info, 0);
+    let alpha_test_pipeline_info = GraphicsPipelineInfo {
+      vs: "shaders/prepass.vert.spv",
+      fs: Some("shaders/prepass_alpha_test.frag.spv"),
+      ..skinned_pipeline_info
+    };
+    let alpha_test_pipeline = shader_manager.request_graphics_pipeline(&alpha_test_pipeline_info, &render_pass_info, 0);
+    let skinned_alpha_test_pipeline_info = GraphicsPipelineInfo {
+      vs: "shaders/prepass_skinned.vert.spv",
+      ..alpha_test_pipeline_info
+    };
+    let skinned_alpha_test_pipeline = shader_manager.request_graphics_pipeline(&skinned_alpha_test_pipeline_info, &render_pass_info, 0);
 
     Self {
-      pipeline
+      pipeline,
+      skinned_pipeline,
+      alpha_test_pipeline,
+      skinned_alpha_test_pipeline
     }
   }
 
@@ -284,6 +295,9 @@ impl Prepass {
     const CHUNK_SIZE: usize = 128;
     let chunks = view.drawable_parts.par_chunks(CHUNK_SIZE);
     let pipeline = shader_manager.get_graphics_pipeline(self.pipeline);
+    let skinned_pipeline = shader_manager.get_graphics_pipeline(self.skinned_pipeline);
+    let alpha_test_pipeline = shader_manager.get_graphics_pipeline(self.alpha_test_pipeline);
+    let skinned_alpha_test_pipeline = shader_manager.get_graphics_pipeline(self.skinned_alpha_test_pipeline);
     let inner_cmd_buffers: Vec<<P::GraphicsBackend as GraphicsBackend>::CommandBufferSubmission> = chunks.map(|chunk| {
       let mut command_buffer = device.graphics_queue().create_inner_command_buffer(inheritance);
 
@@ -303,6 +317,8 @@ impl Prepass {
       command_buffer.bind_uniform_buffer(BindingFrequency::Frequent, 0, camera_buffer, 0, WHOLE_BUFFER);
       command_buffer.bind_uniform_buffer(BindingFrequency::Frequent, 1, camera_history_buffer, 0, WHOLE_BUFFER);
       command_buffer.finish_binding();
+      // (skinned, alpha tested)
+      let mut current_pipeline = (false, false);
 
       for part in chunk.iter() {
         let drawable = &static_drawables[part.drawable_index];
@@ -310,11 +326,6 @@ impl Prepass {
           command_buffer.begin_label(&format!("Drawable {}", part.drawable_index));
         }
 
-        command_buffer.upload_dynamic_data_inline(&[PrepassModelCB {
-          model: drawable.transform,
-          old_model: drawable.old_transform
-        }], ShaderType::VertexShader);
-
         let model = assets.get_model(drawable.model);
         if model.is_none() {
           log::info!("Skipping draw because of missing model");
@@ -328,6 +339,52 @@ impl Prepass {
         }
         let mesh = mesh.unwrap();
 
+        let material = assets.get_material(model.material_handles()[part.part_index]);
+        let is_alpha_tested = material.alpha_mode() == AlphaMode::Mask;
+
+        // The pipeline has to be set before the push constants get uploaded.
+        let skinning = mesh.skinning.as_ref().zip(drawable.bone_palette.as_ref());
+        if (skinning.is_some(), is_alpha_tested) != current_pipeline {
+          current_pipeline = (skinning.is_some(), is_alpha_tested);
+          command_buffer.set_pipeline(PipelineBinding::Graphics(match current_pipeline {
+            (false, false) => &pipeline,
+            (true, false) => &skinned_pipeline,
+            (false, true) => &alpha_test_pipeline,
+            (true, true) => &skinned_alpha_test_pipeline
+          }));
+        }
+        if let Some((skinning, bone_palette)) = skinning {
+          command_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 4, skinning.buffer(), skinning.offset() as usize, skinning.size() as usize);
+          let bone_palette_buffer = command_buffer.upload_dynamic_data(&bone_palette[..], BufferUsage::STORAGE);
+          command_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 5, &bone_palette_buffer, 0, WHOLE_BUFFER);
+        }
+        if is_alpha_tested {
+          let (albedo_view, albedo_color) = match material.get("albedo") {
+            Some(RendererMaterialValue::Texture(handle)) => {
+              let frame = match material.get("albedo_frame") {
+                Some(RendererMaterialValue::Float(frame)) => *frame as u32,
+                _ => 0
+              };
+              (assets.get_texture(*handle).frame_view(frame), Vec4::new(1f32, 1f32, 1f32, 1f32))
+            }
+            Some(RendererMaterialValue::Vec4(color)) => (&assets.placeholder_texture().view, *color),
+            _ => (&assets.placeholder_texture().view, Vec4::new(1f32, 1f32, 1f32, 1f32))
+          };
+          command_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 0, albedo_view, resources.repeat_sampler());
+          command_buffer.track_texture_view(albedo_view);
+          let alpha_test_cb = command_buffer.upload_dynamic_data(&[PrepassAlphaTestCB {
+            albedo_color,
+            alpha_cutoff: material.alpha_cutoff()
+          }], BufferUsage::CONSTANT);
+          command_buffer.bind_uniform_buffer(BindingFrequency::VeryFrequent, 1, &alpha_test_cb, 0, WHOLE_BUFFER);
+        }
+        command_buffer.finish_binding();
+
+        command_buffer.upload_dynamic_data_inline(&[PrepassModelCB {
+          model: drawable.transform,
+          old_model: drawable.old_transform
+        }], ShaderType::VertexShader);
+
         command_buffer.set_vertex_buffer(mesh.vertices.buffer(), mesh.vertices.offset() as usize);
         if let Some(indices) = mesh.indices.as_ref() {
           command_buffer.set_index_buffer(indices.buffer(), indices.offset() as usize, IndexFormat::U32);
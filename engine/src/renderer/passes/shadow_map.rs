@@ -1,116 +1,378 @@
-use std::{sync::Arc, path::Path, io::Read};
+use std::sync::Arc;
 
-use sourcerenderer_core::{graphics::{Backend, TextureInfo, TextureUsage, SampleCount, Format, TextureDimension, Device, GraphicsPipelineInfo, RenderPassInfo, RasterizerInfo, FillMode, CullMode, FrontFace, DepthStencilInfo, CompareFunc, StencilInfo, AttachmentInfo, SubpassInfo, DepthStencilAttachmentRef, BlendInfo, LogicOp, PrimitiveType, ShaderType, VertexLayoutInfo, ShaderInputElement, InputAssemblerElement, InputRate, BarrierSync, TextureViewInfo, TextureLayout, BarrierAccess}, Platform, platform::IO};
+use nalgebra::Point3;
+use sourcerenderer_core::{Matrix4, Platform, Vec2, Vec2I, Vec2UI, Vec3, Vec4};
+use sourcerenderer_core::graphics::{AddressMode, Backend as GraphicsBackend, BarrierAccess, BarrierSync, BindingFrequency, BlendInfo, BufferUsage, CommandBuffer, CompareFunc, CullMode, DepthStencilAttachmentRef, DepthStencilInfo, Device, FillMode, Filter, Format, FrontFace, IndexFormat, LoadOp, LogicOp, PipelineBinding, PrimitiveType, RasterizerInfo, RenderPassAttachment, RenderPassAttachmentView, RenderPassBeginInfo, RenderPassInfo, RenderpassRecordingMode, AttachmentInfo, SampleCount, SamplerInfo, Scissor, ShaderType, StencilInfo, StoreOp, SubpassInfo, TextureDimension, TextureInfo, TextureLayout, TextureUsage, TextureViewInfo, VertexLayoutInfo, Viewport, WHOLE_BUFFER};
 
-use crate::renderer::{renderer_resources::{RendererResources, HistoryResourceEntry}, Vertex};
+use crate::renderer::render_path::SceneInfo;
+use crate::renderer::renderer_assets::{RendererAssets, AlphaMode};
+use crate::renderer::renderer_resources::{RendererResources, HistoryResourceEntry};
+use crate::renderer::shader_manager::{ShaderManager, GraphicsPipelineInfo, GraphicsPipelineHandle};
+use crate::renderer::{Vertex, VertexAttribute};
 
-pub struct ShadowMapPass<B: Backend> {
-  pipeline: Arc<B::GraphicsPipeline>,
+pub const SHADOW_CASCADE_COUNT: usize = 4;
+
+/// Matches ShadowCascades in shadow_map.inc.glsl.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct ShadowCascades {
+  view_proj: [Matrix4; SHADOW_CASCADE_COUNT],
+  split_depths: Vec4,
+  texel_sizes: Vec4,
+  cascade_count: u32,
+  _padding: [u32; 3]
+}
+
+impl Default for ShadowCascades {
+  fn default() -> Self {
+    Self {
+      view_proj: [Matrix4::identity(); SHADOW_CASCADE_COUNT],
+      split_depths: Vec4::zeros(),
+      texel_sizes: Vec4::zeros(),
+      cascade_count: 0,
+      _padding: [0; 3]
+    }
+  }
+}
+
+/// Renders cascaded shadow maps for the first directional light.
+/// It's the fallback for GPUs that can't trace shadow rays, the shading passes sample it as a 2D array with one layer per cascade.
+pub struct ShadowMapPass<P: Platform> {
+  sampler: Arc<<P::GraphicsBackend as GraphicsBackend>::Sampler>,
+  pipeline: GraphicsPipelineHandle,
+  skinned_pipeline: GraphicsPipelineHandle,
+  cascades: ShadowCascades
 }
 
-impl<B: Backend> ShadowMapPass<B> {
+impl<P: Platform> ShadowMapPass<P> {
   pub const SHADOW_MAP_NAME: &'static str = "ShadowMap";
-  pub fn new<P: Platform>(device: &Arc<B::Device>, resources: &mut RendererResources<B>, init_cmd_buffer: &mut B::CommandBuffer) -> Self {
-    resources.create_texture(&Self::SHADOW_MAP_NAME, &TextureInfo {
+
+  const RESOLUTION: u32 = 2048;
+  /// Nothing beyond this distance to the camera receives shadows.
+  const MAX_DISTANCE: f32 = 100f32;
+  /// Weight of the logarithmic split scheme, the rest are uniform splits.
+  const SPLIT_LAMBDA: f32 = 0.75f32;
+
+  pub fn new(
+    device: &Arc<<P::GraphicsBackend as GraphicsBackend>::Device>,
+    resources: &mut RendererResources<P::GraphicsBackend>,
+    shader_manager: &mut ShaderManager<P>
+  ) -> Self {
+    resources.create_texture(Self::SHADOW_MAP_NAME, &TextureInfo {
       dimension: TextureDimension::Dim2D,
       format: Format::D24,
-      width: 4096,
-      height: 4096,
+      width: Self::RESOLUTION,
+      height: Self::RESOLUTION,
       depth: 1,
       mip_levels: 1,
-      array_length: 1,
+      array_length: SHADOW_CASCADE_COUNT as u32,
       samples: SampleCount::Samples1,
       usage: TextureUsage::DEPTH_STENCIL | TextureUsage::SAMPLED,
       supports_srgb: false,
     }, false);
 
-    let vertex_shader = {
-      let mut file = <P::IO as IO>::open_asset(Path::new("shaders").join(Path::new("shadow_map.vert.spv"))).unwrap();
-      let mut bytes: Vec<u8> = Vec::new();
-      file.read_to_end(&mut bytes).unwrap();
-      device.create_shader(ShaderType::VertexShader, &bytes, Some("shadow_map.vert.spv"))
-    };
+    let sampler = device.create_sampler(&SamplerInfo {
+      mag_filter: Filter::Linear,
+      min_filter: Filter::Linear,
+      mip_filter: Filter::Nearest,
+      address_mode_u: AddressMode::ClampToEdge,
+      address_mode_v: AddressMode::ClampToEdge,
+      address_mode_w: AddressMode::ClampToEdge,
+      mip_bias: 0.0,
+      max_anisotropy: 0.0,
+      compare_op: Some(CompareFunc::LessEqual),
+      min_lod: 0.0,
+      max_lod: Some(0.0),
+    });
 
-    let pipeline = device.create_graphics_pipeline(
-      &GraphicsPipelineInfo {
-        vs: &vertex_shader,
-        fs: None,
-        vertex_layout: VertexLayoutInfo {
-          shader_inputs: &[
-            ShaderInputElement {
-              input_assembler_binding: 0,
-              location_vk_mtl: 0,
-              semantic_name_d3d: "pos".to_string(),
-              semantic_index_d3d: 0,
-              offset: 0,
-              format: Format::RGB32Float,
-            }
-          ],
-          input_assembler: &[InputAssemblerElement {
-            binding: 0,
-            input_rate: InputRate::PerVertex,
-            stride: std::mem::size_of::<Vertex>(),
-        }],
-        },
-        rasterizer: RasterizerInfo {
-          fill_mode: FillMode::Fill,
-          cull_mode: CullMode::Back,
-          front_face: FrontFace::CounterClockwise,
-          sample_count: SampleCount::Samples1,
-        },
-        depth_stencil: DepthStencilInfo {
-          depth_test_enabled: true,
-          depth_write_enabled: true,
-          depth_func: CompareFunc::Less,
-          stencil_enable: false,
-          stencil_read_mask: 0,
-          stencil_write_mask: 0,
-          stencil_front: StencilInfo::default(),
-          stencil_back: StencilInfo::default(),
-        },
-        blend: BlendInfo {
-          alpha_to_coverage_enabled: false,
-          logic_op_enabled: false,
-          logic_op: LogicOp::And,
-          attachments: &[],
-          constants: [0f32; 4],
-      },
+    let pipeline_info: GraphicsPipelineInfo = GraphicsPipelineInfo {
+      vs: "shaders/shadow_map.vert.spv",
+      fs: None,
       primitive_type: PrimitiveType::Triangles,
-    }, &RenderPassInfo {
+      vertex_layout: VertexLayoutInfo {
+        input_assembler: &Vertex::INPUT_ASSEMBLER,
+        shader_inputs: &Vertex::shader_inputs(&[
+          VertexAttribute::Position
+        ])
+      },
+      rasterizer: RasterizerInfo {
+        fill_mode: FillMode::Fill,
+        // Lots of world geometry is single sided.
+        cull_mode: CullMode::None,
+        front_face: FrontFace::Clockwise,
+        sample_count: SampleCount::Samples1
+      },
+      depth_stencil: DepthStencilInfo {
+        depth_test_enabled: true,
+        depth_write_enabled: true,
+        depth_func: CompareFunc::Less,
+        stencil_enable: false,
+        stencil_read_mask: 0u8,
+        stencil_write_mask: 0u8,
+        stencil_front: StencilInfo::default(),
+        stencil_back: StencilInfo::default()
+      },
+      blend: BlendInfo {
+        alpha_to_coverage_enabled: false,
+        logic_op_enabled: false,
+        logic_op: LogicOp::And,
+        constants: [0f32, 0f32, 0f32, 0f32],
+        attachments: &[]
+      }
+    };
+    let render_pass_info = RenderPassInfo {
       attachments: &[
         AttachmentInfo {
           format: Format::D24,
           samples: SampleCount::Samples1,
         }
       ],
-      subpasses: &[SubpassInfo {
-        input_attachments: &[],
-        output_color_attachments: &[],
-        depth_stencil_attachment: Some(DepthStencilAttachmentRef {
-          index: 0,
-          read_only: false,
-        }),
-      }],
-    }, 0, Some("ShadowMap"));
+      subpasses: &[
+        SubpassInfo {
+          input_attachments: &[],
+          output_color_attachments: &[],
+          depth_stencil_attachment: Some(DepthStencilAttachmentRef {
+            index: 0,
+            read_only: false
+          })
+        }
+      ]
+    };
+    let pipeline = shader_manager.request_graphics_pipeline(&pipeline_info, &render_pass_info, 0);
+    let skinned_pipeline_info = GraphicsPipelineInfo {
+      vs: "shaders/shadow_map_skinned.vert.spv",
+      ..pipeline_info
+    };
+    let skinned_pipeline = shader_manager.request_graphics_pipeline(&skinned_pipeline_info, &render_pass_info, 0);
 
     Self {
-      pipeline
+      sampler,
+      pipeline,
+      skinned_pipeline,
+      cascades: ShadowCascades::default()
+    }
+  }
+
+  /// The cascades that were rendered last, has a cascade count of 0 if there was no directional light.
+  pub fn cascades(&self) -> &ShadowCascades {
+    &self.cascades
+  }
+
+  /// Comparison sampler for sampler2DArrayShadow.
+  pub fn sampler(&self) -> &Arc<<P::GraphicsBackend as GraphicsBackend>::Sampler> {
+    &self.sampler
+  }
+
+  /// Covers all cascades.
+  pub fn sampling_view_info() -> TextureViewInfo {
+    TextureViewInfo {
+      array_layer_length: SHADOW_CASCADE_COUNT as u32,
+      ..Default::default()
     }
   }
 
-  pub fn execute(cmd_buffer: &mut B::CommandBuffer, resources: &RendererResources<B>, gpu_driven: bool) {
-    resources.access_depth_stencil_view(cmd_buffer,
-      Self::SHADOW_MAP_NAME,
-      BarrierSync::EARLY_DEPTH | BarrierSync::LATE_DEPTH,
-      BarrierAccess::DEPTH_STENCIL_READ | BarrierAccess::DEPTH_STENCIL_WRITE,
-      TextureLayout::DepthStencilReadWrite,
-      true,
-      &TextureViewInfo {
-        base_mip_level: 0,
-        mip_level_length: 1,
-        base_array_layer: 0,
-        array_layer_length: 1,
-        format: None,
-    }, HistoryResourceEntry::Current);
+  #[profiling::function]
+  pub(super) fn execute(
+    &mut self,
+    cmd_buffer: &mut <P::GraphicsBackend as GraphicsBackend>::CommandBuffer,
+    scene: &SceneInfo<P::GraphicsBackend>,
+    resources: &RendererResources<P::GraphicsBackend>,
+    shader_manager: &ShaderManager<P>,
+    assets: &RendererAssets<P>
+  ) {
+    let light = if let Some(light) = scene.scene.directional_lights().first() {
+      light
+    } else {
+      self.cascades = ShadowCascades::default();
+      return;
+    };
+
+    cmd_buffer.begin_label("Shadow maps");
+    let view = &scene.views[scene.active_view_index];
+    let static_drawables = scene.scene.static_drawables();
+
+    let light_direction = light.direction.normalize();
+    let up = if light_direction.y.abs() > 0.99f32 { Vec3::new(0f32, 0f32, 1f32) } else { Vec3::new(0f32, 1f32, 0f32) };
+    let light_view = Matrix4::look_at_lh(&Point3::origin(), &Point3::from(light_direction), &up);
+
+    // Light space bounds of every caster, shared by all cascades.
+    let casters: Vec<(usize, Vec3, Vec3)> = static_drawables.iter()
+      .enumerate()
+      .filter(|(_, drawable)| drawable.cast_shadows)
+      .filter_map(|(index, drawable)| {
+        let model = assets.get_model(drawable.model)?;
+        let mesh = assets.get_mesh(model.mesh_handle())?;
+        let bounding_box = mesh.bounding_box.as_ref()?;
+        let to_light = light_view * drawable.transform;
+        let mut min = Vec3::repeat(f32::MAX);
+        let mut max = Vec3::repeat(f32::MIN);
+        for corner in 0..8 {
+          let position = Vec3::new(
+            if corner & 1 == 0 { bounding_box.min.x } else { bounding_box.max.x },
+            if corner & 2 == 0 { bounding_box.min.y } else { bounding_box.max.y },
+            if corner & 4 == 0 { bounding_box.min.z } else { bounding_box.max.z }
+          );
+          let transformed = to_light.transform_point(&Point3::from(position)).coords;
+          min = min.inf(&transformed);
+          max = max.sup(&transformed);
+        }
+        Some((index, min, max))
+      })
+      .collect();
+
+    let near = view.near_plane;
+    let far = view.far_plane.min(Self::MAX_DISTANCE);
+    let tan_half_fov_x = (view.camera_fov / 2f32).tan();
+    let tan_half_fov_y = tan_half_fov_x / view.aspect_ratio;
+    let forward = view.camera_rotation.transform_vector(&Vec3::new(0f32, 0f32, 1f32));
+
+    let pipeline = shader_manager.get_graphics_pipeline(self.pipeline);
+    let skinned_pipeline = shader_manager.get_graphics_pipeline(self.skinned_pipeline);
+
+    let mut cascades = ShadowCascades {
+      cascade_count: SHADOW_CASCADE_COUNT as u32,
+      ..Default::default()
+    };
+    let mut cascade_near = near;
+    for cascade_index in 0..SHADOW_CASCADE_COUNT {
+      let split = (cascade_index + 1) as f32 / SHADOW_CASCADE_COUNT as f32;
+      let cascade_far = Self::SPLIT_LAMBDA * near * (far / near).powf(split) + (1f32 - Self::SPLIT_LAMBDA) * (near + (far - near) * split);
+
+      // The bounding sphere of the frustum slice doesn't change when the camera rotates, that keeps the shadow edges from swimming.
+      let center_depth = (cascade_near + cascade_far) * 0.5f32;
+      let corner_radius = |depth: f32| Vec3::new(depth * tan_half_fov_x, depth * tan_half_fov_y, depth - center_depth).magnitude();
+      let radius = (corner_radius(cascade_near).max(corner_radius(cascade_far)) * 16f32).ceil() / 16f32;
+      let center = view.camera_position + forward * center_depth;
+
+      // Only move the projection in whole texels.
+      let texel_size = 2f32 * radius / Self::RESOLUTION as f32;
+      let light_center = light_view.transform_point(&Point3::from(center)).coords;
+      let light_center = Vec3::new(
+        (light_center.x / texel_size).floor() * texel_size,
+        (light_center.y / texel_size).floor() * texel_size,
+        light_center.z
+      );
+      let min = Vec2::new(light_center.x - radius, light_center.y - radius);
+      let max = Vec2::new(light_center.x + radius, light_center.y + radius);
+
+      // Casters between the light and the cascade still need to end up in the shadow map, so pull the near plane towards the light.
+      let mut z_min = light_center.z - radius;
+      let z_max = light_center.z + radius;
+      let mut cascade_casters = Vec::<usize>::new();
+      for (index, caster_min, caster_max) in &casters {
+        if caster_max.x < min.x || caster_min.x > max.x || caster_max.y < min.y || caster_min.y > max.y || caster_min.z > z_max {
+          continue;
+        }
+        z_min = z_min.min(caster_min.z);
+        cascade_casters.push(*index);
+      }
+
+      let proj = nalgebra_glm::ortho_lh_zo(min.x, max.x, min.y, max.y, z_min, z_max);
+      let view_proj = proj * light_view;
+      cascades.view_proj[cascade_index] = view_proj;
+      cascades.split_depths[cascade_index] = cascade_far;
+      cascades.texel_sizes[cascade_index] = texel_size;
+      cascade_near = cascade_far;
+
+      let dsv = resources.access_depth_stencil_view(
+        cmd_buffer,
+        Self::SHADOW_MAP_NAME,
+        BarrierSync::EARLY_DEPTH | BarrierSync::LATE_DEPTH,
+        BarrierAccess::DEPTH_STENCIL_READ | BarrierAccess::DEPTH_STENCIL_WRITE,
+        TextureLayout::DepthStencilReadWrite,
+        true,
+        &TextureViewInfo {
+          base_array_layer: cascade_index as u32,
+          ..Default::default()
+        },
+        HistoryResourceEntry::Current
+      );
+
+      cmd_buffer.begin_render_pass(&RenderPassBeginInfo {
+        attachments: &[
+          RenderPassAttachment {
+            view: RenderPassAttachmentView::DepthStencil(&*dsv),
+            load_op: LoadOp::Clear,
+            store_op: StoreOp::Store
+          }
+        ],
+        subpasses: &[
+          SubpassInfo {
+            input_attachments: &[],
+            output_color_attachments: &[],
+            depth_stencil_attachment: Some(DepthStencilAttachmentRef {
+              index: 0,
+              read_only: false
+            })
+          }
+        ]
+      }, RenderpassRecordingMode::Commands);
+
+      cmd_buffer.set_pipeline(PipelineBinding::Graphics(&pipeline));
+      let mut is_skinned_pipeline = false;
+      cmd_buffer.set_viewports(&[Viewport {
+        position: Vec2::new(0.0f32, 0.0f32),
+        extent: Vec2::new(Self::RESOLUTION as f32, Self::RESOLUTION as f32),
+        min_depth: 0.0f32,
+        max_depth: 1.0f32
+      }]);
+      cmd_buffer.set_scissors(&[Scissor {
+        position: Vec2I::new(0, 0),
+        extent: Vec2UI::new(9999, 9999),
+      }]);
+      let cascade_buffer = cmd_buffer.upload_dynamic_data(&[view_proj], BufferUsage::CONSTANT);
+      cmd_buffer.bind_uniform_buffer(BindingFrequency::Frequent, 0, &cascade_buffer, 0, WHOLE_BUFFER);
+
+      for index in cascade_casters {
+        let drawable = &static_drawables[index];
+        let model = if let Some(model) = assets.get_model(drawable.model) {
+          model
+        } else {
+          continue;
+        };
+        let mesh = if let Some(mesh) = assets.get_mesh(model.mesh_handle()) {
+          mesh
+        } else {
+          continue;
+        };
+
+        // The pipeline has to be set before the push constants get uploaded.
+        let skinning = mesh.skinning.as_ref().zip(drawable.bone_palette.as_ref());
+        if skinning.is_some() != is_skinned_pipeline {
+          is_skinned_pipeline = skinning.is_some();
+          cmd_buffer.set_pipeline(PipelineBinding::Graphics(if is_skinned_pipeline { &skinned_pipeline } else { &pipeline }));
+        }
+        if let Some((skinning, bone_palette)) = skinning {
+          cmd_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 4, skinning.buffer(), skinning.offset() as usize, skinning.size() as usize);
+          let bone_palette_buffer = cmd_buffer.upload_dynamic_data(&bone_palette[..], BufferUsage::STORAGE);
+          cmd_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 5, &bone_palette_buffer, 0, WHOLE_BUFFER);
+        }
+        cmd_buffer.finish_binding();
+
+        cmd_buffer.upload_dynamic_data_inline(&[drawable.transform], ShaderType::VertexShader);
+        cmd_buffer.set_vertex_buffer(mesh.vertices.buffer(), mesh.vertices.offset() as usize);
+        if let Some(indices) = mesh.indices.as_ref() {
+          cmd_buffer.set_index_buffer(indices.buffer(), indices.offset() as usize, IndexFormat::U32);
+        }
+
+        for (part_index, range) in mesh.parts.iter().enumerate() {
+          // Alpha tested parts cast solid shadows, blended ones don't cast any.
+          let material = assets.get_material(model.material_handles()[part_index]);
+          if material.alpha_mode() == AlphaMode::Blend {
+            continue;
+          }
+          if mesh.indices.is_some() {
+            cmd_buffer.draw_indexed(1, 0, range.count, range.start, 0);
+          } else {
+            cmd_buffer.draw(range.count, range.start);
+          }
+        }
+      }
+
+      cmd_buffer.end_render_pass();
+    }
+
+    self.cascades = cascades;
+    cmd_buffer.end_label();
   }
 }
@@ -0,0 +1,312 @@
+use sourcerenderer_core::graphics::{OutputAttachmentRef, RenderPassAttachment, RenderPassAttachmentView, RenderPassBeginInfo, RenderpassRecordingMode, TextureViewInfo, TextureLayout, BarrierAccess, BarrierSync, IndexFormat, TextureDimension};
+use sourcerenderer_core::graphics::{AttachmentBlendInfo, AttachmentInfo, Backend as GraphicsBackend, BindingFrequency, BlendInfo, CommandBuffer, CompareFunc, CullMode, DepthStencilAttachmentRef, DepthStencilInfo, FillMode, Format, FrontFace, LoadOp, LogicOp, PipelineBinding, PrimitiveType, RasterizerInfo, RenderPassInfo, SampleCount, Scissor, ShaderType, StencilInfo, StoreOp, SubpassInfo, TextureInfo, TextureUsage, VertexLayoutInfo, Viewport};
+use crate::renderer::render_path::SceneInfo;
+use crate::renderer::renderer_assets::{RendererAssets, RendererMaterialValue};
+use crate::renderer::renderer_resources::{RendererResources, HistoryResourceEntry};
+use crate::renderer::shader_manager::{ShaderManager, GraphicsPipelineInfo, GraphicsPipelineHandle};
+use crate::renderer::{Vertex, VertexAttribute};
+use sourcerenderer_core::{Matrix4, Platform, Vec2, Vec2I, Vec2UI};
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct SkyboxModelCB {
+  view_projection: Matrix4,
+  model: Matrix4
+}
+
+/// Draws the skybox cube map and the 3D skybox into a separate texture
+/// that the render paths use for every pixel that isn't covered by the level.
+pub struct SkyboxPass {
+  cube_pipeline: GraphicsPipelineHandle,
+  pipeline: GraphicsPipelineHandle
+}
+
+impl SkyboxPass {
+  pub const SKYBOX_TEXTURE_NAME: &'static str = "Skybox";
+  const DEPTH_TEXTURE_NAME: &'static str = "SkyboxDepth";
+
+  pub fn new<P: Platform>(
+    resources: &mut RendererResources<P::GraphicsBackend>,
+    shader_manager: &mut ShaderManager<P>,
+    resolution: Vec2UI
+  ) -> Self {
+    let texture_info = TextureInfo {
+      dimension: TextureDimension::Dim2D,
+      format: Format::RGBA16Float,
+      width: resolution.x,
+      height: resolution.y,
+      depth: 1,
+      mip_levels: 1,
+      array_length: 1,
+      samples: SampleCount::Samples1,
+      usage: TextureUsage::RENDER_TARGET | TextureUsage::SAMPLED,
+      supports_srgb: false,
+    };
+    resources.create_texture(Self::SKYBOX_TEXTURE_NAME, &texture_info, false);
+
+    let depth_info = TextureInfo {
+      dimension: TextureDimension::Dim2D,
+      format: Format::D24,
+      width: resolution.x,
+      height: resolution.y,
+      depth: 1,
+      mip_levels: 1,
+      array_length: 1,
+      samples: SampleCount::Samples1,
+      usage: TextureUsage::DEPTH_STENCIL,
+      supports_srgb: false,
+    };
+    resources.create_texture(Self::DEPTH_TEXTURE_NAME, &depth_info, false);
+
+    let render_pass_info = RenderPassInfo {
+      attachments: &[
+        AttachmentInfo {
+          format: texture_info.format,
+          samples: texture_info.samples,
+        },
+        AttachmentInfo {
+          format: depth_info.format,
+          samples: depth_info.samples,
+        }
+      ],
+      subpasses: &[
+        SubpassInfo {
+          input_attachments: &[],
+          output_color_attachments: &[
+            OutputAttachmentRef {
+              index: 0,
+              resolve_attachment_index: None
+            }
+          ],
+          depth_stencil_attachment: Some(DepthStencilAttachmentRef {
+            index: 1,
+            read_only: false
+          })
+        }
+      ]
+    };
+
+    let blend = BlendInfo {
+      alpha_to_coverage_enabled: false,
+      logic_op_enabled: false,
+      logic_op: LogicOp::And,
+      constants: [0f32, 0f32, 0f32, 0f32],
+      attachments: &[
+        AttachmentBlendInfo::default()
+      ]
+    };
+
+    // A single triangle that covers the whole screen, it doesn't need any vertex buffer.
+    let cube_pipeline_info = GraphicsPipelineInfo {
+      vs: "shaders/skybox.vert.spv",
+      fs: Some("shaders/skybox.frag.spv"),
+      primitive_type: PrimitiveType::Triangles,
+      vertex_layout: VertexLayoutInfo {
+        input_assembler: &[],
+        shader_inputs: &[]
+      },
+      rasterizer: RasterizerInfo {
+        fill_mode: FillMode::Fill,
+        cull_mode: CullMode::None,
+        front_face: FrontFace::Clockwise,
+        sample_count: SampleCount::Samples1
+      },
+      depth_stencil: DepthStencilInfo {
+        depth_test_enabled: false,
+        depth_write_enabled: false,
+        depth_func: CompareFunc::Always,
+        stencil_enable: false,
+        stencil_read_mask: 0u8,
+        stencil_write_mask: 0u8,
+        stencil_front: StencilInfo::default(),
+        stencil_back: StencilInfo::default()
+      },
+      blend: blend.clone()
+    };
+    let cube_pipeline = shader_manager.request_graphics_pipeline(&cube_pipeline_info, &render_pass_info, 0);
+
+    let pipeline_info = GraphicsPipelineInfo {
+      vs: "shaders/skybox_3d.vert.spv",
+      fs: Some("shaders/skybox_3d.frag.spv"),
+      primitive_type: PrimitiveType::Triangles,
+      vertex_layout: VertexLayoutInfo {
+        input_assembler: &Vertex::INPUT_ASSEMBLER,
+        shader_inputs: &Vertex::shader_inputs(&[
+          VertexAttribute::Position,
+          VertexAttribute::Uv,
+          VertexAttribute::LightmapUv
+        ])
+      },
+      rasterizer: RasterizerInfo {
+        fill_mode: FillMode::Fill,
+        cull_mode: CullMode::Back,
+        front_face: FrontFace::Clockwise,
+        sample_count: SampleCount::Samples1
+      },
+      depth_stencil: DepthStencilInfo {
+        depth_test_enabled: true,
+        depth_write_enabled: true,
+        depth_func: CompareFunc::Less,
+        stencil_enable: false,
+        stencil_read_mask: 0u8,
+        stencil_write_mask: 0u8,
+        stencil_front: StencilInfo::default(),
+        stencil_back: StencilInfo::default()
+      },
+      blend
+    };
+    let pipeline = shader_manager.request_graphics_pipeline(&pipeline_info, &render_pass_info, 0);
+
+    Self {
+      cube_pipeline,
+      pipeline
+    }
+  }
+
+  #[profiling::function]
+  pub(super) fn execute<P: Platform>(
+    &mut self,
+    cmd_buffer: &mut <P::GraphicsBackend as GraphicsBackend>::CommandBuffer,
+    scene: &SceneInfo<P::GraphicsBackend>,
+    resources: &RendererResources<P::GraphicsBackend>,
+    shader_manager: &ShaderManager<P>,
+    assets: &RendererAssets<P>
+  ) {
+    cmd_buffer.begin_label("Skybox");
+
+    let rtv = resources.access_render_target_view(
+      cmd_buffer,
+      Self::SKYBOX_TEXTURE_NAME,
+      BarrierSync::RENDER_TARGET,
+      BarrierAccess::RENDER_TARGET_WRITE,
+      TextureLayout::RenderTarget,
+      true,
+      &TextureViewInfo::default(),
+      HistoryResourceEntry::Current
+    );
+
+    let depth_buffer = resources.access_depth_stencil_view(
+      cmd_buffer,
+      Self::DEPTH_TEXTURE_NAME,
+      BarrierSync::EARLY_DEPTH | BarrierSync::LATE_DEPTH,
+      BarrierAccess::DEPTH_STENCIL_READ | BarrierAccess::DEPTH_STENCIL_WRITE,
+      TextureLayout::DepthStencilReadWrite,
+      true,
+      &TextureViewInfo::default(),
+      HistoryResourceEntry::Current
+    );
+
+    cmd_buffer.begin_render_pass(&RenderPassBeginInfo {
+      attachments: &[
+        RenderPassAttachment {
+          view: RenderPassAttachmentView::RenderTarget(&*rtv),
+          load_op: LoadOp::Clear,
+          store_op: StoreOp::Store,
+        },
+        RenderPassAttachment {
+          view: RenderPassAttachmentView::DepthStencil(&*depth_buffer),
+          load_op: LoadOp::Clear,
+          store_op: StoreOp::DontCare
+        }
+      ],
+      subpasses: &[
+        SubpassInfo {
+          input_attachments: &[],
+          output_color_attachments: &[
+            OutputAttachmentRef {
+              index: 0,
+              resolve_attachment_index: None
+            }
+          ],
+          depth_stencil_attachment: Some(DepthStencilAttachmentRef {
+            index: 1,
+            read_only: false
+          })
+        }
+      ]
+    }, RenderpassRecordingMode::Commands);
+
+    let (width, height) = {
+      let info = resources.texture_info(Self::SKYBOX_TEXTURE_NAME);
+      (info.width, info.height)
+    };
+    cmd_buffer.set_viewports(&[Viewport {
+      position: Vec2::new(0.0f32, 0.0f32),
+      extent: Vec2::new(width as f32, height as f32),
+      min_depth: 0.0f32,
+      max_depth: 1.0f32
+    }]);
+    cmd_buffer.set_scissors(&[Scissor {
+      position: Vec2I::new(0, 0),
+      extent: Vec2UI::new(9999, 9999),
+    }]);
+
+    if let Some(skybox) = scene.skybox {
+      // The cube map is infinitely far away, so only the rotation of the camera matters.
+      let main_view = &scene.views[scene.active_view_index];
+      let mut rotation_matrix = main_view.view_matrix;
+      rotation_matrix[(0, 3)] = 0f32;
+      rotation_matrix[(1, 3)] = 0f32;
+      rotation_matrix[(2, 3)] = 0f32;
+      let inverse_view_projection = (main_view.proj_matrix * rotation_matrix).try_inverse().unwrap_or_else(Matrix4::identity);
+
+      let pipeline = shader_manager.get_graphics_pipeline(self.cube_pipeline);
+      cmd_buffer.set_pipeline(PipelineBinding::Graphics(&pipeline));
+      cmd_buffer.upload_dynamic_data_inline(&[inverse_view_projection], ShaderType::VertexShader);
+      cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 0, &skybox.view, resources.linear_sampler());
+      cmd_buffer.finish_binding();
+      cmd_buffer.draw(3, 0);
+    }
+
+    if let (Some(sky_view), Some(lightmap)) = (scene.sky_view_index.map(|index| &scene.views[index]), scene.lightmap) {
+      let static_drawables = scene.scene.static_drawables();
+      let view_projection = sky_view.proj_matrix * sky_view.view_matrix;
+      let pipeline = shader_manager.get_graphics_pipeline(self.pipeline);
+      cmd_buffer.set_pipeline(PipelineBinding::Graphics(&pipeline));
+      cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::Frequent, 0, &lightmap.view, resources.linear_sampler());
+
+      // The 3D skybox has no translucent pass, its translucent parts get drawn last without blending.
+      for part in sky_view.drawable_parts.iter().chain(sky_view.translucent_parts.iter()) {
+        let drawable = &static_drawables[part.drawable_index];
+        let model = assets.get_model(drawable.model);
+        if model.is_none() {
+          continue;
+        }
+        let model = model.unwrap();
+        let mesh = assets.get_mesh(model.mesh_handle());
+        if mesh.is_none() {
+          continue;
+        }
+        let mesh = mesh.unwrap();
+
+        let material = assets.get_material(model.material_handles()[part.part_index]);
+        let albedo_view = match material.get("albedo") {
+          Some(RendererMaterialValue::Texture(handle)) => &assets.get_texture(*handle).view,
+          _ => &assets.placeholder_texture().view
+        };
+        cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 0, albedo_view, resources.linear_sampler());
+        cmd_buffer.finish_binding();
+
+        cmd_buffer.upload_dynamic_data_inline(&[SkyboxModelCB {
+          view_projection,
+          model: drawable.transform
+        }], ShaderType::VertexShader);
+
+        cmd_buffer.set_vertex_buffer(mesh.vertices.buffer(), mesh.vertices.offset() as usize);
+        if let Some(indices) = mesh.indices.as_ref() {
+          cmd_buffer.set_index_buffer(indices.buffer(), indices.offset() as usize, IndexFormat::U32);
+        }
+
+        let range = &mesh.parts[part.part_index];
+        if mesh.indices.is_some() {
+          cmd_buffer.draw_indexed(1, 0, range.count, range.start, 0);
+        } else {
+          cmd_buffer.draw(range.count, range.start);
+        }
+      }
+    }
+
+    cmd_buffer.end_render_pass();
+    cmd_buffer.end_label();
+  }
+}
@@ -0,0 +1,293 @@
+use sourcerenderer_core::graphics::{OutputAttachmentRef, RenderPassAttachment, RenderPassAttachmentView, RenderPassBeginInfo, RenderpassRecordingMode, TextureViewInfo, TextureLayout, BarrierAccess, BarrierSync, IndexFormat, BlendFactor, BlendOp, ColorComponents, BufferUsage, ShaderType, WHOLE_BUFFER};
+use sourcerenderer_core::graphics::{AttachmentBlendInfo, AttachmentInfo, Backend as GraphicsBackend, BindingFrequency, BlendInfo, CommandBuffer, CompareFunc, CullMode, DepthStencilAttachmentRef, DepthStencilInfo, FillMode, Format, FrontFace, LoadOp, LogicOp, PipelineBinding, PrimitiveType, RasterizerInfo, RenderPassInfo, SampleCount, Scissor, StencilInfo, StoreOp, SubpassInfo, VertexLayoutInfo, Viewport};
+use crate::renderer::passes::conservative::geometry::{bind_material, bind_drawable};
+use crate::renderer::passes::light_binning::LightBinningPass;
+use crate::renderer::passes::shadow_map::ShadowMapPass;
+use crate::renderer::render_path::{SceneInfo, ZeroTextures};
+use crate::renderer::renderer_assets::RendererAssets;
+use crate::renderer::renderer_resources::{RendererResources, HistoryResourceEntry};
+use crate::renderer::shader_manager::{ShaderManager, GraphicsPipelineInfo, GraphicsPipelineHandle};
+use crate::renderer::{Vertex, VertexAttribute};
+use sourcerenderer_core::{Platform, Vec2, Vec2I, Vec2UI};
+
+/// Draws the parts with blended materials back to front on top of the lit opaque geometry.
+/// They use the same shaders as the opaque geometry of the conservative render path, so they get lit through the clustered light lists.
+pub struct TranslucentPass {
+  pipeline: GraphicsPipelineHandle,
+  skinned_pipeline: GraphicsPipelineHandle
+}
+
+impl TranslucentPass {
+  pub fn new<P: Platform>(
+    shader_manager: &mut ShaderManager<P>,
+    output_format: Format
+  ) -> Self {
+    let render_pass_info = RenderPassInfo {
+      attachments: &[
+        AttachmentInfo {
+          format: output_format,
+          samples: SampleCount::Samples1,
+        },
+        AttachmentInfo {
+          format: Format::D24,
+          samples: SampleCount::Samples1,
+        }
+      ],
+      subpasses: &[
+        SubpassInfo {
+          input_attachments: &[],
+          output_color_attachments: &[
+            OutputAttachmentRef {
+              index: 0,
+              resolve_attachment_index: None
+            }
+          ],
+          depth_stencil_attachment: Some(DepthStencilAttachmentRef {
+            index: 1,
+            read_only: true
+          })
+        }
+      ]
+    };
+
+    let pipeline_info = GraphicsPipelineInfo {
+      vs: "shaders/textured.vert.spv",
+      fs: Some("shaders/textured.frag.spv"),
+      primitive_type: PrimitiveType::Triangles,
+      vertex_layout: VertexLayoutInfo {
+        input_assembler: &Vertex::INPUT_ASSEMBLER,
+        shader_inputs: &Vertex::shader_inputs(&[
+          VertexAttribute::Position,
+          VertexAttribute::Normal,
+          VertexAttribute::Uv,
+          VertexAttribute::LightmapUv,
+          VertexAttribute::Alpha,
+          VertexAttribute::LightmapStyles,
+          VertexAttribute::Multiblend,
+          VertexAttribute::LightmapBumpPageWidth,
+          VertexAttribute::Tangent
+        ])
+      },
+      rasterizer: RasterizerInfo {
+        fill_mode: FillMode::Fill,
+        cull_mode: CullMode::Back,
+        front_face: FrontFace::Clockwise,
+        sample_count: SampleCount::Samples1
+      },
+      depth_stencil: DepthStencilInfo {
+        depth_test_enabled: true,
+        depth_write_enabled: false,
+        depth_func: CompareFunc::LessEqual,
+        stencil_enable: false,
+        stencil_read_mask: 0u8,
+        stencil_write_mask: 0u8,
+        stencil_front: StencilInfo::default(),
+        stencil_back: StencilInfo::default()
+      },
+      blend: BlendInfo {
+        alpha_to_coverage_enabled: false,
+        logic_op_enabled: false,
+        logic_op: LogicOp::And,
+        constants: [0f32, 0f32, 0f32, 0f32],
+        attachments: &[
+          AttachmentBlendInfo {
+            blend_enabled: true,
+            src_color_blend_factor: BlendFactor::SrcAlpha,
+            dst_color_blend_factor: BlendFactor::OneMinusSrcAlpha,
+            color_blend_op: BlendOp::Add,
+            src_alpha_blend_factor: BlendFactor::Zero,
+            dst_alpha_blend_factor: BlendFactor::One,
+            alpha_blend_op: BlendOp::Add,
+            write_mask: ColorComponents::RED | ColorComponents::GREEN | ColorComponents::BLUE
+          }
+        ]
+      }
+    };
+    let pipeline = shader_manager.request_graphics_pipeline(&pipeline_info, &render_pass_info, 0);
+    let skinned_pipeline_info = GraphicsPipelineInfo {
+      vs: "shaders/textured_skinned.vert.spv",
+      ..pipeline_info
+    };
+    let skinned_pipeline = shader_manager.request_graphics_pipeline(&skinned_pipeline_info, &render_pass_info, 0);
+
+    Self {
+      pipeline,
+      skinned_pipeline
+    }
+  }
+
+  /// Expects the frame set to be bound already.
+  #[profiling::function]
+  #[allow(clippy::too_many_arguments)]
+  pub(super) fn execute<P: Platform>(
+    &mut self,
+    cmd_buffer: &mut <P::GraphicsBackend as GraphicsBackend>::CommandBuffer,
+    scene: &SceneInfo<P::GraphicsBackend>,
+    resources: &RendererResources<P::GraphicsBackend>,
+    shader_manager: &ShaderManager<P>,
+    zero_textures: &ZeroTextures<P::GraphicsBackend>,
+    shadow_map: &ShadowMapPass<P>,
+    assets: &RendererAssets<P>,
+    output_name: &str,
+    depth_name: &str
+  ) {
+    let view = &scene.views[scene.active_view_index];
+    if view.translucent_parts.is_empty() {
+      return;
+    }
+
+    cmd_buffer.begin_label("Translucent pass");
+    let static_drawables = scene.scene.static_drawables();
+
+    let light_bitmask_buffer = resources.access_buffer(
+      cmd_buffer,
+      LightBinningPass::LIGHT_BINNING_BUFFER_NAME,
+      BarrierSync::FRAGMENT_SHADER,
+      BarrierAccess::STORAGE_READ,
+      HistoryResourceEntry::Current
+    );
+
+    let shadow_map_view = resources.access_sampling_view(
+      cmd_buffer,
+      ShadowMapPass::<P>::SHADOW_MAP_NAME,
+      BarrierSync::FRAGMENT_SHADER,
+      BarrierAccess::SAMPLING_READ,
+      TextureLayout::Sampled,
+      false,
+      &ShadowMapPass::<P>::sampling_view_info(),
+      HistoryResourceEntry::Current
+    );
+
+    let rtv = resources.access_render_target_view(
+      cmd_buffer,
+      output_name,
+      BarrierSync::RENDER_TARGET,
+      BarrierAccess::RENDER_TARGET_READ | BarrierAccess::RENDER_TARGET_WRITE,
+      TextureLayout::RenderTarget,
+      false,
+      &TextureViewInfo::default(),
+      HistoryResourceEntry::Current
+    );
+
+    let depth_buffer = resources.access_depth_stencil_view(
+      cmd_buffer,
+      depth_name,
+      BarrierSync::EARLY_DEPTH | BarrierSync::LATE_DEPTH,
+      BarrierAccess::DEPTH_STENCIL_READ,
+      TextureLayout::DepthStencilRead,
+      false,
+      &TextureViewInfo::default(),
+      HistoryResourceEntry::Current
+    );
+
+    cmd_buffer.begin_render_pass(&RenderPassBeginInfo {
+      attachments: &[
+        RenderPassAttachment {
+          view: RenderPassAttachmentView::RenderTarget(&*rtv),
+          load_op: LoadOp::Load,
+          store_op: StoreOp::Store,
+        },
+        RenderPassAttachment {
+          view: RenderPassAttachmentView::DepthStencil(&*depth_buffer),
+          load_op: LoadOp::Load,
+          store_op: StoreOp::Store
+        }
+      ],
+      subpasses: &[
+        SubpassInfo {
+          input_attachments: &[],
+          output_color_attachments: &[
+            OutputAttachmentRef {
+              index: 0,
+              resolve_attachment_index: None
+            }
+          ],
+          depth_stencil_attachment: Some(DepthStencilAttachmentRef {
+            index: 1,
+            read_only: true
+          })
+        }
+      ]
+    }, RenderpassRecordingMode::Commands);
+
+    let (width, height) = {
+      let info = resources.texture_info(output_name);
+      (info.width, info.height)
+    };
+    cmd_buffer.set_viewports(&[Viewport {
+      position: Vec2::new(0.0f32, 0.0f32),
+      extent: Vec2::new(width as f32, height as f32),
+      min_depth: 0.0f32,
+      max_depth: 1.0f32
+    }]);
+    cmd_buffer.set_scissors(&[Scissor {
+      position: Vec2I::new(0, 0),
+      extent: Vec2UI::new(9999, 9999),
+    }]);
+
+    let pipeline = shader_manager.get_graphics_pipeline(self.pipeline);
+    let skinned_pipeline = shader_manager.get_graphics_pipeline(self.skinned_pipeline);
+    cmd_buffer.set_pipeline(PipelineBinding::Graphics(&pipeline));
+    let mut is_skinned_pipeline = false;
+
+    let sampler = resources.repeat_sampler();
+    let lightmap = scene.lightmap.unwrap_or_else(|| assets.placeholder_texture());
+    // SSAO and the ray traced shadows only cover the opaque surfaces, textured.frag doesn't sample them for translucent materials.
+    // The shadow map is in world space, so that one applies.
+    cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::Frequent, 0, &lightmap.view, sampler);
+    cmd_buffer.bind_sampler(BindingFrequency::Frequent, 1, sampler);
+    cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::Frequent, 2, zero_textures.zero_texture_view, sampler);
+    cmd_buffer.bind_storage_buffer(BindingFrequency::Frequent, 3, &*light_bitmask_buffer, 0, WHOLE_BUFFER);
+    cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::Frequent, 4, zero_textures.zero_texture_view, sampler);
+    cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::Frequent, 6, &*shadow_map_view, shadow_map.sampler());
+    let shadow_cascades_buffer = cmd_buffer.upload_dynamic_data(&[*shadow_map.cascades()], BufferUsage::CONSTANT);
+    cmd_buffer.bind_uniform_buffer(BindingFrequency::Frequent, 7, &shadow_cascades_buffer, 0, WHOLE_BUFFER);
+
+    for part in &view.translucent_parts {
+      let drawable = &static_drawables[part.drawable_index];
+      let model = if let Some(model) = assets.get_model(drawable.model) {
+        model
+      } else {
+        continue;
+      };
+      let mesh = if let Some(mesh) = assets.get_mesh(model.mesh_handle()) {
+        mesh
+      } else {
+        continue;
+      };
+
+      // The pipeline has to be set before the push constants get uploaded.
+      let skinning = mesh.skinning.as_ref().zip(drawable.bone_palette.as_ref());
+      if skinning.is_some() != is_skinned_pipeline {
+        is_skinned_pipeline = skinning.is_some();
+        cmd_buffer.set_pipeline(PipelineBinding::Graphics(if is_skinned_pipeline { &skinned_pipeline } else { &pipeline }));
+      }
+      if let Some((skinning, bone_palette)) = skinning {
+        cmd_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 4, skinning.buffer(), skinning.offset() as usize, skinning.size() as usize);
+        let bone_palette_buffer = cmd_buffer.upload_dynamic_data(&bone_palette[..], BufferUsage::STORAGE);
+        cmd_buffer.bind_storage_buffer(BindingFrequency::VeryFrequent, 5, &bone_palette_buffer, 0, WHOLE_BUFFER);
+      }
+
+      cmd_buffer.upload_dynamic_data_inline(&[drawable.transform], ShaderType::VertexShader);
+      cmd_buffer.set_vertex_buffer(mesh.vertices.buffer(), mesh.vertices.offset() as usize);
+      if let Some(indices) = mesh.indices.as_ref() {
+        cmd_buffer.set_index_buffer(indices.buffer(), indices.offset() as usize, IndexFormat::U32);
+      }
+
+      let material = assets.get_material(model.material_handles()[part.part_index]);
+      bind_material::<P>(cmd_buffer, material, sampler, zero_textures, assets);
+      bind_drawable::<P>(cmd_buffer, scene.scene, drawable, mesh, sampler, assets);
+      cmd_buffer.finish_binding();
+
+      let range = &mesh.parts[part.part_index];
+      if mesh.indices.is_some() {
+        cmd_buffer.draw_indexed(1, 0, range.count, range.start, 0);
+      } else {
+        cmd_buffer.draw(range.count, range.start);
+      }
+    }
+
+    cmd_buffer.end_render_pass();
+    cmd_buffer.end_label();
+  }
+}
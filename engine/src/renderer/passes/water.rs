@@ -0,0 +1,432 @@
+use sourcerenderer_core::graphics::{OutputAttachmentRef, RenderPassAttachment, RenderPassAttachmentView, RenderPassBeginInfo, RenderpassRecordingMode, TextureViewInfo, TextureLayout, BarrierAccess, BarrierSync, BarrierTextureRange, IndexFormat, BlendFactor, BlendOp, ColorComponents, BufferUsage, ShaderType, WHOLE_BUFFER};
+use sourcerenderer_core::graphics::{AttachmentBlendInfo, AttachmentInfo, Backend as GraphicsBackend, BindingFrequency, BlendInfo, CommandBuffer, CompareFunc, CullMode, DepthStencilAttachmentRef, DepthStencilInfo, FillMode, Format, FrontFace, LoadOp, LogicOp, PipelineBinding, PrimitiveType, RasterizerInfo, RenderPassInfo, SampleCount, Scissor, StencilInfo, StoreOp, SubpassInfo, TextureDimension, TextureInfo, TextureUsage, VertexLayoutInfo, Viewport};
+use crate::renderer::render_path::SceneInfo;
+use crate::renderer::renderer_assets::{RendererAssets, RendererMaterial, RendererMaterialValue};
+use crate::renderer::renderer_resources::{RendererResources, HistoryResourceEntry};
+use crate::renderer::shader_manager::{ShaderManager, GraphicsPipelineInfo, GraphicsPipelineHandle, ComputePipelineHandle};
+use crate::renderer::{Vertex, VertexAttribute};
+use sourcerenderer_core::{Platform, Vec2, Vec2I, Vec2UI, Vec4};
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct WaterCB {
+  fog_color: Vec4,
+  reflect_tint: Vec4,
+  refract_tint: Vec4,
+  fog_start: f32,
+  fog_end: f32,
+  has_normal_map: u32,
+  reflect_skybox: u32,
+  is_underwater: u32
+}
+
+impl WaterCB {
+  fn new(material: &RendererMaterial, reflect_skybox: bool, is_underwater: bool) -> Self {
+    let vec4 = |key: &str, default: Vec4| match material.get(key) {
+      Some(RendererMaterialValue::Vec4(value)) => *value,
+      _ => default
+    };
+    let float = |key: &str, default: f32| match material.get(key) {
+      Some(RendererMaterialValue::Float(value)) => *value,
+      _ => default
+    };
+    Self {
+      fog_color: vec4("fog_color", Vec4::new(0f32, 0f32, 0f32, 1f32)),
+      reflect_tint: vec4("reflect_tint", Vec4::new(1f32, 1f32, 1f32, 1f32)),
+      refract_tint: vec4("refract_tint", Vec4::new(1f32, 1f32, 1f32, 1f32)),
+      fog_start: float("fog_start", 0f32),
+      fog_end: float("fog_end", 1f32),
+      has_normal_map: matches!(material.get("normal"), Some(RendererMaterialValue::Texture(_))) as u32,
+      reflect_skybox: reflect_skybox as u32,
+      is_underwater: is_underwater as u32
+    }
+  }
+}
+
+/// Draws the water surfaces on top of the lit opaque geometry and fogs the image while the camera is under water.
+/// The water refracts a copy of the image and uses a copy of the depth buffer to fog what's below the surface.
+/// Depth formats can't be blitted, so the depth buffer gets copied to a float texture with a compute shader.
+pub struct WaterPass {
+  pipeline: GraphicsPipelineHandle,
+  fog_pipeline: GraphicsPipelineHandle,
+  depth_copy_pipeline: ComputePipelineHandle
+}
+
+impl WaterPass {
+  const REFRACTION_TEXTURE_NAME: &'static str = "WaterRefraction";
+  const REFRACTION_DEPTH_TEXTURE_NAME: &'static str = "WaterRefractionDepth";
+
+  pub fn new<P: Platform>(
+    resources: &mut RendererResources<P::GraphicsBackend>,
+    shader_manager: &mut ShaderManager<P>,
+    resolution: Vec2UI,
+    output_format: Format
+  ) -> Self {
+    resources.create_texture(Self::REFRACTION_TEXTURE_NAME, &TextureInfo {
+      dimension: TextureDimension::Dim2D,
+      format: output_format,
+      width: resolution.x,
+      height: resolution.y,
+      depth: 1,
+      mip_levels: 1,
+      array_length: 1,
+      samples: SampleCount::Samples1,
+      usage: TextureUsage::BLIT_DST | TextureUsage::SAMPLED,
+      supports_srgb: false,
+    }, false);
+    resources.create_texture(Self::REFRACTION_DEPTH_TEXTURE_NAME, &TextureInfo {
+      dimension: TextureDimension::Dim2D,
+      format: Format::R32Float,
+      width: resolution.x,
+      height: resolution.y,
+      depth: 1,
+      mip_levels: 1,
+      array_length: 1,
+      samples: SampleCount::Samples1,
+      usage: TextureUsage::STORAGE | TextureUsage::SAMPLED,
+      supports_srgb: false,
+    }, false);
+
+    let render_pass_info = RenderPassInfo {
+      attachments: &[
+        AttachmentInfo {
+          format: output_format,
+          samples: SampleCount::Samples1,
+        },
+        AttachmentInfo {
+          format: Format::D24,
+          samples: SampleCount::Samples1,
+        }
+      ],
+      subpasses: &[
+        SubpassInfo {
+          input_attachments: &[],
+          output_color_attachments: &[
+            OutputAttachmentRef {
+              index: 0,
+              resolve_attachment_index: None
+            }
+          ],
+          depth_stencil_attachment: Some(DepthStencilAttachmentRef {
+            index: 1,
+            read_only: true
+          })
+        }
+      ]
+    };
+
+    let pipeline_info = GraphicsPipelineInfo {
+      vs: "shaders/water.vert.spv",
+      fs: Some("shaders/water.frag.spv"),
+      primitive_type: PrimitiveType::Triangles,
+      vertex_layout: VertexLayoutInfo {
+        input_assembler: &Vertex::INPUT_ASSEMBLER,
+        shader_inputs: &Vertex::shader_inputs(&[
+          VertexAttribute::Position,
+          VertexAttribute::Uv
+        ])
+      },
+      rasterizer: RasterizerInfo {
+        fill_mode: FillMode::Fill,
+        cull_mode: CullMode::Back,
+        front_face: FrontFace::Clockwise,
+        sample_count: SampleCount::Samples1
+      },
+      depth_stencil: DepthStencilInfo {
+        depth_test_enabled: true,
+        depth_write_enabled: false,
+        depth_func: CompareFunc::LessEqual,
+        stencil_enable: false,
+        stencil_read_mask: 0u8,
+        stencil_write_mask: 0u8,
+        stencil_front: StencilInfo::default(),
+        stencil_back: StencilInfo::default()
+      },
+      blend: BlendInfo {
+        alpha_to_coverage_enabled: false,
+        logic_op_enabled: false,
+        logic_op: LogicOp::And,
+        constants: [0f32, 0f32, 0f32, 0f32],
+        attachments: &[
+          AttachmentBlendInfo::default()
+        ]
+      }
+    };
+    let pipeline = shader_manager.request_graphics_pipeline(&pipeline_info, &render_pass_info, 0);
+
+    let fog_pipeline_info = GraphicsPipelineInfo {
+      vs: "shaders/skybox_composite.vert.spv",
+      fs: Some("shaders/water_fog.frag.spv"),
+      primitive_type: PrimitiveType::Triangles,
+      vertex_layout: VertexLayoutInfo {
+        input_assembler: &[],
+        shader_inputs: &[]
+      },
+      rasterizer: RasterizerInfo {
+        fill_mode: FillMode::Fill,
+        cull_mode: CullMode::None,
+        front_face: FrontFace::Clockwise,
+        sample_count: SampleCount::Samples1
+      },
+      depth_stencil: DepthStencilInfo {
+        depth_test_enabled: false,
+        depth_write_enabled: false,
+        depth_func: CompareFunc::Always,
+        stencil_enable: false,
+        stencil_read_mask: 0u8,
+        stencil_write_mask: 0u8,
+        stencil_front: StencilInfo::default(),
+        stencil_back: StencilInfo::default()
+      },
+      blend: BlendInfo {
+        alpha_to_coverage_enabled: false,
+        logic_op_enabled: false,
+        logic_op: LogicOp::And,
+        constants: [0f32, 0f32, 0f32, 0f32],
+        attachments: &[
+          AttachmentBlendInfo {
+            blend_enabled: true,
+            src_color_blend_factor: BlendFactor::SrcAlpha,
+            dst_color_blend_factor: BlendFactor::OneMinusSrcAlpha,
+            color_blend_op: BlendOp::Add,
+            src_alpha_blend_factor: BlendFactor::Zero,
+            dst_alpha_blend_factor: BlendFactor::One,
+            alpha_blend_op: BlendOp::Add,
+            write_mask: ColorComponents::RED | ColorComponents::GREEN | ColorComponents::BLUE
+          }
+        ]
+      }
+    };
+    let fog_pipeline = shader_manager.request_graphics_pipeline(&fog_pipeline_info, &render_pass_info, 0);
+    let depth_copy_pipeline = shader_manager.request_compute_pipeline("shaders/hi_z_copy.comp.spv");
+
+    Self {
+      pipeline,
+      fog_pipeline,
+      depth_copy_pipeline
+    }
+  }
+
+  /// `has_screen_space_reflections` tells the pass whether the render path composites the output of the `SsrPass`
+  /// on top of the water afterwards, the water reflects the skybox otherwise.
+  #[profiling::function]
+  #[allow(clippy::too_many_arguments)]
+  pub(super) fn execute<P: Platform>(
+    &mut self,
+    cmd_buffer: &mut <P::GraphicsBackend as GraphicsBackend>::CommandBuffer,
+    scene: &SceneInfo<P::GraphicsBackend>,
+    resources: &RendererResources<P::GraphicsBackend>,
+    shader_manager: &ShaderManager<P>,
+    assets: &RendererAssets<P>,
+    output_name: &str,
+    depth_name: &str,
+    has_screen_space_reflections: bool
+  ) {
+    let water: Vec<_> = scene.scene.water_surfaces().iter()
+      .filter_map(|surface| assets.get_model(surface.model).map(|model| (surface, model)))
+      .filter_map(|(surface, model)| assets.get_mesh(model.mesh_handle()).map(|mesh| (surface, model, mesh)))
+      .collect();
+    let camera_position = scene.views[scene.active_view_index].camera_position;
+    let underwater_material = scene.scene.water_volume_at(&camera_position)
+      .map(|volume| assets.get_material(volume.material));
+    if water.is_empty() && underwater_material.is_none() {
+      return;
+    }
+
+    cmd_buffer.begin_label("Water");
+
+    {
+      let src_color = resources.access_texture(
+        cmd_buffer,
+        output_name,
+        &BarrierTextureRange::default(),
+        BarrierSync::COPY,
+        BarrierAccess::COPY_READ,
+        TextureLayout::CopySrc,
+        false,
+        HistoryResourceEntry::Current
+      );
+      let dst_color = resources.access_texture(
+        cmd_buffer,
+        Self::REFRACTION_TEXTURE_NAME,
+        &BarrierTextureRange::default(),
+        BarrierSync::COPY,
+        BarrierAccess::COPY_WRITE,
+        TextureLayout::CopyDst,
+        true,
+        HistoryResourceEntry::Current
+      );
+      cmd_buffer.flush_barriers();
+      cmd_buffer.blit(&*src_color, 0, 0, &*dst_color, 0, 0);
+    }
+
+    {
+      let src_depth = resources.access_sampling_view(
+        cmd_buffer,
+        depth_name,
+        BarrierSync::COMPUTE_SHADER,
+        BarrierAccess::SAMPLING_READ,
+        TextureLayout::Sampled,
+        false,
+        &TextureViewInfo::default(),
+        HistoryResourceEntry::Current
+      );
+      let dst_depth = resources.access_storage_view(
+        cmd_buffer,
+        Self::REFRACTION_DEPTH_TEXTURE_NAME,
+        BarrierSync::COMPUTE_SHADER,
+        BarrierAccess::STORAGE_WRITE,
+        TextureLayout::Storage,
+        true,
+        &TextureViewInfo::default(),
+        HistoryResourceEntry::Current
+      );
+      let (width, height) = {
+        let info = resources.texture_info(Self::REFRACTION_DEPTH_TEXTURE_NAME);
+        (info.width, info.height)
+      };
+      let depth_copy_pipeline = shader_manager.get_compute_pipeline(self.depth_copy_pipeline);
+      cmd_buffer.set_pipeline(PipelineBinding::Compute(&depth_copy_pipeline));
+      cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 0, &src_depth, resources.nearest_sampler());
+      cmd_buffer.bind_storage_texture(BindingFrequency::VeryFrequent, 1, &dst_depth);
+      cmd_buffer.flush_barriers();
+      cmd_buffer.finish_binding();
+      cmd_buffer.dispatch((width + 7) / 8, (height + 7) / 8, 1);
+    }
+
+    let refraction_color = resources.access_sampling_view(
+      cmd_buffer,
+      Self::REFRACTION_TEXTURE_NAME,
+      BarrierSync::FRAGMENT_SHADER,
+      BarrierAccess::SAMPLING_READ,
+      TextureLayout::Sampled,
+      false,
+      &TextureViewInfo::default(),
+      HistoryResourceEntry::Current
+    );
+    let refraction_depth = resources.access_sampling_view(
+      cmd_buffer,
+      Self::REFRACTION_DEPTH_TEXTURE_NAME,
+      BarrierSync::FRAGMENT_SHADER,
+      BarrierAccess::SAMPLING_READ,
+      TextureLayout::Sampled,
+      false,
+      &TextureViewInfo::default(),
+      HistoryResourceEntry::Current
+    );
+
+    let rtv = resources.access_render_target_view(
+      cmd_buffer,
+      output_name,
+      BarrierSync::RENDER_TARGET,
+      BarrierAccess::RENDER_TARGET_READ | BarrierAccess::RENDER_TARGET_WRITE,
+      TextureLayout::RenderTarget,
+      false,
+      &TextureViewInfo::default(),
+      HistoryResourceEntry::Current
+    );
+
+    let depth_buffer = resources.access_depth_stencil_view(
+      cmd_buffer,
+      depth_name,
+      BarrierSync::EARLY_DEPTH | BarrierSync::LATE_DEPTH,
+      BarrierAccess::DEPTH_STENCIL_READ,
+      TextureLayout::DepthStencilRead,
+      false,
+      &TextureViewInfo::default(),
+      HistoryResourceEntry::Current
+    );
+
+    cmd_buffer.begin_render_pass(&RenderPassBeginInfo {
+      attachments: &[
+        RenderPassAttachment {
+          view: RenderPassAttachmentView::RenderTarget(&*rtv),
+          load_op: LoadOp::Load,
+          store_op: StoreOp::Store,
+        },
+        RenderPassAttachment {
+          view: RenderPassAttachmentView::DepthStencil(&*depth_buffer),
+          load_op: LoadOp::Load,
+          store_op: StoreOp::Store
+        }
+      ],
+      subpasses: &[
+        SubpassInfo {
+          input_attachments: &[],
+          output_color_attachments: &[
+            OutputAttachmentRef {
+              index: 0,
+              resolve_attachment_index: None
+            }
+          ],
+          depth_stencil_attachment: Some(DepthStencilAttachmentRef {
+            index: 1,
+            read_only: true
+          })
+        }
+      ]
+    }, RenderpassRecordingMode::Commands);
+
+    let (width, height) = {
+      let info = resources.texture_info(output_name);
+      (info.width, info.height)
+    };
+    cmd_buffer.set_viewports(&[Viewport {
+      position: Vec2::new(0.0f32, 0.0f32),
+      extent: Vec2::new(width as f32, height as f32),
+      min_depth: 0.0f32,
+      max_depth: 1.0f32
+    }]);
+    cmd_buffer.set_scissors(&[Scissor {
+      position: Vec2I::new(0, 0),
+      extent: Vec2UI::new(9999, 9999),
+    }]);
+
+    if !water.is_empty() {
+      let skybox = scene.skybox.unwrap_or_else(|| assets.placeholder_cube());
+      let pipeline = shader_manager.get_graphics_pipeline(self.pipeline);
+      cmd_buffer.set_pipeline(PipelineBinding::Graphics(&pipeline));
+      cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::Frequent, 0, &*refraction_color, resources.linear_sampler());
+      cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::Frequent, 1, &*refraction_depth, resources.nearest_sampler());
+      cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::Frequent, 2, &skybox.view, resources.linear_sampler());
+    }
+    for (surface, model, mesh) in water {
+      cmd_buffer.upload_dynamic_data_inline(&[surface.transform], ShaderType::VertexShader);
+      cmd_buffer.set_vertex_buffer(mesh.vertices.buffer(), mesh.vertices.offset() as usize);
+      if let Some(indices) = mesh.indices.as_ref() {
+        cmd_buffer.set_index_buffer(indices.buffer(), indices.offset() as usize, IndexFormat::U32);
+      }
+
+      for (part_index, range) in mesh.parts.iter().enumerate() {
+        let material = assets.get_material(model.material_handles()[part_index]);
+        let normal_view = match material.get("normal") {
+          Some(RendererMaterialValue::Texture(handle)) => &assets.get_texture(*handle).view,
+          _ => &assets.placeholder_texture().view
+        };
+        cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::VeryFrequent, 0, normal_view, resources.linear_sampler());
+        let water_cb = cmd_buffer.upload_dynamic_data(&[WaterCB::new(material, !has_screen_space_reflections, underwater_material.is_some())], BufferUsage::CONSTANT);
+        cmd_buffer.bind_uniform_buffer(BindingFrequency::VeryFrequent, 1, &water_cb, 0, WHOLE_BUFFER);
+        cmd_buffer.finish_binding();
+
+        if mesh.indices.is_some() {
+          cmd_buffer.draw_indexed(1, 0, range.count, range.start, 0);
+        } else {
+          cmd_buffer.draw(range.count, range.start);
+        }
+      }
+    }
+
+    if let Some(material) = underwater_material {
+      let pipeline = shader_manager.get_graphics_pipeline(self.fog_pipeline);
+      cmd_buffer.set_pipeline(PipelineBinding::Graphics(&pipeline));
+      cmd_buffer.bind_sampling_view_and_sampler(BindingFrequency::Frequent, 1, &*refraction_depth, resources.nearest_sampler());
+      let water_cb = cmd_buffer.upload_dynamic_data(&[WaterCB::new(material, false, true)], BufferUsage::CONSTANT);
+      cmd_buffer.bind_uniform_buffer(BindingFrequency::VeryFrequent, 1, &water_cb, 0, WHOLE_BUFFER);
+      cmd_buffer.finish_binding();
+      cmd_buffer.draw(3, 0);
+    }
+
+    cmd_buffer.end_render_pass();
+    cmd_buffer.end_label();
+  }
+}
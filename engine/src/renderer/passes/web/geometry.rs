@@ -2,9 +2,9 @@ use std::sync::Arc;
 
 use gltf::texture::{MagFilter, MinFilter};
 use smallvec::SmallVec;
-use sourcerenderer_core::{Platform, Vec2, Vec2I, Vec2UI, graphics::{AttachmentBlendInfo, AttachmentInfo, Backend, Barrier, BindingFrequency, BlendInfo, CommandBuffer, CompareFunc, CullMode, DepthStencilAttachmentRef, DepthStencilInfo, FillMode, Format, FrontFace, InputAssemblerElement, InputRate, LoadOp, LogicOp, OutputAttachmentRef, PipelineBinding, PrimitiveType, RasterizerInfo, RenderPassAttachment, RenderPassAttachmentView, RenderPassBeginInfo, RenderPassInfo, RenderpassRecordingMode, SampleCount, Scissor, ShaderInputElement, ShaderType, StencilInfo, StoreOp, SubpassInfo, Swapchain, Texture, TextureViewInfo, TextureInfo, TextureRenderTargetView, TextureUsage, VertexLayoutInfo, Viewport, BarrierSync, BarrierAccess, TextureLayout, IndexFormat, WHOLE_BUFFER, BarrierTextureRange, TextureDimension, SamplerInfo, Filter, AddressMode, Device}};
+use sourcerenderer_core::{Platform, Vec2, Vec2I, Vec2UI, graphics::{AttachmentBlendInfo, AttachmentInfo, Backend, Barrier, BindingFrequency, BlendInfo, CommandBuffer, CompareFunc, CullMode, DepthStencilAttachmentRef, DepthStencilInfo, FillMode, Format, FrontFace, LoadOp, LogicOp, OutputAttachmentRef, PipelineBinding, PrimitiveType, RasterizerInfo, RenderPassAttachment, RenderPassAttachmentView, RenderPassBeginInfo, RenderPassInfo, RenderpassRecordingMode, SampleCount, Scissor, ShaderType, StencilInfo, StoreOp, SubpassInfo, Swapchain, Texture, TextureViewInfo, TextureInfo, TextureRenderTargetView, TextureUsage, VertexLayoutInfo, Viewport, BarrierSync, BarrierAccess, TextureLayout, IndexFormat, WHOLE_BUFFER, BarrierTextureRange, TextureDimension, SamplerInfo, Filter, AddressMode, Device}};
 
-use crate::{renderer::{drawable::View, renderer_assets::{RendererMaterialValue, RendererAssets, RendererMaterial}, renderer_scene::RendererScene, renderer_resources::{RendererResources, HistoryResourceEntry}, shader_manager::{GraphicsPipelineInfo, GraphicsPipelineHandle, ShaderManager}}};
+use crate::{renderer::{Vertex, VertexAttribute, drawable::View, renderer_assets::{RendererMaterialValue, RendererAssets, RendererMaterial}, renderer_scene::RendererScene, renderer_resources::{RendererResources, HistoryResourceEntry}, shader_manager::{GraphicsPipelineInfo, GraphicsPipelineHandle, ShaderManager}}};
 
 pub struct GeometryPass<P: Platform> {
   pipeline: GraphicsPipelineHandle,
@@ -60,55 +60,14 @@ impl<P: Platform> GeometryPass<P> {
       fs: Some(&fs_name),
       primitive_type: PrimitiveType::Triangles,
       vertex_layout: VertexLayoutInfo {
-        input_assembler: &[
-          InputAssemblerElement {
-            binding: 0,
-            stride: 64,
-            input_rate: InputRate::PerVertex
-          }
-        ],
-        shader_inputs: &[
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 0,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 0,
-            format: Format::RGB32Float
-          },
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 1,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 16,
-            format: Format::RGB32Float
-          },
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 2,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 32,
-            format: Format::RG32Float
-          },
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 3,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 40,
-            format: Format::RG32Float
-          },
-          ShaderInputElement {
-            input_assembler_binding: 0,
-            location_vk_mtl: 4,
-            semantic_name_d3d: String::from(""),
-            semantic_index_d3d: 0,
-            offset: 48,
-            format: Format::R32Float
-          }
-        ]
+        input_assembler: &Vertex::INPUT_ASSEMBLER,
+        shader_inputs: &Vertex::shader_inputs(&[
+          VertexAttribute::Position,
+          VertexAttribute::Normal,
+          VertexAttribute::Uv,
+          VertexAttribute::LightmapUv,
+          VertexAttribute::Alpha
+        ])
       },
       rasterizer: RasterizerInfo {
         fill_mode: FillMode::Fill,
@@ -253,7 +212,8 @@ impl<P: Platform> GeometryPass<P> {
     cmd_buffer.bind_uniform_buffer(BindingFrequency::Frame, 0, camera_buffer, 0, WHOLE_BUFFER);
 
     let drawables = scene.static_drawables();
-    let parts = &view.drawable_parts;
+    // The web renderer has no translucent pass, the translucent parts get drawn last without blending.
+    let parts = view.drawable_parts.iter().chain(view.translucent_parts.iter());
     for part in parts {
       let drawable = &drawables[part.drawable_index];
       cmd_buffer.upload_dynamic_data_inline(&[drawable.transform], ShaderType::VertexShader);
@@ -14,6 +14,9 @@ pub struct SceneInfo<'a, B: Backend> {
   pub vertex_buffer: &'a Arc<B::Buffer>,
   pub index_buffer: &'a Arc<B::Buffer>,
   pub lightmap: Option<&'a RendererTexture<B>>,
+  pub skybox: Option<&'a RendererTexture<B>>,
+  /// The view of the sky camera that the 3D skybox gets drawn with.
+  pub sky_view_index: Option<usize>,
 }
 
 pub struct ZeroTextures<'a, B: Backend> {
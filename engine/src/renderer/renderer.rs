@@ -5,7 +5,7 @@ use instant::Duration;
 use log::trace;
 use sourcerenderer_core::{atomic_refcell::AtomicRefCell, platform::{Event, Platform, ThreadHandle}, Console};
 use sourcerenderer_core::graphics::{Backend, Swapchain};
-use sourcerenderer_core::Matrix4;
+use sourcerenderer_core::{Matrix4, Vec3};
 
 use crate::{asset::AssetManager, input::Input, transform::interpolation::InterpolatedTransform};
 
@@ -17,7 +17,7 @@ use legion::systems::Builder;
 
 use crate::renderer::RendererInternal;
 
-use super::{LateLatching, StaticRenderableComponent, ecs::{DirectionalLightComponent, PointLightComponent, RendererInterface}};
+use super::{LateLatching, StaticRenderableComponent, ClusterVisibility, ecs::{DirectionalLightComponent, PointLightComponent, SpotLightComponent, RendererInterface, VisibilityClusters, CubemapIndex, AmbientLightCube, VertexLighting, LightStyles, Decals, WaterSurfaces, WaterVolumes, DetailProps}};
 
 enum RendererImpl<P: Platform> {
   MultiThreaded(P::ThreadHandle),
@@ -199,14 +199,18 @@ impl<P: Platform> Renderer<P> {
 }
 
 impl<P: Platform> RendererInterface for Arc<Renderer<P>> {
-  fn register_static_renderable(&self, entity: Entity, transform: &InterpolatedTransform, renderable: &StaticRenderableComponent) {
+  fn register_static_renderable(&self, entity: Entity, transform: &InterpolatedTransform, renderable: &StaticRenderableComponent, clusters: Option<&VisibilityClusters>, cubemap: Option<&CubemapIndex>, ambient_cube: Option<&AmbientLightCube>, vertex_lighting: Option<&VertexLighting>) {
     let result = self.sender.send(RendererCommand::RegisterStatic {
       entity,
       transform: transform.0,
       model_path: renderable.model_path.to_string(),
       receive_shadows: renderable.receive_shadows,
       cast_shadows: renderable.cast_shadows,
-      can_move: renderable.can_move
+      can_move: renderable.can_move,
+      clusters: clusters.map(|clusters| clusters.0.clone()),
+      cubemap: cubemap.map(|cubemap| cubemap.0),
+      ambient_cube: ambient_cube.map(|ambient_cube| ambient_cube.0),
+      vertex_colors_path: vertex_lighting.map(|vertex_lighting| vertex_lighting.vertex_colors_path.clone())
     });
     if let Result::Err(err) = result {
       panic!("Sending message to render thread failed {:?}", err);
@@ -224,7 +228,10 @@ impl<P: Platform> RendererInterface for Arc<Renderer<P>> {
     let result = self.sender.send(RendererCommand::RegisterPointLight {
       entity,
       transform: transform.0,
-      intensity: component.intensity
+      intensity: component.intensity,
+      color: component.color,
+      range: component.range,
+      attenuation: component.attenuation
     });
     if let Result::Err(err) = result {
       panic!("Sending message to render thread failed {:?}", err);
@@ -242,7 +249,8 @@ impl<P: Platform> RendererInterface for Arc<Renderer<P>> {
     let result = self.sender.send(RendererCommand::RegisterDirectionalLight {
       entity,
       transform: transform.0,
-      intensity: component.intensity
+      intensity: component.intensity,
+      color: component.color
     });
     if let Result::Err(err) = result {
       panic!("Sending message to render thread failed {:?}", err);
@@ -256,6 +264,29 @@ impl<P: Platform> RendererInterface for Arc<Renderer<P>> {
     }
   }
 
+  fn register_spot_light(&self, entity: Entity, transform: &InterpolatedTransform, component: &SpotLightComponent) {
+    let result = self.sender.send(RendererCommand::RegisterSpotLight {
+      entity,
+      transform: transform.0,
+      intensity: component.intensity,
+      color: component.color,
+      range: component.range,
+      attenuation: component.attenuation,
+      inner_cone_angle: component.inner_cone_angle,
+      outer_cone_angle: component.outer_cone_angle
+    });
+    if let Result::Err(err) = result {
+      panic!("Sending message to render thread failed {:?}", err);
+    }
+  }
+
+  fn unregister_spot_light(&self, entity: Entity) {
+    let result = self.sender.send(RendererCommand::UnregisterSpotLight(entity));
+    if let Result::Err(err) = result {
+      panic!("Sending message to render thread failed {:?}", err);
+    }
+  }
+
   fn update_camera_transform(&self, camera_transform_mat: Matrix4, fov: f32) {
     let result = self.sender.send(RendererCommand::UpdateCameraTransform { camera_transform_mat, fov });
     if let Result::Err(err) = result {
@@ -270,6 +301,13 @@ impl<P: Platform> RendererInterface for Arc<Renderer<P>> {
     }
   }
 
+  fn update_bone_palette(&self, entity: Entity, bone_palette: &[Matrix4]) {
+    let result = self.sender.send(RendererCommand::UpdateBonePalette { entity, bone_palette: bone_palette.into() });
+    if let Result::Err(err) = result {
+      panic!("Sending message to render thread failed {:?}", err);
+    }
+  }
+
   fn end_frame(&self) {
     let mut queued_guard = self.queued_frames_counter.lock().unwrap();
     *queued_guard += 1;
@@ -286,6 +324,69 @@ impl<P: Platform> RendererInterface for Arc<Renderer<P>> {
     }
   }
 
+  fn update_light_styles(&self, light_styles: &LightStyles) {
+    let result = self.sender.send(RendererCommand::SetLightStyles(light_styles.clone()));
+    if let Result::Err(err) = result {
+      panic!("Sending message to render thread failed {:?}", err);
+    }
+  }
+
+  fn update_skybox(&self, path: &str) {
+    let result = self.sender.send(RendererCommand::SetSkybox(path.to_string()));
+    if let Result::Err(err) = result {
+      panic!("Sending message to render thread failed {:?}", err);
+    }
+  }
+
+  fn update_decals(&self, decals: &Decals) {
+    let result = self.sender.send(RendererCommand::SetDecals(decals.clone()));
+    if let Result::Err(err) = result {
+      panic!("Sending message to render thread failed {:?}", err);
+    }
+  }
+
+  fn update_water_surfaces(&self, water_surfaces: &WaterSurfaces) {
+    let result = self.sender.send(RendererCommand::SetWaterSurfaces(water_surfaces.clone()));
+    if let Result::Err(err) = result {
+      panic!("Sending message to render thread failed {:?}", err);
+    }
+  }
+
+  fn update_water_volumes(&self, water_volumes: &WaterVolumes) {
+    let result = self.sender.send(RendererCommand::SetWaterVolumes(water_volumes.clone()));
+    if let Result::Err(err) = result {
+      panic!("Sending message to render thread failed {:?}", err);
+    }
+  }
+
+  fn update_detail_props(&self, detail_props: &DetailProps) {
+    let result = self.sender.send(RendererCommand::SetDetailProps(detail_props.clone()));
+    if let Result::Err(err) = result {
+      panic!("Sending message to render thread failed {:?}", err);
+    }
+  }
+
+  fn update_sky_camera(&self, position: Vec3, scale: f32) {
+    let result = self.sender.send(RendererCommand::SetSkyCamera { position, scale });
+    if let Result::Err(err) = result {
+      panic!("Sending message to render thread failed {:?}", err);
+    }
+  }
+
+  fn update_cubemaps(&self, texture_paths: &[String]) {
+    let result = self.sender.send(RendererCommand::SetCubemaps(texture_paths.to_vec().into_boxed_slice()));
+    if let Result::Err(err) = result {
+      panic!("Sending message to render thread failed {:?}", err);
+    }
+  }
+
+  fn update_potentially_visible_set(&self, pvs: &Arc<ClusterVisibility>) {
+    let result = self.sender.send(RendererCommand::SetPotentiallyVisibleSet(pvs.clone()));
+    if let Result::Err(err) = result {
+      panic!("Sending message to render thread failed {:?}", err);
+    }
+  }
+
   fn wait_until_available(&self, timeout: Duration) {
     let queued_guard = self.queued_frames_counter.lock().unwrap();
     #[cfg(not(target_arch = "wasm32"))]
@@ -1,9 +1,10 @@
 use std::sync::Arc;
 use std::collections::HashMap;
+use std::ops::Range;
 
 use smallvec::SmallVec;
 use sourcerenderer_core::{Vec4, graphics::{Backend, Device, Fence, TextureUsage, TextureDimension}};
-use crate::{asset::{Asset, AssetManager, Material, Mesh, Model, Texture, AssetLoadPriority, MeshRange, MaterialValue}, math::BoundingBox};
+use crate::{asset::{Asset, AssetManager, Material, Mesh, Model, Texture, AssetLoadPriority, MeshRange, MeshLOD, MaterialValue}, math::BoundingBox};
 use sourcerenderer_core::Platform;
 use sourcerenderer_core::graphics::{ TextureInfo, MemoryUsage, SampleCount, Format, TextureViewInfo, BufferUsage };
 
@@ -17,6 +18,8 @@ pub struct MaterialHandle { index: u64 }
 pub struct TextureHandle { index: u64 }
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct ModelHandle { index: u64 }
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VertexColorsHandle { index: u64 }
 
 impl IndexHandle for MeshHandle {
   fn new(index: u64) -> Self { Self { index } }
@@ -30,10 +33,35 @@ impl IndexHandle for TextureHandle {
 impl IndexHandle for ModelHandle {
   fn new(index: u64) -> Self { Self { index } }
 }
+impl IndexHandle for VertexColorsHandle {
+  fn new(index: u64) -> Self { Self { index } }
+}
 
 pub struct RendererTexture<B: Backend> {
   pub(super) view: Arc<B::TextureSamplingView>,
-  pub(super) bindless_index: Option<u32>
+  pub(super) bindless_index: Option<u32>,
+  /// One view per animation frame, empty if the texture isn't animated.
+  pub(super) frame_views: Box<[Arc<B::TextureSamplingView>]>,
+  /// Bindless indices of the frame views.
+  pub(super) frame_bindless_indices: Box<[u32]>
+}
+
+impl<B: Backend> RendererTexture<B> {
+  pub(super) fn frame_view(&self, frame: u32) -> &Arc<B::TextureSamplingView> {
+    if self.frame_views.is_empty() {
+      &self.view
+    } else {
+      &self.frame_views[frame as usize % self.frame_views.len()]
+    }
+  }
+
+  pub(super) fn frame_bindless_index(&self, frame: u32) -> Option<u32> {
+    if self.frame_bindless_indices.is_empty() {
+      self.bindless_index
+    } else {
+      Some(self.frame_bindless_indices[frame as usize % self.frame_bindless_indices.len()])
+    }
+  }
 }
 
 impl<B: Backend> PartialEq for RendererTexture<B> {
@@ -45,15 +73,32 @@ impl<B: Backend> Eq for RendererTexture<B> {}
 
 pub struct RendererMaterial {
   pub(super) properties: HashMap<String, RendererMaterialValue>,
-  pub(super) shader_name: String // TODO reference actual shader
+  pub(super) shader_name: String, // TODO reference actual shader
+  pub(super) alpha_mode: AlphaMode,
+  pub(super) alpha_cutoff: f32
 }
 
 impl Clone for RendererMaterial {
   fn clone(&self) -> Self {
-    Self { properties: self.properties.clone(), shader_name: self.shader_name.clone() }
+    Self {
+      properties: self.properties.clone(),
+      shader_name: self.shader_name.clone(),
+      alpha_mode: self.alpha_mode,
+      alpha_cutoff: self.alpha_cutoff
+    }
   }
 }
 
+/// How the alpha of a material is used, the values match the ones in material.inc.glsl.
+/// Masked materials get discarded below the cutoff, blended ones are drawn in the translucent pass.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum AlphaMode {
+  Opaque = 0,
+  Mask = 1,
+  Blend = 2
+}
+
 pub enum RendererMaterialValue {
   Float(f32),
   Vec4(Vec4),
@@ -113,7 +158,7 @@ impl Ord for RendererMaterialValue {
 
 impl PartialEq for RendererMaterial {
   fn eq(&self, other: &Self) -> bool {
-    if self.shader_name != other.shader_name {
+    if self.shader_name != other.shader_name || self.alpha_mode != other.alpha_mode {
       return false;
     }
     for (key, value) in self.properties.iter() {
@@ -131,7 +176,9 @@ impl RendererMaterial {
     props.insert("albedo".to_string(), RendererMaterialValue::Texture(albedo_texture));
     Self {
       shader_name: "pbr".to_string(),
-      properties: props
+      properties: props,
+      alpha_mode: AlphaMode::Opaque,
+      alpha_cutoff: 0f32
     }
   }
 
@@ -140,13 +187,27 @@ impl RendererMaterial {
     props.insert("albedo".to_string(), RendererMaterialValue::Vec4(color));
     Self {
       shader_name: "pbr".to_string(),
-      properties: props
+      properties: props,
+      alpha_mode: AlphaMode::Opaque,
+      alpha_cutoff: 0f32
     }
   }
 
   pub fn get(&self, key: &str) -> Option<&RendererMaterialValue> {
     self.properties.get(key)
   }
+
+  pub fn alpha_mode(&self) -> AlphaMode {
+    self.alpha_mode
+  }
+
+  pub fn alpha_cutoff(&self) -> f32 {
+    self.alpha_cutoff
+  }
+
+  pub fn is_translucent(&self) -> bool {
+    self.alpha_mode == AlphaMode::Blend
+  }
 }
 
 impl Eq for RendererMaterial {}
@@ -160,6 +221,7 @@ impl PartialOrd for RendererMaterial {
 impl Ord for RendererMaterial {
   fn cmp(&self, other: &Self) -> std::cmp::Ordering {
     let mut last_result = self.shader_name.cmp(&other.shader_name)
+    .then(self.alpha_mode.cmp(&other.alpha_mode))
     .then(self.properties.len().cmp(&other.properties.len()));
 
     if last_result != std::cmp::Ordering::Equal {
@@ -205,8 +267,26 @@ pub struct RendererMesh<B: Backend> {
   pub vertices: AssetBufferSlice<B>,
   pub indices: Option<AssetBufferSlice<B>,>,
   pub parts: Box<[MeshRange]>,
+  pub lods: Box<[MeshLOD]>,
   pub bounding_box: Option<BoundingBox>,
   pub vertex_count: u32,
+  pub skinning: Option<AssetBufferSlice<B>>,
+}
+
+impl<B: Backend> RendererMesh<B> {
+  /// Picks the least detailed LOD that is allowed at the given screen size.
+  pub fn select_lod(&self, screen_size: f32) -> usize {
+    self.lods.iter()
+      .rposition(|lod| screen_size < lod.max_screen_size)
+      .unwrap_or(0)
+  }
+
+  /// The parts that get drawn for a LOD. Meshes without LODs draw all of their parts.
+  pub fn lod_parts(&self, lod: usize) -> Range<usize> {
+    self.lods.get(lod)
+      .map(|lod| lod.first_part as usize .. (lod.first_part + lod.part_count) as usize)
+      .unwrap_or(0 .. self.parts.len())
+  }
 }
 
 
@@ -216,7 +296,7 @@ struct DelayedAsset<B: Backend> {
   asset: DelayedAssetType<B>
 }
 enum DelayedAssetType<B: Backend> {
-  TextureView(Arc<B::TextureSamplingView>)
+  TextureView(Arc<B::TextureSamplingView>, Box<[Arc<B::TextureSamplingView>]>)
 }
 
 trait IndexHandle {
@@ -295,8 +375,11 @@ pub struct RendererAssets<P: Platform> {
   meshes: HandleMap<MeshHandle, RendererMesh<P::GraphicsBackend>>,
   materials: HandleMap<MaterialHandle, RendererMaterial>,
   textures: HandleMap<TextureHandle, RendererTexture<P::GraphicsBackend>>,
+  vertex_colors: HandleMap<VertexColorsHandle, AssetBufferSlice<P::GraphicsBackend>>,
   zero_texture: RendererTexture<P::GraphicsBackend>,
   zero_texture_black: RendererTexture<P::GraphicsBackend>,
+  zero_texture_cube: RendererTexture<P::GraphicsBackend>,
+  zero_texture_array: RendererTexture<P::GraphicsBackend>,
   placeholder_material: RendererMaterial,
   delayed_assets: Vec<DelayedAsset<P::GraphicsBackend>>,
   vertex_buffer: AssetBuffer<P::GraphicsBackend>,
@@ -328,7 +411,9 @@ impl<P: Platform> RendererAssets<P> {
     };
     let zero_rtexture = RendererTexture {
       view: zero_view,
-      bindless_index: zero_index
+      bindless_index: zero_index,
+      frame_views: Box::new([]),
+      frame_bindless_indices: Box::new([])
     };
 
     let zero_data_black = [0u8, 0u8, 0u8, 255u8, 0u8, 0u8, 0u8, 255u8, 0u8, 0u8, 0u8, 255u8, 0u8, 0u8, 0u8, 255u8];
@@ -354,7 +439,60 @@ impl<P: Platform> RendererAssets<P> {
     };
     let zero_rtexture_black = RendererTexture {
       view: zero_view_black,
-      bindless_index: zero_black_index
+      bindless_index: zero_black_index,
+      frame_views: Box::new([]),
+      frame_bindless_indices: Box::new([])
+    };
+
+    // Bound in place of missing cubemaps, so it doesn't add any reflections.
+    let zero_data_cube = [0u8, 0u8, 0u8, 255u8];
+    let zero_buffer_cube = device.upload_data(&zero_data_cube, MemoryUsage::UncachedRAM, BufferUsage::COPY_SRC);
+    let zero_texture_cube = device.create_texture(&TextureInfo {
+      dimension: TextureDimension::DimCube,
+      format: Format::RGBA8UNorm,
+      width: 1,
+      height: 1,
+      depth: 1,
+      mip_levels: 1,
+      array_length: 6,
+      samples: SampleCount::Samples1,
+      usage: TextureUsage::SAMPLED | TextureUsage::COPY_DST,
+      supports_srgb: false,
+    }, Some("AssetManagerZeroTextureCube"));
+    for face in 0..6 {
+      device.init_texture(&zero_texture_cube, &zero_buffer_cube, 0, face, 0);
+    }
+    let zero_view_cube = device.create_sampling_view(&zero_texture_cube, &TextureViewInfo {
+      array_layer_length: 6,
+      ..Default::default()
+    }, Some("AssetManagerZeroTextureCubeView"));
+    let zero_rtexture_cube = RendererTexture {
+      view: zero_view_cube,
+      bindless_index: None,
+      frame_views: Box::new([]),
+      frame_bindless_indices: Box::new([])
+    };
+
+    // Bound in place of a missing lightmap, so everything is fully lit.
+    let zero_texture_array = device.create_texture(&TextureInfo {
+      dimension: TextureDimension::Dim2DArray,
+      format: Format::RGBA8UNorm,
+      width: 2,
+      height: 2,
+      depth: 1,
+      mip_levels: 1,
+      array_length: 1,
+      samples: SampleCount::Samples1,
+      usage: TextureUsage::SAMPLED | TextureUsage::COPY_DST,
+      supports_srgb: false,
+    }, Some("AssetManagerZeroTextureArray"));
+    device.init_texture(&zero_texture_array, &zero_buffer, 0, 0, 0);
+    let zero_view_array = device.create_sampling_view(&zero_texture_array, &TextureViewInfo::default(), Some("AssetManagerZeroTextureArrayView"));
+    let zero_rtexture_array = RendererTexture {
+      view: zero_view_array,
+      bindless_index: None,
+      frame_views: Box::new([]),
+      frame_bindless_indices: Box::new([])
     };
     let placeholder_material = RendererMaterial::new_pbr_color(Vec4::new(1f32, 1f32, 1f32, 1f32));
 
@@ -369,8 +507,11 @@ impl<P: Platform> RendererAssets<P> {
       meshes: HandleMap::new(),
       materials: HandleMap::new(),
       textures: HandleMap::new(),
+      vertex_colors: HandleMap::new(),
       zero_texture: zero_rtexture,
       zero_texture_black: zero_rtexture_black,
+      zero_texture_cube: zero_rtexture_cube,
+      zero_texture_array: zero_rtexture_array,
       placeholder_material,
       delayed_assets: Vec::new(),
       vertex_buffer,
@@ -378,7 +519,7 @@ impl<P: Platform> RendererAssets<P> {
     }
   }
 
-  pub fn integrate_texture(&mut self, texture_path: &str, texture: &Arc<<P::GraphicsBackend as Backend>::TextureSamplingView>) -> TextureHandle {
+  pub fn integrate_texture(&mut self, texture_path: &str, texture: &Arc<<P::GraphicsBackend as Backend>::TextureSamplingView>, frame_views: &[Arc<<P::GraphicsBackend as Backend>::TextureSamplingView>]) -> TextureHandle {
     let bindless_index = if self.device.supports_bindless() {
       if texture == &self.zero_texture.view {
         self.zero_texture.bindless_index
@@ -390,9 +531,16 @@ impl<P: Platform> RendererAssets<P> {
     } else {
       None
     };
+    let frame_bindless_indices = if self.device.supports_bindless() {
+      frame_views.iter().map(|frame_view| self.device.insert_texture_into_bindless_heap(frame_view)).collect()
+    } else {
+      Box::new([]) as Box<[u32]>
+    };
     let renderer_texture = RendererTexture {
       view: texture.clone(),
-      bindless_index
+      bindless_index,
+      frame_views: frame_views.iter().cloned().collect(),
+      frame_bindless_indices
     };
     self.textures.insert(&texture_path, renderer_texture)
   }
@@ -411,17 +559,34 @@ impl<P: Platform> RendererAssets<P> {
       buffer
     });
 
+    let skinning_buffer = mesh.skinning.map(|skinning| {
+      let alignment = std::mem::size_of::<crate::renderer::SkinningVertex>().max(self.device.min_storage_buffer_offset_alignment());
+      let buffer = self.vertex_buffer.get_slice(std::mem::size_of_val(&skinning[..]), alignment);
+      let temp_buffer = self.device.upload_data(&skinning[..], MemoryUsage::UncachedRAM, BufferUsage::COPY_SRC);
+      self.device.init_buffer(&temp_buffer, buffer.buffer(), 0, buffer.offset() as usize, buffer.size() as usize);
+      buffer
+    });
+
     let mesh = RendererMesh {
       vertices: vertex_buffer,
       indices: index_buffer,
       parts: mesh.parts.iter().cloned().collect(), // TODO: change base type to boxed slice
+      lods: mesh.lods,
       bounding_box: mesh.bounding_box,
-      vertex_count: mesh.vertex_count
+      vertex_count: mesh.vertex_count,
+      skinning: skinning_buffer
     };
     self.meshes.insert(mesh_path, mesh)
   }
 
-  pub fn upload_texture(&mut self, texture_path: &str, texture: Texture, do_async: bool) -> (Arc<<P::GraphicsBackend as Backend>::TextureSamplingView>, Option<Arc<<P::GraphicsBackend as Backend>::Fence>>) {
+  pub fn integrate_vertex_colors(&mut self, path: &str, colors: &[u32]) -> VertexColorsHandle {
+    let buffer = self.vertex_buffer.get_slice(std::mem::size_of_val(colors), std::mem::size_of::<u32>());
+    let temp_buffer = self.device.upload_data(colors, MemoryUsage::UncachedRAM, BufferUsage::COPY_SRC);
+    self.device.init_buffer(&temp_buffer, buffer.buffer(), 0, buffer.offset() as usize, buffer.size() as usize);
+    self.vertex_colors.insert(path, buffer)
+  }
+
+  pub fn upload_texture(&mut self, texture_path: &str, texture: Texture, do_async: bool) -> (Arc<<P::GraphicsBackend as Backend>::TextureSamplingView>, Box<[Arc<<P::GraphicsBackend as Backend>::TextureSamplingView>]>, Option<Arc<<P::GraphicsBackend as Backend>::Fence>>) {
     let gpu_texture = self.device.create_texture(&texture.info, Some(texture_path));
     let subresources = texture.info.array_length * texture.info.mip_levels;
     let mut fence = Option::<Arc<<P::GraphicsBackend as Backend>::Fence>>::None;
@@ -436,16 +601,37 @@ impl<P: Platform> RendererAssets<P> {
         self.device.init_texture(&gpu_texture, &init_buffer, mip_level, array_index, 0);
       }
     }
+    // Cube maps get a view of the first cube, animated textures one of the first frame and array textures one of all layers.
     let view = self.device.create_sampling_view(
       &gpu_texture, &TextureViewInfo {
         base_mip_level: 0,
         mip_level_length: texture.info.mip_levels,
         base_array_layer: 0,
-        array_layer_length: 1,
+        array_layer_length: match texture.info.dimension {
+          TextureDimension::DimCube => 6,
+          TextureDimension::Dim2DArray => texture.info.array_length,
+          _ => 1
+        },
         format: None,
     }, Some(texture_path));
 
-    (view, fence)
+    // Animated textures store their frames in the array layers.
+    let frame_views: Box<[Arc<<P::GraphicsBackend as Backend>::TextureSamplingView>]> = if texture.info.dimension == TextureDimension::Dim2D && texture.info.array_length > 1 {
+      (0..texture.info.array_length).map(|frame| {
+        self.device.create_sampling_view(
+          &gpu_texture, &TextureViewInfo {
+            base_mip_level: 0,
+            mip_level_length: texture.info.mip_levels,
+            base_array_layer: frame,
+            array_layer_length: 1,
+            format: None,
+        }, Some(&format!("{}_frame{}", texture_path, frame)))
+      }).collect()
+    } else {
+      Box::new([])
+    };
+
+    (view, frame_views, fence)
   }
 
   pub fn integrate_material(&mut self, material_path: &str, material: &Material) -> MaterialHandle {
@@ -467,9 +653,18 @@ impl<P: Platform> RendererAssets<P> {
       }
     }
 
+    // Blending takes priority, blended materials can't write depth so there's nothing to discard.
+    let (alpha_mode, alpha_cutoff) = match (properties.get("alpha_blend"), properties.get("alpha_cutoff")) {
+      (Some(_), _) => (AlphaMode::Blend, 0f32),
+      (None, Some(RendererMaterialValue::Float(cutoff))) => (AlphaMode::Mask, *cutoff),
+      _ => (AlphaMode::Opaque, 0f32)
+    };
+
     let renderer_material = RendererMaterial {
       shader_name: material.shader_name.clone(),
-      properties
+      properties,
+      alpha_mode,
+      alpha_cutoff
     };
 
     self.materials.insert(material_path, renderer_material)
@@ -504,10 +699,24 @@ impl<P: Platform> RendererAssets<P> {
     self.meshes.get_value(handle)
   }
 
+  pub fn get_or_create_vertex_colors_handle(&mut self, path: &str) -> VertexColorsHandle {
+    self.vertex_colors.get_or_create_handle(path)
+  }
+
+  /// The baked colors of a drawable, only if there is one for every vertex of the mesh.
+  pub fn get_vertex_colors(&self, handle: VertexColorsHandle, mesh: &RendererMesh<P::GraphicsBackend>) -> Option<&AssetBufferSlice<P::GraphicsBackend>> {
+    self.vertex_colors.get_value(handle)
+      .filter(|colors| colors.size() as usize == mesh.vertex_count as usize * std::mem::size_of::<u32>())
+  }
+
   pub fn get_or_create_texture_handle(&mut self, path: &str) -> TextureHandle {
     self.textures.get_or_create_handle(path)
   }
 
+  pub fn get_or_create_material_handle(&mut self, path: &str) -> MaterialHandle {
+    self.materials.get_or_create_handle(path)
+  }
+
   pub fn get_material(&self, handle: MaterialHandle) -> &RendererMaterial {
     self.materials.get_value(handle).unwrap_or(&self.placeholder_material)
   }
@@ -517,6 +726,22 @@ impl<P: Platform> RendererAssets<P> {
       .unwrap_or_else(|| &self.zero_texture)
   }
 
+  /// Falls back to a black cube map while the texture isn't loaded.
+  pub fn get_cube_texture(&self, handle: TextureHandle) -> &RendererTexture<P::GraphicsBackend> {
+    self.textures.get_value(handle)
+      .unwrap_or_else(|| &self.zero_texture_cube)
+  }
+
+  /// Falls back to a white texture array while the texture isn't loaded.
+  pub fn get_array_texture(&self, handle: TextureHandle) -> &RendererTexture<P::GraphicsBackend> {
+    self.textures.get_value(handle)
+      .unwrap_or_else(|| &self.zero_texture_array)
+  }
+
+  pub fn has_texture(&self, handle: TextureHandle) -> bool {
+    self.textures.contains(handle)
+  }
+
   pub fn placeholder_texture(&self) -> &RendererTexture<P::GraphicsBackend> {
     &self.zero_texture
   }
@@ -525,6 +750,14 @@ impl<P: Platform> RendererAssets<P> {
     &self.zero_texture_black
   }
 
+  pub fn placeholder_cube(&self) -> &RendererTexture<P::GraphicsBackend> {
+    &self.zero_texture_cube
+  }
+
+  pub fn placeholder_array(&self) -> &RendererTexture<P::GraphicsBackend> {
+    &self.zero_texture_array
+  }
+
   pub fn is_empty(&self) -> bool {
     self.models.len() == 0 && self.meshes.len() == 0 && self.materials.len() == 0 && self.textures.len() == 0
   }
@@ -543,8 +776,8 @@ impl<P: Platform> RendererAssets<P> {
 
     for delayed_asset in ready_delayed_assets.drain(..) {
       match &delayed_asset.asset {
-        DelayedAssetType::TextureView(view) => {
-          self.integrate_texture(&delayed_asset.path, view);
+        DelayedAssetType::TextureView(view, frame_views) => {
+          self.integrate_texture(&delayed_asset.path, view, frame_views);
         }
       }
     }
@@ -556,17 +789,18 @@ impl<P: Platform> RendererAssets<P> {
         Asset::Material(material) => { self.integrate_material(&asset.path, &material); }
         Asset::Model(model) => { self.integrate_model(&asset.path, &model); }
         Asset::Mesh(mesh) => { self.integrate_mesh(&asset.path, mesh); }
+        Asset::VertexColors(colors) => { self.integrate_vertex_colors(&asset.path, &colors); }
         Asset::Texture(texture) => {
           let do_async = asset.priority == AssetLoadPriority::Low;
-          let (view, fence) = self.upload_texture(&asset.path, texture, do_async);
+          let (view, frame_views, fence) = self.upload_texture(&asset.path, texture, do_async);
           if let Some(fence) = fence {
             self.delayed_assets.push(DelayedAsset {
               fence,
               path: asset.path.to_string(),
-              asset: DelayedAssetType::TextureView(view)
+              asset: DelayedAssetType::TextureView(view, frame_views)
             });
           } else {
-            self.integrate_texture(&asset.path, &view);
+            self.integrate_texture(&asset.path, &view, &frame_views);
           }
         },
         Asset::Shader(shader) => {
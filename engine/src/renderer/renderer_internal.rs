@@ -20,11 +20,11 @@ use rayon::prelude::*;
 use crate::math::{Frustum, BoundingBox};
 use instant::Instant;
 
-use super::PointLight;
-use super::drawable::{make_camera_proj, make_camera_view};
-use super::light::DirectionalLight;
+use super::{PointLight, ClusterVisibility};
+use super::drawable::{make_camera_proj, make_camera_view, projected_screen_size};
+use super::light::{DirectionalLight, SpotLight};
 use super::render_path::RenderPath;
-use super::renderer_scene::RendererScene;
+use super::renderer_scene::{RendererScene, RendererWaterVolume, RendererWaterSurface, RendererDetailProps, RendererDecals};
 
 #[cfg(not(target_arch = "wasm32"))]
 use super::passes::modern::ModernRenderer;
@@ -50,6 +50,8 @@ pub(super) struct RendererInternal<P: Platform> {
 }
 
 impl<P: Platform> RendererInternal<P> {
+  const SKY_VIEW_INDEX: usize = 1;
+
   pub(super) fn new(
     device: &Arc<<P::GraphicsBackend as Backend>::Device>,
     swapchain: &Arc<<P::GraphicsBackend as Backend>::Swapchain>,
@@ -210,11 +212,15 @@ impl<P: Platform> RendererInternal<P> {
         RendererCommand::UpdateTransform { entity, transform_mat } => {
           self.scene.update_transform(&entity, transform_mat);
         }
+        RendererCommand::UpdateBonePalette { entity, bone_palette } => {
+          self.scene.update_bone_palette(&entity, bone_palette);
+        }
 
         RendererCommand::RegisterStatic {
-          model_path, entity, transform, receive_shadows, cast_shadows, can_move
+          model_path, entity, transform, receive_shadows, cast_shadows, can_move, clusters, cubemap, ambient_cube, vertex_colors_path
          } => {
           let model = self.assets.get_or_create_model_handle(&model_path);
+          let vertex_colors = vertex_colors_path.map(|path| self.assets.get_or_create_vertex_colors_handle(&path));
           self.scene.add_static_drawable(entity, RendererStaticDrawable {
             entity,
             transform,
@@ -222,7 +228,12 @@ impl<P: Platform> RendererInternal<P> {
             model,
             receive_shadows,
             cast_shadows,
-            can_move
+            can_move,
+            clusters,
+            cubemap,
+            ambient_cube,
+            vertex_colors,
+            bone_palette: None
           });
         }
         RendererCommand::UnregisterStatic(entity) => {
@@ -232,11 +243,17 @@ impl<P: Platform> RendererInternal<P> {
         RendererCommand::RegisterPointLight {
           entity,
           transform,
-          intensity
+          intensity,
+          color,
+          range,
+          attenuation
         } => {
           self.scene.add_point_light(entity, PointLight {
             position: (transform * Vec4::new(0f32, 0f32, 0f32, 1f32)).xyz(),
             intensity,
+            color,
+            range,
+            attenuation
           });
         },
         RendererCommand::UnregisterPointLight(entity) => {
@@ -246,20 +263,97 @@ impl<P: Platform> RendererInternal<P> {
         RendererCommand::RegisterDirectionalLight {
           entity,
           transform,
-          intensity
+          intensity,
+          color
         } => {
           let (_, rotation, _) = deconstruct_transform(&transform);
           let base_dir = Vec3::new(0f32, 1f32, 0f32);
           let dir = rotation.transform_vector(&base_dir);
-          self.scene.add_directional_light(entity, DirectionalLight { direction: dir, intensity});
+          self.scene.add_directional_light(entity, DirectionalLight { direction: dir, intensity, color });
         },
         RendererCommand::UnregisterDirectionalLight(entity) => {
           self.scene.remove_directional_light(&entity);
         },
+
+        RendererCommand::RegisterSpotLight {
+          entity,
+          transform,
+          intensity,
+          color,
+          range,
+          attenuation,
+          inner_cone_angle,
+          outer_cone_angle
+        } => {
+          self.scene.add_spot_light(entity, SpotLight {
+            position: (transform * Vec4::new(0f32, 0f32, 0f32, 1f32)).xyz(),
+            intensity,
+            color,
+            range,
+            attenuation,
+            direction: (transform * Vec4::new(0f32, 1f32, 0f32, 0f32)).xyz().normalize(),
+            inner_cone_angle,
+            outer_cone_angle
+          });
+        },
+        RendererCommand::UnregisterSpotLight(entity) => {
+          self.scene.remove_spot_light(&entity);
+        },
         RendererCommand::SetLightmap(path) => {
           let handle = self.assets.get_or_create_texture_handle(&path);
           self.scene.set_lightmap(Some(handle));
         },
+        RendererCommand::SetLightStyles(light_styles) => {
+          self.scene.set_light_styles(light_styles);
+        },
+        RendererCommand::SetSkybox(path) => {
+          let handle = self.assets.get_or_create_texture_handle(&path);
+          self.scene.set_skybox(Some(handle));
+        },
+        RendererCommand::SetDecals(decals) => {
+          let model = self.assets.get_or_create_model_handle(&decals.model_path);
+          self.scene.set_decals(Some(RendererDecals {
+            model,
+            part_fades: decals.part_fades
+          }));
+        },
+        RendererCommand::SetWaterSurfaces(water_surfaces) => {
+          let surfaces = water_surfaces.surfaces.iter().map(|surface| RendererWaterSurface {
+            model: self.assets.get_or_create_model_handle(&surface.model_path),
+            transform: surface.transform
+          }).collect();
+          self.scene.set_water_surfaces(surfaces);
+        },
+        RendererCommand::SetWaterVolumes(water_volumes) => {
+          let volumes = water_volumes.volumes.iter().map(|volume| RendererWaterVolume {
+            surface_height: volume.surface_height,
+            material: self.assets.get_or_create_material_handle(&volume.material_path)
+          }).collect();
+          self.scene.set_water_volumes(water_volumes.leaf_volumes, volumes);
+        },
+        RendererCommand::SetDetailProps(detail_props) => {
+          let sprite_material = self.assets.get_or_create_material_handle(&detail_props.sprite_material_path);
+          let models = detail_props.models.into_vec().into_iter()
+            .map(|model| (self.assets.get_or_create_model_handle(&model.model_path), model.instances))
+            .collect();
+          self.scene.set_detail_props(Some(RendererDetailProps {
+            sprite_material,
+            sprites: detail_props.sprites,
+            models,
+            fade_start: detail_props.fade_start,
+            fade_end: detail_props.fade_end
+          }));
+        },
+        RendererCommand::SetSkyCamera { position, scale } => {
+          self.scene.set_sky_camera(Some((position, scale)));
+        },
+        RendererCommand::SetCubemaps(paths) => {
+          let handles = paths.iter().map(|path| self.assets.get_or_create_texture_handle(path)).collect();
+          self.scene.set_cubemaps(handles);
+        },
+        RendererCommand::SetPotentiallyVisibleSet(pvs) => {
+          self.scene.set_potentially_visible_set(Some(pvs));
+        },
       }
 
       let message_res = self.receiver.try_recv();
@@ -285,6 +379,7 @@ impl<P: Platform> RendererInternal<P> {
 
     self.views[0].aspect_ratio = (self.swapchain.width() as f32) / (self.swapchain.height() as f32);
 
+    self.update_sky_view();
     self.update_visibility();
     self.reorder();
 
@@ -300,17 +395,22 @@ impl<P: Platform> RendererInternal<P> {
       };
 
       let lightmap: &RendererTexture<P::GraphicsBackend> = if let Some(lightmap_handle) = self.scene.lightmap() {
-        self.assets.get_texture(lightmap_handle)
+        self.assets.get_array_texture(lightmap_handle)
       } else {
-        self.assets.placeholder_texture()
+        self.assets.placeholder_array()
       }; // Doing .map() here is considered a immutable borrow of self
+      let skybox = self.scene.skybox()
+        .filter(|skybox_handle| self.assets.has_texture(*skybox_handle))
+        .map(|skybox_handle| self.assets.get_texture(skybox_handle));
       let scene_info = SceneInfo {
         scene: &self.scene,
         views: &self.views,
         active_view_index: 0,
         vertex_buffer: self.assets.vertex_buffer(),
         index_buffer: self.assets.index_buffer(),
-        lightmap: Some(lightmap)
+        lightmap: Some(lightmap),
+        skybox,
+        sky_view_index: self.sky_view_index()
       };
 
       self.render_path.render(&scene_info, &zero_textures, renderer.late_latching(), renderer.input(), &frame_info, &self.shader_manager, &self.assets)
@@ -358,17 +458,22 @@ impl<P: Platform> RendererInternal<P> {
           };
 
           let lightmap: &RendererTexture<P::GraphicsBackend> = if let Some(lightmap_handle) = self.scene.lightmap() {
-            self.assets.get_texture(lightmap_handle)
+            self.assets.get_array_texture(lightmap_handle)
           } else {
-            self.assets.placeholder_texture()
+            self.assets.placeholder_array()
           }; // Doing .map() here is considered a immutable borrow of self
+          let skybox = self.scene.skybox()
+            .filter(|skybox_handle| self.assets.has_texture(*skybox_handle))
+            .map(|skybox_handle| self.assets.get_texture(skybox_handle));
           let scene_info = SceneInfo {
             scene: &self.scene,
             views: &self.views,
             active_view_index: 0,
             vertex_buffer: self.assets.vertex_buffer(),
             index_buffer: self.assets.index_buffer(),
-            lightmap: Some(lightmap)
+            lightmap: Some(lightmap),
+            skybox,
+            sky_view_index: self.sky_view_index()
           };
 
           self.render_path.render(&scene_info, &zero_textures, renderer.late_latching(), renderer.input(), &frame_info, &self.shader_manager, &self.assets).expect("Rendering still fails after recreating swapchain.");
@@ -381,17 +486,56 @@ impl<P: Platform> RendererInternal<P> {
     profiling::finish_frame!();
   }
 
-  #[profiling::function]
-  fn update_visibility(&mut self) {
-    if self.render_path.is_gpu_driven() {
+  fn sky_view_index(&self) -> Option<usize> {
+    if self.views.len() > Self::SKY_VIEW_INDEX {
+      Some(Self::SKY_VIEW_INDEX)
+    } else {
+      None
+    }
+  }
+
+  /// The sky camera moves through the 3D skybox along with the main camera, scaled down by the skybox scale.
+  fn update_sky_view(&mut self) {
+    let sky_camera = self.scene.sky_camera();
+    if sky_camera.is_none() {
+      self.views.truncate(Self::SKY_VIEW_INDEX);
       return;
     }
+    let (sky_camera_position, scale) = sky_camera.unwrap();
+    if self.views.len() <= Self::SKY_VIEW_INDEX {
+      self.views.push(View::default());
+    }
 
+    let (main_views, sky_views) = self.views.split_at_mut(Self::SKY_VIEW_INDEX);
+    let main_view = &main_views[0];
+    let sky_view = &mut sky_views[0];
+    sky_view.camera_position = sky_camera_position + main_view.camera_position / scale;
+    sky_view.camera_rotation = main_view.camera_rotation;
+    sky_view.camera_fov = main_view.camera_fov;
+    sky_view.aspect_ratio = main_view.aspect_ratio;
+    sky_view.near_plane = main_view.near_plane;
+    sky_view.far_plane = main_view.far_plane;
+    sky_view.camera_transform = Matrix4::new_translation(&sky_view.camera_position) * sky_view.camera_rotation.to_homogeneous();
+    sky_view.old_camera_matrix = sky_view.proj_matrix * sky_view.view_matrix;
+    sky_view.view_matrix = make_camera_view(sky_view.camera_position, sky_view.camera_rotation);
+    sky_view.proj_matrix = make_camera_proj(sky_view.camera_fov, sky_view.aspect_ratio, sky_view.near_plane, sky_view.far_plane);
+  }
+
+  #[profiling::function]
+  fn update_visibility(&mut self) {
+    let is_gpu_driven = self.render_path.is_gpu_driven();
     let static_meshes = self.scene.static_drawables();
+    let pvs = self.scene.potentially_visible_set();
 
     let active_view_index = 0;
+    let sky_view_index = self.sky_view_index();
+    let rt_height = self.swapchain.height();
 
     for (index, view_mut) in self.views.iter_mut().enumerate() {
+      // GPU driven render paths cull the main view on the GPU, only the translucent parts get collected here.
+      let is_gpu_culled = is_gpu_driven && index == active_view_index;
+      let is_sky_view = Some(index) == sky_view_index;
+
       let mut old_visible = std::mem::take(&mut view_mut.visible_drawables_bitset);
 
       if index == active_view_index {
@@ -402,33 +546,51 @@ impl<P: Platform> RendererInternal<P> {
 
       let mut existing_drawable_bitset = std::mem::take(&mut view_mut.old_visible_drawables_bitset);
       let mut existing_parts = std::mem::take(&mut view_mut.drawable_parts);
+      let mut existing_translucent_parts = std::mem::take(&mut view_mut.translucent_parts);
       // take out vector, creating a new one doesn't allocate until we push an element to it.
       existing_drawable_bitset.clear();
       existing_parts.clear();
+      existing_translucent_parts.clear();
       let drawable_u32_count = (static_meshes.len() + 31) / 32;
       if existing_drawable_bitset.len() < drawable_u32_count {
         existing_drawable_bitset.resize(drawable_u32_count, 0);
       }
       let visible_drawables_bitset = Mutex::new(existing_drawable_bitset);
       let visible_parts = Mutex::new(existing_parts);
+      let translucent_parts = Mutex::new(existing_translucent_parts);
 
       let frustum = Frustum::new(view_mut.near_plane, view_mut.far_plane, view_mut.camera_fov, view_mut.aspect_ratio);
       let camera_matrix = view_mut.view_matrix;
       let camera_position = view_mut.camera_position;
       let assets = &self.assets;
+      // Cameras outside of the level can see every cluster.
+      let visible_clusters = pvs.and_then(|pvs| pvs.find_cluster(&camera_position).map(|cluster| pvs.visible_clusters(cluster)));
 
       const CHUNK_SIZE: usize = 64;
       static_meshes.par_chunks(CHUNK_SIZE).enumerate().for_each(|(chunk_index, chunk)| {
         let mut chunk_visible_parts = SmallVec::<[DrawablePart; CHUNK_SIZE]>::new();
+        let mut chunk_translucent_parts = SmallVec::<[DrawablePart; 16]>::new();
         let mut visible_drawables = [0u32; CHUNK_SIZE / 32];
         visible_drawables.bit_init(false);
         for (index, static_mesh) in chunk.iter().enumerate() {
+          // Only drawables in the clusters around the sky camera belong to the 3D skybox.
+          if is_sky_view && (visible_clusters.is_none() || static_mesh.clusters.is_none()) {
+            continue;
+          }
+
+          if let (Some(visible_clusters), Some(clusters)) = (visible_clusters, static_mesh.clusters.as_ref()) {
+            if !clusters.iter().any(|cluster| ClusterVisibility::is_visible(visible_clusters, *cluster)) {
+              continue;
+            }
+          }
+
           let model_view_matrix = camera_matrix * static_mesh.transform;
           let model = assets.get_model(static_mesh.model);
           if model.is_none() {
             continue;
           }
-          let mesh = assets.get_mesh(model.unwrap().mesh_handle());
+          let model = model.unwrap();
+          let mesh = assets.get_mesh(model.mesh_handle());
           if mesh.is_none() {
             continue;
           }
@@ -467,12 +629,25 @@ impl<P: Platform> RendererInternal<P> {
             false
           };
 
-          if old_visible.len() * 32 > drawable_index && !old_visible.bit_test(drawable_index) && !camera_in_bb {
+          if !is_gpu_culled && old_visible.len() * 32 > drawable_index && !old_visible.bit_test(drawable_index) && !camera_in_bb {
             // Mesh was not visible in the previous frame.
             continue;
           }
 
-          for part_index in 0..mesh.parts.len() {
+          let lod = mesh.select_lod(projected_screen_size(view_mut, &static_mesh.transform, rt_height));
+          for part_index in mesh.lod_parts(lod) {
+            let material = assets.get_material(model.material_handles()[part_index]);
+            if material.is_translucent() {
+              chunk_translucent_parts.push(DrawablePart {
+                drawable_index,
+                part_index
+              });
+              continue;
+            }
+            if is_gpu_culled {
+              continue;
+            }
+
             if chunk_visible_parts.len() == chunk_visible_parts.capacity() {
               let mut global_parts = visible_parts.lock().unwrap();
               global_parts.extend_from_slice(&chunk_visible_parts[..]);
@@ -497,9 +672,33 @@ impl<P: Platform> RendererInternal<P> {
         let mut global_parts = visible_parts.lock().unwrap();
         global_parts.extend_from_slice(&chunk_visible_parts[..]);
         chunk_visible_parts.clear();
+        if !chunk_translucent_parts.is_empty() {
+          let mut global_translucent_parts = translucent_parts.lock().unwrap();
+          global_translucent_parts.extend_from_slice(&chunk_translucent_parts[..]);
+        }
       });
 
+      // Blending needs the translucent parts drawn back to front.
+      // The sort is stable, so the parts of a drawable stay in the order of the mesh.
+      let mut translucent_parts = translucent_parts.into_inner().unwrap();
+      let part_distance = |part: &DrawablePart| {
+        let static_mesh = &static_meshes[part.drawable_index];
+        let center = assets.get_model(static_mesh.model)
+          .and_then(|model| assets.get_mesh(model.mesh_handle()))
+          .and_then(|mesh| mesh.bounding_box.as_ref())
+          .map_or_else(|| Vec3::new(0f32, 0f32, 0f32), |bb| bb.min + (bb.max - bb.min) * 0.5f32);
+        let world_center = (static_mesh.transform * Vec4::new(center.x, center.y, center.z, 1f32)).xyz();
+        (world_center - camera_position).magnitude_squared()
+      };
+      // The distances get computed once per part instead of in every comparison.
+      let mut sorted_translucent_parts: Vec<(f32, DrawablePart)> = translucent_parts.drain(..)
+        .map(|part| (part_distance(&part), part))
+        .collect();
+      sorted_translucent_parts.sort_by(|(a, _), (b, _)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+      translucent_parts.extend(sorted_translucent_parts.into_iter().map(|(_, part)| part));
+
       view_mut.drawable_parts = visible_parts.into_inner().unwrap();
+      view_mut.translucent_parts = translucent_parts;
       view_mut.visible_drawables_bitset = visible_drawables_bitset.into_inner().unwrap();
       view_mut.old_visible_drawables_bitset = old_visible;
     }
@@ -73,6 +73,7 @@ pub struct RendererResources<B: Backend> {
   buffers: HashMap<String, AB<RefCell<TrackedBuffer<B>>>>,
   nearest_sampler: Arc<B::Sampler>,
   linear_sampler: Arc<B::Sampler>,
+  repeat_sampler: Arc<B::Sampler>,
   current_pass: ABEntry,
   global: RefCell<GlobalMemoryBarrier>
 }
@@ -105,6 +106,19 @@ impl<B: Backend> RendererResources<B> {
       min_lod: 0f32,
       max_lod: None,
     });
+    let repeat_sampler = device.create_sampler(&SamplerInfo {
+      mag_filter: Filter::Linear,
+      min_filter: Filter::Linear,
+      mip_filter: Filter::Linear,
+      address_mode_u: AddressMode::Repeat,
+      address_mode_v: AddressMode::Repeat,
+      address_mode_w: AddressMode::Repeat,
+      mip_bias: 0f32,
+      max_anisotropy: 0f32,
+      compare_op: None,
+      min_lod: 0f32,
+      max_lod: None,
+    });
 
     Self {
       device: device.clone(),
@@ -112,6 +126,7 @@ impl<B: Backend> RendererResources<B> {
       buffers: HashMap::new(),
       linear_sampler,
       nearest_sampler,
+      repeat_sampler,
       current_pass: ABEntry::A,
       global: RefCell::new(GlobalMemoryBarrier {
         stages: BarrierSync::empty(),
@@ -135,6 +150,11 @@ impl<B: Backend> RendererResources<B> {
     &self.linear_sampler
   }
 
+  /// Linear sampler that wraps around, for material textures.
+  pub fn repeat_sampler(&self) -> &Arc<B::Sampler> {
+    &self.repeat_sampler
+  }
+
   pub fn create_texture(&mut self, name: &str, info: &TextureInfo, has_history: bool) {
     let mut subresources: Vec<TrackedTextureSubresource> = Vec::new();
     subresources.resize(calculate_subresources(info.mip_levels, info.array_length) as usize, TrackedTextureSubresource::default());
@@ -1,18 +1,57 @@
-use std::{collections::HashMap, usize};
+use std::{collections::HashMap, sync::Arc, usize};
 
 use legion::Entity;
-use sourcerenderer_core::{Matrix4, Vec4, graphics::Backend};
+use sourcerenderer_core::{Matrix4, Vec3, Vec4, graphics::Backend};
 
-use super::{PointLight, RendererStaticDrawable, light::{DirectionalLight, RendererDirectionalLight, RendererPointLight}, renderer_assets::TextureHandle};
+use super::{PointLight, RendererStaticDrawable, ClusterVisibility, LightStyles, DecalFade, DetailSprite, DetailModelInstance, light::{DirectionalLight, RendererDirectionalLight, RendererPointLight, SpotLight, RendererSpotLight}, renderer_assets::{TextureHandle, ModelHandle, MaterialHandle}};
+
+/// A body of water, see `WaterVolume`.
+pub struct RendererWaterVolume {
+  pub surface_height: f32,
+  pub material: MaterialHandle
+}
+
+/// The water surfaces of a brush model, see `WaterSurface`.
+pub struct RendererWaterSurface {
+  pub model: ModelHandle,
+  pub transform: Matrix4
+}
+
+/// The overlays and decals of the level, see `Decals`.
+pub struct RendererDecals {
+  pub model: ModelHandle,
+  pub part_fades: Box<[DecalFade]>
+}
+
+/// The detail props of the level, see `DetailProps`.
+pub struct RendererDetailProps {
+  pub sprite_material: MaterialHandle,
+  pub sprites: Box<[DetailSprite]>,
+  pub models: Box<[(ModelHandle, Box<[DetailModelInstance]>)]>,
+  pub fade_start: f32,
+  pub fade_end: f32
+}
 
 pub struct RendererScene<B: Backend> {
   static_meshes: Vec<RendererStaticDrawable>,
   point_lights: Vec<RendererPointLight<B>>,
   directional_lights: Vec<RendererDirectionalLight<B>>,
+  spot_lights: Vec<RendererSpotLight<B>>,
   drawable_entity_map: HashMap<Entity, usize>,
   point_light_entity_map: HashMap<Entity, usize>,
   directional_light_entity_map: HashMap<Entity, usize>,
+  spot_light_entity_map: HashMap<Entity, usize>,
   lightmap: Option<TextureHandle>,
+  light_styles: LightStyles,
+  skybox: Option<TextureHandle>,
+  decals: Option<RendererDecals>,
+  water_surfaces: Box<[RendererWaterSurface]>,
+  leaf_water_volumes: Box<[i16]>,
+  water_volumes: Box<[RendererWaterVolume]>,
+  detail_props: Option<RendererDetailProps>,
+  sky_camera: Option<(Vec3, f32)>,
+  cubemaps: Vec<TextureHandle>,
+  potentially_visible_set: Option<Arc<ClusterVisibility>>,
 }
 
 impl<B: Backend> RendererScene<B> {
@@ -21,10 +60,22 @@ impl<B: Backend> RendererScene<B> {
       static_meshes: Vec::new(),
       point_lights: Vec::new(),
       directional_lights: Vec::new(),
+      spot_lights: Vec::new(),
       drawable_entity_map: HashMap::new(),
       point_light_entity_map: HashMap::new(),
       directional_light_entity_map: HashMap::new(),
-      lightmap: None
+      spot_light_entity_map: HashMap::new(),
+      lightmap: None,
+      light_styles: LightStyles::default(),
+      skybox: None,
+      decals: None,
+      water_surfaces: Box::new([]),
+      leaf_water_volumes: Box::new([]),
+      water_volumes: Box::new([]),
+      detail_props: None,
+      sky_camera: None,
+      cubemaps: Vec::new(),
+      potentially_visible_set: None
     }
   }
 
@@ -40,6 +91,10 @@ impl<B: Backend> RendererScene<B> {
     &self.directional_lights
   }
 
+  pub fn spot_lights(&self) -> &[RendererSpotLight<B>] {
+    &self.spot_lights
+  }
+
   pub fn add_static_drawable(&mut self, entity: Entity, static_drawable: RendererStaticDrawable) {
     self.drawable_entity_map.insert(entity, self.static_meshes.len());
     self.static_meshes.push(static_drawable);
@@ -70,12 +125,26 @@ impl<B: Backend> RendererScene<B> {
       return;
     }
 
+    let index = self.spot_light_entity_map.get(entity);
+    if let Some(index) = index {
+      let spot_light = &mut self.spot_lights[*index];
+      spot_light.position = (transform * Vec4::new(0f32, 0f32, 0f32, 1f32)).xyz();
+      spot_light.direction = (transform * Vec4::new(0f32, 1f32, 0f32, 0f32)).xyz().normalize();
+      return;
+    }
+
     debug_assert!(false); // debug unreachable
   }
 
+  pub fn update_bone_palette(&mut self, entity: &Entity, bone_palette: Box<[Matrix4]>) {
+    if let Some(index) = self.drawable_entity_map.get(entity) {
+      self.static_meshes[*index].bone_palette = Some(bone_palette);
+    }
+  }
+
   pub fn add_point_light(&mut self, entity: Entity, light: PointLight) {
     self.point_light_entity_map.insert(entity, self.point_lights.len());
-    let renderer_point_light = RendererPointLight::new(light.position, light.intensity);
+    let renderer_point_light = RendererPointLight::new(light);
     self.point_lights.push(renderer_point_light);
   }
 
@@ -91,7 +160,7 @@ impl<B: Backend> RendererScene<B> {
 
   pub fn add_directional_light(&mut self, entity: Entity, light: DirectionalLight) {
     self.point_light_entity_map.insert(entity, self.point_lights.len());
-    let renderer_directional_light = RendererDirectionalLight::new(light.direction, light.intensity);
+    let renderer_directional_light = RendererDirectionalLight::new(light);
     self.directional_lights.push(renderer_directional_light);
   }
 
@@ -105,6 +174,26 @@ impl<B: Backend> RendererScene<B> {
     self.point_lights.remove(index);
   }
 
+  pub fn add_spot_light(&mut self, entity: Entity, light: SpotLight) {
+    self.spot_light_entity_map.insert(entity, self.spot_lights.len());
+    self.spot_lights.push(RendererSpotLight::new(light));
+  }
+
+  pub fn remove_spot_light(&mut self, entity: &Entity) {
+    let index = self.spot_light_entity_map.remove(entity);
+    debug_assert!(index.is_some());
+    if index.is_none() {
+      return;
+    }
+    let index = index.unwrap();
+    self.spot_lights.remove(index);
+    for other_index in self.spot_light_entity_map.values_mut() {
+      if *other_index > index {
+        *other_index -= 1;
+      }
+    }
+  }
+
   pub fn set_lightmap(&mut self, lightmap: Option<TextureHandle>) {
     self.lightmap = lightmap;
   }
@@ -112,4 +201,88 @@ impl<B: Backend> RendererScene<B> {
   pub fn lightmap(&self) -> Option<TextureHandle> {
     self.lightmap
   }
+
+  pub fn set_light_styles(&mut self, light_styles: LightStyles) {
+    self.light_styles = light_styles;
+  }
+
+  pub fn light_styles(&self) -> &LightStyles {
+    &self.light_styles
+  }
+
+  pub fn set_skybox(&mut self, skybox: Option<TextureHandle>) {
+    self.skybox = skybox;
+  }
+
+  pub fn skybox(&self) -> Option<TextureHandle> {
+    self.skybox
+  }
+
+  /// The model that contains the overlays and decals of the level.
+  pub fn set_decals(&mut self, decals: Option<RendererDecals>) {
+    self.decals = decals;
+  }
+
+  pub fn decals(&self) -> Option<&RendererDecals> {
+    self.decals.as_ref()
+  }
+
+  pub fn set_water_surfaces(&mut self, water_surfaces: Box<[RendererWaterSurface]>) {
+    self.water_surfaces = water_surfaces;
+  }
+
+  pub fn water_surfaces(&self) -> &[RendererWaterSurface] {
+    &self.water_surfaces
+  }
+
+  /// `leaf_volumes` indexes `volumes` for every leaf of the potentially visible set.
+  pub fn set_water_volumes(&mut self, leaf_volumes: Box<[i16]>, volumes: Box<[RendererWaterVolume]>) {
+    self.leaf_water_volumes = leaf_volumes;
+    self.water_volumes = volumes;
+  }
+
+  /// Returns the water volume if the position is below its surface.
+  pub fn water_volume_at(&self, position: &Vec3) -> Option<&RendererWaterVolume> {
+    let leaf = self.potentially_visible_set.as_ref()?.find_leaf(position)?;
+    let volume_index = *self.leaf_water_volumes.get(leaf)?;
+    if volume_index < 0 {
+      return None;
+    }
+    self.water_volumes.get(volume_index as usize)
+      .filter(|volume| position.y < volume.surface_height)
+  }
+
+  pub fn set_detail_props(&mut self, detail_props: Option<RendererDetailProps>) {
+    self.detail_props = detail_props;
+  }
+
+  pub fn detail_props(&self) -> Option<&RendererDetailProps> {
+    self.detail_props.as_ref()
+  }
+
+  /// The position of the sky camera and the scale of the 3D skybox.
+  pub fn set_sky_camera(&mut self, sky_camera: Option<(Vec3, f32)>) {
+    self.sky_camera = sky_camera;
+  }
+
+  pub fn sky_camera(&self) -> Option<(Vec3, f32)> {
+    self.sky_camera
+  }
+
+  /// The cubemaps that drawables index for their reflections.
+  pub fn set_cubemaps(&mut self, cubemaps: Vec<TextureHandle>) {
+    self.cubemaps = cubemaps;
+  }
+
+  pub fn cubemaps(&self) -> &[TextureHandle] {
+    &self.cubemaps
+  }
+
+  pub fn set_potentially_visible_set(&mut self, pvs: Option<Arc<ClusterVisibility>>) {
+    self.potentially_visible_set = pvs;
+  }
+
+  pub fn potentially_visible_set(&self) -> Option<&Arc<ClusterVisibility>> {
+    self.potentially_visible_set.as_ref()
+  }
 }
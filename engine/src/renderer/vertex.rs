@@ -1,16 +1,97 @@
-use sourcerenderer_core::{Vec2, Vec3};
+use sourcerenderer_core::graphics::{Format, InputAssemblerElement, InputRate, ShaderInputElement};
+use sourcerenderer_core::{Vec2, Vec3, Vec4};
 
 #[repr(C)]
 #[derive(Clone, PartialEq, Debug, Default)]
 pub struct Vertex {
   pub position: Vec3,
-  pub _padding: u32,
+  /// The four-way blend weights of displacements, one byte per material.
+  pub multiblend: u32,
   pub normal: Vec3,
   pub _padding1: u32,
   pub uv: Vec2,
   pub lightmap_uv: Vec2,
   pub alpha: f32,
-  pub _padding2: u32,
+  /// The light styles of the lightmap layers plus one, one byte per layer.
+  /// Zero means the vertex only uses the default style in the first layer.
+  pub lightmap_styles: u32,
   pub _padding3: u32,
-  pub _padding4: u32,
+  /// The horizontal distance between the pages of a bumped lightmap in lightmap UV space.
+  /// Zero if the lightmap isn't bumped.
+  pub lightmap_bump_page_width: f32,
+  /// The direction of the U texture axis in xyz, the sign of the bitangent in w.
+  pub tangent: Vec4,
+}
+
+/// The attributes of a `Vertex`.
+/// Every attribute is bound at the same shader location in every pass, which is its index in this enum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VertexAttribute {
+  Position,
+  Normal,
+  Uv,
+  LightmapUv,
+  Alpha,
+  LightmapStyles,
+  Multiblend,
+  LightmapBumpPageWidth,
+  Tangent
+}
+
+impl VertexAttribute {
+  fn offset(self) -> usize {
+    match self {
+      VertexAttribute::Position => 0,
+      VertexAttribute::Normal => 16,
+      VertexAttribute::Uv => 32,
+      VertexAttribute::LightmapUv => 40,
+      VertexAttribute::Alpha => 48,
+      VertexAttribute::LightmapStyles => 52,
+      VertexAttribute::Multiblend => 12,
+      VertexAttribute::LightmapBumpPageWidth => 60,
+      VertexAttribute::Tangent => 64
+    }
+  }
+
+  fn format(self) -> Format {
+    match self {
+      VertexAttribute::Position | VertexAttribute::Normal => Format::RGB32Float,
+      VertexAttribute::Uv | VertexAttribute::LightmapUv => Format::RG32Float,
+      VertexAttribute::Alpha | VertexAttribute::LightmapBumpPageWidth => Format::R32Float,
+      VertexAttribute::LightmapStyles | VertexAttribute::Multiblend => Format::R32UInt,
+      VertexAttribute::Tangent => Format::RGBA32Float
+    }
+  }
+}
+
+impl Vertex {
+  /// The vertex buffer binding of passes that draw `Vertex` buffers.
+  pub const INPUT_ASSEMBLER: [InputAssemblerElement; 1] = [
+    InputAssemblerElement {
+      binding: 0,
+      stride: std::mem::size_of::<Vertex>(),
+      input_rate: InputRate::PerVertex
+    }
+  ];
+
+  /// Builds the shader inputs for the attributes a pass reads.
+  pub fn shader_inputs(attributes: &[VertexAttribute]) -> Vec<ShaderInputElement> {
+    attributes.iter().map(|attribute| ShaderInputElement {
+      input_assembler_binding: 0,
+      location_vk_mtl: *attribute as u32,
+      semantic_name_d3d: String::from(""),
+      semantic_index_d3d: 0,
+      offset: attribute.offset(),
+      format: attribute.format()
+    }).collect()
+  }
+}
+
+/// Per vertex bone influences of skinned meshes.
+/// The bone indices are stored as floats so the stream can be read without integer vertex formats.
+#[repr(C)]
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct SkinningVertex {
+  pub bone_indices: Vec4,
+  pub bone_weights: Vec4,
 }
@@ -11,7 +11,7 @@ use sourcerenderer_core::{Platform, Quaternion};
 use sourcerenderer_core::Vec3;
 use sourcerenderer_core::Vec2;
 use std::sync::Arc;
-use crate::renderer::{PointLightComponent, StaticRenderableComponent, Vertex};
+use crate::renderer::{LightAttenuation, PointLightComponent, StaticRenderableComponent, Vertex};
 use legion::systems::{Builder as SystemBuilder, CommandBuffer};
 
 use crate::camera::ActiveCamera;
@@ -325,7 +325,12 @@ fn place_lights(#[resource] input: &InputState, transform: &mut Transform, #[sta
         position: transform.position,
         rotation: UnitQuaternion::default(),
         scale: Vec3::new(1f32, 1f32, 1f32),
-      }, PointLightComponent { intensity: 1.0f32 })]);
+      }, PointLightComponent {
+        intensity: 1.0f32,
+        color: Vec3::new(1f32, 1f32, 1f32),
+        range: LightAttenuation::INVERSE_SQUARE.cutoff_range(1.0f32),
+        attenuation: LightAttenuation::INVERSE_SQUARE
+      })]);
     }
     state.was_space_down = true;
   } else {
@@ -618,6 +618,12 @@ impl VkCommandBuffer {
     if dst_aspect.is_empty() {
       dst_aspect = vk::ImageAspectFlags::COLOR;
     }
+    // Depth and stencil images can only be blitted with nearest filtering.
+    let filter = if src_aspect == vk::ImageAspectFlags::COLOR {
+      vk::Filter::LINEAR
+    } else {
+      vk::Filter::NEAREST
+    };
 
     unsafe {
       self.device.cmd_blit_image(self.buffer, *src_texture.handle(), vk::ImageLayout::TRANSFER_SRC_OPTIMAL, *dst_texture.handle(), vk::ImageLayout::TRANSFER_DST_OPTIMAL,
@@ -652,7 +658,7 @@ impl VkCommandBuffer {
           y: dst_info.height as i32,
           z: dst_info.depth as i32,
         }]
-      }], vk::Filter::LINEAR);
+      }], filter);
     }
 
     self.trackers.track_texture(src_texture);
@@ -318,6 +318,10 @@ impl Device<VkBackend> for VkDevice {
     self.context.prerendered_frames()
   }
 
+  fn min_storage_buffer_offset_alignment(&self) -> usize {
+    self.device.properties.limits.min_storage_buffer_offset_alignment as usize
+  }
+
   fn supports_bindless(&self) -> bool {
     self.device.features.contains(VkFeatures::DESCRIPTOR_INDEXING)
   }
@@ -616,6 +616,8 @@ pub fn blend_factor_to_vk(blend_factor: BlendFactor) -> vk::BlendFactor {
     BlendFactor::OneMinusSrcColor => vk::BlendFactor::ONE_MINUS_SRC_COLOR,
     BlendFactor::Src1Alpha => vk::BlendFactor::SRC1_ALPHA,
     BlendFactor::Src1Color => vk::BlendFactor::SRC1_COLOR,
+    BlendFactor::SrcAlpha => vk::BlendFactor::SRC_ALPHA,
+    BlendFactor::OneMinusSrcAlpha => vk::BlendFactor::ONE_MINUS_SRC_ALPHA,
     BlendFactor::SrcAlphaSaturate => vk::BlendFactor::SRC_ALPHA_SATURATE,
     BlendFactor::SrcColor => vk::BlendFactor::SRC_COLOR,
     BlendFactor::Zero => vk::BlendFactor::ZERO,
@@ -50,7 +50,7 @@ impl VkTexture {
       usage: texture_usage_to_vk(info.usage),
       image_type: match info.dimension {
         TextureDimension::Dim1D => vk::ImageType::TYPE_1D,
-        TextureDimension::Dim2D => vk::ImageType::TYPE_2D,
+        TextureDimension::Dim2D | TextureDimension::Dim2DArray | TextureDimension::DimCube => vk::ImageType::TYPE_2D,
         TextureDimension::Dim3D => vk::ImageType::TYPE_3D,
       },
       extent: vk::Extent3D {
@@ -65,6 +65,10 @@ impl VkTexture {
       ..Default::default()
     };
 
+    if info.dimension == TextureDimension::DimCube {
+      create_info.flags |= vk::ImageCreateFlags::CUBE_COMPATIBLE;
+    }
+
     let mut compatible_formats = SmallVec::<[vk::Format; 2]>::with_capacity(2);
     compatible_formats.push(create_info.format);
     let mut format_list = vk::ImageFormatListCreateInfo {
@@ -273,9 +277,17 @@ impl VkTextureView {
     let view_create_info = vk::ImageViewCreateInfo {
       image: *texture.handle(),
       view_type: match texture.info.dimension {
+        TextureDimension::Dim1D if info.array_layer_length > 1 => vk::ImageViewType::TYPE_1D_ARRAY,
         TextureDimension::Dim1D => vk::ImageViewType::TYPE_1D,
+        TextureDimension::Dim2D if info.array_layer_length > 1 => vk::ImageViewType::TYPE_2D_ARRAY,
         TextureDimension::Dim2D => vk::ImageViewType::TYPE_2D,
+        TextureDimension::Dim2DArray => vk::ImageViewType::TYPE_2D_ARRAY,
         TextureDimension::Dim3D => vk::ImageViewType::TYPE_3D,
+        TextureDimension::DimCube if info.array_layer_length > 6 => vk::ImageViewType::CUBE_ARRAY,
+        TextureDimension::DimCube if info.array_layer_length == 6 => vk::ImageViewType::CUBE,
+        // Views of individual faces
+        TextureDimension::DimCube if info.array_layer_length > 1 => vk::ImageViewType::TYPE_2D_ARRAY,
+        TextureDimension::DimCube => vk::ImageViewType::TYPE_2D,
       },
       format: format_to_vk(format, device.supports_d24),
       components: vk::ComponentMapping {
@@ -225,6 +225,11 @@ impl Device<WebGLBackend> for WebGLDevice {
     3
   }
 
+  fn min_storage_buffer_offset_alignment(&self) -> usize {
+    // WebGL doesn't have storage buffers.
+    1
+  }
+
   fn supports_bindless(&self) -> bool {
     false
   }
@@ -1,8 +1,13 @@
+#[macro_use]
+extern crate bitflags;
+
 pub mod lump;
 pub mod lump_data;
 mod read_util;
 mod map_header;
+mod map;
 
 pub use self::read_util::*;
 pub use self::map_header::MapHeader;
+pub use self::map::Map;
 pub use self::lump_data::*;
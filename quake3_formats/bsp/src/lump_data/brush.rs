@@ -0,0 +1,31 @@
+use std::io::{Read, Result as IOResult};
+use crate::lump_data::{LumpData, LumpType};
+use crate::PrimitiveRead;
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Brush {
+  pub first_side: i32,
+  pub sides_count: i32,
+  pub texture: i32
+}
+
+impl LumpData for Brush {
+  fn lump_type() -> LumpType {
+    LumpType::Brushes
+  }
+
+  fn element_size(_version: i32) -> usize {
+    12
+  }
+
+  fn read(reader: &mut dyn Read, _version: i32) -> IOResult<Self> {
+    let first_side = reader.read_i32()?;
+    let sides_count = reader.read_i32()?;
+    let texture = reader.read_i32()?;
+    Ok(Self {
+      first_side,
+      sides_count,
+      texture
+    })
+  }
+}
@@ -0,0 +1,28 @@
+use std::io::{Read, Result as IOResult};
+use crate::lump_data::{LumpData, LumpType};
+use crate::PrimitiveRead;
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct BrushSide {
+  pub plane: i32,
+  pub texture: i32
+}
+
+impl LumpData for BrushSide {
+  fn lump_type() -> LumpType {
+    LumpType::BrushSides
+  }
+
+  fn element_size(_version: i32) -> usize {
+    8
+  }
+
+  fn read(reader: &mut dyn Read, _version: i32) -> IOResult<Self> {
+    let plane = reader.read_i32()?;
+    let texture = reader.read_i32()?;
+    Ok(Self {
+      plane,
+      texture
+    })
+  }
+}
@@ -0,0 +1,31 @@
+use std::io::{Read, Result as IOResult};
+use crate::lump_data::{LumpData, LumpType};
+use crate::{PrimitiveRead, StringRead};
+
+/// A fog volume.
+pub struct Effect {
+  pub name: String,
+  pub brush: i32,
+  pub visible_side: i32
+}
+
+impl LumpData for Effect {
+  fn lump_type() -> LumpType {
+    LumpType::Effects
+  }
+
+  fn element_size(_version: i32) -> usize {
+    72
+  }
+
+  fn read(reader: &mut dyn Read, _version: i32) -> IOResult<Self> {
+    let name = reader.read_fixed_length_null_terminated_string(64).unwrap_or_default();
+    let brush = reader.read_i32()?;
+    let visible_side = reader.read_i32()?;
+    Ok(Self {
+      name,
+      brush,
+      visible_side
+    })
+  }
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::io::{Read, Result as IOResult};
+use crate::RawDataRead;
+
+pub struct Entities {
+  pub entities: Vec<Entity>
+}
+
+impl Entities {
+  pub fn read(reader: &mut dyn Read, length: u32) -> IOResult<Self> {
+    let data = reader.read_data(length as usize)?;
+    let text = String::from_utf8_lossy(&data);
+    let mut entities = Vec::<Entity>::new();
+    let mut key_values = Option::<HashMap<String, String>>::None;
+    let mut pending_key = Option::<String>::None;
+    let mut chars = text.chars();
+    while let Some(char) = chars.next() {
+      match char {
+        '{' => key_values = Some(HashMap::new()),
+        '}' => {
+          if let Some(key_values) = key_values.take() {
+            entities.push(Entity {
+              key_values
+            });
+          }
+          pending_key = None;
+        }
+        '"' => {
+          let string: String = chars.by_ref().take_while(|char| *char != '"').collect();
+          if let Some(key) = pending_key.take() {
+            if let Some(key_values) = key_values.as_mut() {
+              key_values.insert(key, string);
+            }
+          } else {
+            pending_key = Some(string.to_lowercase());
+          }
+        }
+        _ => {}
+      }
+    }
+
+    Ok(Self {
+      entities
+    })
+  }
+}
+
+pub struct Entity {
+  key_values: HashMap<String, String>
+}
+
+impl Entity {
+  pub fn get(&self, key: &str) -> Option<&str> {
+    self.key_values.get(&key.to_lowercase()).map(|s| s.as_str())
+  }
+
+  pub fn key_values(&self) -> &HashMap<String, String> {
+    &self.key_values
+  }
+
+  pub fn class_name(&self) -> Option<&str> {
+    self.get("classname")
+  }
+}
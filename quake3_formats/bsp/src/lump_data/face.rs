@@ -1,7 +1,9 @@
 use std::io::{Read, Result as IOResult, Error as IOError, ErrorKind};
+use nalgebra::Vector3;
 use crate::lump_data::{LumpData, LumpType};
 use crate::PrimitiveRead;
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum FaceType {
   Polygon = 1,
   Patch = 2,
@@ -13,14 +15,17 @@ pub struct Face {
   pub texture: i32,
   pub effect: i32,
   pub face_type: FaceType,
+  pub first_vertex: i32,
   pub vertex_count: i32,
   pub mesh_vert: i32,
   pub mesh_vert_count: i32,
   pub lightmap_index: i32,
   pub lightmap_start: [i32; 2],
   pub lightmap_size: [i32; 2],
-  pub lightmap_origin: [f32; 3],
-  pub lightmap_vecs: [f32; 3],
+  pub lightmap_origin: Vector3<f32>,
+  pub lightmap_vecs: [Vector3<f32>; 2],
+  pub normal: Vector3<f32>,
+  /// Dimensions of the control point grid of patches.
   pub size: [i32; 2]
 }
 
@@ -30,7 +35,7 @@ impl LumpData for Face {
   }
 
   fn element_size(_version: i32) -> usize {
-    72
+    104
   }
 
   fn read(reader: &mut dyn Read, _version: i32) -> IOResult<Self> {
@@ -45,19 +50,25 @@ impl LumpData for Face {
         return Err(IOError::new(ErrorKind::Other, "Invalid face type"));
       }
     };
+    let first_vertex = reader.read_i32()?;
     let vertex_count = reader.read_i32()?;
     let mesh_vert = reader.read_i32()?;
     let mesh_vert_count = reader.read_i32()?;
     let lightmap_index = reader.read_i32()?;
     let lightmap_start = [reader.read_i32()?, reader.read_i32()?];
     let lightmap_size = [reader.read_i32()?, reader.read_i32()?];
-    let lightmap_origin = [reader.read_f32()?, reader.read_f32()?, reader.read_f32()?];
-    let lightmap_vecs = [reader.read_f32()?, reader.read_f32()?, reader.read_f32()?];
+    let lightmap_origin = Vector3::<f32>::new(reader.read_f32()?, reader.read_f32()?, reader.read_f32()?);
+    let lightmap_vecs = [
+      Vector3::<f32>::new(reader.read_f32()?, reader.read_f32()?, reader.read_f32()?),
+      Vector3::<f32>::new(reader.read_f32()?, reader.read_f32()?, reader.read_f32()?)
+    ];
+    let normal = Vector3::<f32>::new(reader.read_f32()?, reader.read_f32()?, reader.read_f32()?);
     let size = [reader.read_i32()?, reader.read_i32()?];
     Ok(Self {
       texture,
       effect,
       face_type,
+      first_vertex,
       vertex_count,
       mesh_vert,
       mesh_vert_count,
@@ -66,6 +77,7 @@ impl LumpData for Face {
       lightmap_size,
       lightmap_origin,
       lightmap_vecs,
+      normal,
       size
     })
   }
@@ -0,0 +1,47 @@
+use std::io::{Read, Result as IOResult};
+use crate::lump_data::{LumpData, LumpType};
+use crate::PrimitiveRead;
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Leaf {
+  /// Negative if the leaf is outside of the map.
+  pub cluster: i32,
+  pub area: i32,
+  pub mins: [i32; 3],
+  pub maxs: [i32; 3],
+  pub first_leaf_face: i32,
+  pub leaf_faces_count: i32,
+  pub first_leaf_brush: i32,
+  pub leaf_brushes_count: i32
+}
+
+impl LumpData for Leaf {
+  fn lump_type() -> LumpType {
+    LumpType::Leafs
+  }
+
+  fn element_size(_version: i32) -> usize {
+    48
+  }
+
+  fn read(reader: &mut dyn Read, _version: i32) -> IOResult<Self> {
+    let cluster = reader.read_i32()?;
+    let area = reader.read_i32()?;
+    let mins = [reader.read_i32()?, reader.read_i32()?, reader.read_i32()?];
+    let maxs = [reader.read_i32()?, reader.read_i32()?, reader.read_i32()?];
+    let first_leaf_face = reader.read_i32()?;
+    let leaf_faces_count = reader.read_i32()?;
+    let first_leaf_brush = reader.read_i32()?;
+    let leaf_brushes_count = reader.read_i32()?;
+    Ok(Self {
+      cluster,
+      area,
+      mins,
+      maxs,
+      first_leaf_face,
+      leaf_faces_count,
+      first_leaf_brush,
+      leaf_brushes_count
+    })
+  }
+}
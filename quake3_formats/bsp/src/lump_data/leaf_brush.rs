@@ -0,0 +1,25 @@
+use std::io::{Read, Result as IOResult};
+use crate::lump_data::{LumpData, LumpType};
+use crate::PrimitiveRead;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct LeafBrush {
+  pub index: i32
+}
+
+impl LumpData for LeafBrush {
+  fn lump_type() -> LumpType {
+    LumpType::LeafBrushes
+  }
+
+  fn element_size(_version: i32) -> usize {
+    4
+  }
+
+  fn read(reader: &mut dyn Read, _version: i32) -> IOResult<Self> {
+    let index = reader.read_i32()?;
+    Ok(Self {
+      index
+    })
+  }
+}
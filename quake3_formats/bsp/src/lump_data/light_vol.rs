@@ -0,0 +1,34 @@
+use std::io::{Read, Result as IOResult};
+use crate::lump_data::{LumpData, LumpType};
+use crate::PrimitiveRead;
+
+/// A cell of the uniform grid that stores the lighting for models.
+/// The grid spans the bounds of the world model with cells of 64x64x128 units.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct LightVol {
+  pub ambient: [u8; 3],
+  pub directional: [u8; 3],
+  /// Spherical coordinates of the light direction, 0-255 maps to 0-360 degrees.
+  pub direction: [u8; 2]
+}
+
+impl LumpData for LightVol {
+  fn lump_type() -> LumpType {
+    LumpType::LightVols
+  }
+
+  fn element_size(_version: i32) -> usize {
+    8
+  }
+
+  fn read(reader: &mut dyn Read, _version: i32) -> IOResult<Self> {
+    let ambient = [reader.read_u8()?, reader.read_u8()?, reader.read_u8()?];
+    let directional = [reader.read_u8()?, reader.read_u8()?, reader.read_u8()?];
+    let direction = [reader.read_u8()?, reader.read_u8()?];
+    Ok(Self {
+      ambient,
+      directional,
+      direction
+    })
+  }
+}
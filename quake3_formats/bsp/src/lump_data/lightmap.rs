@@ -0,0 +1,34 @@
+use std::io::{Read, Result as IOResult};
+use crate::lump_data::{LumpData, LumpType};
+use crate::RawDataRead;
+
+pub const LIGHTMAP_SIZE: u32 = 128;
+
+/// A 128x128 RGB8 lightmap page that faces reference by index.
+pub struct Lightmap {
+  pub data: Box<[u8]>
+}
+
+impl Lightmap {
+  pub fn texel(&self, x: u32, y: u32) -> [u8; 3] {
+    let offset = ((x + y * LIGHTMAP_SIZE) * 3) as usize;
+    [self.data[offset], self.data[offset + 1], self.data[offset + 2]]
+  }
+}
+
+impl LumpData for Lightmap {
+  fn lump_type() -> LumpType {
+    LumpType::Lightmaps
+  }
+
+  fn element_size(_version: i32) -> usize {
+    (LIGHTMAP_SIZE * LIGHTMAP_SIZE * 3) as usize
+  }
+
+  fn read(reader: &mut dyn Read, version: i32) -> IOResult<Self> {
+    let data = reader.read_data(Self::element_size(version))?;
+    Ok(Self {
+      data
+    })
+  }
+}
@@ -0,0 +1,26 @@
+use std::io::{Read, Result as IOResult};
+use crate::lump_data::{LumpData, LumpType};
+use crate::PrimitiveRead;
+
+/// A vertex index relative to the first vertex of a face.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct MeshVert {
+  pub index: i32
+}
+
+impl LumpData for MeshVert {
+  fn lump_type() -> LumpType {
+    LumpType::MeshVerts
+  }
+
+  fn element_size(_version: i32) -> usize {
+    4
+  }
+
+  fn read(reader: &mut dyn Read, _version: i32) -> IOResult<Self> {
+    let index = reader.read_i32()?;
+    Ok(Self {
+      index
+    })
+  }
+}
@@ -1,12 +1,40 @@
 use std::io::{Read, Result as IOResult};
 
+pub use crate::lump_data::brush::Brush;
 pub use crate::lump_data::brush_model::BrushModel;
-pub use crate::lump_data::face::Face;
+pub use crate::lump_data::brush_side::BrushSide;
+pub use crate::lump_data::effect::Effect;
+pub use crate::lump_data::entity::{Entities, Entity};
+pub use crate::lump_data::face::{Face, FaceType};
+pub use crate::lump_data::leaf::Leaf;
+pub use crate::lump_data::leaf_brush::LeafBrush;
+pub use crate::lump_data::leaf_face::LeafFace;
+pub use crate::lump_data::light_vol::LightVol;
+pub use crate::lump_data::lightmap::{Lightmap, LIGHTMAP_SIZE};
+pub use crate::lump_data::mesh_vert::MeshVert;
+pub use crate::lump_data::node::Node;
+pub use crate::lump_data::plane::Plane;
+pub use crate::lump_data::shader::{Shader, SurfaceFlags, ContentFlags};
 pub use crate::lump_data::vertex::Vertex;
+pub use crate::lump_data::vis_data::VisData;
 
+mod brush;
 mod brush_model;
+mod brush_side;
+mod effect;
+mod entity;
 mod face;
+mod leaf;
+mod leaf_brush;
+mod leaf_face;
+mod light_vol;
+mod lightmap;
+mod mesh_vert;
+mod node;
+mod plane;
+mod shader;
 mod vertex;
+mod vis_data;
 
 #[derive(Clone, Copy, Debug)]
 #[repr(u8)]
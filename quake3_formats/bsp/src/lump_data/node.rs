@@ -0,0 +1,54 @@
+use std::io::{Read, Result as IOResult};
+use nalgebra::Vector3;
+use crate::lump_data::{LumpData, LumpType};
+use crate::{PrimitiveRead, Plane};
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Node {
+  pub plane: i32,
+  /// Negative children are leafs with the index -(child + 1).
+  pub children: [i32; 2],
+  pub mins: [i32; 3],
+  pub maxs: [i32; 3]
+}
+
+impl LumpData for Node {
+  fn lump_type() -> LumpType {
+    LumpType::Nodes
+  }
+
+  fn element_size(_version: i32) -> usize {
+    36
+  }
+
+  fn read(reader: &mut dyn Read, _version: i32) -> IOResult<Self> {
+    let plane = reader.read_i32()?;
+    let children = [reader.read_i32()?, reader.read_i32()?];
+    let mins = [reader.read_i32()?, reader.read_i32()?, reader.read_i32()?];
+    let maxs = [reader.read_i32()?, reader.read_i32()?, reader.read_i32()?];
+    Ok(Self {
+      plane,
+      children,
+      mins,
+      maxs
+    })
+  }
+}
+
+impl Node {
+  /// Walks the BSP tree from the root node and returns the index of the leaf that contains `position`.
+  pub fn find_leaf(nodes: &[Node], planes: &[Plane], position: &Vector3<f32>) -> usize {
+    let mut index = 0;
+    while index >= 0 {
+      let node = &nodes[index as usize];
+      let plane = &planes[node.plane as usize];
+      let distance = plane.normal.dot(position) - plane.dist;
+      index = if distance >= 0f32 {
+        node.children[0]
+      } else {
+        node.children[1]
+      };
+    }
+    (-index - 1) as usize
+  }
+}
@@ -0,0 +1,81 @@
+use std::io::{Read, Result as IOResult};
+use crate::lump_data::{LumpData, LumpType};
+use crate::{PrimitiveRead, StringRead};
+
+bitflags! {
+  pub struct SurfaceFlags: i32 {
+    const NODAMAGE = 1;
+    const SLICK = 1 << 1;
+    const SKY = 1 << 2;
+    const LADDER = 1 << 3;
+    const NOIMPACT = 1 << 4;
+    const NOMARKS = 1 << 5;
+    const FLESH = 1 << 6;
+    const NODRAW = 1 << 7;
+    const HINT = 1 << 8;
+    const SKIP = 1 << 9;
+    const NOLIGHTMAP = 1 << 10;
+    const POINTLIGHT = 1 << 11;
+    const METALSTEPS = 1 << 12;
+    const NOSTEPS = 1 << 13;
+    const NONSOLID = 1 << 14;
+    const LIGHTFILTER = 1 << 15;
+    const ALPHASHADOW = 1 << 16;
+    const NODLIGHT = 1 << 17;
+    const DUST = 1 << 18;
+  }
+}
+
+bitflags! {
+  pub struct ContentFlags: i32 {
+    const SOLID = 1;
+    const LAVA = 1 << 3;
+    const SLIME = 1 << 4;
+    const WATER = 1 << 5;
+    const FOG = 1 << 6;
+    const AREAPORTAL = 1 << 15;
+    const PLAYERCLIP = 1 << 16;
+    const MONSTERCLIP = 1 << 17;
+    const TELEPORTER = 1 << 18;
+    const JUMPPAD = 1 << 19;
+    const CLUSTERPORTAL = 1 << 20;
+    const DONOTENTER = 1 << 21;
+    const ORIGIN = 1 << 24;
+    const BODY = 1 << 25;
+    const CORPSE = 1 << 26;
+    const DETAIL = 1 << 27;
+    const STRUCTURAL = 1 << 28;
+    const TRANSLUCENT = 1 << 29;
+    const TRIGGER = 1 << 30;
+    const NODROP = 1 << 31;
+  }
+}
+
+/// An entry of the textures lump. Quake 3 calls them shaders because the name
+/// refers to either a shader script or a texture file without its extension.
+pub struct Shader {
+  pub name: String,
+  pub surface_flags: SurfaceFlags,
+  pub contents: ContentFlags
+}
+
+impl LumpData for Shader {
+  fn lump_type() -> LumpType {
+    LumpType::Textures
+  }
+
+  fn element_size(_version: i32) -> usize {
+    72
+  }
+
+  fn read(reader: &mut dyn Read, _version: i32) -> IOResult<Self> {
+    let name = reader.read_fixed_length_null_terminated_string(64).unwrap_or_default();
+    let surface_flags = SurfaceFlags::from_bits_truncate(reader.read_i32()?);
+    let contents = ContentFlags::from_bits_truncate(reader.read_i32()?);
+    Ok(Self {
+      name,
+      surface_flags,
+      contents
+    })
+  }
+}
@@ -0,0 +1,52 @@
+use std::io::{Read, Result as IOResult, Error as IOError, ErrorKind};
+use crate::{PrimitiveRead, RawDataRead};
+
+/// Uncompressed cluster to cluster visibility bit vectors.
+pub struct VisData {
+  pub clusters_count: i32,
+  pub bytes_per_cluster: i32,
+  data: Box<[u8]>
+}
+
+impl VisData {
+  pub fn read(reader: &mut dyn Read, length: u32) -> IOResult<Self> {
+    if length == 0 {
+      return Ok(Self {
+        clusters_count: 0,
+        bytes_per_cluster: 0,
+        data: Box::new([0u8; 0])
+      });
+    }
+
+    let clusters_count = reader.read_i32()?;
+    let bytes_per_cluster = reader.read_i32()?;
+    let data_length = clusters_count.max(0) as u64 * bytes_per_cluster.max(0) as u64;
+    if data_length + 8 > length as u64 {
+      return Err(IOError::new(ErrorKind::InvalidData, "Vis data lump is too small for its cluster count."));
+    }
+    let data = reader.read_data(data_length as usize)?;
+    Ok(Self {
+      clusters_count,
+      bytes_per_cluster,
+      data
+    })
+  }
+
+  /// Clusters outside of the map and maps without vis data can see everything.
+  pub fn is_cluster_visible(&self, from: i32, to: i32) -> bool {
+    if from < 0 || to < 0 || from >= self.clusters_count || to >= self.clusters_count {
+      return true;
+    }
+    let offset = (from * self.bytes_per_cluster + to / 8) as usize;
+    self.data[offset] & (1 << (to % 8)) != 0
+  }
+
+  /// The visibility bit vector of a single cluster.
+  pub fn visible_clusters(&self, cluster: i32) -> Option<&[u8]> {
+    if cluster < 0 || cluster >= self.clusters_count {
+      return None;
+    }
+    let start = (cluster * self.bytes_per_cluster) as usize;
+    Some(&self.data[start .. start + self.bytes_per_cluster as usize])
+  }
+}
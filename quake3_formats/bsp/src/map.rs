@@ -0,0 +1,119 @@
+use std::io::{Seek, SeekFrom, Read, Result as IOResult, Error as IOError, ErrorKind};
+use crate::map_header::MapHeader;
+use crate::lump_data::{LumpData, LumpType, Brush, BrushModel, BrushSide, Effect, Entities, Face,
+                       Leaf, LeafBrush, LeafFace, LightVol, Lightmap, MeshVert, Node, Plane,
+                       Shader, Vertex, VisData};
+
+const IDENTIFIER: &[u8; 4] = b"IBSP";
+const VERSION: i32 = 46;
+
+pub struct Map<R: Read + Seek> {
+  pub name: String,
+  header: MapHeader,
+  reader: R,
+}
+
+impl<R: Read + Seek> Map<R> {
+  pub fn read(name: &str, mut reader: R) -> IOResult<Map<R>> {
+    reader.seek(SeekFrom::Start(0))?;
+    let header = MapHeader::read(&mut reader)?;
+    if header.identifier != i32::from_le_bytes(*IDENTIFIER) {
+      return Err(IOError::new(ErrorKind::InvalidData, "Not an IBSP file."));
+    }
+    if header.version != VERSION {
+      return Err(IOError::new(ErrorKind::InvalidData, format!("Unsupported IBSP version: {}", header.version)));
+    }
+    Ok(Map {
+      name: name.to_owned(),
+      header,
+      reader,
+    })
+  }
+
+  pub fn read_entities(&mut self) -> IOResult<Entities> {
+    let lump = self.seek_to_lump(LumpType::Entities)?;
+    Entities::read(&mut self.reader, lump.file_length as u32)
+  }
+
+  pub fn read_shaders(&mut self) -> IOResult<Vec<Shader>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_planes(&mut self) -> IOResult<Vec<Plane>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_nodes(&mut self) -> IOResult<Vec<Node>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_leafs(&mut self) -> IOResult<Vec<Leaf>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_leaf_faces(&mut self) -> IOResult<Vec<LeafFace>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_leaf_brushes(&mut self) -> IOResult<Vec<LeafBrush>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_brush_models(&mut self) -> IOResult<Vec<BrushModel>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_brushes(&mut self) -> IOResult<Vec<Brush>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_brush_sides(&mut self) -> IOResult<Vec<BrushSide>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_vertices(&mut self) -> IOResult<Vec<Vertex>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_mesh_verts(&mut self) -> IOResult<Vec<MeshVert>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_effects(&mut self) -> IOResult<Vec<Effect>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_faces(&mut self) -> IOResult<Vec<Face>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_lightmaps(&mut self) -> IOResult<Vec<Lightmap>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_light_vols(&mut self) -> IOResult<Vec<LightVol>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_vis_data(&mut self) -> IOResult<VisData> {
+    let lump = self.seek_to_lump(LumpType::VisData)?;
+    VisData::read(&mut self.reader, lump.file_length as u32)
+  }
+
+  fn seek_to_lump(&mut self, lump_type: LumpType) -> IOResult<crate::lump::Lump> {
+    let lump = self.header.lumps[lump_type as usize];
+    self.reader.seek(SeekFrom::Start(lump.file_offset as u64))?;
+    Ok(lump)
+  }
+
+  fn read_lump_data<T: LumpData>(&mut self) -> IOResult<Vec<T>> {
+    let lump = self.seek_to_lump(T::lump_type())?;
+    let element_count = lump.file_length / T::element_size(self.header.version) as i32;
+    let mut elements: Vec<T> = Vec::with_capacity(element_count.max(0) as usize);
+    for _ in 0..element_count {
+      let element = T::read(&mut self.reader, self.header.version)?;
+      elements.push(element);
+    }
+    Ok(elements)
+  }
+}
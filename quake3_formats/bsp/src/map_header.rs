@@ -3,7 +3,7 @@ use crate::lump::Lump;
 use std::io::{Read, Result as IOResult};
 use crate::PrimitiveRead;
 
-const LUMP_COUNT: usize = 16;
+const LUMP_COUNT: usize = 17;
 
 pub struct MapHeader {
   pub identifier: i32,
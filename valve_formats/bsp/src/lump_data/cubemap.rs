@@ -0,0 +1,34 @@
+use std::io::{Read, Result as IOResult};
+use crate::lump_data::{LumpData, LumpType};
+use crate::PrimitiveRead;
+use nalgebra::Vector3;
+
+/// A position that VBSP baked a cubemap for. The texture is stored in the pakfile as `maps/<map>/c<x>_<y>_<z>.vtf`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub struct CubemapSample {
+  pub origin: Vector3<i32>,
+  /// The resolution is `1 << (size - 1)`, 0 means the default size.
+  pub size: i32
+}
+
+impl LumpData for CubemapSample {
+  fn lump_type() -> LumpType {
+    LumpType::Cubemaps
+  }
+  fn lump_type_hdr() -> Option<LumpType> {
+    None
+  }
+
+  fn element_size(_version: i32) -> usize {
+    16
+  }
+
+  fn read(reader: &mut dyn Read, _version: i32) -> IOResult<Self> {
+    let origin = Vector3::<i32>::new(reader.read_i32()?, reader.read_i32()?, reader.read_i32()?);
+    let size = reader.read_i32()?;
+    Ok(Self {
+      origin,
+      size
+    })
+  }
+}
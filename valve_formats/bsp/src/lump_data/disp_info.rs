@@ -19,6 +19,15 @@ pub struct DispInfo {
 }
 
 impl DispInfo {
+  const FLAG_MAGIC: u32 = 0x80000000;
+  const FLAG_HAS_MULTIBLEND: u32 = 0x40000000;
+
+  /// CS:GO reuses `min_tess` for flags, the magic bit marks that.
+  pub fn has_multiblend(&self) -> bool {
+    let flags = self.min_tess as u32;
+    flags & Self::FLAG_MAGIC != 0 && flags & Self::FLAG_HAS_MULTIBLEND != 0
+  }
+
   pub fn edge_neighbor(&self, edge: NeighborEdge) -> &DispNeighbor {
     let index: u8 = unsafe { std::mem::transmute(edge) };
     &self.edge_neighbors[index as usize]
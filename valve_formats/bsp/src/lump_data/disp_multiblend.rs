@@ -0,0 +1,40 @@
+use nalgebra::{Vector3, Vector4};
+use crate::{LumpType, LumpData};
+use crate::PrimitiveRead;
+use std::io::{Read, Result as IOResult};
+
+/// The blend weights of a vertex of a displacement that uses a four-way blend material.
+/// The lump only has entries for the vertices of displacements with `DispInfo::has_multiblend`,
+/// in the order of the displacement infos.
+pub struct DispMultiblend {
+  pub multiblend: Vector4<f32>,
+  pub alpha_blend: Vector4<f32>,
+  pub multiblend_colors: [Vector3<f32>; 4]
+}
+
+impl LumpData for DispMultiblend {
+  fn lump_type() -> LumpType {
+    LumpType::DisplacementMultiblend
+  }
+  fn lump_type_hdr() -> Option<LumpType> {
+    None
+  }
+
+  fn element_size(_version: i32) -> usize {
+    80
+  }
+
+  fn read(read: &mut dyn Read, _version: i32) -> IOResult<Self> {
+    let multiblend = Vector4::new(read.read_f32()?, read.read_f32()?, read.read_f32()?, read.read_f32()?);
+    let alpha_blend = Vector4::new(read.read_f32()?, read.read_f32()?, read.read_f32()?, read.read_f32()?);
+    let mut multiblend_colors = [Vector3::<f32>::zeros(); 4];
+    for color in &mut multiblend_colors {
+      *color = Vector3::new(read.read_f32()?, read.read_f32()?, read.read_f32()?);
+    }
+    Ok(Self {
+      multiblend,
+      alpha_blend,
+      multiblend_colors
+    })
+  }
+}
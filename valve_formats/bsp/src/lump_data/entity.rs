@@ -42,6 +42,10 @@ impl Entity {
     self.key_values.get(&lower_key).map(|s| s.as_str())
   }
 
+  pub fn key_values(&self) -> &HashMap<String, String> {
+    &self.key_values
+  }
+
   pub fn class_name(&self) -> EntityClass {
     let class_name = self.key_values.get("classname").unwrap().as_str();
     match class_name {
@@ -53,6 +57,12 @@ impl Entity {
       "prop_physics_multiplayer" => EntityClass::PropPhysicsMultiplayer,
       "prop_physics_override" => EntityClass::PropPhysicsOverride,
       "prop_dynamic_override" => EntityClass::PropDynamicOverride,
+      "light" => EntityClass::Light,
+      "light_spot" => EntityClass::LightSpot,
+      "light_environment" => EntityClass::LightEnvironment,
+      "info_player_start" => EntityClass::InfoPlayerStart,
+      "worldspawn" => EntityClass::Worldspawn,
+      "sky_camera" => EntityClass::SkyCamera,
       _ => EntityClass::Unknown(class_name.to_string())
     }
   }
@@ -86,5 +96,11 @@ pub enum EntityClass {
   PropPhysicsMultiplayer,
   PropPhysicsOverride,
   PropDynamicOverride,
+  Light,
+  LightSpot,
+  LightEnvironment,
+  InfoPlayerStart,
+  Worldspawn,
+  SkyCamera,
   Unknown(String)
 }
@@ -0,0 +1,160 @@
+use std::io::{Read, Result as IOResult};
+use crate::{PrimitiveRead, StringRead};
+use nalgebra::{Vector2, Vector3};
+use crate::lump_data::leaf::ColorRGBExp32;
+
+/// The detail props (grass and other foliage) that VBSP scattered over the surfaces with a detail type.
+#[derive(Default)]
+pub struct DetailPropDict {
+  /// The models of detail props with the `Model` type.
+  pub names: Box<[String]>,
+  /// The regions of the detail sprite texture that sprites and shapes use.
+  pub sprites: Box<[DetailSprite]>,
+  pub props: Box<[DetailProp]>
+}
+
+impl DetailPropDict {
+  pub fn id() -> u32 {
+    1685090928 // dprp
+  }
+
+  pub fn read(read: &mut dyn Read, version: u16) -> IOResult<Self> {
+    let dict_entries = read.read_i32()?;
+    let mut names = Vec::<String>::with_capacity(dict_entries as usize);
+    for _ in 0..dict_entries {
+      let name = read.read_fixed_length_null_terminated_string(128).unwrap();
+      names.push(name);
+    }
+
+    let sprite_count = read.read_i32()?;
+    let mut sprites = Vec::<DetailSprite>::with_capacity(sprite_count as usize);
+    for _ in 0..sprite_count {
+      sprites.push(DetailSprite::read(read)?);
+    }
+
+    let prop_count = read.read_i32()?;
+    let mut props = Vec::<DetailProp>::with_capacity(prop_count as usize);
+    for _ in 0..prop_count {
+      props.push(DetailProp::read(read, version)?);
+    }
+
+    Ok(Self {
+      names: names.into_boxed_slice(),
+      sprites: sprites.into_boxed_slice(),
+      props: props.into_boxed_slice()
+    })
+  }
+}
+
+/// A sprite quad in world units around the origin of the prop and the matching region of the detail sprite texture.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DetailSprite {
+  pub upper_left: Vector2<f32>,
+  pub lower_right: Vector2<f32>,
+  pub tex_upper_left: Vector2<f32>,
+  pub tex_lower_right: Vector2<f32>
+}
+
+impl DetailSprite {
+  pub fn read(read: &mut dyn Read) -> IOResult<Self> {
+    let upper_left = Vector2::<f32>::new(read.read_f32()?, read.read_f32()?);
+    let lower_right = Vector2::<f32>::new(read.read_f32()?, read.read_f32()?);
+    let tex_upper_left = Vector2::<f32>::new(read.read_f32()?, read.read_f32()?);
+    let tex_lower_right = Vector2::<f32>::new(read.read_f32()?, read.read_f32()?);
+    Ok(Self {
+      upper_left,
+      lower_right,
+      tex_upper_left,
+      tex_lower_right
+    })
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DetailPropType {
+  Model,
+  Sprite,
+  /// Two sprites that cross each other.
+  ShapeCross,
+  /// Three sprites that lean against each other.
+  ShapeTri
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DetailPropOrientation {
+  /// Uses the angles of the prop.
+  Normal,
+  ScreenAligned,
+  /// Faces the camera but only rotates around the up axis.
+  ScreenAlignedVertical
+}
+
+pub struct DetailProp {
+  pub origin: Vector3<f32>,
+  pub angles: Vector3<f32>,
+  /// Indexes the names for models and the sprites for everything else.
+  pub detail_model: u16,
+  pub leaf: u16,
+  pub lighting: ColorRGBExp32,
+  pub light_styles: u32,
+  pub light_style_count: u8,
+  pub sway_amount: u8,
+  pub shape_angle: u8,
+  pub shape_size: u8,
+  pub orientation: DetailPropOrientation,
+  pub prop_type: DetailPropType,
+  pub scale: f32
+}
+
+impl DetailProp {
+  pub fn read(read: &mut dyn Read, version: u16) -> IOResult<Self> {
+    let origin = Vector3::<f32>::new(read.read_f32()?, read.read_f32()?, read.read_f32()?);
+    let angles = Vector3::<f32>::new(read.read_f32()?, read.read_f32()?, read.read_f32()?);
+    let detail_model = read.read_u16()?;
+    let leaf = read.read_u16()?;
+    let lighting = ColorRGBExp32::read(read)?;
+    let light_styles = read.read_u32()?;
+    let light_style_count = read.read_u8()?;
+    let sway_amount = read.read_u8()?;
+    let shape_angle = read.read_u8()?;
+    let shape_size = read.read_u8()?;
+    let orientation = match read.read_u8()? {
+      1 => DetailPropOrientation::ScreenAligned,
+      2 => DetailPropOrientation::ScreenAlignedVertical,
+      _ => DetailPropOrientation::Normal
+    };
+    for _ in 0..3 {
+      read.read_u8()?;
+    }
+    let prop_type = match read.read_u8()? {
+      0 => DetailPropType::Model,
+      2 => DetailPropType::ShapeCross,
+      3 => DetailPropType::ShapeTri,
+      _ => DetailPropType::Sprite
+    };
+    for _ in 0..3 {
+      read.read_u8()?;
+    }
+    let scale = if version >= 4 {
+      read.read_f32()?
+    } else {
+      1f32
+    };
+
+    Ok(Self {
+      origin,
+      angles,
+      detail_model,
+      leaf,
+      lighting,
+      light_styles,
+      light_style_count,
+      sway_amount,
+      shape_angle,
+      shape_size,
+      orientation,
+      prop_type,
+      scale
+    })
+  }
+}
@@ -1,6 +1,6 @@
 use std::io::{Read, Result as IOResult, Seek, SeekFrom, Cursor, Error as IOError, ErrorKind};
 use crate::read_util::PrimitiveRead;
-use crate::lump_data::game_lumps::StaticPropDict;
+use crate::lump_data::game_lumps::{StaticPropDict, DetailPropDict};
 
 pub struct GameLumps {
   game_lumps: Box<[GameLump]>
@@ -20,21 +20,29 @@ impl GameLumps {
   }
 
   pub(crate) fn read_static_prop_dict<R: Read + Seek>(&self, read: &mut R) -> IOResult<StaticPropDict> {
-    for lump in self.game_lumps.as_ref() {
-      if lump.id == StaticPropDict::id() {
-        read.seek(SeekFrom::Start(lump.file_offset as u64))?;
-        let mut data = Vec::with_capacity(lump.file_length as usize);
-        unsafe  {
-          data.set_len(lump.file_length as usize);
-        }
-        read.read_exact(&mut data)?;
-        let mut cursor = Cursor::new(data);
-        let static_props = StaticPropDict::read(&mut cursor, lump.version)?;
-        return Ok(static_props);
-      }
+    let (mut cursor, version) = self.read_lump(StaticPropDict::id(), read)?
+      .ok_or_else(|| IOError::new(ErrorKind::Other, "Game lump not found"))?;
+    StaticPropDict::read(&mut cursor, version)
+  }
+
+  /// Returns an empty dictionary if the map doesn't have any detail props.
+  pub(crate) fn read_detail_prop_dict<R: Read + Seek>(&self, read: &mut R) -> IOResult<DetailPropDict> {
+    match self.read_lump(DetailPropDict::id(), read)? {
+      Some((mut cursor, version)) => DetailPropDict::read(&mut cursor, version),
+      None => Ok(DetailPropDict::default())
     }
+  }
 
-    Err(IOError::new(ErrorKind::Other, "Game lump not found"))
+  fn read_lump<R: Read + Seek>(&self, id: u32, read: &mut R) -> IOResult<Option<(Cursor<Vec<u8>>, u16)>> {
+    let lump = self.game_lumps.iter().find(|lump| lump.id == id);
+    if let Some(lump) = lump {
+      read.seek(SeekFrom::Start(lump.file_offset as u64))?;
+      let mut data = vec![0u8; lump.file_length as usize];
+      read.read_exact(&mut data)?;
+      Ok(Some((Cursor::new(data), lump.version)))
+    } else {
+      Ok(None)
+    }
   }
 }
 
@@ -1,5 +1,7 @@
-pub use self::static_prop_dict::StaticPropDict;
+pub use self::static_prop_dict::{StaticPropDict, StaticProp, StaticPropFlags};
+pub use self::detail_prop_dict::{DetailPropDict, DetailSprite, DetailProp, DetailPropType, DetailPropOrientation};
 pub use self::game::*;
 
 mod static_prop_dict;
+mod detail_prop_dict;
 mod game;
@@ -55,10 +55,16 @@ impl ColorRGBExp32 {
 
     r | g << 8 | b << 16 | 255 << 24
   }
+
+  /// Decodes the color without clamping it, 1.0 is the brightest color an LDR lightmap can store.
+  pub fn to_rgb_f32(&self) -> [f32; 3] {
+    let scale = 2f32.powi(self.exponent as i32) / 255f32;
+    [self.r as f32 * scale, self.g as f32 * scale, self.b as f32 * scale]
+  }
 }
 
 impl CompressedLightCube {
-  fn read(reader: &mut dyn Read) -> IOResult<Self> {
+  pub(super) fn read(reader: &mut dyn Read) -> IOResult<Self> {
     let mut colors: [ColorRGBExp32; 6] = [Default::default(); 6];
     for i in 0..6 {
       let color = ColorRGBExp32::read(reader)?;
@@ -0,0 +1,72 @@
+use std::io::{Read, Result as IOResult};
+use crate::lump_data::{LumpData, LumpType, leaf::CompressedLightCube};
+use crate::PrimitiveRead;
+
+/// The range of ambient lighting samples that belong to a leaf.
+/// BSP version 20 and newer store those instead of a single cube per leaf.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct LeafAmbientIndex {
+  pub ambient_sample_count: u16,
+  pub first_ambient_sample: u16
+}
+
+impl LumpData for LeafAmbientIndex {
+  fn lump_type() -> LumpType {
+    LumpType::LeafAmbientIndex
+  }
+  fn lump_type_hdr() -> Option<LumpType> {
+    // The HDR samples get read explicitly using Map::read_hdr_leaf_ambient_indices.
+    None
+  }
+
+  fn element_size(_version: i32) -> usize {
+    4
+  }
+
+  fn read(reader: &mut dyn Read, _version: i32) -> IOResult<Self> {
+    let ambient_sample_count = reader.read_u16()?;
+    let first_ambient_sample = reader.read_u16()?;
+    Ok(Self {
+      ambient_sample_count,
+      first_ambient_sample
+    })
+  }
+}
+
+/// An ambient light cube somewhere inside of a leaf.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct LeafAmbientLighting {
+  pub cube: CompressedLightCube,
+  /// The position of the sample inside of the bounding box of the leaf, 0 is the minimum and 255 the maximum.
+  pub x: u8,
+  pub y: u8,
+  pub z: u8
+}
+
+impl LumpData for LeafAmbientLighting {
+  fn lump_type() -> LumpType {
+    LumpType::LeafAmbientLighting
+  }
+  fn lump_type_hdr() -> Option<LumpType> {
+    // The HDR samples get read explicitly using Map::read_hdr_leaf_ambient_lighting.
+    None
+  }
+
+  fn element_size(_version: i32) -> usize {
+    28
+  }
+
+  fn read(reader: &mut dyn Read, _version: i32) -> IOResult<Self> {
+    let cube = CompressedLightCube::read(reader)?;
+    let x = reader.read_u8()?;
+    let y = reader.read_u8()?;
+    let z = reader.read_u8()?;
+    let _padding = reader.read_u8()?;
+    Ok(Self {
+      cube,
+      x,
+      y,
+      z
+    })
+  }
+}
@@ -0,0 +1,39 @@
+use std::io::{Read, Result as IOResult};
+use crate::lump_data::{LumpData, LumpType};
+use crate::PrimitiveRead;
+
+/// The water volume that a leaf with `leaf_water_data_id` is part of.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LeafWaterData {
+  /// The height of the water surface.
+  pub surface_z: f32,
+  /// The lowest point of the water volume.
+  pub min_z: f32,
+  /// The texture info of the water surface, its material describes the fog.
+  pub surface_tex_info: i16
+}
+
+impl LumpData for LeafWaterData {
+  fn lump_type() -> LumpType {
+    LumpType::LeafWaterData
+  }
+  fn lump_type_hdr() -> Option<LumpType> {
+    None
+  }
+
+  fn element_size(_version: i32) -> usize {
+    12
+  }
+
+  fn read(reader: &mut dyn Read, _version: i32) -> IOResult<Self> {
+    let surface_z = reader.read_f32()?;
+    let min_z = reader.read_f32()?;
+    let surface_tex_info = reader.read_i16()?;
+    let _padding = reader.read_i16()?;
+    Ok(Self {
+      surface_z,
+      min_z,
+      surface_tex_info
+    })
+  }
+}
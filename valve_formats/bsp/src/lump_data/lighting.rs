@@ -12,7 +12,8 @@ impl LumpData for Lighting {
     LumpType::Lighting
   }
   fn lump_type_hdr() -> Option<LumpType> {
-    Some(LumpType::LightingHDR)
+    // The HDR lighting gets read explicitly using Map::read_hdr_lighting.
+    None
   }
 
   fn element_size(_version: i32) -> usize {
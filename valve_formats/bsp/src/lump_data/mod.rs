@@ -2,14 +2,18 @@ use std::io::{Read, Result as IOResult};
 
 pub use crate::lump_data::brush_model::BrushModel;
 pub use crate::lump_data::brush_side::BrushSide;
+pub use crate::lump_data::cubemap::CubemapSample;
 pub use crate::lump_data::disp_info::*;
+pub use crate::lump_data::disp_multiblend::DispMultiblend;
 pub use crate::lump_data::disp_tri::DispTri;
 pub use crate::lump_data::disp_vert::DispVert;
 pub use crate::lump_data::edge::Edge;
 pub use crate::lump_data::face::Face;
 pub use crate::lump_data::leaf_brush::LeafBrush;
 pub use crate::lump_data::leaf_face::LeafFace;
+pub use crate::lump_data::leaf_water_data::LeafWaterData;
 pub use crate::lump_data::lighting::Lighting;
+pub use crate::lump_data::overlay::{Overlay, WaterOverlay, OverlayFade};
 pub use crate::lump_data::pakfile::PakFile;
 pub use crate::lump_data::plane::Plane;
 pub use crate::lump_data::surface_edge::SurfaceEdge;
@@ -20,22 +24,25 @@ pub use crate::lump_data::texture_string_data::TextureStringData;
 pub use crate::lump_data::vertex::Vertex;
 pub use crate::lump_data::vertex_normal::VertexNormal;
 pub use crate::lump_data::vertex_normal_index::VertexNormalIndex;
-pub use crate::lump_data::visibility::Visibility;
+pub use crate::lump_data::visibility::{Visibility, ClusterSet};
 pub use crate::game_lumps::GameLumps;
-pub use crate::lump_data::entity::Entities;
+pub use crate::lump_data::entity::{Entities, Entity, EntityClass};
 
 pub use self::brush::Brush;
-pub use self::leaf::Leaf;
+pub use self::leaf::{Leaf, CompressedLightCube, ColorRGBExp32};
+pub use self::leaf_ambient::{LeafAmbientIndex, LeafAmbientLighting};
 pub use self::node::Node;
 
 mod brush;
 mod node;
 mod leaf;
+mod leaf_ambient;
 mod edge;
 mod face;
 mod brush_side;
 mod plane;
 mod leaf_face;
+mod leaf_water_data;
 mod leaf_brush;
 mod surface_edge;
 mod vertex;
@@ -50,8 +57,11 @@ mod pakfile;
 mod disp_info;
 mod disp_vert;
 mod disp_tri;
+mod disp_multiblend;
 mod lighting;
 mod visibility;
+mod cubemap;
+mod overlay;
 pub mod game_lumps;
 mod entity;
 
@@ -1,6 +1,7 @@
 use std::io::{Read, Result as IOResult};
+use nalgebra::Vector3;
 use crate::lump_data::{LumpData, LumpType};
-use crate::PrimitiveRead;
+use crate::{PrimitiveRead, Plane};
 
 #[derive(Copy, Clone, Debug, Default)]
 pub struct Node {
@@ -62,3 +63,50 @@ impl LumpData for Node {
     })
   }
 }
+
+impl Node {
+  /// Walks the BSP tree starting at `head_node` and returns the index of the leaf that contains `position`.
+  pub fn find_leaf(nodes: &[Node], planes: &[Plane], head_node: i32, position: &Vector3<f32>) -> usize {
+    let mut index = head_node;
+    while index >= 0 {
+      let node = &nodes[index as usize];
+      let plane = &planes[node.plane_number as usize];
+      let distance = plane.normal.dot(position) - plane.dist;
+      index = if distance >= 0f32 {
+        node.children[0]
+      } else {
+        node.children[1]
+      };
+    }
+    (-index - 1) as usize
+  }
+
+  /// Walks the BSP tree starting at `head_node` and collects the indices of all leafs that touch the given box.
+  pub fn find_leaves_in_box(nodes: &[Node], planes: &[Plane], head_node: i32, min: &Vector3<f32>, max: &Vector3<f32>, leaves: &mut Vec<usize>) {
+    let mut stack = vec![head_node];
+    while let Some(index) = stack.pop() {
+      if index < 0 {
+        leaves.push((-index - 1) as usize);
+        continue;
+      }
+
+      let node = &nodes[index as usize];
+      let plane = &planes[node.plane_number as usize];
+      // Pick the box corners that are closest to and furthest away from the plane.
+      let mut near = *max;
+      let mut far = *min;
+      for axis in 0..3 {
+        if plane.normal[axis] < 0f32 {
+          near[axis] = min[axis];
+          far[axis] = max[axis];
+        }
+      }
+      if plane.normal.dot(&near) - plane.dist >= 0f32 {
+        stack.push(node.children[0]);
+      }
+      if plane.normal.dot(&far) - plane.dist < 0f32 {
+        stack.push(node.children[1]);
+      }
+    }
+  }
+}
@@ -0,0 +1,137 @@
+use std::io::{Read, Result as IOResult};
+use crate::lump_data::{LumpData, LumpType};
+use crate::PrimitiveRead;
+use nalgebra::Vector3;
+
+const OVERLAY_MAX_FACES: usize = 64;
+const WATER_OVERLAY_MAX_FACES: usize = 256;
+
+/// A texture that gets projected onto the faces it references (`info_overlay`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct Overlay {
+  pub id: i32,
+  pub tex_info: i16,
+  /// Overlays with a higher render order get drawn on top of the ones with a lower order.
+  pub render_order: u8,
+  pub faces: Vec<i32>,
+  /// The texture coordinate range along the U axis.
+  pub u: [f32; 2],
+  /// The texture coordinate range along the V axis.
+  pub v: [f32; 2],
+  /// The corners of the overlay quad in the plane spanned by the basis vectors.
+  /// VBSP stores the U basis vector in the z components of the first three points.
+  pub uv_points: [Vector3<f32>; 4],
+  pub origin: Vector3<f32>,
+  pub basis_normal: Vector3<f32>
+}
+
+impl Overlay {
+  pub fn basis_u(&self) -> Vector3<f32> {
+    Vector3::new(self.uv_points[0].z, self.uv_points[1].z, self.uv_points[2].z)
+  }
+
+  pub fn basis_v(&self) -> Vector3<f32> {
+    self.basis_normal.cross(&self.basis_u())
+  }
+
+  fn read_with_max_faces(reader: &mut dyn Read, max_faces: usize) -> IOResult<Self> {
+    let id = reader.read_i32()?;
+    let tex_info = reader.read_i16()?;
+    let face_count_and_render_order = reader.read_u16()?;
+    let face_count = (face_count_and_render_order & 0x3fff) as usize;
+    let render_order = (face_count_and_render_order >> 14) as u8;
+    let mut faces = Vec::with_capacity(face_count.min(max_faces));
+    for i in 0..max_faces {
+      let face = reader.read_i32()?;
+      if i < face_count {
+        faces.push(face);
+      }
+    }
+    let u = [reader.read_f32()?, reader.read_f32()?];
+    let v = [reader.read_f32()?, reader.read_f32()?];
+    let mut uv_points = [Vector3::<f32>::zeros(); 4];
+    for point in &mut uv_points {
+      *point = Vector3::new(reader.read_f32()?, reader.read_f32()?, reader.read_f32()?);
+    }
+    let origin = Vector3::new(reader.read_f32()?, reader.read_f32()?, reader.read_f32()?);
+    let basis_normal = Vector3::new(reader.read_f32()?, reader.read_f32()?, reader.read_f32()?);
+    Ok(Self {
+      id,
+      tex_info,
+      render_order,
+      faces,
+      u,
+      v,
+      uv_points,
+      origin,
+      basis_normal
+    })
+  }
+}
+
+impl LumpData for Overlay {
+  fn lump_type() -> LumpType {
+    LumpType::Overlays
+  }
+  fn lump_type_hdr() -> Option<LumpType> {
+    None
+  }
+
+  fn element_size(_version: i32) -> usize {
+    352
+  }
+
+  fn read(reader: &mut dyn Read, _version: i32) -> IOResult<Self> {
+    Self::read_with_max_faces(reader, OVERLAY_MAX_FACES)
+  }
+}
+
+/// An overlay on a water surface (`info_overlay` with up to 256 faces).
+#[derive(Clone, Debug, PartialEq)]
+pub struct WaterOverlay(pub Overlay);
+
+impl LumpData for WaterOverlay {
+  fn lump_type() -> LumpType {
+    LumpType::WaterOverlays
+  }
+  fn lump_type_hdr() -> Option<LumpType> {
+    None
+  }
+
+  fn element_size(_version: i32) -> usize {
+    1120
+  }
+
+  fn read(reader: &mut dyn Read, _version: i32) -> IOResult<Self> {
+    Ok(Self(Overlay::read_with_max_faces(reader, WATER_OVERLAY_MAX_FACES)?))
+  }
+}
+
+/// The fade distances of the overlay with the same index.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OverlayFade {
+  pub fade_dist_min_sq: f32,
+  pub fade_dist_max_sq: f32
+}
+
+impl LumpData for OverlayFade {
+  fn lump_type() -> LumpType {
+    LumpType::OverlayFades
+  }
+  fn lump_type_hdr() -> Option<LumpType> {
+    None
+  }
+
+  fn element_size(_version: i32) -> usize {
+    8
+  }
+
+  fn read(reader: &mut dyn Read, _version: i32) -> IOResult<Self> {
+    let fade_dist_min_sq = reader.read_f32()?;
+    let fade_dist_max_sq = reader.read_f32()?;
+    Ok(Self {
+      fade_dist_min_sq,
+      fade_dist_max_sq
+    })
+  }
+}
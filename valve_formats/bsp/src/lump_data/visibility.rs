@@ -1,30 +1,118 @@
-use std::io::{Read, Result as IOResult};
-use crate::PrimitiveRead;
+use std::io::{Read, Result as IOResult, Error as IOError, ErrorKind};
+use crate::{PrimitiveRead, RawDataRead};
+
+const PVS: usize = 0;
+const PAS: usize = 1;
 
 pub struct Visibility {
   pub num_clusters: i32,
-  pub byte_offsets: [Box<[i32]>; 2]
+  pub byte_offsets: [Box<[i32]>; 2],
+  data: Box<[u8]>
 }
 
 impl Visibility {
-  pub fn read(reader: &mut dyn Read) -> IOResult<Self> {
+  pub fn read(reader: &mut dyn Read, length: u32) -> IOResult<Self> {
+    if length == 0 {
+      return Ok(Self {
+        num_clusters: 0,
+        byte_offsets: [Box::new([0i32; 0]), Box::new([0i32; 0])],
+        data: Box::new([0u8; 0])
+      });
+    }
+
     let num_clusters = reader.read_i32()?;
-    let mut byte_offsets: [Box<[i32]>; 2] = [
-      Box::new([0i32; 0]),
-      Box::new([0i32; 0]),
-    ];
-
-    for offsets in &mut byte_offsets {
-      let mut offsets_vec = Vec::with_capacity(num_clusters as usize);
-      for _ in 0 .. num_clusters {
-        offsets_vec.push(reader.read_i32()?);
-      }
-      *offsets = offsets_vec.into_boxed_slice();
+    let mut pvs_offsets = Vec::with_capacity(num_clusters as usize);
+    let mut pas_offsets = Vec::with_capacity(num_clusters as usize);
+    // The offsets are stored as a [PVS, PAS] pair for each cluster.
+    for _ in 0 .. num_clusters {
+      pvs_offsets.push(reader.read_i32()?);
+      pas_offsets.push(reader.read_i32()?);
     }
 
+    // The offsets are relative to the beginning of the lump, so keep the whole lump around.
+    let header_size = 4 + num_clusters as u32 * 8;
+    if header_size > length {
+      return Err(IOError::new(ErrorKind::InvalidData, "Visibility lump is too small for its cluster count."));
+    }
+    let mut data = vec![0u8; header_size as usize];
+    data.extend_from_slice(&reader.read_data((length - header_size) as usize)?);
+
     Ok(Self {
       num_clusters,
-      byte_offsets
+      byte_offsets: [pvs_offsets.into_boxed_slice(), pas_offsets.into_boxed_slice()],
+      data: data.into_boxed_slice()
     })
   }
-}
\ No newline at end of file
+
+  /// Decompresses the potentially visible set of the given cluster.
+  pub fn visible_clusters(&self, cluster: i32) -> ClusterSet {
+    self.decompress(cluster, PVS)
+  }
+
+  /// Decompresses the potentially audible set of the given cluster.
+  pub fn audible_clusters(&self, cluster: i32) -> ClusterSet {
+    self.decompress(cluster, PAS)
+  }
+
+  fn decompress(&self, cluster: i32, kind: usize) -> ClusterSet {
+    let byte_count = (self.num_clusters.max(0) as usize + 7) / 8;
+    if cluster < 0 || cluster >= self.num_clusters {
+      // Leafs outside of the map or without vis data can see everything.
+      return ClusterSet {
+        cluster_count: self.num_clusters.max(0) as u32,
+        bits: vec![0xFFu8; byte_count].into_boxed_slice()
+      };
+    }
+
+    let mut bits = Vec::<u8>::with_capacity(byte_count);
+    let mut offset = self.byte_offsets[kind][cluster as usize] as usize;
+    // Run length encoding: a zero byte is followed by the number of zero bytes it represents.
+    while bits.len() < byte_count && offset < self.data.len() {
+      let byte = self.data[offset];
+      offset += 1;
+      if byte != 0 {
+        bits.push(byte);
+        continue;
+      }
+
+      let run_length = self.data.get(offset).copied().unwrap_or(0) as usize;
+      offset += 1;
+      let run_length = run_length.min(byte_count - bits.len());
+      bits.resize(bits.len() + run_length, 0u8);
+    }
+    bits.resize(byte_count, 0u8);
+
+    ClusterSet {
+      cluster_count: self.num_clusters as u32,
+      bits: bits.into_boxed_slice()
+    }
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct ClusterSet {
+  cluster_count: u32,
+  bits: Box<[u8]>
+}
+
+impl ClusterSet {
+  pub fn contains(&self, cluster: i32) -> bool {
+    if cluster < 0 || cluster as u32 >= self.cluster_count {
+      return false;
+    }
+    let cluster = cluster as usize;
+    (self.bits[cluster / 8] & (1 << (cluster % 8))) != 0
+  }
+
+  pub fn cluster_count(&self) -> u32 {
+    self.cluster_count
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+    (0..self.cluster_count).filter(move |cluster| self.contains(*cluster as i32))
+  }
+
+  pub fn as_bytes(&self) -> &[u8] {
+    &self.bits
+  }
+}
@@ -6,8 +6,8 @@ use crate::lump_data::{Brush, Node, Leaf, Face,
                        TextureData, SurfaceEdge, Vertex,
                        VertexNormalIndex, VertexNormal,
                        TextureDataStringTable, TextureStringData};
-use crate::{LumpType, BrushModel, RawDataRead, PakFile, DispTri, DispInfo, DispVert, Lighting, Visibility, GameLumps, Entities};
-use crate::lump_data::game_lumps::StaticPropDict;
+use crate::{LumpType, BrushModel, RawDataRead, PakFile, DispTri, DispInfo, DispVert, DispMultiblend, Lighting, Visibility, GameLumps, Entities, CubemapSample, Overlay, WaterOverlay, OverlayFade, LeafAmbientIndex, LeafAmbientLighting, LeafWaterData};
+use crate::lump_data::game_lumps::{StaticPropDict, DetailPropDict};
 
 pub struct Map<R: Read + Seek> {
   pub name: String,
@@ -123,11 +123,56 @@ impl<R: Read + Seek> Map<R> {
     self.read_lump_data()
   }
 
+  /// Returns an empty list if no displacement of the map uses a four-way blend material.
+  pub fn read_disp_multiblend(&mut self) -> IOResult<Vec<DispMultiblend>> {
+    self.read_lump_data()
+  }
+
   pub fn read_lighting(&mut self) -> IOResult<Vec<Lighting>> {
     self.read_lump_data()
   }
 
+  /// Returns an empty list if the map was compiled without HDR lighting.
   pub fn read_hdr_lighting(&mut self) -> IOResult<Vec<Lighting>> {
+    self.read_lump_data_of_type(LumpType::LightingHDR)
+  }
+
+  /// Returns an empty list if the map stores the ambient lighting in its leaves.
+  pub fn read_leaf_ambient_indices(&mut self) -> IOResult<Vec<LeafAmbientIndex>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_leaf_ambient_lighting(&mut self) -> IOResult<Vec<LeafAmbientLighting>> {
+    self.read_lump_data()
+  }
+
+  /// Returns an empty list if the map was compiled without HDR lighting.
+  pub fn read_hdr_leaf_ambient_indices(&mut self) -> IOResult<Vec<LeafAmbientIndex>> {
+    self.read_lump_data_of_type(LumpType::LeafAmbientIndexHDR)
+  }
+
+  pub fn read_hdr_leaf_ambient_lighting(&mut self) -> IOResult<Vec<LeafAmbientLighting>> {
+    self.read_lump_data_of_type(LumpType::LeafAmbientLightingHDR)
+  }
+
+  pub fn read_leaf_water_data(&mut self) -> IOResult<Vec<LeafWaterData>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_cubemaps(&mut self) -> IOResult<Vec<CubemapSample>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_overlays(&mut self) -> IOResult<Vec<Overlay>> {
+    self.read_lump_data()
+  }
+
+  pub fn read_water_overlays(&mut self) -> IOResult<Vec<WaterOverlay>> {
+    self.read_lump_data()
+  }
+
+  /// Returns an empty list if none of the overlays fade out.
+  pub fn read_overlay_fades(&mut self) -> IOResult<Vec<OverlayFade>> {
     self.read_lump_data()
   }
 
@@ -143,13 +188,17 @@ impl<R: Read + Seek> Map<R> {
     let index = LumpType::Visibility as usize;
     let lump = self.header.lumps[index];
     self.reader.seek(SeekFrom::Start(lump.file_offset as u64))?;
-    Visibility::read(&mut self.reader)
+    Visibility::read(&mut self.reader, lump.file_length as u32)
   }
 
   pub fn read_static_props(&mut self) -> IOResult<StaticPropDict> {
     self.game_lumps.read_static_prop_dict(&mut self.reader)
   }
 
+  pub fn read_detail_props(&mut self) -> IOResult<DetailPropDict> {
+    self.game_lumps.read_detail_prop_dict(&mut self.reader)
+  }
+
   pub fn read_entities(&mut self) -> IOResult<Entities> {
     let index = LumpType::Entities as usize;
     let lump = self.header.lumps[index];
@@ -168,6 +217,10 @@ impl<R: Read + Seek> Map<R> {
       }
     }
 
+    self.read_lump_data_of_type(lump_type)
+  }
+
+  fn read_lump_data_of_type<T: LumpData>(&mut self, lump_type: LumpType) -> IOResult<Vec<T>> {
     let index = lump_type as usize;
     let lump = &self.header.lumps[index];
     self.reader.seek(SeekFrom::Start(lump.file_offset as u64))?;
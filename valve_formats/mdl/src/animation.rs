@@ -0,0 +1,276 @@
+use std::io::{Read, Seek, SeekFrom, Result as IOResult, Error as IOError, ErrorKind};
+
+use nalgebra::{Vector3, Quaternion};
+
+use crate::{AnimDesc, Bone, PrimitiveRead, StringRead};
+
+bitflags! {
+  pub struct AnimDescFlags: i32 {
+    const LOOPING = 1;
+    const SNAP = 1 << 1;
+    const DELTA = 1 << 2;
+    const AUTOPLAY = 1 << 3;
+    const POST = 1 << 4;
+    const ALL_ZEROS = 1 << 5;
+    const FRAME_ANIM = 1 << 6;
+    const CYCLE_POSE = 1 << 7;
+    const REALTIME = 1 << 8;
+    const LOCAL = 1 << 9;
+    const HIDDEN = 1 << 10;
+    const OVERRIDE = 1 << 11;
+    const ACTIVITY = 1 << 12;
+    const EVENT = 1 << 13;
+    const WORLD = 1 << 14;
+  }
+}
+
+bitflags! {
+  struct BoneAnimFlags: u8 {
+    const RAW_POS = 1;
+    const RAW_ROT = 1 << 1;
+    const ANIM_POS = 1 << 2;
+    const ANIM_ROT = 1 << 3;
+    const DELTA = 1 << 4;
+    const RAW_ROT2 = 1 << 5;
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct BonePose {
+  pub position: Vector3<f32>,
+  pub rotation: Quaternion<f32>
+}
+
+/// An animation decoded into one pose per bone for every frame.
+pub struct Animation {
+  pub name: String,
+  pub fps: f32,
+  pub flags: AnimDescFlags,
+  pub frames: Vec<Vec<BonePose>>
+}
+
+impl Animation {
+  pub(crate) fn read<R: Read + Seek>(reader: &mut R, desc_start: u64, desc: &AnimDesc, bones: &[Bone]) -> IOResult<Self> {
+    reader.seek(SeekFrom::Start(desc_start + desc.name_index as u64))?;
+    let name = reader.read_null_terminated_string().unwrap_or_default();
+    let flags = AnimDescFlags::from_bits_truncate(desc.flags);
+    if flags.contains(AnimDescFlags::FRAME_ANIM) {
+      return Err(IOError::new(ErrorKind::Other, "Frame animations are not supported."));
+    }
+
+    let frames_count = desc.frames_count.max(1) as u32;
+    let mut sections = Vec::<(i32, i32)>::new();
+    if desc.section_frames != 0 {
+      reader.seek(SeekFrom::Start(desc_start + desc.section_index as u64))?;
+      let sections_count = frames_count / desc.section_frames as u32 + 2;
+      for _ in 0..sections_count {
+        sections.push((reader.read_i32()?, reader.read_i32()?));
+      }
+    }
+
+    let is_delta = flags.contains(AnimDescFlags::DELTA);
+    let mut frames = Vec::<Vec<BonePose>>::with_capacity(frames_count as usize);
+    for frame in 0..frames_count {
+      let mut poses: Vec<BonePose> = bones.iter().map(|bone| default_pose(bone, is_delta)).collect();
+      if !flags.contains(AnimDescFlags::ALL_ZEROS) {
+        let (block, index, local_frame) = if desc.section_frames != 0 {
+          // The last frame of long animations is stored in a separate section.
+          let section_frames = desc.section_frames as u32;
+          let (section, local_frame) = if frames_count > section_frames && frame == frames_count - 1 {
+            (frames_count / section_frames + 1, 0)
+          } else {
+            (frame / section_frames, frame % section_frames)
+          };
+          let (block, index) = sections.get(section as usize).copied()
+            .ok_or_else(|| IOError::new(ErrorKind::InvalidData, "Animation section out of range."))?;
+          (block, index, local_frame)
+        } else {
+          (desc.anim_block, desc.anim_index, frame)
+        };
+
+        if block != 0 {
+          return Err(IOError::new(ErrorKind::Other, "Animations in external animation blocks are not supported."));
+        }
+        read_bone_tracks(reader, desc_start + index as u64, local_frame, bones, is_delta, &mut poses)?;
+      }
+      frames.push(poses);
+    }
+
+    Ok(Self {
+      name,
+      fps: desc.fps,
+      flags,
+      frames
+    })
+  }
+
+  pub fn is_looping(&self) -> bool {
+    self.flags.contains(AnimDescFlags::LOOPING)
+  }
+}
+
+fn default_pose(bone: &Bone, is_delta: bool) -> BonePose {
+  if is_delta {
+    BonePose {
+      position: Vector3::new(0f32, 0f32, 0f32),
+      rotation: Quaternion::identity()
+    }
+  } else {
+    BonePose {
+      position: bone.position,
+      rotation: bone.quaternion
+    }
+  }
+}
+
+fn read_bone_tracks<R: Read + Seek>(reader: &mut R, start: u64, frame: u32, bones: &[Bone], is_delta: bool, poses: &mut [BonePose]) -> IOResult<()> {
+  let mut offset = start;
+  loop {
+    reader.seek(SeekFrom::Start(offset))?;
+    let bone_index = reader.read_u8()? as usize;
+    let flags = BoneAnimFlags::from_bits_truncate(reader.read_u8()?);
+    let next_offset = reader.read_i16()?;
+    let data = offset + 4;
+
+    if let Some(bone) = bones.get(bone_index) {
+      let pose = &mut poses[bone_index];
+      if flags.contains(BoneAnimFlags::RAW_ROT2) {
+        reader.seek(SeekFrom::Start(data))?;
+        pose.rotation = read_quaternion64(reader)?;
+      } else if flags.contains(BoneAnimFlags::RAW_ROT) {
+        reader.seek(SeekFrom::Start(data))?;
+        pose.rotation = read_quaternion48(reader)?;
+      } else if flags.contains(BoneAnimFlags::ANIM_ROT) {
+        let mut angles = read_anim_values(reader, data, frame, &bone.rot_scale)?;
+        if !is_delta {
+          angles += bone.rotation;
+        }
+        pose.rotation = angle_quaternion(&angles);
+      }
+
+      if flags.contains(BoneAnimFlags::RAW_POS) {
+        let mut pos_data = data;
+        if flags.contains(BoneAnimFlags::RAW_ROT) {
+          pos_data += 6;
+        }
+        if flags.contains(BoneAnimFlags::RAW_ROT2) {
+          pos_data += 8;
+        }
+        reader.seek(SeekFrom::Start(pos_data))?;
+        pose.position = read_vector48(reader)?;
+      } else if flags.contains(BoneAnimFlags::ANIM_POS) {
+        let pos_data = if flags.contains(BoneAnimFlags::ANIM_ROT) { data + 6 } else { data };
+        let mut position = read_anim_values(reader, pos_data, frame, &bone.pos_scale)?;
+        if !is_delta {
+          position += bone.position;
+        }
+        pose.position = position;
+      }
+    }
+
+    if next_offset <= 0 {
+      break;
+    }
+    offset += next_offset as u64;
+  }
+  Ok(())
+}
+
+/// Reads the three run length encoded tracks an animation value pointer refers to.
+fn read_anim_values<R: Read + Seek>(reader: &mut R, value_ptr: u64, frame: u32, scale: &Vector3<f32>) -> IOResult<Vector3<f32>> {
+  reader.seek(SeekFrom::Start(value_ptr))?;
+  let offsets = [reader.read_i16()?, reader.read_i16()?, reader.read_i16()?];
+  let mut values = Vector3::<f32>::new(0f32, 0f32, 0f32);
+  for (axis, offset) in offsets.iter().enumerate() {
+    if *offset > 0 {
+      values[axis] = extract_anim_value(reader, value_ptr + *offset as u64, frame)? * scale[axis];
+    }
+  }
+  Ok(values)
+}
+
+fn extract_anim_value<R: Read + Seek>(reader: &mut R, start: u64, frame: u32) -> IOResult<f32> {
+  let mut offset = start;
+  let mut k = frame;
+  let valid = loop {
+    reader.seek(SeekFrom::Start(offset))?;
+    let valid = reader.read_u8()? as u32;
+    let total = reader.read_u8()? as u32;
+    if total == 0 {
+      return Ok(0f32);
+    }
+    if total > k {
+      break valid;
+    }
+    k -= total;
+    offset += (valid as u64 + 1) * 2;
+  };
+
+  let value_index = if valid > k { k + 1 } else { valid };
+  reader.seek(SeekFrom::Start(offset + value_index as u64 * 2))?;
+  Ok(reader.read_i16()? as f32)
+}
+
+fn read_quaternion48<R: Read>(reader: &mut R) -> IOResult<Quaternion<f32>> {
+  let x = reader.read_u16()?;
+  let y = reader.read_u16()?;
+  let zw = reader.read_u16()?;
+  let x = (x as f32 - 32768f32) / 32768f32;
+  let y = (y as f32 - 32768f32) / 32768f32;
+  let z = ((zw & 0x7FFF) as f32 - 16384f32) / 16384f32;
+  let mut w = (1f32 - x * x - y * y - z * z).max(0f32).sqrt();
+  if zw >> 15 != 0 {
+    w = -w;
+  }
+  Ok(Quaternion::new(w, x, y, z))
+}
+
+fn read_quaternion64<R: Read>(reader: &mut R) -> IOResult<Quaternion<f32>> {
+  let value = reader.read_u64()?;
+  let x = ((value & 0x1FFFFF) as f64 - 1048576f64) / 1048576.5f64;
+  let y = (((value >> 21) & 0x1FFFFF) as f64 - 1048576f64) / 1048576.5f64;
+  let z = (((value >> 42) & 0x1FFFFF) as f64 - 1048576f64) / 1048576.5f64;
+  let mut w = (1f64 - x * x - y * y - z * z).max(0f64).sqrt();
+  if value >> 63 != 0 {
+    w = -w;
+  }
+  Ok(Quaternion::new(w as f32, x as f32, y as f32, z as f32))
+}
+
+fn read_vector48<R: Read>(reader: &mut R) -> IOResult<Vector3<f32>> {
+  Ok(Vector3::new(
+    f16_to_f32(reader.read_u16()?),
+    f16_to_f32(reader.read_u16()?),
+    f16_to_f32(reader.read_u16()?)
+  ))
+}
+
+fn f16_to_f32(value: u16) -> f32 {
+  let sign = if value & 0x8000 != 0 { -1f32 } else { 1f32 };
+  let exponent = ((value >> 10) & 0x1F) as i32;
+  let mantissa = (value & 0x3FF) as f32;
+  match exponent {
+    0 => sign * mantissa * 2f32.powi(-24),
+    0x1F if mantissa == 0f32 => sign * f32::INFINITY,
+    0x1F => f32::NAN,
+    _ => sign * (1f32 + mantissa / 1024f32) * 2f32.powi(exponent - 15)
+  }
+}
+
+/// Converts Source euler angles in radians (roll, pitch, yaw) to a quaternion.
+fn angle_quaternion(angles: &Vector3<f32>) -> Quaternion<f32> {
+  let (sr, cr) = (angles.x * 0.5f32).sin_cos();
+  let (sp, cp) = (angles.y * 0.5f32).sin_cos();
+  let (sy, cy) = (angles.z * 0.5f32).sin_cos();
+
+  let sr_cp = sr * cp;
+  let cr_sp = cr * sp;
+  let cr_cp = cr * cp;
+  let sr_sp = sr * sp;
+  Quaternion::new(
+    cr_cp * cy + sr_sp * sy,
+    sr_cp * cy - cr_sp * sy,
+    cr_sp * cy + sr_cp * sy,
+    cr_cp * sy - sr_sp * cy
+  )
+}
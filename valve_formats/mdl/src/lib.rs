@@ -17,6 +17,7 @@ mod model_file;
 mod body_part;
 mod model;
 mod mesh;
+mod animation;
 
 pub use self::read_util::*;
 pub use self::header::{Header, StudioHDRFlags};
@@ -27,7 +28,8 @@ pub use self::bone::Bone;
 pub use self::bone_controller::BoneController;
 pub use self::hitbox_set::HitboxSet;
 pub use self::anim_desc::AnimDesc;
-pub use self::sequence_desc::SequenceDesc;
+pub use self::sequence_desc::{SequenceDesc, Sequence};
+pub use self::animation::{Animation, AnimDescFlags, BonePose};
 pub use self::model_file::ModelFile;
 pub use self::body_part::BodyPart;
 pub use self::model::{Model, ModelVertexData};
@@ -1,7 +1,7 @@
 use std::io::{Read, Seek, Result as IOResult, Error as IOError, SeekFrom, ErrorKind};
 use crate::header::Header;
 use crate::header2::Header2;
-use crate::{Bone, BoneController, HitboxSet, AnimDesc, SequenceDesc, Texture, StringRead, PrimitiveRead, BodyPart, Model, Mesh};
+use crate::{Bone, BoneController, HitboxSet, AnimDesc, SequenceDesc, Sequence, Animation, Texture, StringRead, PrimitiveRead, BodyPart, Model, Mesh};
 
 pub struct ModelFile<R: Read + Seek> {
   header: Header,
@@ -81,6 +81,50 @@ impl<R: Read + Seek> ModelFile<R> {
     Ok(seqs)
   }
 
+  /// Decodes all local animations into per frame bone poses.
+  pub fn animations(&mut self) -> IOResult<Vec<Animation>> {
+    let bones = self.bones()?;
+    let mut descs = Vec::<(u64, AnimDesc)>::with_capacity(self.header.local_anim_count as usize);
+    self.reader.seek(SeekFrom::Start(self.start_offset + self.header.local_anim_offset as u64))?;
+    for _ in 0..self.header.local_anim_count {
+      let desc_start = self.reader.seek(SeekFrom::Current(0))?;
+      descs.push((desc_start, AnimDesc::read(&mut self.reader)?));
+    }
+
+    let mut anims = Vec::<Animation>::with_capacity(descs.len());
+    for (desc_start, desc) in &descs {
+      anims.push(Animation::read(&mut self.reader, *desc_start, desc, &bones)?);
+    }
+    Ok(anims)
+  }
+
+  pub fn sequences(&mut self) -> IOResult<Vec<Sequence>> {
+    let mut descs = Vec::<(u64, SequenceDesc)>::with_capacity(self.header.local_seq_count as usize);
+    self.reader.seek(SeekFrom::Start(self.start_offset + self.header.local_seq_offset as u64))?;
+    for _ in 0..self.header.local_seq_count {
+      let seq_start = self.reader.seek(SeekFrom::Current(0))?;
+      descs.push((seq_start, SequenceDesc::read(&mut self.reader)?));
+    }
+
+    let mut seqs = Vec::<Sequence>::with_capacity(descs.len());
+    for (seq_start, desc) in descs {
+      self.reader.seek(SeekFrom::Start(seq_start + desc.label_index as u64))?;
+      let name = self.reader.read_null_terminated_string().unwrap_or_default();
+      let animations_count = (desc.group_size[0].max(1) * desc.group_size[1].max(1)) as usize;
+      let mut animation_indices = Vec::<i16>::with_capacity(animations_count);
+      self.reader.seek(SeekFrom::Start(seq_start + desc.anim_index_index as u64))?;
+      for _ in 0..animations_count {
+        animation_indices.push(self.reader.read_i16()?);
+      }
+      seqs.push(Sequence {
+        name,
+        desc,
+        animation_indices
+      });
+    }
+    Ok(seqs)
+  }
+
   pub fn textures(&mut self) -> IOResult<Vec<(String, Texture)>> {
     let mut textures = Vec::<(String, Texture)>::with_capacity(self.header.texture_count as usize);
     self.reader.seek(SeekFrom::Start(self.start_offset + self.header.texture_offset as u64))?;
@@ -4,6 +4,8 @@ use nalgebra::Vector3;
 
 use crate::PrimitiveRead;
 
+const STUDIO_LOOPING: i32 = 1;
+
 pub struct SequenceDesc {
   pub base_ptr: i32,
 
@@ -172,3 +174,16 @@ impl SequenceDesc {
     })
   }
 }
+
+/// A sequence with its name and the animations of its blend grid.
+pub struct Sequence {
+  pub name: String,
+  pub desc: SequenceDesc,
+  pub animation_indices: Vec<i16>
+}
+
+impl Sequence {
+  pub fn is_looping(&self) -> bool {
+    self.desc.flags & STUDIO_LOOPING != 0
+  }
+}
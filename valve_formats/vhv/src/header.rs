@@ -0,0 +1,38 @@
+use std::io::{Read, Result as IOResult};
+
+use crate::PrimitiveRead;
+
+pub struct Header {
+  pub version: i32,
+  pub checksum: u32,
+  pub vertex_flags: u32,
+  pub vertex_size: u32,
+  pub vertexes_count: u32,
+  pub meshes_count: i32,
+  pub unused: [u32; 4]
+}
+
+impl Header {
+  pub fn read(read: &mut dyn Read) -> IOResult<Self> {
+    let version = read.read_i32()?;
+    let checksum = read.read_u32()?;
+    let vertex_flags = read.read_u32()?;
+    let vertex_size = read.read_u32()?;
+    let vertexes_count = read.read_u32()?;
+    let meshes_count = read.read_i32()?;
+    let mut unused = [0u32; 4];
+    for value in &mut unused {
+      *value = read.read_u32()?;
+    }
+
+    Ok(Self {
+      version,
+      checksum,
+      vertex_flags,
+      vertex_size,
+      vertexes_count,
+      meshes_count,
+      unused
+    })
+  }
+}
@@ -0,0 +1,9 @@
+mod header;
+mod mesh_header;
+mod vertex_color;
+mod read_util;
+
+pub use self::read_util::*;
+pub use self::header::Header;
+pub use self::mesh_header::MeshHeader;
+pub use self::vertex_color::VertexColor;
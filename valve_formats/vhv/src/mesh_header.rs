@@ -0,0 +1,31 @@
+use std::io::{Read, Result as IOResult};
+
+use crate::PrimitiveRead;
+
+/// There is one mesh header for every mesh of every LOD of the model in the order of the VTX file.
+pub struct MeshHeader {
+  pub lod: u32,
+  pub vertexes_count: u32,
+  /// Offset of the vertex data from the start of the file.
+  pub offset: u32,
+  pub unused: [u32; 4]
+}
+
+impl MeshHeader {
+  pub fn read(read: &mut dyn Read) -> IOResult<Self> {
+    let lod = read.read_u32()?;
+    let vertexes_count = read.read_u32()?;
+    let offset = read.read_u32()?;
+    let mut unused = [0u32; 4];
+    for value in &mut unused {
+      *value = read.read_u32()?;
+    }
+
+    Ok(Self {
+      lod,
+      vertexes_count,
+      offset,
+      unused
+    })
+  }
+}
@@ -0,0 +1,28 @@
+use std::io::{Read, Result as IOResult};
+
+use crate::PrimitiveRead;
+
+/// Baked lighting of a single vertex. The color is stored in gamma space and scaled down by the overbright factor.
+#[derive(Clone, Copy, Debug)]
+pub struct VertexColor {
+  pub r: u8,
+  pub g: u8,
+  pub b: u8,
+  pub a: u8
+}
+
+impl VertexColor {
+  pub fn read(read: &mut dyn Read) -> IOResult<Self> {
+    // The color is stored as BGRA.
+    let b = read.read_u8()?;
+    let g = read.read_u8()?;
+    let r = read.read_u8()?;
+    let a = read.read_u8()?;
+    Ok(Self {
+      r,
+      g,
+      b,
+      a
+    })
+  }
+}
@@ -0,0 +1,294 @@
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// Conditionals that evaluate to true when no defines are passed explicitly.
+pub const DEFAULT_DEFINES: &[&str] = &["$WIN32", "$WINDOWS", "$PC"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyValuesError {
+  UnexpectedToken(String),
+  UnexpectedEnd,
+  UnterminatedString
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KeyValue {
+  Value(String),
+  Block(KeyValues)
+}
+
+impl KeyValue {
+  pub fn as_value(&self) -> Option<&str> {
+    match self {
+      KeyValue::Value(value) => Some(value.as_str()),
+      KeyValue::Block(_) => None
+    }
+  }
+
+  pub fn as_block(&self) -> Option<&KeyValues> {
+    match self {
+      KeyValue::Value(_) => None,
+      KeyValue::Block(block) => Some(block)
+    }
+  }
+}
+
+/// An ordered list of key value pairs where values can be nested blocks.
+/// Keys are compared case insensitively.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KeyValues {
+  entries: Vec<(String, KeyValue)>
+}
+
+impl KeyValues {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn parse(text: &str) -> Result<Self, KeyValuesError> {
+    Self::parse_with_defines(text, DEFAULT_DEFINES)
+  }
+
+  /// Entries with a conditional like `[$X360]` are only kept if the conditional is true for the given defines.
+  pub fn parse_with_defines(text: &str, defines: &[&str]) -> Result<Self, KeyValuesError> {
+    let mut tokenizer = Tokenizer::new(text);
+    Self::parse_block(&mut tokenizer, defines, false)
+  }
+
+  fn parse_block(tokenizer: &mut Tokenizer, defines: &[&str], nested: bool) -> Result<Self, KeyValuesError> {
+    let mut key_values = KeyValues::new();
+    loop {
+      let key = match tokenizer.next_token()? {
+        None if nested => return Err(KeyValuesError::UnexpectedEnd),
+        None => return Ok(key_values),
+        Some(Token::CloseBrace) if nested => return Ok(key_values),
+        Some(Token::String(key)) => key,
+        Some(token) => return Err(KeyValuesError::UnexpectedToken(token.to_string()))
+      };
+
+      let mut condition = None;
+      let mut value_token = tokenizer.next_token()?;
+      if let Some(Token::Conditional(conditional)) = &value_token {
+        if is_conditional(conditional) {
+          // Blocks have the conditional between the key and the opening brace.
+          condition = Some(conditional.clone());
+          value_token = tokenizer.next_token()?;
+        }
+      }
+
+      let value = match value_token {
+        None => return Err(KeyValuesError::UnexpectedEnd),
+        Some(Token::OpenBrace) => KeyValue::Block(Self::parse_block(tokenizer, defines, true)?),
+        Some(Token::String(value)) => KeyValue::Value(value),
+        // Unquoted vectors like [1 1 1]
+        Some(Token::Conditional(value)) => KeyValue::Value(format!("[{}]", value)),
+        Some(token) => return Err(KeyValuesError::UnexpectedToken(token.to_string()))
+      };
+
+      if condition.is_none() {
+        if let Some(Token::Conditional(conditional)) = tokenizer.peek_token()? {
+          if is_conditional(&conditional) {
+            tokenizer.next_token()?;
+            condition = Some(conditional);
+          }
+        }
+      }
+
+      let is_included = match condition {
+        Some(condition) => evaluate_conditional(&condition, defines),
+        None => true
+      };
+      if is_included {
+        key_values.entries.push((key, value));
+      }
+    }
+  }
+
+  pub fn get(&self, key: &str) -> Option<&KeyValue> {
+    self.entries.iter()
+      .find(|(entry_key, _)| entry_key.eq_ignore_ascii_case(key))
+      .map(|(_, value)| value)
+  }
+
+  pub fn get_mut(&mut self, key: &str) -> Option<&mut KeyValue> {
+    self.entries.iter_mut()
+      .find(|(entry_key, _)| entry_key.eq_ignore_ascii_case(key))
+      .map(|(_, value)| value)
+  }
+
+  pub fn get_value(&self, key: &str) -> Option<&str> {
+    self.get(key).and_then(|value| value.as_value())
+  }
+
+  pub fn get_block(&self, key: &str) -> Option<&KeyValues> {
+    self.get(key).and_then(|value| value.as_block())
+  }
+
+  pub fn contains_key(&self, key: &str) -> bool {
+    self.get(key).is_some()
+  }
+
+  /// Replaces the first entry with the given key or appends a new one.
+  pub fn set(&mut self, key: &str, value: KeyValue) {
+    if let Some(existing) = self.get_mut(key) {
+      *existing = value;
+    } else {
+      self.entries.push((key.to_string(), value));
+    }
+  }
+
+  /// Appends an entry, even if there already is one with the same key.
+  pub fn push(&mut self, key: &str, value: KeyValue) {
+    self.entries.push((key.to_string(), value));
+  }
+
+  pub fn remove(&mut self, key: &str) -> Option<KeyValue> {
+    let index = self.entries.iter().position(|(entry_key, _)| entry_key.eq_ignore_ascii_case(key))?;
+    Some(self.entries.remove(index).1)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (&str, &KeyValue)> {
+    self.entries.iter().map(|(key, value)| (key.as_str(), value))
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+  String(String),
+  Conditional(String),
+  OpenBrace,
+  CloseBrace
+}
+
+impl Display for Token {
+  fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+    match self {
+      Token::String(text) => write!(f, "{}", text),
+      Token::Conditional(text) => write!(f, "[{}]", text),
+      Token::OpenBrace => write!(f, "{{"),
+      Token::CloseBrace => write!(f, "}}")
+    }
+  }
+}
+
+struct Tokenizer<'a> {
+  chars: Peekable<Chars<'a>>,
+  peeked: Option<Option<Token>>
+}
+
+impl<'a> Tokenizer<'a> {
+  fn new(text: &'a str) -> Self {
+    Self {
+      chars: text.chars().peekable(),
+      peeked: None
+    }
+  }
+
+  fn peek_token(&mut self) -> Result<Option<Token>, KeyValuesError> {
+    if self.peeked.is_none() {
+      self.peeked = Some(self.read_token()?);
+    }
+    Ok(self.peeked.clone().unwrap())
+  }
+
+  fn next_token(&mut self) -> Result<Option<Token>, KeyValuesError> {
+    if let Some(token) = self.peeked.take() {
+      return Ok(token);
+    }
+    self.read_token()
+  }
+
+  fn read_token(&mut self) -> Result<Option<Token>, KeyValuesError> {
+    self.skip_whitespace_and_comments();
+    let char = self.chars.next();
+    if char.is_none() {
+      return Ok(None);
+    }
+    let char = char.unwrap();
+    match char {
+      '{' => Ok(Some(Token::OpenBrace)),
+      '}' => Ok(Some(Token::CloseBrace)),
+      '"' => {
+        let mut text = String::new();
+        loop {
+          match self.chars.next() {
+            None => return Err(KeyValuesError::UnterminatedString),
+            Some('"') => break,
+            Some(char) => text.push(char)
+          }
+        }
+        Ok(Some(Token::String(text)))
+      }
+      '[' => {
+        let mut text = String::new();
+        loop {
+          match self.chars.next() {
+            None => return Err(KeyValuesError::UnexpectedEnd),
+            Some(']') => break,
+            Some(char) => text.push(char)
+          }
+        }
+        Ok(Some(Token::Conditional(text)))
+      }
+      _ => {
+        let mut text = String::new();
+        text.push(char);
+        while let Some(next) = self.chars.peek() {
+          if next.is_whitespace() || *next == '{' || *next == '}' || *next == '"' {
+            break;
+          }
+          text.push(*next);
+          self.chars.next();
+        }
+        Ok(Some(Token::String(text)))
+      }
+    }
+  }
+
+  fn skip_whitespace_and_comments(&mut self) {
+    loop {
+      while self.chars.peek().is_some_and(|char| char.is_whitespace() || *char == '\0') {
+        self.chars.next();
+      }
+
+      let mut lookahead = self.chars.clone();
+      if lookahead.next() == Some('/') && lookahead.next() == Some('/') {
+        while self.chars.peek().is_some_and(|char| *char != '\n') {
+          self.chars.next();
+        }
+        continue;
+      }
+      break;
+    }
+  }
+}
+
+fn is_conditional(text: &str) -> bool {
+  let text = text.trim();
+  text.starts_with('$') || text.starts_with('!')
+}
+
+/// Evaluates conditionals like `$WIN32`, `!$X360` or `$WIN32 || $OSX`.
+fn evaluate_conditional(conditional: &str, defines: &[&str]) -> bool {
+  conditional.split("||").any(|or_part| {
+    or_part.split("&&").all(|term| {
+      let term = term.trim();
+      let (negated, define) = if let Some(define) = term.strip_prefix('!') {
+        (true, define.trim())
+      } else {
+        (false, term)
+      };
+      let defined = defines.iter().any(|known| known.eq_ignore_ascii_case(define));
+      defined != negated
+    })
+  })
+}
@@ -1,9 +1,11 @@
 mod read_util;
+mod key_values;
 
-use std::collections::HashMap;
 use std::io::{Read, Error as IOError};
 use crate::read_util::RawDataRead;
 
+pub use crate::key_values::{KeyValues, KeyValue, KeyValuesError, DEFAULT_DEFINES};
+
 pub const SHADER_LIGHT_MAPPED_GENERIC: &str = "lightmappedgeneric";
 pub const SHADER_VERTEX_LIT_GENERIC: &str = "vertexlitgeneric";
 pub const SHADER_UNLIT_GENERIC: &str = "unlitgeneric";
@@ -13,8 +15,11 @@ pub const SHADER_WATER: &str = "water";
 pub const BASE_TEXTURE_NAME: &str = "basetexture";
 pub const PATCH: &str = "patch";
 pub const PATCH_INCLUDE: &str = "include";
-#[allow(dead_code)]
-const PATCH_INSERT: &str = "insert";
+pub const PATCH_INSERT: &str = "insert";
+pub const PATCH_REPLACE: &str = "replace";
+pub const PROXIES: &str = "proxies";
+/// The DirectX level that fallback blocks like ">=dx90" are evaluated against.
+pub const DX_LEVEL: u32 = 95;
 
 #[derive(Debug)]
 pub enum VMTError {
@@ -24,28 +29,19 @@ pub enum VMTError {
 
 pub struct VMTMaterial {
   shader_name: String,
-  values: HashMap<String, String>
+  values: KeyValues
 }
 
 impl VMTMaterial {
   pub fn new(reader: &mut dyn Read, length: u32) -> Result<Self, VMTError> {
-    let mut values = HashMap::<String, String>::new();
-
     let data = reader.read_data(length as usize).map_err(VMTError::IOError)?;
-    let mut text = String::from_utf8(data.to_vec()).map_err(|_e| VMTError::FileError("Could not read text".to_string()))?;
-    text = text.replace("\r\n", "\n");
-    text = text.replace('\t', " ");
-    text = text.trim_end_matches('\0').to_string();
-    let block_start = text.find('{').ok_or_else(|| VMTError::FileError("Could not find start of material block".to_string()))?;
-
-    let mut shader_name: String = String::new();
-    let shader_name_lines = (&text[..block_start]).split('\n');
-    for line in shader_name_lines {
-      let line = remove_line_comments(line);
-      if !line.trim().is_empty() {
-        shader_name = line.replace("\"", "").trim().to_lowercase();
-      }
-    }
+    let text = String::from_utf8_lossy(&data);
+    let text = text.trim_start_matches('\u{feff}').trim_end_matches('\0');
+    let root = KeyValues::parse(text).map_err(|e| VMTError::FileError(format!("Could not parse material: {:?}", e)))?;
+
+    let (shader_name, block) = root.iter()
+      .find_map(|(key, value)| value.as_block().map(|block| (key.to_lowercase(), block)))
+      .ok_or_else(|| VMTError::FileError("Could not find material block".to_string()))?;
 
     if shader_name != SHADER_LIGHT_MAPPED_GENERIC
       && shader_name != PATCH
@@ -57,33 +53,32 @@ impl VMTMaterial {
       println!("Found unsupported shader: \"{}\"", shader_name);
     }
 
-    let block_end = text.find('}').ok_or_else(|| VMTError::FileError("Could not find end of material block".to_string()))?;
-    let block = &text[block_start .. block_end];
-    let lines = block.split('\n');
-    for line in lines {
-      let trimmed_line = line.trim().replace(&['$', '%', '"', '\''][..], "");
-      if trimmed_line.is_empty() || trimmed_line == "{" || trimmed_line == "}" || trimmed_line == PATCH_INCLUDE {
-        continue;
-      }
-
-      let key_end_opt = trimmed_line.find(' ');
-      if key_end_opt.is_none() {
-        continue;
-      }
-      let key_end = key_end_opt.unwrap();
-      let key = (&trimmed_line[.. key_end]).trim().to_lowercase();
-      let value = remove_line_comments(&trimmed_line[key_end + 1 ..]).trim().to_string();
-      values.insert(key, value);
-    }
-
+    let values = apply_fallbacks(&shader_name, block);
     Ok(Self {
       shader_name,
       values
     })
   }
 
+  /// Looks up a material parameter. The $ or % prefix of the key is optional.
   pub fn get_value(&self, key: &str) -> Option<&str> {
-    self.values.get(key).map(|v| v.as_str())
+    self.find(key).and_then(|value| value.as_value())
+  }
+
+  /// Looks up a nested block like the proxies.
+  pub fn get_block(&self, key: &str) -> Option<&KeyValues> {
+    self.find(key).and_then(|value| value.as_block())
+  }
+
+  pub fn key_values(&self) -> &KeyValues {
+    &self.values
+  }
+
+  fn find(&self, key: &str) -> Option<&KeyValue> {
+    let key = key.trim_start_matches(&['$', '%'][..]);
+    self.values.iter()
+      .find(|(entry_key, _)| entry_key.trim_start_matches(&['$', '%'][..]).eq_ignore_ascii_case(key))
+      .map(|(_, value)| value)
   }
 
   pub fn get_shader(&self) -> &str {
@@ -98,23 +93,113 @@ impl VMTMaterial {
     self.get_value(PATCH_INCLUDE)
   }
 
+  pub fn get_proxies(&self) -> Option<&KeyValues> {
+    self.get_block(PROXIES)
+  }
+
   pub fn is_patch(&self) -> bool {
     self.shader_name == PATCH
   }
 
+  /// Parameters in the insert block get added or overwritten,
+  /// parameters in the replace block only overwrite ones that already exist.
   pub fn apply_patch(&mut self, patch: &VMTMaterial) {
     if !patch.is_patch() {
       panic!("Material must be a patch");
     }
 
-    self.values.extend(patch.values.iter().map(|(key, value)| (key.clone(), value.clone())));
+    if let Some(insert) = patch.values.get_block(PATCH_INSERT) {
+      for (key, value) in insert.iter() {
+        let existing_key = self.existing_key(key);
+        self.values.set(existing_key.as_deref().unwrap_or(key), value.clone());
+      }
+    }
+
+    if let Some(replace) = patch.values.get_block(PATCH_REPLACE) {
+      for (key, value) in replace.iter() {
+        if let Some(existing_key) = self.existing_key(key) {
+          self.values.set(&existing_key, value.clone());
+        }
+      }
+    }
+  }
+
+  fn existing_key(&self, key: &str) -> Option<String> {
+    let key = key.trim_start_matches(&['$', '%'][..]);
+    self.values.iter()
+      .find(|(entry_key, _)| entry_key.trim_start_matches(&['$', '%'][..]).eq_ignore_ascii_case(key))
+      .map(|(entry_key, _)| entry_key.to_string())
   }
 }
 
-fn remove_line_comments(text: &str) -> &str {
-  let comment_start = text.find("//");
-  if comment_start.is_none() {
-    return text;
+/// Merges the fallback blocks that apply to a DX9 HDR renderer into the material parameters.
+fn apply_fallbacks(shader_name: &str, block: &KeyValues) -> KeyValues {
+  let mut values = KeyValues::new();
+  let mut fallbacks = Vec::<(u32, &KeyValues)>::new();
+  for (key, value) in block.iter() {
+    match value {
+      KeyValue::Block(fallback_block) => {
+        match fallback_priority(shader_name, key) {
+          Some(priority) => fallbacks.push((priority, fallback_block)),
+          None if is_fallback(shader_name, key) => {},
+          None => values.push(key, value.clone())
+        }
+      }
+      KeyValue::Value(_) => values.push(key, value.clone())
+    }
+  }
+
+  fallbacks.sort_by_key(|(priority, _)| *priority);
+  for (_, fallback) in fallbacks {
+    for (key, value) in fallback.iter() {
+      values.set(key, value.clone());
+    }
+  }
+  values
+}
+
+fn is_fallback(shader_name: &str, key: &str) -> bool {
+  let key = key.to_lowercase();
+  key.starts_with('<') || key.starts_with('>')
+    || key.starts_with("hdr") || key.starts_with("ldr")
+    || key.starts_with(&(shader_name.to_string() + "_"))
+}
+
+fn fallback_priority(shader_name: &str, key: &str) -> Option<u32> {
+  let key = key.to_lowercase();
+  if let Some(suffix) = key.strip_prefix(shader_name).and_then(|suffix| suffix.strip_prefix('_')) {
+    return match suffix {
+      "dx9" => Some(1),
+      "hdr_dx9" => Some(2),
+      _ => None
+    };
+  }
+
+  if key == "hdr" || key == "hdr_dx9" {
+    return Some(2);
+  }
+
+  let (comparison, level) = if let Some(level) = key.strip_prefix(">=") {
+    (">=", level)
+  } else if let Some(level) = key.strip_prefix("<=") {
+    ("<=", level)
+  } else if let Some(level) = key.strip_prefix('>') {
+    (">", level)
+  } else if let Some(level) = key.strip_prefix('<') {
+    ("<", level)
+  } else {
+    return None;
+  };
+  let level: u32 = level.strip_prefix("dx")?.split('_').next()?.parse().ok()?;
+  let applies = match comparison {
+    ">=" => DX_LEVEL >= level,
+    "<=" => DX_LEVEL <= level,
+    ">" => DX_LEVEL > level,
+    _ => DX_LEVEL < level
+  };
+  if applies {
+    Some(0)
+  } else {
+    None
   }
-  &text[..comment_start.unwrap()]
 }
@@ -69,16 +69,19 @@ impl Header {
     let header_size = reader.read_u32()?;
     let width = reader.read_u16()?;
     let height = reader.read_u16()?;
-    let flags = TextureFlags::from_bits(reader.read_u32()?).unwrap();
+    let flags = TextureFlags::from_bits_truncate(reader.read_u32()?);
     let frames = reader.read_u16()?;
     let first_frame = reader.read_u16()?;
     reader.seek(SeekFrom::Current(4))?;
     let reflectivity = [reader.read_f32()?, reader.read_f32()?, reader.read_f32()?];
     reader.seek(SeekFrom::Current(4))?;
     let bumpmap_scale = reader.read_f32()?;
-    let high_res_image_format: ImageFormat = unsafe { std::mem::transmute(reader.read_u32()?) };
+    let high_res_image_format_id = reader.read_u32()?;
+    let high_res_image_format = ImageFormat::from_u32(high_res_image_format_id)
+      .filter(|format| *format != ImageFormat::None)
+      .ok_or_else(|| IOError::new(ErrorKind::InvalidData, format!("Unknown VTF image format: {}", high_res_image_format_id)))?;
     let mipmap_count = reader.read_u8()?;
-    let low_res_image_format: ImageFormat =  unsafe { std::mem::transmute(reader.read_u32()?) };
+    let low_res_image_format = ImageFormat::from_u32(reader.read_u32()?).unwrap_or(ImageFormat::None);
     let low_res_image_width = reader.read_u8()?;
     let low_res_image_height = reader.read_u8()?;
 
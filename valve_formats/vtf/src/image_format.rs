@@ -29,7 +29,45 @@ pub enum ImageFormat {
   UVWQ8888,
   RGBA16161616F,
   RGBA16161616,
-  UV1X8888
+  UV1X8888,
+  /// Used for the low resolution image of textures without a thumbnail.
+  None = 0xFFFFFFFF
+}
+
+impl ImageFormat {
+  pub fn from_u32(value: u32) -> Option<Self> {
+    Some(match value {
+      0 => ImageFormat::RGBA8888,
+      1 => ImageFormat::ABGR8888,
+      2 => ImageFormat::RGB8888,
+      3 => ImageFormat::BGR888,
+      4 => ImageFormat::RGB565,
+      5 => ImageFormat::I8,
+      6 => ImageFormat::IA88,
+      7 => ImageFormat::P8,
+      8 => ImageFormat::A8,
+      9 => ImageFormat::RGB888Bluescreen,
+      10 => ImageFormat::BGR888Bluescreen,
+      11 => ImageFormat::ARGB8888,
+      12 => ImageFormat::BGRA8888,
+      13 => ImageFormat::DXT1,
+      14 => ImageFormat::DXT3,
+      15 => ImageFormat::DXT5,
+      16 => ImageFormat::BGRX8888,
+      17 => ImageFormat::BGR565,
+      18 => ImageFormat::BGRX5551,
+      19 => ImageFormat::BGRA4444,
+      20 => ImageFormat::DXT1OneBitAlpha,
+      21 => ImageFormat::BGRA5551,
+      22 => ImageFormat::UV88,
+      23 => ImageFormat::UVWQ8888,
+      24 => ImageFormat::RGBA16161616F,
+      25 => ImageFormat::RGBA16161616,
+      26 => ImageFormat::UV1X8888,
+      0xFFFFFFFF => ImageFormat::None,
+      _ => return None
+    })
+  }
 }
 
 pub enum FormatSizeInfo {
@@ -53,195 +91,71 @@ pub struct ImageFormatInfo {
   pub is_supported: bool
 }
 
+fn pixel_format(red: u8, green: u8, blue: u8, alpha: u8, total: u8) -> ImageFormatInfo {
+  ImageFormatInfo {
+    is_supported: true,
+    size_info: FormatSizeInfo::Pixel {
+      red_bits_per_pixel: red,
+      green_bits_per_pixel: green,
+      blue_bits_per_pixel: blue,
+      alpha_bits_per_pixel: alpha,
+      total_bits_per_pixel: total
+    }
+  }
+}
+
+fn block_format(total_bits_per_block: u8) -> ImageFormatInfo {
+  ImageFormatInfo {
+    is_supported: true,
+    size_info: FormatSizeInfo::Block {
+      block_width: 4,
+      block_height: 4,
+      block_depth: 1,
+      total_bits_per_block
+    }
+  }
+}
+
 lazy_static! {
   static ref IMAGE_FORMAT_INFO_MAP: HashMap<ImageFormat, ImageFormatInfo> = {
     let mut m = HashMap::new();
-    m.insert(ImageFormat::A8, ImageFormatInfo {
-      is_supported: true,
+    m.insert(ImageFormat::RGBA8888, pixel_format(8, 8, 8, 8, 32));
+    m.insert(ImageFormat::ABGR8888, pixel_format(8, 8, 8, 8, 32));
+    m.insert(ImageFormat::RGB8888, pixel_format(8, 8, 8, 0, 24));
+    m.insert(ImageFormat::BGR888, pixel_format(8, 8, 8, 0, 24));
+    m.insert(ImageFormat::RGB565, pixel_format(5, 6, 5, 0, 16));
+    m.insert(ImageFormat::I8, pixel_format(8, 8, 8, 0, 8));
+    m.insert(ImageFormat::IA88, pixel_format(8, 8, 8, 8, 16));
+    m.insert(ImageFormat::P8, ImageFormatInfo {
+      // Paletted textures are not used by any shipped game.
+      is_supported: false,
       size_info: FormatSizeInfo::Pixel {
         red_bits_per_pixel: 0,
         green_bits_per_pixel: 0,
         blue_bits_per_pixel: 0,
-        alpha_bits_per_pixel: 8,
-        total_bits_per_pixel: 8
-      }
-    });
-    m.insert(ImageFormat::ARGB8888, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Pixel {
-        red_bits_per_pixel: 8,
-        green_bits_per_pixel: 8,
-        blue_bits_per_pixel: 8,
-        alpha_bits_per_pixel: 8,
-        total_bits_per_pixel: 32
-      }
-    });
-    m.insert(ImageFormat::ABGR8888, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Pixel {
-        red_bits_per_pixel: 8,
-        green_bits_per_pixel: 8,
-        blue_bits_per_pixel: 8,
-        alpha_bits_per_pixel: 8,
-        total_bits_per_pixel: 32
-      }
-    });
-    m.insert(ImageFormat::BGR565, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Pixel {
-        red_bits_per_pixel: 5,
-        green_bits_per_pixel: 6,
-        blue_bits_per_pixel: 5,
         alpha_bits_per_pixel: 0,
-        total_bits_per_pixel: 16
-      }
-    });
-    m.insert(ImageFormat::BGR888, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Pixel {
-        red_bits_per_pixel: 8,
-        green_bits_per_pixel: 8,
-        blue_bits_per_pixel: 8,
-        alpha_bits_per_pixel: 0,
-        total_bits_per_pixel: 24
-      }
-    });
-    m.insert(ImageFormat::BGR888Bluescreen, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Pixel {
-        red_bits_per_pixel: 8,
-        green_bits_per_pixel: 8,
-        blue_bits_per_pixel: 8,
-        alpha_bits_per_pixel: 0,
-        total_bits_per_pixel: 24
-      }
-    });
-    m.insert(ImageFormat::BGRA4444, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Pixel {
-        red_bits_per_pixel: 4,
-        green_bits_per_pixel: 4,
-        blue_bits_per_pixel: 4,
-        alpha_bits_per_pixel: 4,
-        total_bits_per_pixel: 16
-      }
-    });
-    m.insert(ImageFormat::BGRA5551, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Pixel {
-        red_bits_per_pixel: 5,
-        green_bits_per_pixel: 5,
-        blue_bits_per_pixel: 5,
-        alpha_bits_per_pixel: 1,
-        total_bits_per_pixel: 16
-      }
-    });
-    m.insert(ImageFormat::BGRA8888, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Pixel {
-        red_bits_per_pixel: 8,
-        green_bits_per_pixel: 8,
-        blue_bits_per_pixel: 8,
-        alpha_bits_per_pixel: 8,
-        total_bits_per_pixel: 32
-      }
-    });
-    m.insert(ImageFormat::BGRX8888, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Pixel {
-        red_bits_per_pixel: 8,
-        green_bits_per_pixel: 8,
-        blue_bits_per_pixel: 8,
-        alpha_bits_per_pixel: 0,
-        total_bits_per_pixel: 32
-      }
-    });
-    m.insert(ImageFormat::DXT1, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Block {
-        block_width: 4,
-        block_height: 4,
-        block_depth: 1,
-        total_bits_per_block: 8
-      }
-    });
-    m.insert(ImageFormat::DXT1OneBitAlpha, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Block {
-        block_width: 4,
-        block_height: 4,
-        block_depth: 1,
-        total_bits_per_block: 8
-      }
-    });
-    m.insert(ImageFormat::DXT1, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Block {
-        block_width: 4,
-        block_height: 4,
-        block_depth: 1,
-        total_bits_per_block: 8
-      }
-    });
-    m.insert(ImageFormat::DXT5, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Block {
-        block_width: 4,
-        block_height: 4,
-        block_depth: 1,
-        total_bits_per_block: 16
-      }
-    });
-    m.insert(ImageFormat::RGB565, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Pixel {
-        red_bits_per_pixel: 5,
-        green_bits_per_pixel: 6,
-        blue_bits_per_pixel: 5,
-        alpha_bits_per_pixel: 0,
-        total_bits_per_pixel: 16
-      }
-    });
-    m.insert(ImageFormat::RGB888Bluescreen, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Pixel {
-        red_bits_per_pixel: 8,
-        green_bits_per_pixel: 8,
-        blue_bits_per_pixel: 8,
-        alpha_bits_per_pixel: 0,
-        total_bits_per_pixel: 24
-      }
-    });
-    m.insert(ImageFormat::RGBA16161616, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Pixel {
-        red_bits_per_pixel: 16,
-        green_bits_per_pixel: 16,
-        blue_bits_per_pixel: 16,
-        alpha_bits_per_pixel: 16,
-        total_bits_per_pixel: 64
-      }
-    });
-    m.insert(ImageFormat::RGBA16161616F, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Pixel {
-        red_bits_per_pixel: 16,
-        green_bits_per_pixel: 16,
-        blue_bits_per_pixel: 16,
-        alpha_bits_per_pixel: 16,
-        total_bits_per_pixel: 64
-      }
-    });
-    m.insert(ImageFormat::RGBA8888, ImageFormatInfo {
-      is_supported: true,
-      size_info: FormatSizeInfo::Pixel {
-        red_bits_per_pixel: 8,
-        green_bits_per_pixel: 8,
-        blue_bits_per_pixel: 8,
-        alpha_bits_per_pixel: 8,
-        total_bits_per_pixel: 32
+        total_bits_per_pixel: 8
       }
     });
+    m.insert(ImageFormat::A8, pixel_format(0, 0, 0, 8, 8));
+    m.insert(ImageFormat::RGB888Bluescreen, pixel_format(8, 8, 8, 0, 24));
+    m.insert(ImageFormat::BGR888Bluescreen, pixel_format(8, 8, 8, 0, 24));
+    m.insert(ImageFormat::ARGB8888, pixel_format(8, 8, 8, 8, 32));
+    m.insert(ImageFormat::BGRA8888, pixel_format(8, 8, 8, 8, 32));
+    m.insert(ImageFormat::DXT1, block_format(64));
+    m.insert(ImageFormat::DXT3, block_format(128));
+    m.insert(ImageFormat::DXT5, block_format(128));
+    m.insert(ImageFormat::BGRX8888, pixel_format(8, 8, 8, 0, 32));
+    m.insert(ImageFormat::BGR565, pixel_format(5, 6, 5, 0, 16));
+    m.insert(ImageFormat::BGRX5551, pixel_format(5, 5, 5, 0, 16));
+    m.insert(ImageFormat::BGRA4444, pixel_format(4, 4, 4, 4, 16));
+    m.insert(ImageFormat::DXT1OneBitAlpha, block_format(64));
+    m.insert(ImageFormat::BGRA5551, pixel_format(5, 5, 5, 1, 16));
+    m.insert(ImageFormat::UV88, pixel_format(8, 8, 0, 0, 16));
+    m.insert(ImageFormat::UVWQ8888, pixel_format(8, 8, 8, 8, 32));
+    m.insert(ImageFormat::RGBA16161616F, pixel_format(16, 16, 16, 16, 64));
+    m.insert(ImageFormat::RGBA16161616, pixel_format(16, 16, 16, 16, 64));
+    m.insert(ImageFormat::UV1X8888, pixel_format(8, 8, 8, 8, 32));
     m
   };
 }
@@ -250,17 +164,18 @@ pub(crate) fn is_image_format_supported(format: ImageFormat) -> bool {
   IMAGE_FORMAT_INFO_MAP.get(&format).map_or(false, |format_info| format_info.is_supported)
 }
 
-pub(crate) fn calculate_image_size(width: u32, height: u32, depth: u32, format: ImageFormat) -> u32 {
-  let info = IMAGE_FORMAT_INFO_MAP.get(&format).expect("Unsupported format");
-  match info.size_info {
+/// Returns the size in bytes or None if the format is unknown.
+pub(crate) fn calculate_image_size(width: u32, height: u32, depth: u32, format: ImageFormat) -> Option<u32> {
+  let info = IMAGE_FORMAT_INFO_MAP.get(&format)?;
+  Some(match info.size_info {
     FormatSizeInfo::Pixel { total_bits_per_pixel, .. } => {
-      total_bits_per_pixel as u32 * width * height * depth
+      total_bits_per_pixel as u32 * width * height * depth / 8
     }
     FormatSizeInfo::Block { block_width, block_height, block_depth, total_bits_per_block } => {
       ((width + block_width as u32 - 1) / block_width as u32)
       * ((height + block_height as u32 - 1) / block_height as u32)
       * ((depth + block_depth as u32 - 1) / block_depth as u32)
-      * total_bits_per_block as u32
+      * total_bits_per_block as u32 / 8
     }
-  }
+  })
 }
@@ -1,5 +1,5 @@
 use crate::header::Header;
-use std::io::{Read, Seek, Result as IOResult, SeekFrom};
+use std::io::{Read, Seek, Result as IOResult, SeekFrom, Error as IOError, ErrorKind};
 use crate::thumbnail::Thumbnail;
 use std::collections::HashMap;
 use crate::read_util::{PrimitiveRead, RawDataRead};
@@ -7,6 +7,7 @@ use crate::image_format::{is_image_format_supported, calculate_image_size};
 use crate::{MipMap, Face, Slice};
 use std::cmp::max;
 use crate::Frame;
+use crate::TextureFlags;
 
 pub struct VtfTexture<R: Read + Seek> {
   reader: R,
@@ -30,10 +31,16 @@ impl<R: Read + Seek> VtfTexture<R> {
 
   pub fn new(mut reader: R) -> IOResult<Self> {
     let header = Header::read(&mut reader)?;
+    if !is_image_format_supported(header.high_res_image_format) {
+      return Err(IOError::new(ErrorKind::InvalidData, format!("Unsupported VTF image format: {:?}", header.high_res_image_format)));
+    }
+    if header.mipmap_count == 0 {
+      return Err(IOError::new(ErrorKind::InvalidData, "VTF texture has no mip maps"));
+    }
     let resource_offsets = Self::read_resource_offsets(&mut reader, &header)?;
     let thumbnail = resource_offsets.get(&Resource::Thumbnail).and_then(|offset| {
       reader.seek(SeekFrom::Start(*offset as u64)).ok()?;
-      let size = calculate_image_size(header.low_res_image_width as u32, header.low_res_image_height as u32, 1, header.low_res_image_format) as usize;
+      let size = calculate_image_size(header.low_res_image_width as u32, header.low_res_image_height as u32, 1, header.low_res_image_format)? as usize;
       let buffer = reader.read_data(size).ok()?;
       Some(Thumbnail {
         data: buffer,
@@ -51,10 +58,10 @@ impl<R: Read + Seek> VtfTexture<R> {
   }
 
   fn calculate_mip_offset(&self, level: u32) -> Option<u64> {
-    if level > self.header.mipmap_count as u32 {
+    if level >= self.header.mipmap_count as u32 {
       return None;
     }
-    let mut offset = *self.resource_offsets.get(&Resource::Image).unwrap() as u64;
+    let mut offset = *self.resource_offsets.get(&Resource::Image)? as u64;
 
     // I'm sure there's a way to simply calculate that with math but this works just fine
     for level in 0 .. level {
@@ -62,15 +69,37 @@ impl<R: Read + Seek> VtfTexture<R> {
 
       let level_width = max(1, self.header.width >> reversed_level) as u32;
       let level_height = max(1, self.header.height >> reversed_level) as u32;
-      let level_image_size = calculate_image_size(level_width, level_height, 1, self.header.high_res_image_format) as u64;
-      let frames_count = self.header.frames as u64;
-      let faces_count = 1u64; // TODO
-      let slices_count = max(1, self.header.depth as u64); // does this perhaps scale with the mip level in some cases?
+      let level_image_size = calculate_image_size(level_width, level_height, 1, self.header.high_res_image_format)? as u64;
+      let frames_count = self.frames_count() as u64;
+      let faces_count = self.faces_count() as u64;
+      let slices_count = max(1, self.depth() >> reversed_level) as u64;
       offset += level_image_size * frames_count * faces_count * slices_count;
     }
     Some(offset)
   }
 
+  /// Environment maps have 6 faces, older ones store an additional sphere map as a 7th face.
+  pub fn faces_count(&self) -> u32 {
+    if !self.header.flags.contains(TextureFlags::ENV_MAP) {
+      return 1;
+    }
+    let has_sphere_map = self.header.version[0] == 7 && self.header.version[1] < 5 && self.header.first_frame != 0xFFFF;
+    if has_sphere_map {
+      7
+    } else {
+      6
+    }
+  }
+
+  pub fn frames_count(&self) -> u32 {
+    max(1, self.header.frames as u32)
+  }
+
+  /// The depth of the largest mip map, 1 for textures that aren't volume textures.
+  pub fn depth(&self) -> u32 {
+    max(1, self.header.depth as u32)
+  }
+
   pub fn header(&self) -> &Header {
     &self.header
   }
@@ -82,11 +111,11 @@ impl<R: Read + Seek> VtfTexture<R> {
     let reversed_level = self.header.mipmap_count as u32 - 1 - level;
     let level_width = max(1, self.header.width >> reversed_level) as u32;
     let level_height = max(1, self.header.height >> reversed_level) as u32;
-    let level_image_size = calculate_image_size(level_width, level_height, 1, self.header.high_res_image_format);
+    let level_image_size = calculate_image_size(level_width, level_height, 1, self.header.high_res_image_format)?;
 
-    let frames_count = self.header.frames;
-    let faces_count = 1; // TODO
-    let slices_count = max(1, self.header.depth); // does this perhaps scale with the mip level in some cases?
+    let frames_count = self.frames_count();
+    let faces_count = self.faces_count();
+    let slices_count = max(1, self.depth() >> reversed_level);
 
     let mut frames = Vec::<Frame>::with_capacity(frames_count as usize);
     for _frame in 0..frames_count {
@@ -112,7 +141,8 @@ impl<R: Read + Seek> VtfTexture<R> {
       frames,
       format: self.header.high_res_image_format,
       width: level_width,
-      height: level_height
+      height: level_height,
+      depth: slices_count
     })
   }
 
@@ -120,7 +150,7 @@ impl<R: Read + Seek> VtfTexture<R> {
     let has_thumbnail = header.low_res_image_width != 0
       && header.low_res_image_height != 0
       && is_image_format_supported(header.low_res_image_format)
-      && calculate_image_size(header.low_res_image_width as u32, header.low_res_image_height as u32, 1, header.low_res_image_format) > 0;
+      && calculate_image_size(header.low_res_image_width as u32, header.low_res_image_height as u32, 1, header.low_res_image_format).unwrap_or(0) > 0;
     let mut resource_offsets = HashMap::<Resource, u32>::new();
     if header.version[0] > 7 || header.version[0] == 7 && header.version[1] >= 3 {
       for _ in 0 .. header.num_resources {
@@ -144,7 +174,12 @@ impl<R: Read + Seek> VtfTexture<R> {
       if has_thumbnail {
         resource_offsets.insert(Resource::Thumbnail, header.header_size);
       }
-      resource_offsets.insert(Resource::Image, header.header_size + calculate_image_size(header.low_res_image_width as u32, header.low_res_image_height as u32, 1, header.low_res_image_format));
+      let thumbnail_size = if has_thumbnail {
+        calculate_image_size(header.low_res_image_width as u32, header.low_res_image_height as u32, 1, header.low_res_image_format).unwrap_or(0)
+      } else {
+        0
+      };
+      resource_offsets.insert(Resource::Image, header.header_size + thumbnail_size);
     }
 
     Ok(resource_offsets)
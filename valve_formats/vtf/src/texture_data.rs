@@ -4,7 +4,8 @@ pub struct MipMap {
   pub frames: Vec<Frame>,
   pub format: ImageFormat,
   pub width: u32,
-  pub height: u32
+  pub height: u32,
+  pub depth: u32
 }
 
 pub struct Frame {